@@ -1,3 +1,65 @@
+/// Every command in `generate_handler!`. Listing them makes Tauri check each
+/// invoke against the window's capabilities; `permissions/capture.toml`
+/// groups them into the sets capabilities grant.
+const COMMANDS: &[&str] = &[
+    "greet",
+    "start_capture",
+    "stop_capture",
+    "arm_capture",
+    "disarm_capture",
+    "pause_capture",
+    "resume_capture",
+    "capture_status",
+    "add_marker",
+    "set_layout",
+    "set_capture_profile",
+    "start_preview_playback",
+    "capture_frames",
+    "read_recent_audio",
+    "enroll_wake_word",
+    "run_benchmark",
+    "flush_chunks_now",
+    "capture_capabilities",
+    "list_capture_targets",
+    "list_capture_devices",
+    "list_saved_screen_permissions",
+    "clear_screen_permission",
+    "list_templates",
+    "save_template",
+    "delete_template",
+    "start_from_template",
+    "enqueue_job",
+    "job_status",
+    "pending_uploads",
+    "connect_chunk_consumer",
+    "ack_chunks",
+    "disconnect_chunk_consumer",
+    "get_chunk",
+    "get_chunk_data",
+    "find_sessions_by_window",
+    "list_sessions",
+    "import_file",
+    "chunk_timeline",
+    "set_session_tags",
+    "rename_session",
+    "add_session_note",
+    "export_session_metadata",
+    "export_subtitles",
+    "search_recordings",
+    "extract_frame",
+    "find_similar_frames",
+    "redact_range",
+    "delete_session",
+    "remux_raw_chunks",
+    "stitch_raw_chunks",
+];
+
 fn main() {
-    tauri_build::build()
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/chunks.proto").expect("failed to compile proto/chunks.proto");
+    println!("cargo:rerun-if-changed=permissions");
+    tauri_build::try_build(
+        tauri_build::Attributes::new().app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+    )
+    .expect("failed to run tauri-build");
 }
@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::capture_manager::CapturedChunk;
+
+const DEFAULT_REPLAY_MB: usize = 256;
+
+/// Identifies a chunk; ids count per stream, so the stream is part of it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ChunkKey {
+    pub stream: String,
+    pub chunk_id: u64,
+}
+
+impl ChunkKey {
+    pub fn of(chunk: &CapturedChunk) -> Self {
+        Self {
+            stream: chunk.kind.clone(),
+            chunk_id: chunk.id,
+        }
+    }
+}
+
+/// A chunk without its data, as handed to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkInfo {
+    pub stream: String,
+    pub chunk_id: u64,
+    pub start_ts_unix_nanos: u128,
+    pub duration_ms: u64,
+    pub bytes: usize,
+    pub metadata: Value,
+}
+
+impl ChunkInfo {
+    pub fn of(chunk: &CapturedChunk) -> Self {
+        Self {
+            stream: chunk.kind.clone(),
+            chunk_id: chunk.id,
+            start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+            duration_ms: chunk.duration_ms,
+            bytes: chunk.data.len(),
+            metadata: chunk.metadata.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Consumer {
+    unacked: VecDeque<Arc<CapturedChunk>>,
+    bytes: usize,
+    /// Chunks evicted before they were acked, since the last connect.
+    dropped: u64,
+}
+
+/// Replay buffers for consumers that acknowledge chunks. Every chunk of the
+/// session is retained for each registered consumer until it acks it, so a
+/// consumer that reconnects after a brief outage gets the gap re-delivered.
+/// Each buffer is capped at `SC_REPLAY_BUFFER_MB` (default 256); beyond
+/// that the oldest unacked chunks are dropped.
+pub struct AckTracker {
+    consumers: Mutex<BTreeMap<String, Consumer>>,
+    max_bytes: usize,
+}
+
+impl Default for AckTracker {
+    fn default() -> Self {
+        let max_mb = std::env::var("SC_REPLAY_BUFFER_MB")
+            .ok()
+            .and_then(|mb| mb.parse().ok())
+            .unwrap_or(DEFAULT_REPLAY_MB);
+        Self {
+            consumers: Mutex::default(),
+            max_bytes: max_mb * 1024 * 1024,
+        }
+    }
+}
+
+impl AckTracker {
+    /// Registers `consumer` if needed and returns what it hasn't acked yet,
+    /// oldest first.
+    pub fn connect(&self, consumer: &str) -> Vec<Arc<CapturedChunk>> {
+        let mut consumers = self.consumers.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = consumers.entry(consumer.to_string()).or_default();
+        if state.dropped > 0 {
+            eprintln!("[acks] {consumer}: {} unacked chunks were dropped while away", state.dropped);
+            state.dropped = 0;
+        }
+        state.unacked.iter().cloned().collect()
+    }
+
+    /// Stops retaining chunks for `consumer`.
+    pub fn forget(&self, consumer: &str) -> bool {
+        self.consumers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(consumer)
+            .is_some()
+    }
+
+    /// Releases the acked chunks; returns how many were still retained.
+    pub fn ack(&self, consumer: &str, keys: &[ChunkKey]) -> usize {
+        let mut consumers = self.consumers.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(state) = consumers.get_mut(consumer) else {
+            return 0;
+        };
+        let before = state.unacked.len();
+        state.unacked.retain(|chunk| !keys.contains(&ChunkKey::of(chunk)));
+        state.bytes = state.unacked.iter().map(|chunk| chunk.data.len()).sum();
+        before - state.unacked.len()
+    }
+
+    /// A chunk some consumer still retains.
+    pub fn find(&self, key: &ChunkKey) -> Option<Arc<CapturedChunk>> {
+        self.consumers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .flat_map(|state| state.unacked.iter())
+            .find(|chunk| ChunkKey::of(chunk) == *key)
+            .cloned()
+    }
+
+    pub fn offer(&self, chunk: &Arc<CapturedChunk>) {
+        let mut consumers = self.consumers.lock().unwrap_or_else(PoisonError::into_inner);
+        for (name, state) in consumers.iter_mut() {
+            state.unacked.push_back(Arc::clone(chunk));
+            state.bytes += chunk.data.len();
+            while state.bytes > self.max_bytes && state.unacked.len() > 1 {
+                if let Some(evicted) = state.unacked.pop_front() {
+                    state.bytes -= evicted.data.len();
+                    if state.dropped == 0 {
+                        eprintln!("[acks] {name}: replay buffer full, dropping unacked chunks");
+                    }
+                    state.dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Chunk ids restart with each session, so buffers are emptied too.
+    pub fn reset(&self) {
+        for state in self.consumers.lock().unwrap_or_else(PoisonError::into_inner).values_mut() {
+            *state = Consumer::default();
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, VideoQuality};
+use crate::events::CaptureEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Quality levels tried from best to cheapest.
+const LADDER: &[VideoQuality] = &[
+    VideoQuality { framerate: 30, scale_percent: 100 },
+    VideoQuality { framerate: 20, scale_percent: 100 },
+    VideoQuality { framerate: 15, scale_percent: 75 },
+    VideoQuality { framerate: 10, scale_percent: 50 },
+];
+/// Overloaded when processing eats this share of the per-frame budget...
+const OVERLOAD_BUDGET_RATIO: f64 = 0.8;
+/// ...or fewer than this share of the expected frames arrive.
+const OVERLOAD_DELIVERY_RATIO: f64 = 0.7;
+const RECOVER_BUDGET_RATIO: f64 = 0.3;
+const RECOVER_DELIVERY_RATIO: f64 = 0.95;
+/// Consecutive healthy windows required before stepping quality back up.
+const RECOVER_WINDOWS: u32 = 3;
+
+/// Counters fed by the video appsink callback.
+#[derive(Default)]
+pub struct LoadStats {
+    samples: AtomicU64,
+    processing_us: AtomicU64,
+}
+
+impl LoadStats {
+    pub fn record(&self, processing: Duration) {
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        self.processing_us
+            .fetch_add(processing.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns `(samples, total processing time)` since the last call.
+    fn take(&self) -> (u64, Duration) {
+        let samples = self.samples.swap(0, Ordering::Relaxed);
+        let processing_us = self.processing_us.swap(0, Ordering::Relaxed);
+        (samples, Duration::from_micros(processing_us))
+    }
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("adaptive_quality".into())
+        .spawn(move || {
+            let mut controller = Controller::default();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                controller.poll(&app);
+            }
+        })?;
+    Ok(())
+}
+
+/// Ladder level `level`, never above the target's configured quality.
+fn rung(level: usize, base: VideoQuality) -> VideoQuality {
+    VideoQuality {
+        framerate: LADDER[level].framerate.min(base.framerate),
+        scale_percent: LADDER[level].scale_percent.min(base.scale_percent),
+    }
+}
+
+#[derive(Default)]
+struct Controller {
+    level: usize,
+    healthy_windows: u32,
+    window_start: Option<Instant>,
+}
+
+impl Controller {
+    fn poll(&mut self, app: &AppHandle) {
+        let manager = app.state::<CaptureManager>();
+        let (samples, processing) = manager.load_stats().take();
+        let window = self.window_start.replace(Instant::now()).map(|start| start.elapsed());
+
+        if manager.status() != CaptureState::Running || !manager.options().adaptive_quality {
+            if std::mem::take(&mut self.level) > 0 {
+                // the session may have ended; a new one starts uncapped anyway
+                let _ = manager.set_adaptive_cap(None);
+            }
+            self.healthy_windows = 0;
+            return;
+        }
+        let Some(window) = window else {
+            return;
+        };
+
+        let base = manager.options().base_video_quality();
+        // what the pipeline actually runs at, battery cap included
+        let current = manager.video_quality();
+        let expected = current.framerate as f64 * window.as_secs_f64();
+        let delivery = samples as f64 / expected.max(1.0);
+        let budget_share = if samples == 0 {
+            0.0
+        } else {
+            (processing.as_secs_f64() / samples as f64) * current.framerate as f64
+        };
+
+        let overloaded = budget_share > OVERLOAD_BUDGET_RATIO || delivery < OVERLOAD_DELIVERY_RATIO;
+        let healthy = budget_share < RECOVER_BUDGET_RATIO && delivery >= RECOVER_DELIVERY_RATIO;
+
+        let next = if overloaded && self.level + 1 < LADDER.len() {
+            self.healthy_windows = 0;
+            self.level + 1
+        } else if healthy && self.level > 0 {
+            self.healthy_windows += 1;
+            if self.healthy_windows < RECOVER_WINDOWS {
+                return;
+            }
+            self.healthy_windows = 0;
+            self.level - 1
+        } else {
+            if !healthy {
+                self.healthy_windows = 0;
+            }
+            return;
+        };
+
+        let cap = rung(next, base);
+        let quality = match manager.set_adaptive_cap(Some(cap)) {
+            Ok(quality) => quality,
+            Err(err) => {
+                eprintln!("[adaptive] failed to apply {cap:?}: {err}");
+                return;
+            }
+        };
+        let reason = format!(
+            "frame processing at {:.0}% of budget, {:.0}% of frames delivered",
+            budget_share * 100.0,
+            delivery * 100.0
+        );
+        println!("[adaptive] level {} -> {next} ({reason})", self.level);
+        let event = if next > self.level {
+            CaptureEvent::CaptureDegraded {
+                reason,
+                framerate: Some(quality.framerate),
+                paused: false,
+            }
+        } else {
+            CaptureEvent::CaptureRestored { reason }
+        };
+        self.level = next;
+        manager.events().emit(event);
+    }
+}
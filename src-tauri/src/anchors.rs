@@ -0,0 +1,100 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+
+/// Wall-clock and monotonic time read at the same moment. Wall-clock time
+/// jumps when NTP corrects it; the monotonic clock (`CLOCK_MONOTONIC` on
+/// Linux, as in journald's monotonic timestamps) never does, so a pair of
+/// anchors maps either clock onto the other.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ClockAnchor {
+    pub unix_nanos: u128,
+    pub monotonic_nanos: u64,
+    /// Running time of the stream's pipeline at that moment, the time base
+    /// of buffer timestamps. Its clock may be an audio device's, which
+    /// drifts from both others.
+    #[serde(default)]
+    pub pipeline_running_nanos: Option<u64>,
+}
+
+impl ClockAnchor {
+    pub fn now() -> Self {
+        let monotonic_nanos = gst::SystemClock::obtain().time().map_or(0, gst::ClockTime::nseconds);
+        Self {
+            unix_nanos: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+            monotonic_nanos,
+            pipeline_running_nanos: None,
+        }
+    }
+
+    /// An anchor that also reads the running time of `appsink`'s pipeline.
+    pub fn at(appsink: &gst_app::AppSink) -> Self {
+        let running = appsink
+            .clock()
+            .and_then(|clock| clock.time())
+            .zip(appsink.base_time())
+            .and_then(|(now, base)| now.checked_sub(base));
+        Self {
+            pipeline_running_nanos: running.map(gst::ClockTime::nseconds),
+            ..Self::now()
+        }
+    }
+}
+
+/// The anchor taken when a chunk's first sample arrived.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkAnchor {
+    pub stream: String,
+    pub chunk_id: u64,
+    #[serde(flatten)]
+    pub anchor: ClockAnchor,
+}
+
+impl ChunkAnchor {
+    /// Reads the `clock_anchor` chunk buffers put into chunk metadata.
+    pub fn from_chunk(chunk: &CapturedChunk) -> Option<Self> {
+        let anchor = serde_json::from_value(chunk.metadata.get("clock_anchor")?.clone()).ok()?;
+        Some(Self {
+            stream: chunk.kind.clone(),
+            chunk_id: chunk.id,
+            anchor,
+        })
+    }
+}
+
+/// Anchors at the start and stop of a session and at every chunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClock {
+    pub start: ClockAnchor,
+    pub stop: ClockAnchor,
+    pub chunks: Vec<ChunkAnchor>,
+}
+
+/// Chunk anchors of the running session.
+#[derive(Default)]
+pub struct AnchorLog {
+    chunks: Mutex<Vec<ChunkAnchor>>,
+}
+
+impl AnchorLog {
+    pub fn reset(&self) {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn push(&self, anchor: ChunkAnchor) {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).push(anchor);
+    }
+
+    pub fn snapshot(&self) -> Vec<ChunkAnchor> {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
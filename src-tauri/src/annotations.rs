@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::captions::LiveCaptions;
+use crate::capture_manager::CapturedChunk;
+use crate::language;
+use crate::proxy;
+use crate::session::SessionManifest;
+use crate::translation::TRANSLATION_LABEL;
+
+/// Chunks waiting for annotation before new ones are skipped, so a slow
+/// model can't pile up raw video in memory.
+const QUEUE_CHUNKS: usize = 8;
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Hook for summarizing or labelling chunks, e.g. with a local or remote
+/// model. Runs on its own thread, off the capture and consumer paths.
+pub trait ChunkAnnotator: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether `chunk` should be passed to [`annotate`](Self::annotate).
+    fn wants(&self, _chunk: &CapturedChunk) -> bool {
+        true
+    }
+
+    /// Whether to run on a chunk whose language, as found from an earlier
+    /// annotator's transcript, is `language`.
+    fn wants_language(&self, _language: Option<&str>) -> bool {
+        true
+    }
+
+    /// `Ok(None)` when there is nothing to say about the chunk.
+    fn annotate(&self, chunk: &CapturedChunk) -> Result<Option<Annotation>>;
+
+    /// Like [`annotate`](Self::annotate), with what the annotators before
+    /// this one said about the chunk, e.g. to build on a transcript.
+    fn annotate_after(&self, chunk: &CapturedChunk, _earlier: &[Annotation]) -> Result<Option<Annotation>> {
+        self.annotate(chunk)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Where the chunk's embedding was stored, e.g. a vector DB id.
+    #[serde(default)]
+    pub embedding_ref: Option<String>,
+    /// ISO 639-1 code of the spoken language. Detected from `summary` for
+    /// audio chunks when the annotator doesn't say.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Anything else the annotator wants kept.
+    #[serde(default)]
+    pub extra: Value,
+}
+
+/// One annotator's annotation of one chunk, as stored in the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkRecord {
+    pub chunk_id: u64,
+    pub stream: String,
+    pub start_ts_unix_nanos: u128,
+    pub annotator: String,
+    #[serde(flatten)]
+    pub annotation: Annotation,
+}
+
+/// An annotator backed by an HTTP endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotatorConfig {
+    pub name: String,
+    /// Receives each chunk like an `upload` sink does and answers with an
+    /// [`Annotation`] as JSON, or 204 for none.
+    pub url: String,
+    /// Streams to annotate, e.g. `["video"]`; empty means all.
+    #[serde(default)]
+    pub streams: Vec<String>,
+    /// Only chunks found to be in one of these languages, e.g. `["de"]` for
+    /// a German model; empty means all. A chunk's language is known once an
+    /// annotator listed before this one has transcribed it.
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+struct HttpAnnotator {
+    config: AnnotatorConfig,
+}
+
+impl ChunkAnnotator for HttpAnnotator {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn wants(&self, chunk: &CapturedChunk) -> bool {
+        self.config.streams.is_empty() || self.config.streams.contains(&chunk.kind)
+    }
+
+    fn wants_language(&self, language: Option<&str>) -> bool {
+        self.config.languages.is_empty()
+            || language.is_some_and(|language| self.config.languages.iter().any(|wanted| wanted == language))
+    }
+
+    fn annotate(&self, chunk: &CapturedChunk) -> Result<Option<Annotation>> {
+        let response = ureq::AgentBuilder::new()
+            .timeout_connect(IO_TIMEOUT)
+            .timeout_read(IO_TIMEOUT)
+            .timeout_write(IO_TIMEOUT)
+            .build()
+            .post(&self.config.url)
+            .set("Content-Type", "application/octet-stream")
+            .set("X-Chunk-Id", &chunk.id.to_string())
+            .set("X-Chunk-Stream", &chunk.kind)
+            .set("X-Chunk-Start-Unix-Nanos", &chunk.start_ts_unix_nanos.to_string())
+            .set("X-Chunk-Duration-Ms", &chunk.duration_ms.to_string())
+            .set("X-Chunk-Metadata", &chunk.metadata.to_string())
+            .send_bytes(&chunk.data)
+            .map_err(|err| anyhow!("annotator {} failed: {err}", self.config.name))?;
+        if response.status() == 204 {
+            return Ok(None);
+        }
+        Ok(Some(response.into_json()?))
+    }
+}
+
+pub fn from_configs(configs: &[AnnotatorConfig]) -> Vec<Arc<dyn ChunkAnnotator>> {
+    configs
+        .iter()
+        .map(|config| Arc::new(HttpAnnotator { config: config.clone() }) as Arc<dyn ChunkAnnotator>)
+        .collect()
+}
+
+#[derive(Default)]
+struct RecordsState {
+    records: Vec<ChunkRecord>,
+    /// Set once the manifest exists, so late annotations are merged into it.
+    manifest_dir: Option<PathBuf>,
+}
+
+/// A session's annotations. The annotator thread may still be working when
+/// `stop_capture` writes the manifest, so whichever side comes last updates it.
+pub struct SessionAnnotations {
+    state: Mutex<RecordsState>,
+    /// Shows the transcripts of audio chunks as they arrive.
+    captions: Option<Arc<LiveCaptions>>,
+}
+
+impl SessionAnnotations {
+    pub fn new(captions: Option<Arc<LiveCaptions>>) -> Self {
+        Self {
+            state: Mutex::default(),
+            captions,
+        }
+    }
+
+    fn push(&self, record: ChunkRecord, duration_ms: u64) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let (Some(captions), Some(summary)) = (&self.captions, &record.annotation.summary) {
+            // the chunk's transcript, replaced by its translation if there is one
+            let first = !state
+                .records
+                .iter()
+                .any(|seen| seen.chunk_id == record.chunk_id && seen.stream == record.stream);
+            let translated = record.annotation.labels.iter().any(|label| label == TRANSLATION_LABEL);
+            if !proxy::is_video(&record.stream) && (first || translated) {
+                captions.show(summary, record.start_ts_unix_nanos, duration_ms);
+            }
+        }
+        state.records.push(record);
+    }
+
+    /// Writes `manifest` with the annotations so far.
+    pub fn write_manifest(&self, mut manifest: SessionManifest, dir: &Path) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        manifest.chunk_annotations = state.records.clone();
+        manifest.write(dir)?;
+        state.manifest_dir = Some(dir.to_path_buf());
+        Ok(())
+    }
+
+    fn finish(&self) {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(dir) = &state.manifest_dir else {
+            return;
+        };
+        let updated = SessionManifest::read(dir).and_then(|mut manifest| {
+            manifest.chunk_annotations = state.records.clone();
+            manifest.write(dir)
+        });
+        if let Err(err) = updated {
+            eprintln!("[annotations] failed to add annotations to the manifest: {err}");
+        }
+    }
+}
+
+/// Feeds chunks from the consumer to the annotator thread.
+pub struct AnnotatorQueue {
+    sender: SyncSender<Arc<CapturedChunk>>,
+}
+
+impl AnnotatorQueue {
+    /// Starts the annotator thread; it finishes once the queue is dropped
+    /// and the backlog is annotated.
+    pub fn start(annotators: Vec<Arc<dyn ChunkAnnotator>>, records: Arc<SessionAnnotations>) -> Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Arc<CapturedChunk>>(QUEUE_CHUNKS);
+        std::thread::Builder::new()
+            .name("chunk_annotator".into())
+            .spawn(move || {
+                for chunk in receiver {
+                    let mut chunk_language: Option<String> = None;
+                    let mut earlier: Vec<Annotation> = Vec::new();
+                    for annotator in annotators.iter().filter(|annotator| annotator.wants(&chunk)) {
+                        if !annotator.wants_language(chunk_language.as_deref()) {
+                            continue;
+                        }
+                        match annotator.annotate_after(&chunk, &earlier) {
+                            Ok(Some(mut annotation)) => {
+                                if annotation.language.is_none() && !proxy::is_video(&chunk.kind) {
+                                    annotation.language = annotation
+                                        .summary
+                                        .as_deref()
+                                        .and_then(language::detect)
+                                        .map(str::to_string);
+                                }
+                                // the first language found is the spoken one; later
+                                // ones may be translations
+                                if chunk_language.is_none() {
+                                    chunk_language.clone_from(&annotation.language);
+                                }
+                                earlier.push(annotation.clone());
+                                let record = ChunkRecord {
+                                    chunk_id: chunk.id,
+                                    stream: chunk.kind.clone(),
+                                    start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+                                    annotator: annotator.name().to_string(),
+                                    annotation,
+                                };
+                                records.push(record, chunk.duration_ms);
+                            }
+                            Ok(None) => {}
+                            Err(err) => eprintln!("[annotations] {} chunk {}: {err}", chunk.kind, chunk.id),
+                        }
+                    }
+                }
+                records.finish();
+            })?;
+        Ok(Self { sender })
+    }
+
+    pub fn offer(&self, chunk: &Arc<CapturedChunk>) {
+        if let Err(TrySendError::Full(chunk)) = self.sender.try_send(Arc::clone(chunk)) {
+            eprintln!("[annotations] annotators are behind, skipping {} chunk {}", chunk.kind, chunk.id);
+        }
+    }
+}
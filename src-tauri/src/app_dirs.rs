@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use tauri::{AppHandle, Manager};
+
+/// The app's config and data directories, resolved once at startup.
+static DIRS: OnceCell<(PathBuf, PathBuf)> = OnceCell::new();
+
+/// Resolves and creates the platform config and data directories, e.g.
+/// `~/.config/<identifier>` and `~/.local/share/<identifier>` on Linux.
+pub fn init(app: &AppHandle) -> Result<()> {
+    let config = app.path().app_config_dir()?;
+    let data = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&config)?;
+    std::fs::create_dir_all(&data)?;
+    let _ = DIRS.set((config, data));
+    Ok(())
+}
+
+/// `name` in the config directory, for settings the user edits.
+pub fn config_file(name: &str) -> Result<PathBuf> {
+    DIRS.get()
+        .map(|(config, _)| config.join(name))
+        .ok_or_else(|| anyhow!("app directories are not resolved yet"))
+}
+
+/// `name` in the data directory, for state the app keeps itself.
+pub fn data_file(name: &str) -> Result<PathBuf> {
+    DIRS.get()
+        .map(|(_, data)| data.join(name))
+        .ok_or_else(|| anyhow!("app directories are not resolved yet"))
+}
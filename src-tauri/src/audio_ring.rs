@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use serde::Serialize;
+
+/// The longest stretch `read_recent_audio` hands out at once, and so the
+/// most the ring keeps.
+const MAX_READ_MS: u64 = 30_000;
+
+/// The layout of the PCM the ring holds; a change clears it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcmFormat {
+    pub rate: u32,
+    pub channels: u32,
+    pub format: Option<String>,
+    pub bytes_per_sample: u32,
+}
+
+impl PcmFormat {
+    fn bytes_per_frame(&self) -> usize {
+        (self.channels.max(1) * self.bytes_per_sample.max(1)) as usize
+    }
+
+    fn bytes_for_ms(&self, ms: u64) -> usize {
+        (self.rate as u64 * ms / 1000) as usize * self.bytes_per_frame()
+    }
+}
+
+/// The last few seconds of mic audio, interleaved, base64'd so they can go
+/// straight to a wake-word model in the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentAudio {
+    pub rate: u32,
+    pub channels: u32,
+    pub format: Option<String>,
+    pub bytes_per_sample: u32,
+    /// When the newest sample arrived.
+    pub end_unix_ms: u64,
+    pub duration_ms: u64,
+    pub data_base64: String,
+}
+
+#[derive(Default)]
+struct Ring {
+    capacity_ms: u64,
+    format: Option<PcmFormat>,
+    data: VecDeque<u8>,
+    newest_unix_ms: u64,
+}
+
+/// Always-on mic PCM, filled sample by sample alongside chunking, so readers
+/// see audio as soon as it arrives rather than once a chunk is cut.
+#[derive(Default)]
+pub struct AudioRing {
+    ring: Mutex<Ring>,
+}
+
+impl AudioRing {
+    /// Empties the ring and keeps `capacity_ms` (at most [`MAX_READ_MS`])
+    /// from now on; 0 turns it off.
+    pub fn reset(&self, capacity_ms: u64) {
+        if capacity_ms > MAX_READ_MS {
+            eprintln!("[audio_ring] audio_ring_ms {capacity_ms} is over {MAX_READ_MS}; keeping {MAX_READ_MS}");
+        }
+        let capacity_ms = capacity_ms.min(MAX_READ_MS);
+        *self.ring.lock().unwrap_or_else(PoisonError::into_inner) = Ring {
+            capacity_ms,
+            ..Ring::default()
+        };
+    }
+
+    pub fn clear(&self) {
+        self.reset(0);
+    }
+
+    pub fn push(&self, format: PcmFormat, bytes: &[u8], unix_ms: u64) {
+        let mut ring = self.ring.lock().unwrap_or_else(PoisonError::into_inner);
+        if ring.capacity_ms == 0 {
+            return;
+        }
+        if ring.format.as_ref() != Some(&format) {
+            ring.data.clear();
+        }
+        let capacity = format.bytes_for_ms(ring.capacity_ms);
+        ring.data.extend(bytes);
+        let excess = ring.data.len().saturating_sub(capacity);
+        ring.data.drain(..excess);
+        ring.format = Some(format);
+        ring.newest_unix_ms = unix_ms;
+    }
+
+    #[cfg_attr(not(feature = "wake_word"), allow(dead_code))]
+    pub fn capacity_ms(&self) -> u64 {
+        self.ring.lock().unwrap_or_else(PoisonError::into_inner).capacity_ms
+    }
+
+    /// Up to the last `ms` of audio in whole frames, with its format and
+    /// when the newest sample arrived.
+    pub fn recent(&self, ms: u64) -> Result<(PcmFormat, Vec<u8>, u64)> {
+        let ring = self.ring.lock().unwrap_or_else(PoisonError::into_inner);
+        if ring.capacity_ms == 0 {
+            return Err(anyhow!("the audio ring is off; start capture with capture_mic and audio_ring_ms"));
+        }
+        let format = ring.format.clone().ok_or_else(|| anyhow!("no mic audio captured yet"))?;
+        let frame = format.bytes_per_frame();
+        let wanted = format.bytes_for_ms(ms).min(ring.data.len() / frame * frame);
+        let data = ring.data.range(ring.data.len() - wanted..).copied().collect();
+        Ok((format, data, ring.newest_unix_ms))
+    }
+
+    /// [`Self::recent`] for the frontend, capped at [`MAX_READ_MS`].
+    pub fn read(&self, ms: u64) -> Result<RecentAudio> {
+        if ms == 0 || ms > MAX_READ_MS {
+            return Err(anyhow!("ms must be between 1 and {MAX_READ_MS}"));
+        }
+        let (format, data, end_unix_ms) = self.recent(ms)?;
+        Ok(RecentAudio {
+            duration_ms: (data.len() / format.bytes_per_frame()) as u64 * 1000 / format.rate.max(1) as u64,
+            rate: format.rate,
+            channels: format.channels,
+            format: format.format,
+            bytes_per_sample: format.bytes_per_sample,
+            end_unix_ms,
+            data_base64: base64::engine::general_purpose::STANDARD.encode(data),
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Largest slice handed out at once, so a cap is honoured smoothly rather
+/// than in bursts of whole chunks.
+const SLICE_BYTES: usize = 16 * 1024;
+
+struct Bucket {
+    /// `None` means unlimited.
+    bytes_per_sec: Option<u64>,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, bytes_per_sec: u64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.refilled = now;
+        // allow at most one second of burst, but never less than a slice
+        let capacity = bytes_per_sec.max(SLICE_BYTES as u64) as f64;
+        self.tokens = (self.tokens + elapsed * bytes_per_sec as f64).min(capacity);
+    }
+}
+
+/// Token bucket shared by every network upload, so together they stay under
+/// the configured rate. Uploads run one at a time, each at the rate of the
+/// session it belongs to.
+#[derive(Clone)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                bytes_per_sec: None,
+                tokens: 0.0,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+}
+
+impl BandwidthLimiter {
+    pub fn set_limit_kbps(&self, kbps: Option<u64>) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let bytes_per_sec = kbps.map(|kbps| (kbps * 1000 / 8).max(1));
+        if bucket.bytes_per_sec == bytes_per_sec {
+            return;
+        }
+        bucket.bytes_per_sec = bytes_per_sec;
+        bucket.tokens = 0.0;
+        bucket.refilled = Instant::now();
+        match kbps {
+            Some(kbps) => println!("[bandwidth] uploads capped at {kbps} kbit/s"),
+            None => println!("[bandwidth] uploads unlimited"),
+        }
+    }
+
+    /// Blocks until `bytes` may be sent.
+    fn take(&self, bytes: usize) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(bytes_per_sec) = bucket.bytes_per_sec else {
+                    return;
+                };
+                bucket.refill(bytes_per_sec);
+                if bucket.tokens >= bytes as f64 {
+                    bucket.tokens -= bytes as f64;
+                    return;
+                }
+                Duration::from_secs_f64((bytes as f64 - bucket.tokens) / bytes_per_sec as f64)
+            };
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Wraps `reader` so the bytes read from it are paced by this limiter.
+    pub fn throttle<R: Read>(&self, reader: R) -> Throttled<R> {
+        Throttled {
+            reader,
+            limiter: self.clone(),
+        }
+    }
+}
+
+pub struct Throttled<R> {
+    reader: R,
+    limiter: BandwidthLimiter,
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(SLICE_BYTES);
+        let read = self.reader.read(&mut buf[..len])?;
+        self.limiter.take(read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_reads_pass_straight_through() {
+        let data: Vec<u8> = (0..100_000).map(|byte| byte as u8).collect();
+        let started = Instant::now();
+        let mut read = Vec::new();
+        BandwidthLimiter::default().throttle(data.as_slice()).read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn reads_are_sliced() {
+        let data = vec![0u8; 3 * SLICE_BYTES];
+        let mut buf = vec![0u8; data.len()];
+        let read = BandwidthLimiter::default().throttle(data.as_slice()).read(&mut buf).unwrap();
+        assert_eq!(read, SLICE_BYTES);
+    }
+
+    #[test]
+    fn capped_reads_take_their_share_of_time() {
+        let limiter = BandwidthLimiter::default();
+        // 32 000 bytes a second, so 16 000 bytes take half a second
+        limiter.set_limit_kbps(Some(256));
+        let data = vec![0u8; 16_000];
+        let started = Instant::now();
+        let mut read = Vec::new();
+        limiter.throttle(data.as_slice()).read_to_end(&mut read).unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(read.len(), data.len());
+        assert!(elapsed >= Duration::from_millis(450), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+
+        limiter.set_limit_kbps(None);
+        let started = Instant::now();
+        limiter.throttle(data.as_slice()).read_to_end(&mut Vec::new()).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+}
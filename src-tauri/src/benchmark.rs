@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::ensure_gstreamer_initialized;
+
+/// A configuration must encode this much faster than real time to be
+/// recommended, leaving room for capture and chunking.
+const REALTIME_HEADROOM: f64 = 1.25;
+const RUN_TIMEOUT: Duration = Duration::from_secs(120);
+/// `/proc/self/stat` counts CPU time in USER_HZ ticks, 100 on every common kernel.
+#[cfg(target_os = "linux")]
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkEncoder {
+    X264Ultrafast,
+    X264Veryfast,
+    /// VA-API, Intel and AMD GPUs.
+    VaapiH264,
+    /// NVENC, NVIDIA GPUs.
+    NvencH264,
+}
+
+impl BenchmarkEncoder {
+    const ALL: [BenchmarkEncoder; 4] = [
+        BenchmarkEncoder::X264Ultrafast,
+        BenchmarkEncoder::X264Veryfast,
+        BenchmarkEncoder::VaapiH264,
+        BenchmarkEncoder::NvencH264,
+    ];
+
+    fn factory(self) -> &'static str {
+        match self {
+            BenchmarkEncoder::X264Ultrafast | BenchmarkEncoder::X264Veryfast => "x264enc",
+            BenchmarkEncoder::VaapiH264 => "vaapih264enc",
+            BenchmarkEncoder::NvencH264 => "nvh264enc",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            BenchmarkEncoder::X264Ultrafast => "x264enc speed-preset=ultrafast",
+            BenchmarkEncoder::X264Veryfast => "x264enc speed-preset=veryfast",
+            BenchmarkEncoder::VaapiH264 => "vaapih264enc",
+            BenchmarkEncoder::NvencH264 => "nvh264enc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BenchmarkRequest {
+    /// Seconds of video encoded per configuration.
+    #[serde(default = "BenchmarkRequest::default_seconds")]
+    pub seconds: u32,
+    #[serde(default = "BenchmarkRequest::default_framerate")]
+    pub framerate: u32,
+    #[serde(default = "BenchmarkRequest::default_resolutions")]
+    pub resolutions: Vec<Resolution>,
+    /// Defaults to every encoder; ones that aren't installed are reported
+    /// with an error.
+    #[serde(default = "BenchmarkRequest::default_encoders")]
+    pub encoders: Vec<BenchmarkEncoder>,
+}
+
+impl Default for BenchmarkRequest {
+    fn default() -> Self {
+        Self {
+            seconds: Self::default_seconds(),
+            framerate: Self::default_framerate(),
+            resolutions: Self::default_resolutions(),
+            encoders: Self::default_encoders(),
+        }
+    }
+}
+
+impl BenchmarkRequest {
+    pub const fn default_seconds() -> u32 {
+        10
+    }
+
+    pub const fn default_framerate() -> u32 {
+        30
+    }
+
+    pub fn default_resolutions() -> Vec<Resolution> {
+        [(1280, 720), (1920, 1080), (2560, 1440), (3840, 2160)]
+            .into_iter()
+            .map(|(width, height)| Resolution { width, height })
+            .collect()
+    }
+
+    pub fn default_encoders() -> Vec<BenchmarkEncoder> {
+        BenchmarkEncoder::ALL.to_vec()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub encoder: BenchmarkEncoder,
+    pub width: u32,
+    pub height: u32,
+    /// Frames encoded per second of wall time.
+    pub fps: f64,
+    /// Process CPU time over wall time; 100 is one full core. `None` where
+    /// CPU time isn't available.
+    pub cpu_percent: Option<f64>,
+    /// Whether `fps` keeps up with the requested framerate with headroom.
+    pub realtime: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub framerate: u32,
+    pub seconds: u32,
+    pub results: Vec<BenchmarkResult>,
+    /// The largest real-time configuration, cheapest on CPU among equals.
+    pub recommended: Option<BenchmarkResult>,
+}
+
+/// Encodes `seconds` of synthesized RGBA video, the capture's own format,
+/// for every resolution and encoder, one configuration at a time and as
+/// fast as the encoder goes.
+pub fn run(request: &BenchmarkRequest) -> Result<BenchmarkReport> {
+    ensure_gstreamer_initialized()?;
+    if request.seconds == 0 || request.framerate == 0 {
+        return Err(anyhow!("benchmark needs a non-zero duration and framerate"));
+    }
+    let mut results = Vec::new();
+    for &encoder in &request.encoders {
+        for &resolution in &request.resolutions {
+            let result = run_one(request, encoder, resolution);
+            match &result.error {
+                Some(err) => eprintln!(
+                    "[benchmark] {:?} {}x{}: {err}",
+                    encoder, resolution.width, resolution.height
+                ),
+                None => println!(
+                    "[benchmark] {:?} {}x{}: {:.1} fps",
+                    encoder, resolution.width, resolution.height, result.fps
+                ),
+            }
+            results.push(result);
+        }
+    }
+    let recommended = results
+        .iter()
+        .filter(|result| result.realtime)
+        .max_by(|a, b| {
+            let pixels = |result: &BenchmarkResult| result.width as u64 * result.height as u64;
+            let cpu = |result: &BenchmarkResult| result.cpu_percent.unwrap_or_default();
+            pixels(a).cmp(&pixels(b)).then(cpu(b).total_cmp(&cpu(a)))
+        })
+        .cloned();
+    Ok(BenchmarkReport {
+        framerate: request.framerate,
+        seconds: request.seconds,
+        results,
+        recommended,
+    })
+}
+
+fn run_one(request: &BenchmarkRequest, encoder: BenchmarkEncoder, resolution: Resolution) -> BenchmarkResult {
+    let mut result = BenchmarkResult {
+        encoder,
+        width: resolution.width,
+        height: resolution.height,
+        fps: 0.0,
+        cpu_percent: None,
+        realtime: false,
+        error: None,
+    };
+    if gst::ElementFactory::find(encoder.factory()).is_none() {
+        result.error = Some(format!("'{}' is not installed", encoder.factory()));
+        return result;
+    }
+    let frames = request.seconds as u64 * request.framerate as u64;
+    let description = format!(
+        "videotestsrc num-buffers={frames} pattern=ball \
+         ! video/x-raw,format=RGBA,width={},height={},framerate={}/1 \
+         ! videoconvert ! {} ! fakesink sync=false",
+        resolution.width,
+        resolution.height,
+        request.framerate,
+        encoder.description()
+    );
+    let cpu_before = cpu_time();
+    let started = Instant::now();
+    match encode(&description) {
+        Ok(()) => {
+            let wall = started.elapsed().as_secs_f64().max(f64::EPSILON);
+            result.fps = frames as f64 / wall;
+            result.cpu_percent = cpu_before
+                .zip(cpu_time())
+                .map(|(before, after)| (after - before).as_secs_f64() / wall * 100.0);
+            result.realtime = result.fps >= request.framerate as f64 * REALTIME_HEADROOM;
+        }
+        Err(err) => result.error = Some(err.to_string()),
+    }
+    result
+}
+
+fn encode(description: &str) -> Result<()> {
+    let pipeline = gst::parse::launch(description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("benchmark description did not produce a pipeline"))?;
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|err| anyhow!("failed to start benchmark pipeline: {err:?}"))?;
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("benchmark pipeline has no bus"))?;
+    let message = bus.timed_pop_filtered(
+        gst::ClockTime::from_mseconds(RUN_TIMEOUT.as_millis() as u64),
+        &[gst::MessageType::Eos, gst::MessageType::Error],
+    );
+    let result = match message.as_ref().map(|message| message.view()) {
+        Some(gst::MessageView::Eos(..)) => Ok(()),
+        Some(gst::MessageView::Error(err)) => Err(anyhow!("{}", err.error())),
+        _ => Err(anyhow!("timed out after {}s", RUN_TIMEOUT.as_secs())),
+    };
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
+
+/// User plus system CPU time of this process.
+#[cfg(target_os = "linux")]
+fn cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // fields after the parenthesised command name, starting at `state`
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Duration::from_secs_f64((utime + stime) as f64 / CLOCK_TICKS_PER_SEC))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_time() -> Option<Duration> {
+    None
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::Result;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, CaptureTarget};
+use crate::events::CaptureEvent;
+use crate::focus;
+
+/// Short, so as little as possible of a window covering the target is
+/// recorded before the frames go black.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shared between the blackout thread and the video pipeline's probe.
+#[derive(Default)]
+pub struct BlackoutState {
+    hidden: Arc<AtomicBool>,
+    /// Compositor id of the target window, resolved from its PipeWire node
+    /// when the session starts; `None` when blackout is off.
+    window: Mutex<Option<String>>,
+}
+
+impl BlackoutState {
+    /// With a `window` to watch, a session starts dark until the first check
+    /// finds it focused.
+    pub fn reset(&self, window: Option<String>) {
+        self.hidden.store(window.is_some(), Ordering::Relaxed);
+        *self.window.lock().unwrap_or_else(PoisonError::into_inner) = window;
+    }
+
+    fn window(&self) -> Option<String> {
+        self.window.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Blacks out every buffer leaving `pad` while the target window is out
+    /// of focus, which includes while it is minimized.
+    pub fn add_probe(&self, pad: &gst::Pad) {
+        let hidden = Arc::clone(&self.hidden);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            if !hidden.load(Ordering::Relaxed) {
+                return gst::PadProbeReturn::Ok;
+            }
+            if let Some(gst::PadProbeData::Buffer(buffer)) = &mut info.data {
+                if let Ok(mut map) = buffer.make_mut().map_writable() {
+                    map.as_mut_slice().fill(0);
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    /// Returns true when the flag actually flipped.
+    fn set_hidden(&self, hidden: bool) -> bool {
+        self.hidden.swap(hidden, Ordering::Relaxed) != hidden
+    }
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("window_blackout".into())
+        .spawn(move || {
+            let mut backend_failed = false;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                poll(&app, &mut backend_failed);
+            }
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle, backend_failed: &mut bool) {
+    let manager = app.state::<CaptureManager>();
+    let options = manager.options();
+    let CaptureTarget::Window { id } = &options.target else {
+        return;
+    };
+    if !matches!(manager.status(), CaptureState::Running | CaptureState::Paused) {
+        return;
+    }
+    let Some(window_id) = manager.blackout().window() else {
+        return;
+    };
+    let hidden = match focus::focused_window() {
+        Ok(focused) => {
+            *backend_failed = false;
+            !focused.is_some_and(|window| window.id == window_id)
+        }
+        Err(err) => {
+            if !std::mem::replace(backend_failed, true) {
+                eprintln!("[blackout] failed to query the focused window: {err}");
+            }
+            // can't tell what is on top, so keep the frames dark
+            true
+        }
+    };
+    if manager.blackout().set_hidden(hidden) {
+        println!("[blackout] window {id} {}", if hidden { "hidden, blanking" } else { "focused again" });
+        manager.events().emit(CaptureEvent::WindowHidden { hidden });
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::{Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+/// The active tab as reported by a companion browser extension.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrowserTab {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// e.g. `firefox`, for users running more than one browser.
+    #[serde(default)]
+    pub browser: Option<String>,
+}
+
+/// A stretch of time one tab was active. `end_unix_ms` stays open until the
+/// next tab report or the end of the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabSpan {
+    pub url: String,
+    pub title: Option<String>,
+    pub browser: Option<String>,
+    pub start_unix_ms: u64,
+    pub end_unix_ms: Option<u64>,
+}
+
+/// Tab history of the running session, shared with the chunk buffers.
+#[derive(Default)]
+pub struct BrowserTimeline {
+    spans: Mutex<Vec<TabSpan>>,
+}
+
+impl BrowserTimeline {
+    pub fn reset(&self) {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Closes the open span and starts one for `tab`; reports of the tab
+    /// that is already active only refresh its title.
+    pub fn push(&self, tab: BrowserTab, now_ms: u64) {
+        let mut spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(last) = spans.last_mut().filter(|span| span.end_unix_ms.is_none()) {
+            if last.url == tab.url && last.browser == tab.browser {
+                last.title = tab.title.or(last.title.take());
+                return;
+            }
+            last.end_unix_ms = Some(now_ms);
+        }
+        spans.push(TabSpan {
+            url: tab.url,
+            title: tab.title,
+            browser: tab.browser,
+            start_unix_ms: now_ms,
+            end_unix_ms: None,
+        });
+    }
+
+    /// Spans overlapping `start_ms..=end_ms`.
+    pub fn between(&self, start_ms: u64, end_ms: u64) -> Vec<TabSpan> {
+        self.spans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|span| span.start_unix_ms <= end_ms && span.end_unix_ms.is_none_or(|end| end >= start_ms))
+            .cloned()
+            .collect()
+    }
+
+    /// Every span, with the open one closed at `end_ms`.
+    pub fn finish(&self, end_ms: u64) -> Vec<TabSpan> {
+        let mut spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(last) = spans.last_mut() {
+            last.end_unix_ms.get_or_insert(end_ms);
+        }
+        spans.clone()
+    }
+}
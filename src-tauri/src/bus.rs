@@ -0,0 +1,45 @@
+use std::sync::{Mutex, PoisonError};
+
+use zbus::blocking::Connection;
+use zbus::zvariant::DynamicType;
+use zbus::Message;
+
+/// Connections shared by the pollers, opened on first use rather than on
+/// every tick.
+static SESSION: Mutex<Option<Connection>> = Mutex::new(None);
+static SYSTEM: Mutex<Option<Connection>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Bus {
+    Session,
+    System,
+}
+
+/// Calls `method` over the shared connection to `bus`; `None` when the bus
+/// or the service isn't there or the call fails. An I/O error drops the
+/// connection, so a restarted bus is reconnected to on the next call.
+pub fn call<B>(bus: Bus, destination: &str, path: &str, interface: &str, method: &str, body: &B) -> Option<Message>
+where
+    B: serde::Serialize + DynamicType,
+{
+    let (slot, connect): (_, fn() -> zbus::Result<Connection>) = match bus {
+        Bus::Session => (&SESSION, Connection::session),
+        Bus::System => (&SYSTEM, Connection::system),
+    };
+    let conn = {
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if slot.is_none() {
+            *slot = connect().ok();
+        }
+        slot.clone()?
+    };
+    // the lock isn't held during the call, so a slow service only stalls its caller
+    match conn.call_method(Some(destination), path, Some(interface), method, body) {
+        Ok(reply) => Some(reply),
+        Err(zbus::Error::InputOutput(_)) => {
+            *slot.lock().unwrap_or_else(PoisonError::into_inner) = None;
+            None
+        }
+        Err(_) => None,
+    }
+}
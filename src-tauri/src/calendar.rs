@@ -0,0 +1,1206 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::app_dirs;
+use crate::capture_manager::{CaptureManager, CaptureOptions};
+use crate::daily_summary;
+use crate::templates;
+
+const DEFAULT_CONFIG_FILE: &str = "calendar.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const FETCH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// How far ahead events are fetched and recurring events expanded.
+const LOOKAHEAD_MS: u64 = 24 * 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// The calendar event a session was recorded for.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub title: String,
+    pub start_unix_ms: u64,
+    pub end_unix_ms: u64,
+    #[serde(default)]
+    pub organizer: Option<String>,
+    #[serde(default)]
+    pub attendees: Vec<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CalendarConfig {
+    sources: Vec<CalendarSource>,
+    rules: Vec<CalendarRule>,
+    /// Offset from UTC of floating times, and of `TZID` times whose zone the
+    /// document doesn't define.
+    #[serde(default)]
+    utc_offset_minutes: i64,
+    /// Start this long before the event.
+    #[serde(default)]
+    start_early_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CalendarSource {
+    /// An `.ics` URL or file, or a CalDAV calendar collection.
+    url: String,
+    #[serde(default)]
+    caldav: bool,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// Events matching every set field are recorded.
+#[derive(Debug, Clone, Deserialize)]
+struct CalendarRule {
+    #[serde(default)]
+    title_regex: Option<String>,
+    /// `title_regex`, compiled when the config is read.
+    #[serde(skip)]
+    title: Option<Regex>,
+    #[serde(default)]
+    min_attendees: Option<usize>,
+    #[serde(default)]
+    max_attendees: Option<usize>,
+    /// Recording template to start; default options otherwise.
+    #[serde(default)]
+    template: Option<String>,
+}
+
+impl CalendarRule {
+    fn matches(&self, event: &CalendarEvent) -> bool {
+        let attendees = event.attendees.len();
+        self.title.as_ref().is_none_or(|title| title.is_match(&event.title))
+            && self.min_attendees.is_none_or(|min| attendees >= min)
+            && self.max_attendees.is_none_or(|max| attendees <= max)
+    }
+}
+
+/// Read from the app config directory; `SC_CALENDAR_CONFIG` overrides it.
+fn config_path() -> Result<PathBuf> {
+    match std::env::var_os("SC_CALENDAR_CONFIG") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_dirs::config_file(DEFAULT_CONFIG_FILE),
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Records calendar events that match the configured rules: sources are
+/// re-fetched every few minutes, and a capture is started when a matching
+/// event begins and stopped when it ends. Does nothing without a config.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut config: CalendarConfig = serde_json::from_slice(&std::fs::read(&path)?)
+        .map_err(|err| anyhow!("invalid calendar config {}: {err}", path.display()))?;
+    for rule in &mut config.rules {
+        if let Some(pattern) = &rule.title_regex {
+            rule.title = Some(Regex::new(pattern).map_err(|err| anyhow!("invalid title_regex '{pattern}': {err}"))?);
+        }
+    }
+    println!(
+        "[calendar] watching {} source(s) with {} rule(s)",
+        config.sources.len(),
+        config.rules.len()
+    );
+    std::thread::Builder::new()
+        .name("calendar".into())
+        .spawn(move || {
+            let mut scheduler = Scheduler::new(config);
+            loop {
+                scheduler.poll(&app);
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
+
+struct Scheduler {
+    config: CalendarConfig,
+    last_fetch: Option<Instant>,
+    /// Matching events that haven't ended, with the template to use.
+    upcoming: Vec<(CalendarEvent, Option<String>)>,
+    /// The event being recorded and its session.
+    recording: Option<(CalendarEvent, String)>,
+    /// Events already started, so a stopped recording isn't restarted.
+    handled: HashSet<(String, u64)>,
+}
+
+impl Scheduler {
+    fn new(config: CalendarConfig) -> Self {
+        Self {
+            config,
+            last_fetch: None,
+            upcoming: Vec::new(),
+            recording: None,
+            handled: HashSet::new(),
+        }
+    }
+
+    fn poll(&mut self, app: &AppHandle) {
+        if self.last_fetch.is_none_or(|fetched| fetched.elapsed() >= FETCH_INTERVAL) {
+            self.last_fetch = Some(Instant::now());
+            self.refresh();
+        }
+        let now_ms = unix_now_ms();
+        let manager = app.state::<CaptureManager>();
+
+        if let Some((event, session_id)) = &self.recording {
+            if now_ms < event.end_unix_ms {
+                return;
+            }
+            // a session the user already stopped or replaced is theirs
+            if manager.session_id().as_deref() == Some(session_id.as_str()) {
+                println!("[calendar] '{}' ended, stopping {session_id}", event.title);
+                if let Err(err) = manager.stop_capture() {
+                    eprintln!("[calendar] failed to stop {session_id}: {err}");
+                }
+            }
+            self.recording = None;
+        }
+        if manager.status().is_active() {
+            return;
+        }
+        let early_ms = self.config.start_early_secs * 1000;
+        let Some((event, template)) = self
+            .upcoming
+            .iter()
+            .find(|(event, _)| {
+                event.start_unix_ms.saturating_sub(early_ms) <= now_ms
+                    && now_ms < event.end_unix_ms
+                    && !self.handled.contains(&(event.uid.clone(), event.start_unix_ms))
+            })
+            .cloned()
+        else {
+            return;
+        };
+        self.handled.insert((event.uid.clone(), event.start_unix_ms));
+        let options = match &template {
+            Some(name) => templates::resolve(name, manager.displays()),
+            None => Ok(CaptureOptions::default()),
+        };
+        let result = options.and_then(|options| {
+            manager.start_capture(CaptureOptions {
+                calendar_event: Some(event.clone()),
+                ..options
+            })
+        });
+        match (result, manager.session_id()) {
+            (Ok(()), Some(session_id)) => {
+                println!("[calendar] recording '{}' as {session_id}", event.title);
+                self.recording = Some((event, session_id));
+            }
+            (Ok(()), None) => {}
+            (Err(err), _) => eprintln!("[calendar] failed to start recording '{}': {err}", event.title),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let now_ms = unix_now_ms();
+        let mut upcoming = Vec::new();
+        for source in &self.config.sources {
+            let events = match fetch(source, now_ms, self.config.utc_offset_minutes) {
+                Ok(events) => events,
+                Err(err) => {
+                    eprintln!("[calendar] failed to fetch {}: {err}", source.url);
+                    continue;
+                }
+            };
+            for event in events.into_iter().filter(|event| event.end_unix_ms > now_ms) {
+                if let Some(rule) = self.config.rules.iter().find(|rule| rule.matches(&event)) {
+                    upcoming.push((event, rule.template.clone()));
+                }
+            }
+        }
+        upcoming.sort_by_key(|(event, _)| event.start_unix_ms);
+        self.handled.retain(|(uid, start_ms)| {
+            upcoming
+                .iter()
+                .any(|(event, _)| &event.uid == uid && event.start_unix_ms == *start_ms)
+        });
+        self.upcoming = upcoming;
+    }
+}
+
+fn fetch(source: &CalendarSource, now_ms: u64, utc_offset_minutes: i64) -> Result<Vec<CalendarEvent>> {
+    let (from_ms, to_ms) = (now_ms, now_ms + LOOKAHEAD_MS);
+    if !source.url.starts_with("http://") && !source.url.starts_with("https://") {
+        return Ok(parse_ics(&std::fs::read_to_string(&source.url)?, utc_offset_minutes, from_ms, to_ms));
+    }
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    let auth = match (&source.username, &source.password) {
+        (Some(user), Some(password)) => {
+            let token = base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+            Some(format!("Basic {token}"))
+        }
+        _ => None,
+    };
+    let request = if source.caldav {
+        agent
+            .request("REPORT", &source.url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8")
+    } else {
+        agent.get(&source.url)
+    };
+    let request = match &auth {
+        Some(auth) => request.set("Authorization", auth),
+        None => request,
+    };
+    if !source.caldav {
+        return Ok(parse_ics(&request.call()?.into_string()?, utc_offset_minutes, from_ms, to_ms));
+    }
+    // the server expands recurring events into the instances in the window
+    let range = format!(
+        r#"start="{}" end="{}""#,
+        caldav_time(now_ms.saturating_sub(DAY_MS)),
+        caldav_time(to_ms)
+    );
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop><C:calendar-data><C:expand {range}/></C:calendar-data></D:prop>
+  <C:filter><C:comp-filter name="VCALENDAR"><C:comp-filter name="VEVENT"><C:time-range {range}/></C:comp-filter></C:comp-filter></C:filter>
+</C:calendar-query>"#
+    );
+    let response = request.send_string(&body)?.into_string()?;
+    Ok(calendar_data(&response)
+        .iter()
+        .flat_map(|ics| parse_ics(ics, utc_offset_minutes, from_ms, to_ms))
+        .collect())
+}
+
+/// The `calendar-data` payloads of a CalDAV multistatus response.
+fn calendar_data(response: &str) -> Vec<String> {
+    let mut payloads = Vec::new();
+    let mut rest = response;
+    while let Some(start) = rest.find("calendar-data") {
+        rest = &rest[start..];
+        let Some(open_end) = rest.find('>') else { break };
+        // `<C:calendar-data/>` carries nothing
+        if rest[..open_end].ends_with('/') {
+            rest = &rest[open_end..];
+            continue;
+        }
+        let content = &rest[open_end + 1..];
+        let Some(close) = content.find("</") else { break };
+        payloads.push(
+            content[..close]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&#13;", "\r")
+                .replace("&amp;", "&"),
+        );
+        rest = &content[close..];
+        // step past the closing tag's own `calendar-data`
+        rest = rest.find('>').map_or("", |end| &rest[end..]);
+    }
+    payloads
+}
+
+/// `YYYYMMDDTHHMMSSZ`, the only form CalDAV time ranges take.
+fn caldav_time(unix_ms: u64) -> String {
+    let secs = unix_ms / 1000;
+    let date = daily_summary::format_date(unix_ms).replace('-', "");
+    format!("{date}T{:02}{:02}{:02}Z", secs / 3600 % 24, secs / 60 % 60, secs % 60)
+}
+
+/// The timed, non-cancelled events of an iCalendar document that overlap
+/// `from_ms..to_ms`. Recurring events are expanded into their instances,
+/// leaving out `EXDATE`s and instances that have a `RECURRENCE-ID` event of
+/// their own.
+pub fn parse_ics(ics: &str, utc_offset_minutes: i64, from_ms: u64, to_ms: u64) -> Vec<CalendarEvent> {
+    // long lines are folded with a leading space or tab on the continuation
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(previous)) => previous.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut vevents: Vec<Vec<Property>> = Vec::new();
+    let mut zones: HashMap<String, Zone> = HashMap::new();
+    let mut observances: Vec<Observance> = Vec::new();
+    // components nest, e.g. VALARM in VEVENT and STANDARD in VTIMEZONE
+    let mut stack: Vec<(String, Vec<Property>)> = Vec::new();
+    for line in &lines {
+        let line = line.trim_end();
+        if let Some(name) = line.strip_prefix("BEGIN:") {
+            stack.push((name.to_ascii_uppercase(), Vec::new()));
+        } else if line.starts_with("END:") {
+            let Some((name, props)) = stack.pop() else {
+                continue;
+            };
+            match name.as_str() {
+                "VEVENT" => vevents.push(props),
+                "STANDARD" | "DAYLIGHT" => observances.extend(observance(&props)),
+                "VTIMEZONE" => {
+                    let observances = std::mem::take(&mut observances);
+                    if let Some(tzid) = find(&props, "TZID") {
+                        zones.insert(tzid.value.clone(), Zone { observances });
+                    }
+                }
+                _ => {}
+            }
+        } else if let (Some((_, props)), Some(prop)) = (stack.last_mut(), property(line)) {
+            props.push(prop);
+        }
+    }
+
+    let times = Times {
+        zones: &zones,
+        utc_offset_minutes,
+    };
+    // instances moved or cancelled by an event of their own
+    let moved: HashSet<(String, u64)> = vevents
+        .iter()
+        .filter_map(|props| Some((find(props, "UID")?.value.clone(), times.utc_ms(find(props, "RECURRENCE-ID")?)?)))
+        .collect();
+    let mut events = Vec::new();
+    for props in &vevents {
+        let Some(event) = event(props, &times) else {
+            continue;
+        };
+        let length_ms = event.end_unix_ms.saturating_sub(event.start_unix_ms);
+        let recurring = find(props, "RECURRENCE-ID").is_none()
+            && props.iter().any(|prop| prop.name == "RRULE" || prop.name == "RDATE");
+        let starts = if recurring {
+            occurrences(props, &times, from_ms.saturating_sub(length_ms), to_ms)
+        } else {
+            vec![event.start_unix_ms]
+        };
+        for start_unix_ms in starts {
+            let end_unix_ms = start_unix_ms + length_ms;
+            if end_unix_ms <= from_ms || start_unix_ms >= to_ms {
+                continue;
+            }
+            if recurring && moved.contains(&(event.uid.clone(), start_unix_ms)) {
+                continue;
+            }
+            events.push(CalendarEvent {
+                start_unix_ms,
+                end_unix_ms,
+                ..event.clone()
+            });
+        }
+    }
+    events
+}
+
+/// A content line, `NAME;PARAM=VALUE:VALUE`. `TZID` is the only parameter
+/// that matters here.
+struct Property {
+    name: String,
+    tzid: Option<String>,
+    value: String,
+}
+
+/// Splits a content line; a colon inside a quoted parameter value doesn't
+/// end the name.
+fn property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        (c == ':' && !quoted).then_some(index)
+    })?;
+    let mut head = line[..colon].split(';');
+    let name = head.next()?.to_ascii_uppercase();
+    let tzid = head
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("TZID"))
+        .map(|(_, tzid)| tzid.trim_matches('"').to_string());
+    Some(Property {
+        name,
+        tzid,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+fn find<'a>(props: &'a [Property], name: &str) -> Option<&'a Property> {
+    props.iter().find(|prop| prop.name == name)
+}
+
+fn event(props: &[Property], times: &Times) -> Option<CalendarEvent> {
+    if find(props, "STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED")) {
+        return None;
+    }
+    let start_unix_ms = times.utc_ms(find(props, "DTSTART")?)?;
+    let end_unix_ms = match (find(props, "DTEND"), find(props, "DURATION")) {
+        (Some(end), _) => times.utc_ms(end)?,
+        (None, Some(duration)) => start_unix_ms + ics_duration_ms(&duration.value)?,
+        (None, None) => return None,
+    };
+    let person = |value: &str| {
+        let value = value.trim();
+        value
+            .strip_prefix("mailto:")
+            .or_else(|| value.strip_prefix("MAILTO:"))
+            .unwrap_or(value)
+            .to_string()
+    };
+    Some(CalendarEvent {
+        uid: find(props, "UID").map(|uid| uid.value.clone()).unwrap_or_default(),
+        title: find(props, "SUMMARY").map(|title| unescape(&title.value)).unwrap_or_default(),
+        start_unix_ms,
+        end_unix_ms,
+        organizer: find(props, "ORGANIZER").map(|organizer| person(&organizer.value)),
+        attendees: props
+            .iter()
+            .filter(|prop| prop.name == "ATTENDEE")
+            .map(|attendee| person(&attendee.value))
+            .collect(),
+        location: find(props, "LOCATION").map(|location| unescape(&location.value)),
+    })
+}
+
+/// Start times of a recurring event that can fall in `from_ms..to_ms`:
+/// DTSTART, the RRULE's occurrences and any RDATEs, minus EXDATEs.
+fn occurrences(props: &[Property], times: &Times, from_ms: u64, to_ms: u64) -> Vec<u64> {
+    let Some(start) = find(props, "DTSTART") else {
+        return Vec::new();
+    };
+    let Some((local_start, utc)) = wall_clock(&start.value) else {
+        return Vec::new();
+    };
+    let zone = times.zone(start);
+    // occurrences keep their wall-clock time across DST changes
+    let to_utc = |local: i64| if utc { local } else { local - times.offset(local, zone) };
+    let mut starts = Vec::new();
+    match find(props, "RRULE").and_then(|rule| parse_rule(&rule.value)) {
+        Some(rule) => {
+            // offsets stay within a day of UTC
+            let from = (from_ms / 1000) as i64 - DAY_SECS;
+            let to = (to_ms / 1000) as i64 + DAY_SECS;
+            rule.expand(local_start, from, to, to_utc, |local| {
+                starts.extend(u64::try_from(to_utc(local)).ok().map(|secs| secs * 1000));
+            });
+        }
+        // a rule this parser can't follow counts once, at DTSTART
+        None => starts.extend(times.utc_ms(start)),
+    }
+    for rdate in props.iter().filter(|prop| prop.name == "RDATE") {
+        // a PERIOD value starts at the part before the slash
+        starts.extend(rdate.value.split(',').filter_map(|value| {
+            let value = value.split_once('/').map_or(value, |(start, _)| start);
+            times.value_ms(value, times.zone(rdate))
+        }));
+    }
+    let excluded: HashSet<u64> = props
+        .iter()
+        .filter(|prop| prop.name == "EXDATE")
+        .flat_map(|exdate| {
+            exdate
+                .value
+                .split(',')
+                .filter_map(move |value| times.value_ms(value, times.zone(exdate)))
+        })
+        .collect();
+    starts.retain(|start| !excluded.contains(start));
+    starts.sort_unstable();
+    starts.dedup();
+    starts
+}
+
+/// Turns property times into UTC. `Z` times carry their own zone, `TZID`
+/// times use the document's `VTIMEZONE` of that name, and floating times,
+/// or zones the document doesn't define, are UTC plus `utc_offset_minutes`.
+struct Times<'a> {
+    zones: &'a HashMap<String, Zone>,
+    utc_offset_minutes: i64,
+}
+
+impl Times<'_> {
+    fn zone(&self, prop: &Property) -> Option<&Zone> {
+        prop.tzid.as_ref().and_then(|tzid| self.zones.get(tzid))
+    }
+
+    /// Seconds east of UTC at wall-clock time `local` in `zone`.
+    fn offset(&self, local: i64, zone: Option<&Zone>) -> i64 {
+        zone.and_then(|zone| zone.offset(local))
+            .unwrap_or(self.utc_offset_minutes * 60)
+    }
+
+    fn utc_ms(&self, prop: &Property) -> Option<u64> {
+        self.value_ms(&prop.value, self.zone(prop))
+    }
+
+    fn value_ms(&self, value: &str, zone: Option<&Zone>) -> Option<u64> {
+        let (local, utc) = wall_clock(value)?;
+        let secs = if utc { local } else { local - self.offset(local, zone) };
+        u64::try_from(secs).ok().map(|secs| secs * 1000)
+    }
+}
+
+/// A `VTIMEZONE`: the offsets it switches between, and when.
+struct Zone {
+    observances: Vec<Observance>,
+}
+
+/// The `STANDARD` or `DAYLIGHT` part of a zone.
+struct Observance {
+    /// Wall-clock time of the first onset, in the offset before it.
+    start: i64,
+    offset_from: i64,
+    offset_to: i64,
+    rule: Option<Rule>,
+    rdates: Vec<i64>,
+}
+
+impl Zone {
+    /// The offset of the observance with the latest onset at or before
+    /// `local`; before any onset, the earliest one's `TZOFFSETFROM`.
+    fn offset(&self, local: i64) -> Option<i64> {
+        let mut current: Option<(i64, i64)> = None;
+        for observance in &self.observances {
+            let mut onsets = observance.rdates.clone();
+            match &observance.rule {
+                Some(rule) => {
+                    let to_utc = |onset: i64| onset - observance.offset_from;
+                    rule.expand(observance.start, local - DAY_SECS * 366, local, to_utc, |onset| onsets.push(onset));
+                }
+                None => onsets.push(observance.start),
+            }
+            let latest = onsets.into_iter().filter(|&onset| onset <= local).max();
+            if let Some(onset) = latest.filter(|&onset| current.is_none_or(|(at, _)| onset > at)) {
+                current = Some((onset, observance.offset_to));
+            }
+        }
+        current.map(|(_, offset)| offset).or_else(|| {
+            self.observances
+                .iter()
+                .min_by_key(|observance| observance.start)
+                .map(|observance| observance.offset_from)
+        })
+    }
+}
+
+fn observance(props: &[Property]) -> Option<Observance> {
+    let (start, _) = wall_clock(&find(props, "DTSTART")?.value)?;
+    Some(Observance {
+        start,
+        offset_from: utc_offset(&find(props, "TZOFFSETFROM")?.value)?,
+        offset_to: utc_offset(&find(props, "TZOFFSETTO")?.value)?,
+        rule: find(props, "RRULE").and_then(|rule| parse_rule(&rule.value)),
+        rdates: props
+            .iter()
+            .filter(|prop| prop.name == "RDATE")
+            .flat_map(|rdate| rdate.value.split(','))
+            .filter_map(|value| wall_clock(value).map(|(onset, _)| onset))
+            .collect(),
+    })
+}
+
+/// `+0200`, `-0530` or `+013045` as seconds east of UTC.
+fn utc_offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, digits) = match value.strip_prefix('+') {
+        Some(digits) => (1, digits),
+        None => (-1, value.strip_prefix('-')?),
+    };
+    if digits.len() != 4 && digits.len() != 6 {
+        return None;
+    }
+    let field = |at: usize| digits.get(at..at + 2)?.parse::<i64>().ok();
+    let seconds = if digits.len() == 6 { field(4)? } else { 0 };
+    Some(sign * (field(0)? * 3600 + field(2)? * 60 + seconds))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Until {
+    Utc(i64),
+    /// Wall-clock, for floating and date-only `UNTIL`s.
+    Local(i64),
+}
+
+/// The parts of an RRULE this parser follows. Weekdays count from Monday
+/// as 0.
+#[derive(Debug, Clone)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<Until>,
+    week_start: i64,
+    by_month: Vec<i64>,
+    by_month_day: Vec<i64>,
+    /// `MO`, `2TU` or `-1FR`: a weekday, optionally the nth in the month or
+    /// year.
+    by_day: Vec<(Option<i64>, i64)>,
+    by_set_pos: Vec<i64>,
+}
+
+/// `None` for rules with parts that aren't followed, e.g. `FREQ=HOURLY` or
+/// `BYWEEKNO`.
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut frequency = None;
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        week_start: 0,
+        by_month: Vec::new(),
+        by_month_day: Vec::new(),
+        by_day: Vec::new(),
+        by_set_pos: Vec::new(),
+    };
+    for part in value.trim().split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=')?;
+        let numbers = || value.split(',').map(|item| item.trim().parse::<i64>().ok()).collect::<Option<Vec<_>>>();
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => rule.interval = value.parse::<i64>().ok()?.max(1),
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => {
+                rule.until = Some(match wall_clock(value) {
+                    Some((secs, true)) => Until::Utc(secs),
+                    Some((secs, false)) => Until::Local(secs),
+                    // a date includes the whole day
+                    None => Until::Local(date_days(value.trim())? * DAY_SECS + DAY_SECS - 1),
+                })
+            }
+            "WKST" => rule.week_start = weekday_code(value)?,
+            "BYMONTH" => rule.by_month = numbers()?,
+            "BYMONTHDAY" => rule.by_month_day = numbers()?,
+            "BYSETPOS" => rule.by_set_pos = numbers()?,
+            "BYDAY" => {
+                rule.by_day = value
+                    .split(',')
+                    .map(|item| {
+                        let item = item.trim();
+                        let split = item.len().checked_sub(2)?;
+                        let (ordinal, day) = (item.get(..split)?, item.get(split..)?);
+                        let ordinal = match ordinal {
+                            "" => None,
+                            ordinal => Some(ordinal.trim_start_matches('+').parse().ok()?),
+                        };
+                        Some((ordinal, weekday_code(day)?))
+                    })
+                    .collect::<Option<_>>()?
+            }
+            _ => return None,
+        }
+    }
+    rule.frequency = frequency?;
+    Some(rule)
+}
+
+impl Rule {
+    /// Calls `visit` with `start` and every later occurrence in a period that
+    /// overlaps `from..=to`, all wall-clock seconds. `to_utc` places
+    /// occurrences for a UTC `UNTIL`. Without `COUNT`, earlier periods can't
+    /// change the result and are skipped.
+    fn expand(&self, start: i64, from: i64, to: i64, to_utc: impl Fn(i64) -> i64, mut visit: impl FnMut(i64)) {
+        let start_day = start.div_euclid(DAY_SECS);
+        let time = start.rem_euclid(DAY_SECS);
+        let (year, month, day) = daily_summary::civil_from_days(start_day);
+        let week = start_day - (weekday(start_day) - self.week_start).rem_euclid(7);
+        let mut remaining = self.count.unwrap_or(usize::MAX);
+        // DTSTART is the first occurrence whether or not the rule matches it
+        visit(start);
+        remaining = remaining.saturating_sub(1);
+
+        let skip_to = match self.until {
+            Some(Until::Utc(until) | Until::Local(until)) => from.min(until),
+            None => from,
+        }
+        .div_euclid(DAY_SECS)
+            - 1;
+        let first_period = match (self.count, self.frequency) {
+            (Some(_), _) => 0,
+            (None, Frequency::Daily) => (skip_to - start_day).div_euclid(self.interval),
+            (None, Frequency::Weekly) => (skip_to - week).div_euclid(7 * self.interval),
+            (None, Frequency::Monthly) => {
+                let (skip_year, skip_month, _) = daily_summary::civil_from_days(skip_to);
+                (skip_year * 12 + skip_month - year * 12 - month).div_euclid(self.interval)
+            }
+            (None, Frequency::Yearly) => (daily_summary::civil_from_days(skip_to).0 - year).div_euclid(self.interval),
+        }
+        .max(0);
+
+        for period in first_period.. {
+            let step = period * self.interval;
+            let (first_day, days) = match self.frequency {
+                Frequency::Daily => (start_day + step, vec![start_day + step]),
+                Frequency::Weekly => {
+                    let first = week + 7 * step;
+                    let days = (first..first + 7)
+                        .filter(|&candidate| !self.by_day.is_empty() || weekday(candidate) == weekday(start_day))
+                        .collect();
+                    (first, days)
+                }
+                Frequency::Monthly => {
+                    let index = year * 12 + month - 1 + step;
+                    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+                    (daily_summary::days_from_civil(year, month, 1), self.month_days(year, month, day))
+                }
+                Frequency::Yearly => {
+                    let year = year + step;
+                    (daily_summary::days_from_civil(year, 1, 1), self.year_days(year, month, day))
+                }
+            };
+            if first_day > to.div_euclid(DAY_SECS) {
+                return;
+            }
+            let days: Vec<i64> = days.into_iter().filter(|&candidate| self.keeps(candidate)).collect();
+            let days = if self.by_set_pos.is_empty() {
+                days
+            } else {
+                self.by_set_pos
+                    .iter()
+                    .filter_map(|&position| {
+                        let index = if position > 0 { position - 1 } else { days.len() as i64 + position };
+                        usize::try_from(index).ok().and_then(|index| days.get(index).copied())
+                    })
+                    .collect()
+            };
+            let mut occurrences: Vec<i64> = days.into_iter().map(|day| day * DAY_SECS + time).collect();
+            occurrences.sort_unstable();
+            for occurrence in occurrences.into_iter().filter(|&occurrence| occurrence > start) {
+                let ended = match self.until {
+                    Some(Until::Utc(until)) => to_utc(occurrence) > until,
+                    Some(Until::Local(until)) => occurrence > until,
+                    None => false,
+                };
+                if ended || remaining == 0 {
+                    return;
+                }
+                visit(occurrence);
+                remaining -= 1;
+            }
+        }
+    }
+
+    /// The days of a month picked by BYMONTHDAY or BYDAY, or the start's
+    /// day of the month when neither is set.
+    fn month_days(&self, year: i64, month: i64, start_day_of_month: i64) -> Vec<i64> {
+        let first = daily_summary::days_from_civil(year, month, 1);
+        let days: Vec<i64> = (first..first + month_len(year, month)).collect();
+        if !self.by_month_day.is_empty() {
+            days
+        } else if !self.by_day.is_empty() {
+            select_by_day(&days, &self.by_day)
+        } else {
+            days.into_iter()
+                .filter(|&day| day - first + 1 == start_day_of_month)
+                .collect()
+        }
+    }
+
+    /// The days of a year the rule picks: per month when BYMONTH or
+    /// BYMONTHDAY is set, BYDAY across the whole year otherwise, or the
+    /// start's anniversary.
+    fn year_days(&self, year: i64, start_month: i64, start_day_of_month: i64) -> Vec<i64> {
+        if !self.by_month.is_empty() || !self.by_month_day.is_empty() {
+            let months = if self.by_month.is_empty() { (1..=12).collect() } else { self.by_month.clone() };
+            months
+                .into_iter()
+                .filter(|month| (1..=12).contains(month))
+                .flat_map(|month| self.month_days(year, month, start_day_of_month))
+                .collect()
+        } else if !self.by_day.is_empty() {
+            let first = daily_summary::days_from_civil(year, 1, 1);
+            let days: Vec<i64> = (first..daily_summary::days_from_civil(year + 1, 1, 1)).collect();
+            select_by_day(&days, &self.by_day)
+        } else {
+            self.month_days(year, start_month, start_day_of_month)
+        }
+    }
+
+    /// BYMONTH, BYMONTHDAY and the BYDAY weekdays as filters.
+    fn keeps(&self, day: i64) -> bool {
+        let (year, month, day_of_month) = daily_summary::civil_from_days(day);
+        let from_end = day_of_month - month_len(year, month) - 1;
+        (self.by_month.is_empty() || self.by_month.contains(&month))
+            && (self.by_month_day.is_empty()
+                || self.by_month_day.iter().any(|&wanted| wanted == day_of_month || wanted == from_end))
+            && (self.by_day.is_empty() || self.by_day.iter().any(|&(_, wanted)| wanted == weekday(day)))
+    }
+}
+
+/// `days`, a whole month or year, narrowed to the BYDAY weekdays, with
+/// `2TU` the second Tuesday and `-1FR` the last Friday in it.
+fn select_by_day(days: &[i64], by_day: &[(Option<i64>, i64)]) -> Vec<i64> {
+    let (Some(&first), Some(&last)) = (days.first(), days.last()) else {
+        return Vec::new();
+    };
+    days.iter()
+        .copied()
+        .filter(|&day| {
+            by_day.iter().any(|&(ordinal, wanted)| {
+                weekday(day) == wanted
+                    && match ordinal {
+                        None => true,
+                        Some(n) if n > 0 => (day - first) / 7 + 1 == n,
+                        Some(n) => (last - day) / 7 + 1 == -n,
+                    }
+            })
+        })
+        .collect()
+}
+
+fn month_len(year: i64, month: i64) -> i64 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    daily_summary::days_from_civil(next_year, next_month, 1) - daily_summary::days_from_civil(year, month, 1)
+}
+
+/// Monday is 0; 1970-01-01 was a Thursday.
+fn weekday(day: i64) -> i64 {
+    (day + 3).rem_euclid(7)
+}
+
+fn weekday_code(code: &str) -> Option<i64> {
+    ["MO", "TU", "WE", "TH", "FR", "SA", "SU"]
+        .iter()
+        .position(|weekday| weekday.eq_ignore_ascii_case(code.trim()))
+        .map(|index| index as i64)
+}
+
+/// `YYYYMMDDTHHMMSS[Z]` as wall-clock seconds since 1970, and whether it is
+/// UTC; all-day dates aren't meetings to record.
+fn wall_clock(value: &str) -> Option<(i64, bool)> {
+    let (date, time) = value.trim().split_once('T')?;
+    let (time, utc) = match time.strip_suffix('Z') {
+        Some(time) => (time, true),
+        None => (time, false),
+    };
+    let field = |at: usize| time.get(at..at + 2)?.parse::<i64>().ok();
+    let secs = field(0)? * 3600 + field(2)? * 60 + field(4)?;
+    Some((date_days(date)? * DAY_SECS + secs, utc))
+}
+
+/// `YYYYMMDD` as days since 1970-01-01.
+fn date_days(date: &str) -> Option<i64> {
+    if date.len() != 8 {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(daily_summary::days_from_civil(year, month, day))
+}
+
+/// `P1W`, `PT1H30M`, `P1DT2H`; signs aren't meaningful for an event length.
+fn ics_duration_ms(value: &str) -> Option<u64> {
+    let value = value.trim().trim_start_matches(['+', '-']).strip_prefix('P')?;
+    let mut total_secs = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: u64 = number.parse().ok()?;
+                number.clear();
+                total_secs += n * match unit {
+                    'W' => 7 * 86_400,
+                    'D' => 86_400,
+                    'H' => 3600,
+                    'M' => 60,
+                    'S' => 1,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total_secs * 1000)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW_YORK: &str = "BEGIN:VTIMEZONE
+TZID:America/New_York
+BEGIN:DAYLIGHT
+TZOFFSETFROM:-0500
+TZOFFSETTO:-0400
+DTSTART:19700308T020000
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU
+END:DAYLIGHT
+BEGIN:STANDARD
+TZOFFSETFROM:-0400
+TZOFFSETTO:-0500
+DTSTART:19701101T020000
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU
+END:STANDARD
+END:VTIMEZONE
+";
+
+    fn ms(value: &str) -> u64 {
+        let (secs, utc) = wall_clock(value).unwrap();
+        assert!(utc);
+        secs as u64 * 1000
+    }
+
+    fn calendar(body: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{body}END:VCALENDAR\r\n")
+    }
+
+    fn starts(events: &[CalendarEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.start_unix_ms).collect()
+    }
+
+    #[test]
+    fn parses_a_single_event() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r
+UID:1\r
+SUMMARY:Planning\\, Q3\r
+DTSTART:20240506T090000Z\r
+DURATION:PT1H30M\r
+ORGANIZER;CN=\"Lee: PM\":mailto:lee@example.com\r
+ATTENDEE:mailto:a@example.com\r
+ATTENDEE:MAILTO:b@example.com\r
+LOCATION:Room\r
+  4\r
+BEGIN:VALARM\r
+DESCRIPTION:reminder\r
+END:VALARM\r
+END:VEVENT\r
+",
+        );
+        let events = parse_ics(&ics, 0, 0, u64::MAX);
+        assert_eq!(
+            events,
+            vec![CalendarEvent {
+                uid: "1".into(),
+                title: "Planning, Q3".into(),
+                start_unix_ms: ms("20240506T090000Z"),
+                end_unix_ms: ms("20240506T103000Z"),
+                organizer: Some("lee@example.com".into()),
+                attendees: vec!["a@example.com".into(), "b@example.com".into()],
+                location: Some("Room 4".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_cancelled_all_day_and_out_of_window_events() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:cancelled
+STATUS:CANCELLED
+DTSTART:20240506T090000Z
+DTEND:20240506T100000Z
+END:VEVENT
+BEGIN:VEVENT
+UID:all-day
+DTSTART;VALUE=DATE:20240506
+DTEND;VALUE=DATE:20240507
+END:VEVENT
+BEGIN:VEVENT
+UID:over
+DTSTART:20240506T070000Z
+DTEND:20240506T080000Z
+END:VEVENT
+BEGIN:VEVENT
+UID:running
+DTSTART:20240506T080000Z
+DTEND:20240506T093000Z
+END:VEVENT
+",
+        );
+        let events = parse_ics(&ics, 0, ms("20240506T090000Z"), ms("20240507T090000Z"));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].uid, "running");
+    }
+
+    #[test]
+    fn floating_and_unknown_zones_use_the_configured_offset() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:floating
+DTSTART:20240506T090000
+DTEND:20240506T100000
+END:VEVENT
+BEGIN:VEVENT
+UID:unknown
+DTSTART;TZID=Mars/Olympus:20240506T110000
+DTEND;TZID=Mars/Olympus:20240506T120000
+END:VEVENT
+",
+        );
+        let events = parse_ics(&ics, 120, 0, u64::MAX);
+        assert_eq!(starts(&events), vec![ms("20240506T070000Z"), ms("20240506T090000Z")]);
+    }
+
+    #[test]
+    fn follows_the_zone_across_daylight_saving() {
+        let ics = calendar(&format!(
+            "{NEW_YORK}BEGIN:VEVENT
+UID:standup
+DTSTART;TZID=America/New_York:20240101T090000
+DTEND;TZID=America/New_York:20240101T091500
+RRULE:FREQ=MONTHLY;BYMONTHDAY=1
+END:VEVENT
+"
+        ));
+        let window = |from: &str, to: &str| starts(&parse_ics(&ics, 0, ms(from), ms(to)));
+        assert_eq!(window("20240301T000000Z", "20240302T000000Z"), vec![ms("20240301T140000Z")]);
+        assert_eq!(window("20240401T000000Z", "20240402T000000Z"), vec![ms("20240401T130000Z")]);
+        assert_eq!(window("20241201T000000Z", "20241202T000000Z"), vec![ms("20241201T140000Z")]);
+    }
+
+    #[test]
+    fn expands_weekly_rules_without_excluded_or_moved_instances() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:sync
+SUMMARY:Sync
+DTSTART:20240506T090000Z
+DTEND:20240506T093000Z
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6
+EXDATE:20240508T090000Z
+END:VEVENT
+BEGIN:VEVENT
+UID:sync
+SUMMARY:Sync (moved)
+RECURRENCE-ID:20240513T090000Z
+DTSTART:20240514T100000Z
+DTEND:20240514T103000Z
+END:VEVENT
+BEGIN:VEVENT
+UID:sync
+RECURRENCE-ID:20240515T090000Z
+STATUS:CANCELLED
+DTSTART:20240515T090000Z
+DTEND:20240515T093000Z
+END:VEVENT
+",
+        );
+        let mut events = parse_ics(&ics, 0, 0, u64::MAX);
+        events.sort_by_key(|event| event.start_unix_ms);
+        assert_eq!(
+            starts(&events),
+            vec![
+                ms("20240506T090000Z"),
+                ms("20240514T100000Z"),
+                ms("20240520T090000Z"),
+                ms("20240522T090000Z"),
+            ]
+        );
+        assert_eq!(events[1].title, "Sync (moved)");
+        assert!(events.iter().all(|event| event.end_unix_ms - event.start_unix_ms == 30 * 60 * 1000));
+    }
+
+    #[test]
+    fn expands_ordinal_weekdays_and_set_positions() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:retro
+DTSTART:20240126T150000Z
+DTEND:20240126T160000Z
+RRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20240430T000000Z
+END:VEVENT
+BEGIN:VEVENT
+UID:report
+DTSTART:20240131T080000Z
+DTEND:20240131T083000Z
+RRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=3
+END:VEVENT
+",
+        );
+        let events = parse_ics(&ics, 0, 0, u64::MAX);
+        let of = |uid: &str| {
+            let mut starts: Vec<u64> = events.iter().filter(|event| event.uid == uid).map(|event| event.start_unix_ms).collect();
+            starts.sort_unstable();
+            starts
+        };
+        assert_eq!(
+            of("retro"),
+            vec![
+                ms("20240126T150000Z"),
+                ms("20240223T150000Z"),
+                ms("20240329T150000Z"),
+                ms("20240426T150000Z"),
+            ]
+        );
+        assert_eq!(
+            of("report"),
+            vec![ms("20240131T080000Z"), ms("20240229T080000Z"), ms("20240329T080000Z")]
+        );
+    }
+
+    #[test]
+    fn only_expands_open_ended_rules_up_to_the_window() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:daily
+DTSTART:20200101T120000Z
+DTEND:20200101T121500Z
+RRULE:FREQ=DAILY;INTERVAL=2
+END:VEVENT
+",
+        );
+        let events = parse_ics(&ics, 0, ms("20240506T000000Z"), ms("20240510T000000Z"));
+        assert_eq!(
+            starts(&events),
+            vec![ms("20240507T120000Z"), ms("20240509T120000Z")]
+        );
+    }
+
+    #[test]
+    fn unsupported_rules_count_once() {
+        let ics = calendar(
+            "BEGIN:VEVENT
+UID:hourly
+DTSTART:20240506T090000Z
+DTEND:20240506T091000Z
+RRULE:FREQ=HOURLY
+END:VEVENT
+",
+        );
+        assert_eq!(starts(&parse_ics(&ics, 0, 0, u64::MAX)), vec![ms("20240506T090000Z")]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::muxer::make;
+
+/// Captions longer than this keep only their end, which is the newest speech.
+const MAX_CAPTION_CHARS: usize = 160;
+/// Timed captions ending this long before the newest one are forgotten; a
+/// recording writer is never that far behind the transcripts.
+const CUE_RETENTION_NANOS: u128 = 5 * 60 * 1_000_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptionConfig {
+    /// Also draw the captions into the recording, not only the preview.
+    #[serde(default)]
+    pub burn_in: bool,
+    /// How long a caption stays up when no newer one replaces it.
+    #[serde(default = "CaptionConfig::default_hold_ms")]
+    pub hold_ms: u64,
+    /// Pango font description, e.g. `Sans 24`.
+    #[serde(default)]
+    pub font: Option<String>,
+}
+
+impl CaptionConfig {
+    pub const fn default_hold_ms() -> u64 {
+        6_000
+    }
+}
+
+/// A transcript pinned to the wall-clock span of the chunk it came from.
+struct Cue {
+    start_unix_nanos: u128,
+    end_unix_nanos: u128,
+    text: String,
+}
+
+#[derive(Default)]
+struct CaptionState {
+    text: String,
+    shown_until: Option<Instant>,
+    cues: Vec<Cue>,
+}
+
+/// How an overlay decides which caption a frame gets.
+#[derive(Debug, Clone, Copy)]
+pub enum CaptionTiming {
+    /// The newest transcript, from when it arrives; for the live preview.
+    Live,
+    /// The transcript of the audio at the frame's own time, from its PTS
+    /// counted from `origin_unix_nanos`; for recordings, so text lands on
+    /// the frames it belongs to.
+    Pts { origin_unix_nanos: u128 },
+}
+
+/// The captions to show, written by the annotator thread as transcripts come
+/// in and read by the `textoverlay` of every writer that shows captions.
+pub struct LiveCaptions {
+    config: CaptionConfig,
+    state: Mutex<CaptionState>,
+}
+
+impl LiveCaptions {
+    pub fn new(config: CaptionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn burn_in(&self) -> bool {
+        self.config.burn_in
+    }
+
+    /// Shows the transcript of the chunk starting at `start_unix_nanos` and
+    /// lasting `duration_ms`. Timed overlays show it over that span, held for
+    /// at least `hold_ms`.
+    pub fn show(&self, text: &str, start_unix_nanos: u128, duration_ms: u64) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let chars = text.chars().count();
+        let text: String = text.chars().skip(chars.saturating_sub(MAX_CAPTION_CHARS)).collect();
+        let shown_nanos = u128::from(duration_ms.max(self.config.hold_ms)) * 1_000_000;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.cues.retain(|cue| cue.end_unix_nanos + CUE_RETENTION_NANOS >= start_unix_nanos);
+        state.cues.push(Cue {
+            start_unix_nanos,
+            end_unix_nanos: start_unix_nanos + shown_nanos,
+            text: text.clone(),
+        });
+        state.text = text;
+        state.shown_until = Some(Instant::now() + Duration::from_millis(self.config.hold_ms));
+    }
+
+    fn current(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.shown_until {
+            Some(until) if Instant::now() < until => state.text.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// The caption for a frame at `unix_nanos`: the latest-starting cue that
+    /// covers it, so a newer transcript replaces a held one.
+    fn at(&self, unix_nanos: u128) -> String {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .cues
+            .iter()
+            .filter(|cue| cue.start_unix_nanos <= unix_nanos && unix_nanos < cue.end_unix_nanos)
+            .max_by_key(|cue| cue.start_unix_nanos)
+            .map(|cue| cue.text.clone())
+            .unwrap_or_default()
+    }
+
+    /// A `textoverlay` that follows the captions; each frame through it
+    /// picks up its caption as `timing` says.
+    pub fn overlay(self: &Arc<Self>, name: String, timing: CaptionTiming) -> Result<gst::Element> {
+        let overlay = make("textoverlay", name)?;
+        overlay.set_property_from_str("valignment", "bottom");
+        overlay.set_property_from_str("halignment", "center");
+        overlay.set_property_from_str("wrap-mode", "wordchar");
+        overlay.set_property("shaded-background", true);
+        if let Some(font) = &self.config.font {
+            overlay.set_property("font-desc", font);
+        }
+        let sink_pad = overlay
+            .static_pad("video_sink")
+            .ok_or_else(|| anyhow!("textoverlay has no video_sink pad"))?;
+        let captions = Arc::clone(self);
+        let weak_overlay = overlay.downgrade();
+        let shown = Mutex::new(String::new());
+        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let text = match timing {
+                CaptionTiming::Live => captions.current(),
+                CaptionTiming::Pts { origin_unix_nanos } => match info.buffer().and_then(|buffer| buffer.pts()) {
+                    Some(pts) => captions.at(origin_unix_nanos + u128::from(pts.nseconds())),
+                    None => String::new(),
+                },
+            };
+            let mut shown = shown.lock().unwrap_or_else(PoisonError::into_inner);
+            if *shown != text {
+                if let Some(overlay) = weak_overlay.upgrade() {
+                    overlay.set_property("text", &text);
+                }
+                *shown = text;
+            }
+            gst::PadProbeReturn::Ok
+        });
+        Ok(overlay)
+    }
+}
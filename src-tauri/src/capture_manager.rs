@@ -1,4 +1,8 @@
-use std::sync::{mpsc, Arc, Mutex};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
@@ -6,16 +10,82 @@ use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
 use gstreamer_audio as gst_audio;
+use gstreamer_video as gst_video;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use crate::acks::AckTracker;
+use crate::adaptive::LoadStats;
+use crate::anchors::{AnchorLog, ChunkAnchor, ClockAnchor, SessionClock};
+use crate::annotations::{self, AnnotatorConfig, AnnotatorQueue, ChunkAnnotator, SessionAnnotations};
+use crate::audio_ring::{AudioRing, PcmFormat, RecentAudio};
+use crate::blackout::BlackoutState;
+use crate::browser::{BrowserTab, BrowserTimeline};
+use crate::calendar::CalendarEvent;
+use crate::captions::{CaptionConfig, LiveCaptions};
+use crate::chunk_map::{ChunkMap, ChunkSpan};
+use crate::clipboard::{ClipboardConfig, ClipboardLog};
+use crate::compression;
+use crate::consent::{self, ConsentConfig};
+use crate::devices;
+#[cfg(feature = "diarization")]
+use crate::diarization::{DiarizationConfig, Diarizer};
+use crate::disk_writer::{DiskWriteSettings, DiskWriter};
+use crate::displays::{DisplayLostAction, DisplayTracker};
+use crate::escalation::{EscalationSettings, EscalationState};
+use crate::events::{CaptureEvent, EventBus};
+use crate::focus::{self, FocusedWindow, WindowTimeline};
+use crate::frame_hash::{self, FrameHash, FrameHashes};
+use crate::frames::{CapturedFrame, FrameFormat, LatestFrame};
+use crate::highlights::{self, ActivityLog};
+use crate::geometry::{self, AspectMode, AspectPreset, Rect};
+use crate::gst_log;
+use crate::hdr::{self, ColorInfo, HdrMode};
+use crate::heartbeat::SampleClock;
+use crate::idle::{ActivityTracker, IdleAction};
+#[cfg(target_os = "linux")]
+use crate::inhibit::{self, Inhibitor};
+use crate::input::{InputActivity, InputCounts};
+use crate::latency::{self, LatencyTracker, SampleTimes};
+use crate::layout::{self, LayoutConfig, LiveLayout, WindowTiling};
+use crate::memory::{self, MemoryReport, MemoryStats};
+use crate::moments::{self, MomentLog, MomentSettings};
+use crate::muxer::{EncoderSettings, RollingMkvWriter, SharedRecording};
+#[cfg(feature = "ndi")]
+use crate::ndi;
+use crate::overlap;
+use crate::overrides::{self, ElementOverrides};
+#[cfg(target_os = "linux")]
+use crate::portal::{self, ScreenCastSession};
+use crate::platform;
+use crate::policy::{self, AppPolicy, PolicyAction, PolicyState};
+use crate::power::BatteryPolicy;
+use crate::preview::{self, PreviewPlayback};
+use crate::privacy::{self, PrivacyFilter};
+use crate::proxy::{self, ProxyConfig, ProxyLinks};
+use crate::screen_lock::{LockAction, LockTracker};
+use crate::session::{self, ChunkStats, SessionManifest, SessionSummary, TrackInfo, TrackMedia};
+use crate::shm;
+use crate::sinks::{ChunkTaps, SinkConfig, SinkContext, SinkHandle};
+pub use crate::state_machine::CaptureState;
+use crate::state_machine::{StateMachine, Transition};
+use crate::stitch::GapTracker;
+use crate::threading::ThreadTuning;
+use crate::translation::{TranslationAnnotator, TranslationConfig};
+use crate::uploads::UploadSpool;
+#[cfg(target_os = "linux")]
+use crate::wlr_screencopy::{self, Screencopy};
+
 static GSTREAMER: OnceCell<()> = OnceCell::new();
 
-fn ensure_gstreamer_initialized() -> Result<()> {
+pub(crate) const DEBUG_OUTPUT_DIR: &str = "debug_output";
+
+pub(crate) fn ensure_gstreamer_initialized() -> Result<()> {
     GSTREAMER
         .get_or_try_init(|| {
             gst::init()?;
+            gst_log::install();
             Ok(())
         })
         .map(|_| ())
@@ -26,6 +96,28 @@ fn ensure_gstreamer_initialized() -> Result<()> {
 pub enum CaptureTarget {
     FullDisplay,
     Window { id: String },
+    /// Several windows by PipeWire node id, tiled into one picture so only
+    /// they are recorded.
+    Windows {
+        ids: Vec<String>,
+        #[serde(default)]
+        tiling: WindowTiling,
+        #[serde(default = "LayoutConfig::default_width")]
+        width: i32,
+        #[serde(default = "LayoutConfig::default_height")]
+        height: i32,
+    },
+    /// A V4L2 capture card or other video device, e.g. `/dev/video2`.
+    Device { path: String },
+    /// Receive an NDI stream by its advertised name.
+    #[cfg(feature = "ndi")]
+    Ndi { source: String },
+    /// Whichever window has focus, switched to once focus has rested on it
+    /// for `min_dwell_ms`.
+    FollowFocus {
+        #[serde(default = "CaptureTarget::default_min_dwell_ms")]
+        min_dwell_ms: u64,
+    },
 }
 
 impl Default for CaptureTarget {
@@ -34,25 +126,479 @@ impl Default for CaptureTarget {
     }
 }
 
+impl CaptureTarget {
+    pub const fn default_min_dwell_ms() -> u64 {
+        3_000
+    }
+
+    /// Key of this target in [`CaptureOptions::target_settings`], e.g.
+    /// `full_display`, `window:42`, `windows:42,57` or `device:/dev/video2`.
+    pub fn key(&self) -> String {
+        match self {
+            CaptureTarget::FullDisplay => "full_display".to_string(),
+            CaptureTarget::Window { id } => format!("window:{id}"),
+            CaptureTarget::Windows { ids, .. } => format!("windows:{}", ids.join(",")),
+            CaptureTarget::Device { path } => format!("device:{path}"),
+            #[cfg(feature = "ndi")]
+            CaptureTarget::Ndi { source } => format!("ndi:{source}"),
+            CaptureTarget::FollowFocus { .. } => "follow_focus".to_string(),
+        }
+    }
+}
+
+/// Video settings for one capture target; unset fields keep the defaults.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TargetSettings {
+    #[serde(default)]
+    pub framerate: Option<u32>,
+    /// Output size as a percentage of the target's native size.
+    #[serde(default)]
+    pub scale_percent: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioChannels {
+    /// Both channels mixed down to one; half the data to store or transcribe.
+    Mono,
+    #[default]
+    Stereo,
+}
+
+/// Sample format of the raw audio chunks, little-endian and interleaved.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSampleFormat {
+    /// 32-bit float.
+    #[default]
+    F32le,
+    /// 16-bit signed integer, what most speech models take.
+    S16le,
+}
+
+impl AudioSampleFormat {
+    /// The GStreamer format name, as reported in chunk metadata.
+    fn gst_name(self) -> &'static str {
+        match self {
+            AudioSampleFormat::F32le => "F32LE",
+            AudioSampleFormat::S16le => "S16LE",
+        }
+    }
+}
+
+/// Raw audio format of the system audio and mic chunks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AudioSettings {
+    #[serde(default)]
+    pub format: AudioSampleFormat,
+    #[serde(default)]
+    pub channels: AudioChannels,
+    /// Clamped to 8–192kHz.
+    #[serde(default = "AudioSettings::default_sample_rate")]
+    pub sample_rate: u32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            format: AudioSampleFormat::default(),
+            channels: AudioChannels::default(),
+            sample_rate: Self::default_sample_rate(),
+        }
+    }
+}
+
+impl AudioSettings {
+    pub const fn default_sample_rate() -> u32 {
+        48_000
+    }
+
+    fn caps(&self) -> gst::Caps {
+        let channels = match self.channels {
+            AudioChannels::Mono => 1i32,
+            AudioChannels::Stereo => 2i32,
+        };
+        gst::Caps::builder("audio/x-raw")
+            .field("format", &self.format.gst_name())
+            .field("rate", &(self.sample_rate.clamp(8_000, 192_000) as i32))
+            .field("channels", &channels)
+            .build()
+    }
+}
+
+/// How hard a session works. `Ambient` is for all-day background capture:
+/// a frame or two per second, encoded at a low bitrate.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureProfile {
+    #[default]
+    Full,
+    Ambient,
+}
+
+/// Video settings of the ambient profile. Resolution is kept by default,
+/// since text must stay legible for OCR; frame rate and bitrate give way.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AmbientSettings {
+    #[serde(default = "AmbientSettings::default_framerate")]
+    pub framerate: u32,
+    #[serde(default = "AmbientSettings::default_scale_percent")]
+    pub scale_percent: u32,
+    /// Bitrate of the recording's video encoder.
+    #[serde(default = "AmbientSettings::default_video_bitrate_kbps")]
+    pub video_bitrate_kbps: u32,
+}
+
+impl Default for AmbientSettings {
+    fn default() -> Self {
+        Self {
+            framerate: Self::default_framerate(),
+            scale_percent: Self::default_scale_percent(),
+            video_bitrate_kbps: Self::default_video_bitrate_kbps(),
+        }
+    }
+}
+
+impl AmbientSettings {
+    pub const fn default_framerate() -> u32 {
+        1
+    }
+
+    pub const fn default_scale_percent() -> u32 {
+        100
+    }
+
+    pub const fn default_video_bitrate_kbps() -> u32 {
+        250
+    }
+}
+
+/// Appsink and queue sizing of the capture pipelines. Bigger buffers ride
+/// out stalls on slow machines at the cost of latency and memory.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BufferingSettings {
+    /// Frames the video appsink holds for the chunk buffer; 0 is unlimited.
+    #[serde(default = "BufferingSettings::default_video_max_buffers")]
+    pub video_max_buffers: u32,
+    /// Buffers each audio appsink holds; 0 is unlimited.
+    #[serde(default = "BufferingSettings::default_audio_max_buffers")]
+    pub audio_max_buffers: u32,
+    /// Drop the oldest buffer when an appsink is full. Without it nothing is
+    /// lost, but a stall backs up into the source and delays capture.
+    #[serde(default = "BufferingSettings::default_drop")]
+    pub drop: bool,
+    /// Limits of the queue in front of the video appsink, which exists when
+    /// NDI or shared-memory output is on; GStreamer's defaults when unset.
+    #[serde(default)]
+    pub queue_max_buffers: Option<u32>,
+    #[serde(default)]
+    pub queue_max_bytes: Option<u32>,
+    #[serde(default)]
+    pub queue_max_time_ms: Option<u64>,
+}
+
+impl Default for BufferingSettings {
+    fn default() -> Self {
+        Self {
+            video_max_buffers: Self::default_video_max_buffers(),
+            audio_max_buffers: Self::default_audio_max_buffers(),
+            drop: Self::default_drop(),
+            queue_max_buffers: None,
+            queue_max_bytes: None,
+            queue_max_time_ms: None,
+        }
+    }
+}
+
+impl BufferingSettings {
+    pub const fn default_video_max_buffers() -> u32 {
+        5
+    }
+
+    pub const fn default_audio_max_buffers() -> u32 {
+        20
+    }
+
+    pub const fn default_drop() -> bool {
+        true
+    }
+
+    fn configure_appsink(&self, appsink: &gst_app::AppSink, max_buffers: u32) {
+        appsink.set_property("max-buffers", &max_buffers);
+        appsink.set_property("drop", &self.drop);
+    }
+
+    fn configure_queue(&self, queue: &gst::Element) {
+        if let Some(buffers) = self.queue_max_buffers {
+            queue.set_property("max-size-buffers", buffers);
+        }
+        if let Some(bytes) = self.queue_max_bytes {
+            queue.set_property("max-size-bytes", bytes);
+        }
+        if let Some(time_ms) = self.queue_max_time_ms {
+            queue.set_property("max-size-time", time_ms * 1_000_000);
+        }
+    }
+}
+
+/// Per-stream gst-launch source descriptions, e.g. `"v4l2src device=/dev/video2"`
+/// for a capture card. Each must end in one unlinked src pad.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomSources {
+    #[serde(default)]
+    pub video: Option<String>,
+    #[serde(default)]
+    pub system_audio: Option<String>,
+    #[serde(default)]
+    pub mic: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaptureOptions {
     #[serde(default = "CaptureOptions::default_chunk_ms")]
     pub chunk_duration_ms: u64,
+    /// Each chunk also starts with this much of the end of the one before,
+    /// so speech or an action cut at a boundary is whole in one of them.
+    #[serde(default)]
+    pub overlap_ms: u64,
+    /// Chunk length for the audio streams, when they should be cut more
+    /// often than video; `None` uses `chunk_duration_ms`.
+    #[serde(default)]
+    pub audio_chunk_duration_ms: Option<u64>,
     #[serde(default)]
     pub capture_mic: bool,
+    /// Keep the last this-many ms (at most 30000) of mic PCM for
+    /// `read_recent_audio`, independent of chunking; 0 keeps none.
+    #[serde(default)]
+    pub audio_ring_ms: u64,
+    /// Channels and sample rate of both audio streams.
+    #[serde(default)]
+    pub audio: AudioSettings,
+    /// Appsink and queue sizes; the defaults suit most machines.
+    #[serde(default)]
+    pub buffering: BufferingSettings,
+    /// Profile the session starts in; `set_capture_profile` switches it.
+    #[serde(default)]
+    pub profile: CaptureProfile,
+    #[serde(default)]
+    pub ambient: AmbientSettings,
+    /// Steps an ambient session up to full while a meeting, a recent marker
+    /// or a listed app calls for it.
+    #[serde(default)]
+    pub escalation: Option<EscalationSettings>,
+    /// Keeps a frame, the mic ring and the focused window for each marker
+    /// under `moments/`; needs `record_mkv`.
+    #[serde(default)]
+    pub moments: Option<MomentSettings>,
     #[serde(default)]
     pub debug_save: bool,
+    /// Batching and direct IO for the `debug_save` files.
+    #[serde(default)]
+    pub debug_write: DiskWriteSettings,
     #[serde(default)]
     pub target: CaptureTarget,
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub idle_action: IdleAction,
+    #[serde(default)]
+    pub battery_policy: Option<BatteryPolicy>,
+    #[serde(default)]
+    pub adaptive_quality: bool,
+    #[serde(default)]
+    pub capture_thread_tuning: ThreadTuning,
+    #[serde(default)]
+    pub consumer_thread_tuning: ThreadTuning,
+    #[serde(default)]
+    pub record_mkv: bool,
+    #[serde(default)]
+    pub max_file_size_mb: Option<u64>,
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    #[serde(default = "CaptureOptions::default_gap_tolerance_ms")]
+    pub gap_tolerance_ms: u64,
+    /// Longest source stall bridged by repeating the last frame; longer
+    /// ones are left as a hole. `None` bridges stalls of any length.
+    #[serde(default)]
+    pub freeze_fill_max_ms: Option<u64>,
+    /// Advanced: properties to set on named pipeline elements.
+    #[serde(default)]
+    pub element_overrides: ElementOverrides,
+    #[serde(default)]
+    pub crop: Option<Rect>,
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270.
+    #[serde(default)]
+    pub rotation: Option<u16>,
+    /// Output aspect ratio, reached by padding or cropping per `aspect_mode`.
+    #[serde(default)]
+    pub aspect: Option<AspectPreset>,
+    #[serde(default)]
+    pub aspect_mode: AspectMode,
+    #[serde(default)]
+    pub hdr: HdrMode,
+    /// Scenes composing the screen with cameras and overlays.
+    #[serde(default)]
+    pub layout: Option<LayoutConfig>,
+    /// Advanced: gst-launch descriptions replacing the stock sources.
+    #[serde(default)]
+    pub custom_sources: CustomSources,
+    /// Extra outputs fed alongside `record_mkv`, each with its own encoder settings.
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// Cap for the session's network sinks, so they don't starve a live
+    /// call: the combined upload rate, and the RTMP encoder bitrates.
+    #[serde(default)]
+    pub upload_limit_kbps: Option<u64>,
+    /// Socket path of a `shmsink` exposing raw frames to other local processes.
+    #[serde(default)]
+    pub shm_output: Option<String>,
+    /// zstd level (1-22) for chunks leaving raw: debug saves and uploads.
+    #[serde(default)]
+    pub chunk_zstd_level: Option<i32>,
+    /// Sample the focused window into chunk metadata and the manifest.
+    #[serde(default)]
+    pub track_active_window: bool,
+    /// Count keystrokes, clicks and scrolls per chunk (never key contents).
+    #[serde(default)]
+    pub track_input_activity: bool,
+    /// Log clipboard changes into the manifest.
+    #[serde(default)]
+    pub clipboard: Option<ClipboardConfig>,
+    /// HTTP endpoints annotating chunks into the manifest.
+    #[serde(default)]
+    pub annotators: Vec<AnnotatorConfig>,
+    /// Emit a low-res `video_proxy` chunk next to every video chunk.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Store perceptual hashes of sampled frames per video chunk, for
+    /// `find_similar_frames`.
+    #[serde(default)]
+    pub frame_hashes: bool,
+    /// Also write the session as HLS, so it can be played back while it records.
+    #[serde(default)]
+    pub preview_playback: bool,
+    /// Consent flags stored in the manifest, optionally shown as a banner.
+    #[serde(default)]
+    pub consent: Option<ConsentConfig>,
+    /// Blur faces and PII in frames before they reach chunks or side outputs.
+    #[serde(default)]
+    pub privacy: Option<PrivacyFilter>,
+    /// Inhibit the screen saver and automatic suspend while recording.
+    #[serde(default = "CaptureOptions::default_keep_awake")]
+    pub keep_awake: bool,
+    /// Seconds to keep the ScreenCast portal session open after stopping,
+    /// so a start within them reuses it without asking or renegotiating.
+    /// `None` closes it at stop.
+    #[serde(default)]
+    pub keep_portal_session_secs: Option<u64>,
+    /// Pause capture, or tag chunks `screen_locked`, while the session is
+    /// locked; `None` records the lock screen like anything else.
+    #[serde(default)]
+    pub screen_lock_action: Option<LockAction>,
+    /// Pause, or restart on another display, when the captured display is
+    /// disconnected; `None` only reports it.
+    #[serde(default)]
+    pub display_lost_action: Option<DisplayLostAction>,
+    /// Display to record with `FullDisplay`, by name. Honoured where the
+    /// screen can be picked without the portal (wlroots screencopy).
+    #[serde(default)]
+    pub display: Option<String>,
+    /// Draw transcripts from the annotators as captions over the preview
+    /// and, with `burn_in`, the recording.
+    #[serde(default)]
+    pub live_captions: Option<CaptionConfig>,
+    /// Translate the transcripts from the `annotators` as they come in.
+    #[serde(default)]
+    pub translation: Option<TranslationConfig>,
+    /// The calendar event this recording is for; kept in the manifest.
+    #[serde(default)]
+    pub calendar_event: Option<CalendarEvent>,
+    /// Apps that are never recorded; capture pauses or blanks while one is
+    /// on screen.
+    #[serde(default)]
+    pub app_policy: Option<AppPolicy>,
+    /// For a window target, black out frames whenever the window loses focus
+    /// or is minimized, so whatever covers it is never recorded.
+    #[serde(default)]
+    pub blackout_when_hidden: bool,
+    /// Framerate and scale per target, keyed by [`CaptureTarget::key`], so
+    /// e.g. a secondary display can run at 5fps and half size.
+    #[serde(default)]
+    pub target_settings: BTreeMap<String, TargetSettings>,
+    /// Publish the captured video as an NDI sender with this name.
+    #[cfg(feature = "ndi")]
+    #[serde(default)]
+    pub ndi_output: Option<String>,
+    /// Label audio chunks with who is speaking, after the `annotators`.
+    #[cfg(feature = "diarization")]
+    #[serde(default)]
+    pub diarization: Option<DiarizationConfig>,
 }
 
 impl Default for CaptureOptions {
     fn default() -> Self {
         Self {
             chunk_duration_ms: Self::default_chunk_ms(),
+            overlap_ms: 0,
+            audio_chunk_duration_ms: None,
             capture_mic: false,
+            audio_ring_ms: 0,
+            audio: AudioSettings::default(),
+            buffering: BufferingSettings::default(),
+            profile: CaptureProfile::default(),
+            ambient: AmbientSettings::default(),
+            escalation: None,
+            moments: None,
             debug_save: false,
+            debug_write: DiskWriteSettings::default(),
             target: CaptureTarget::FullDisplay,
+            idle_timeout_secs: None,
+            idle_action: IdleAction::default(),
+            battery_policy: None,
+            adaptive_quality: false,
+            capture_thread_tuning: ThreadTuning::default(),
+            consumer_thread_tuning: ThreadTuning::default(),
+            record_mkv: false,
+            max_file_size_mb: None,
+            output_dir: None,
+            gap_tolerance_ms: Self::default_gap_tolerance_ms(),
+            freeze_fill_max_ms: None,
+            element_overrides: ElementOverrides::new(),
+            crop: None,
+            rotation: None,
+            aspect: None,
+            aspect_mode: AspectMode::default(),
+            hdr: HdrMode::default(),
+            layout: None,
+            custom_sources: CustomSources::default(),
+            sinks: Vec::new(),
+            upload_limit_kbps: None,
+            shm_output: None,
+            chunk_zstd_level: None,
+            track_active_window: false,
+            track_input_activity: false,
+            clipboard: None,
+            annotators: Vec::new(),
+            proxy: None,
+            frame_hashes: false,
+            preview_playback: false,
+            consent: None,
+            privacy: None,
+            keep_awake: Self::default_keep_awake(),
+            keep_portal_session_secs: None,
+            screen_lock_action: None,
+            display_lost_action: None,
+            display: None,
+            live_captions: None,
+            translation: None,
+            calendar_event: None,
+            app_policy: None,
+            blackout_when_hidden: false,
+            target_settings: BTreeMap::new(),
+            #[cfg(feature = "ndi")]
+            ndi_output: None,
+            #[cfg(feature = "diarization")]
+            diarization: None,
         }
     }
 }
@@ -62,29 +608,78 @@ impl CaptureOptions {
         Duration::from_millis(self.chunk_duration_ms.max(1000))
     }
 
+    /// Encoder settings of the `record_mkv` recording in the current profile.
+    pub fn recording_encoder(&self) -> EncoderSettings {
+        match self.profile {
+            CaptureProfile::Full => EncoderSettings::default(),
+            CaptureProfile::Ambient => EncoderSettings {
+                video_bitrate_kbps: Some(self.ambient.video_bitrate_kbps),
+                ..EncoderSettings::default()
+            },
+        }
+    }
+
+    pub fn audio_chunk_duration(&self) -> Duration {
+        self.audio_chunk_duration_ms
+            .map_or_else(|| self.chunk_duration(), |ms| Duration::from_millis(ms.max(1000)))
+    }
+
     pub const fn default_chunk_ms() -> u64 {
         5_000
     }
-}
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-pub enum CaptureState {
-    Idle,
-    Starting,
-    Running,
-    Stopping,
-}
+    pub const fn default_keep_awake() -> bool {
+        false
+    }
 
-impl CaptureState {
-    fn is_active(self) -> bool {
-        matches!(self, CaptureState::Starting | CaptureState::Running | CaptureState::Stopping)
+    /// Video chunks can overrun their nominal length by a frame, which is
+    /// 200ms at the lowest battery framerate.
+    pub const fn default_gap_tolerance_ms() -> u64 {
+        250
     }
+
+    /// The best quality the captured target runs at, before adaptive quality
+    /// or battery policies step it down.
+    pub fn base_video_quality(&self) -> VideoQuality {
+        if self.profile == CaptureProfile::Ambient {
+            return VideoQuality {
+                framerate: self.ambient.framerate.max(1),
+                scale_percent: self.ambient.scale_percent.clamp(1, 100),
+            };
+        }
+        let settings = self
+            .target_settings
+            .get(&self.target.key())
+            .copied()
+            .unwrap_or_default();
+        let defaults = VideoQuality::default();
+        VideoQuality {
+            framerate: settings.framerate.unwrap_or(defaults.framerate).max(1),
+            scale_percent: settings
+                .scale_percent
+                .unwrap_or(defaults.scale_percent)
+                .clamp(1, 100),
+        }
+    }
+}
+
+/// Why a session is paused. It stays paused while any reason holds and
+/// resumes once the last one is lifted, so e.g. a denylisted app shown while
+/// idle keeps capture paused after activity returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PauseReason {
+    User,
+    Idle,
+    ScreenLock,
+    DisplayLost,
+    Battery,
+    Policy,
 }
 
 struct ManagerState {
-    status: CaptureState,
+    machine: StateMachine,
     options: CaptureOptions,
+    pause_reasons: BTreeSet<PauseReason>,
     video_pipeline: Option<gst::Pipeline>,
     video_chunk_buffer: Option<Arc<Mutex<VideoChunkBuffer>>>,
     system_audio_pipeline: Option<gst::Pipeline>,
@@ -92,13 +687,50 @@ struct ManagerState {
     mic_pipeline: Option<gst::Pipeline>,
     mic_chunk_buffer: Option<Arc<Mutex<AudioChunkBuffer>>>,
     chunk_sender: Option<mpsc::Sender<CapturedChunk>>,
+    /// Drains the session's chunks and finalizes its outputs.
+    chunk_consumer: Option<JoinHandle<()>>,
+    recording: Option<SharedRecording>,
+    sink_recordings: Vec<SharedRecording>,
+    session_id: Option<String>,
+    session_started_unix_ms: u128,
+    session_started: Option<Instant>,
+    session_anchor: Option<ClockAnchor>,
+    markers: Vec<Marker>,
+    /// The quality the video pipeline runs at, caps applied.
+    video_quality: VideoQuality,
+    /// The adaptive controller's ceiling while it has stepped down.
+    adaptive_cap: Option<VideoQuality>,
+    /// The battery policy's framerate ceiling while on low battery.
+    battery_framerate: Option<u32>,
+    layout: Option<LiveLayout>,
+    #[cfg(target_os = "linux")]
+    screen_cast: Option<ScreenCastSession>,
+    /// The wlroots recorder standing in for a missing ScreenCast portal.
+    #[cfg(target_os = "linux")]
+    screencopy: Option<Screencopy>,
+    #[cfg(target_os = "linux")]
+    inhibitor: Option<Inhibitor>,
+    /// Portal session of the last recording and when it is closed, kept
+    /// for a quick restart with `keep_portal_session_secs`.
+    #[cfg(target_os = "linux")]
+    parked_screen_cast: Option<(ScreenCastSession, Instant)>,
+    /// Window currently captured in [`CaptureTarget::FollowFocus`] mode.
+    followed_window: Option<String>,
+    annotations: Option<Arc<SessionAnnotations>>,
+    /// HLS playlist of the running session, with `preview_playback`.
+    preview_playlist: Option<PathBuf>,
+    /// Pipelines built by [`CaptureManager::arm_capture`], waiting in
+    /// `Paused` for the next start.
+    armed: Option<ArmedPipelines>,
+    last_error: Option<CaptureError>,
 }
 
 impl Default for ManagerState {
     fn default() -> Self {
         Self {
-            status: CaptureState::Idle,
+            machine: StateMachine::default(),
             options: CaptureOptions::default(),
+            pause_reasons: BTreeSet::new(),
             video_pipeline: None,
             video_chunk_buffer: None,
             system_audio_pipeline: None,
@@ -106,105 +738,1399 @@ impl Default for ManagerState {
             mic_pipeline: None,
             mic_chunk_buffer: None,
             chunk_sender: None,
+            chunk_consumer: None,
+            recording: None,
+            sink_recordings: Vec::new(),
+            session_id: None,
+            session_started_unix_ms: 0,
+            session_started: None,
+            session_anchor: None,
+            markers: Vec::new(),
+            video_quality: VideoQuality::default(),
+            adaptive_cap: None,
+            battery_framerate: None,
+            layout: None,
+            #[cfg(target_os = "linux")]
+            screen_cast: None,
+            #[cfg(target_os = "linux")]
+            screencopy: None,
+            #[cfg(target_os = "linux")]
+            inhibitor: None,
+            #[cfg(target_os = "linux")]
+            parked_screen_cast: None,
+            followed_window: None,
+            annotations: None,
+            preview_playlist: None,
+            armed: None,
+            last_error: None,
         }
     }
 }
 
+impl ManagerState {
+    /// Drops everything a panicking holder may have left half-updated.
+    fn reset_after_panic(&mut self) {
+        for pipeline in self.pipelines() {
+            let _ = pipeline.set_state(gst::State::Null);
+        }
+        if let Some(armed) = self.armed.take() {
+            armed.pipelines.teardown();
+        }
+        let options = std::mem::take(&mut self.options);
+        *self = Self {
+            options,
+            ..Self::default()
+        };
+    }
+
+    fn pipelines(&self) -> impl Iterator<Item = &gst::Pipeline> {
+        self.video_pipeline
+            .iter()
+            .chain(self.system_audio_pipeline.iter())
+            .chain(self.mic_pipeline.iter())
+    }
+}
+
 #[derive(Default)]
 pub struct CaptureManager {
     inner: Mutex<ManagerState>,
+    events: EventBus,
+    activity: Arc<ActivityTracker>,
+    screen_lock: Arc<LockTracker>,
+    displays: Arc<DisplayTracker>,
+    load_stats: Arc<LoadStats>,
+    latency: Arc<LatencyTracker>,
+    sample_clock: Arc<SampleClock>,
+    chunk_stats: Arc<ChunkStats>,
+    window_timeline: Arc<WindowTimeline>,
+    input_activity: Arc<InputActivity>,
+    clipboard_log: ClipboardLog,
+    browser_timeline: Arc<BrowserTimeline>,
+    proxy_links: Arc<ProxyLinks>,
+    frame_hashes: Arc<FrameHashes>,
+    activity_log: Arc<ActivityLog>,
+    anchors: Arc<AnchorLog>,
+    chunk_map: Arc<ChunkMap>,
+    memory: Arc<MemoryStats>,
+    latest_frame: Arc<LatestFrame>,
+    audio_ring: Arc<AudioRing>,
+    policy: Arc<PolicyState>,
+    blackout: Arc<BlackoutState>,
+    escalation: EscalationState,
+    moments: Arc<MomentLog>,
+    /// Bumped by `flush_chunks_now`; each chunk buffer cuts when it sees a
+    /// new value.
+    flush_requests: Arc<AtomicU64>,
+    /// In-process annotators, run alongside the session's `annotators`.
+    annotators: Mutex<Vec<Arc<dyn ChunkAnnotator>>>,
+    uploads: UploadSpool,
+    chunk_taps: ChunkTaps,
+    acks: Arc<AckTracker>,
 }
 
 impl CaptureManager {
+    /// Locks the manager state, resetting it to idle if a previous holder panicked.
+    fn lock_state(&self) -> MutexGuard<'_, ManagerState> {
+        self.recover_state().unwrap_or_else(|guard| guard)
+    }
+
+    /// Like [`Self::lock_state`], but reports a recovered panic to the caller.
+    fn lock_checked(&self) -> Result<MutexGuard<'_, ManagerState>> {
+        self.recover_state().map_err(|_| {
+            anyhow!("capture was reset after an internal error; please retry")
+        })
+    }
+
+    fn recover_state(&self) -> std::result::Result<MutexGuard<'_, ManagerState>, MutexGuard<'_, ManagerState>> {
+        match self.inner.lock() {
+            Ok(guard) => Ok(guard),
+            Err(poisoned) => {
+                eprintln!("[capture] manager state poisoned by a panic, resetting to idle");
+                let mut guard = poisoned.into_inner();
+                guard.reset_after_panic();
+                self.inner.clear_poison();
+                Err(guard)
+            }
+        }
+    }
+
     pub fn start_capture(&self, options: CaptureOptions) -> Result<()> {
+        platform::ensure_supported()?;
         ensure_gstreamer_initialized()?;
+        if let Some(dir) = &options.output_dir {
+            session::confine(std::path::Path::new(dir))?;
+        }
 
-        {
-            let mut inner = self.inner.lock().expect("manager mutex poisoned");
-            if inner.status.is_active() {
+        let starting = {
+            let mut inner = self.lock_checked()?;
+            if inner.machine.state().is_active() {
                 return Err(anyhow!("capture already running"));
             }
-            inner.status = CaptureState::Starting;
+            let transition = inner.machine.transition(CaptureState::Starting)?;
             inner.options = options.clone();
+            inner.last_error = None;
+            transition
+        };
+        self.emit_transition(starting);
+
+        let start_anchor = ClockAnchor::now();
+        let started_unix_ms = start_anchor.unix_nanos / 1_000_000;
+        let session_id = format!("session-{started_unix_ms}");
+        let resources = match self.configure_pipelines(&options, &session_id) {
+            Ok(resources) => resources,
+            Err(err) => {
+                let failed = {
+                    let mut inner = self.lock_state();
+                    let mut error = err
+                        .downcast_ref::<CaptureError>()
+                        .cloned()
+                        .unwrap_or_else(|| CaptureError::new(err.to_string(), None));
+                    error.session_id = Some(session_id.clone());
+                    inner.last_error = Some(error);
+                    inner.machine.transition(CaptureState::Failed)
+                };
+                if let Ok(transition) = failed {
+                    self.emit_transition(transition);
+                }
+                self.events.emit(CaptureEvent::SessionFailed {
+                    session_id: Some(session_id.clone()),
+                    error: err.to_string(),
+                });
+                return Err(err);
+            }
+        };
+
+        let running = self.adopt_session(resources, &session_id, start_anchor, &options)?;
+        self.emit_transition(running);
+        self.events.emit(CaptureEvent::SessionStarted {
+            session_id,
+            started_unix_ms,
+            options,
+        });
+        Ok(())
+    }
+
+    /// Hands a started session's resources to the manager and marks it
+    /// running. A stop that landed while the pipelines were coming up has
+    /// already moved the state on and released what the manager held, so
+    /// the resources are released here instead of being stored.
+    fn adopt_session(
+        &self,
+        resources: SessionResources,
+        session_id: &str,
+        start_anchor: ClockAnchor,
+        options: &CaptureOptions,
+    ) -> Result<Transition> {
+        let mut inner = self.lock_state();
+        if inner.machine.state() != CaptureState::Starting {
+            drop(inner);
+            resources.release();
+            self.clipboard_log.finish();
+            return Err(anyhow!("capture was stopped before it finished starting"));
+        }
+        let transition = inner.machine.transition(CaptureState::Running)?;
+        let SessionResources {
+            video,
+            system_audio,
+            mic,
+            #[cfg(target_os = "linux")]
+            inhibitor,
+            chunk_sender,
+            chunk_consumer,
+            annotations,
+            preview_playlist,
+            recording,
+            sink_recordings,
+        } = resources;
+        inner.video_pipeline = Some(video.pipeline);
+        inner.video_chunk_buffer = Some(video.chunk_buffer);
+        inner.layout = video.layout;
+        #[cfg(target_os = "linux")]
+        {
+            inner.screen_cast = video.screen_cast;
+            inner.screencopy = video.screencopy;
+            inner.inhibitor = inhibitor;
         }
+        inner.followed_window = video.followed_window;
+        inner.system_audio_pipeline = Some(system_audio.pipeline);
+        inner.system_audio_chunk_buffer = Some(system_audio.chunk_buffer);
+        (inner.mic_pipeline, inner.mic_chunk_buffer) = match mic {
+            Some(mic) => (Some(mic.pipeline), Some(mic.chunk_buffer)),
+            None => (None, None),
+        };
+        inner.chunk_sender = Some(chunk_sender);
+        inner.chunk_consumer = Some(chunk_consumer);
+        inner.annotations = annotations;
+        inner.preview_playlist = preview_playlist;
+        inner.recording = recording;
+        inner.sink_recordings = sink_recordings;
+        inner.session_id = Some(session_id.to_string());
+        inner.session_started_unix_ms = start_anchor.unix_nanos / 1_000_000;
+        inner.session_started = Some(Instant::now());
+        inner.session_anchor = Some(start_anchor);
+        inner.markers.clear();
+        inner.pause_reasons.clear();
+        inner.video_quality = options.base_video_quality();
+        inner.adaptive_cap = None;
+        inner.battery_framerate = None;
+        self.input_activity.set_enabled(options.track_input_activity);
+        Ok(transition)
+    }
 
-        if let Err(err) = self.configure_pipelines(&options) {
-            let mut inner = self.inner.lock().expect("manager mutex poisoned");
-            inner.status = CaptureState::Idle;
-            return Err(err);
+    /// Stops the session and returns its summary; `None` when there was no
+    /// session to stop. The pipelines are torn down, partial chunks flushed
+    /// and the consumer drained outside the state lock, so the summary and
+    /// manifest count every chunk and nothing else waits on the disk.
+    pub fn stop_capture(&self) -> Result<Option<SessionSummary>> {
+        let (stopping, stopped) = {
+            let mut inner = self.lock_state();
+            match inner.machine.state() {
+                CaptureState::Idle => return Ok(None),
+                CaptureState::Failed => {
+                    // stopping acknowledges the failure
+                    let transition = inner.machine.transition(CaptureState::Idle)?;
+                    drop(inner);
+                    self.emit_transition(transition);
+                    return Ok(None);
+                }
+                _ => {}
+            }
+            let stopping = inner.machine.transition(CaptureState::Stopping)?;
+            inner.pause_reasons.clear();
+            inner.layout = None;
+            #[cfg(target_os = "linux")]
+            {
+                if let (Some(session), Some(secs)) = (inner.screen_cast.take(), inner.options.keep_portal_session_secs) {
+                    println!("[capture] keeping the portal session for {secs}s");
+                    inner.parked_screen_cast = Some((session, Instant::now() + Duration::from_secs(secs)));
+                }
+                inner.screencopy = None;
+                inner.inhibitor = None;
+            }
+            inner.followed_window = None;
+            inner.preview_playlist = None;
+            let stopped = StoppedSession {
+                video_pipeline: inner.video_pipeline.take(),
+                system_audio_pipeline: inner.system_audio_pipeline.take(),
+                mic_pipeline: inner.mic_pipeline.take(),
+                video_chunk_buffer: inner.video_chunk_buffer.take(),
+                system_audio_chunk_buffer: inner.system_audio_chunk_buffer.take(),
+                mic_chunk_buffer: inner.mic_chunk_buffer.take(),
+                chunk_sender: inner.chunk_sender.take(),
+                chunk_consumer: inner.chunk_consumer.take(),
+                recording: inner.recording.take(),
+                sink_recordings: std::mem::take(&mut inner.sink_recordings),
+                annotations: inner.annotations.take(),
+                session_id: inner.session_id.take(),
+                started_unix_ms: inner.session_started_unix_ms,
+                started: inner.session_started.take(),
+                anchor: inner.session_anchor.take(),
+                markers: inner.markers.clone(),
+                options: inner.options.clone(),
+            };
+            (stopping, stopped)
+        };
+        self.emit_transition(stopping);
+
+        let video_color = stopped
+            .video_pipeline
+            .as_ref()
+            .and_then(|pipeline| pipeline.by_name("video_sink"))
+            .and_then(|sink| sink.static_pad("sink"))
+            .and_then(|pad| pad.current_caps())
+            .and_then(|caps| ColorInfo::from_caps(&caps));
+        let dropped_frames = stopped
+            .video_pipeline
+            .as_ref()
+            .and_then(|pipeline| pipeline.by_name("video_rate"))
+            .map(|rate| rate.property::<u64>("drop"))
+            .unwrap_or_default();
+        Self::teardown_pipeline(stopped.video_pipeline);
+        Self::teardown_pipeline(stopped.system_audio_pipeline);
+        Self::teardown_pipeline(stopped.mic_pipeline);
+        let stop_anchor = ClockAnchor::now();
+        let duration_ms = stopped
+            .started
+            .map(|started| started.elapsed().as_millis() as u64)
+            .unwrap_or_default();
+        // the pipelines are stopped, so what the buffers hold is the last of it
+        if let Some(buffer) = &stopped.video_chunk_buffer {
+            buffer.lock().unwrap_or_else(PoisonError::into_inner).close();
+        }
+        for buffer in [&stopped.system_audio_chunk_buffer, &stopped.mic_chunk_buffer]
+            .into_iter()
+            .flatten()
+        {
+            buffer.lock().unwrap_or_else(PoisonError::into_inner).close();
+        }
+        self.latest_frame.clear();
+        self.audio_ring.clear();
+        self.input_activity.set_enabled(false);
+        let clipboard = self.clipboard_log.finish();
+        // chapters go in while the consumer still has the recordings open
+        let mut recordings: Vec<SharedRecording> = stopped.recording.iter().cloned().collect();
+        recordings.extend(stopped.sink_recordings.iter().cloned());
+        for recording in &recordings {
+            let state = recording.lock().unwrap_or_else(PoisonError::into_inner);
+            state.write_chapters(stop_anchor.unix_nanos);
+        }
+        // last sender gone: the consumer drains and finalizes its outputs
+        drop(stopped.chunk_sender);
+        if let Some(consumer) = stopped.chunk_consumer {
+            if consumer.join().is_err() {
+                eprintln!("[capture] chunk consumer panicked while finishing the session");
+            }
+        }
+
+        let (recording_parts, keyframes) = stopped
+            .recording
+            .map(|recording| {
+                let state = recording.lock().unwrap_or_else(PoisonError::into_inner);
+                (state.files(), state.keyframes())
+            })
+            .unwrap_or_default();
+        let mut files = recording_parts.clone();
+        for recording in &stopped.sink_recordings {
+            files.extend(recording.lock().unwrap_or_else(PoisonError::into_inner).files());
+        }
+        let session_clock = stopped.anchor.map(|start| SessionClock {
+            start,
+            stop: stop_anchor,
+            chunks: self.anchors.snapshot(),
+        });
+        let options = &stopped.options;
+        let finished = stopped.session_id.map(|session_id| {
+            let streams = self.chunk_stats.snapshot();
+            let mut summary = SessionSummary {
+                session_id: session_id.clone(),
+                duration_ms,
+                chunks: streams.values().map(|stream| stream.chunks).sum(),
+                bytes: streams.values().map(|stream| stream.bytes).sum(),
+                dropped_frames,
+                streams,
+                output_dir: None,
+                files,
+            };
+            let output_dir = if options.record_mkv {
+                let dir = session::session_dir(options, &session_id);
+                summary.output_dir = Some(dir.to_string_lossy().to_string());
+                summary.files.push(dir.join(session::MANIFEST_FILE).to_string_lossy().to_string());
+                let manifest = SessionManifest {
+                    session_id: session_id.clone(),
+                    started_unix_ms: stopped.started_unix_ms,
+                    duration_ms,
+                    recording: recording_parts.first().cloned(),
+                    recording_parts,
+                    tracks: session::tracks_for(options)
+                        .into_iter()
+                        .map(|track| match track.media {
+                            TrackMedia::Video => TrackInfo {
+                                color: video_color.clone(),
+                                ..track
+                            },
+                            TrackMedia::Audio => track,
+                        })
+                        .collect(),
+                    markers: stopped.markers.clone(),
+                    windows: self.window_timeline.snapshot(),
+                    clipboard,
+                    tabs: self.browser_timeline.finish(unix_now_millis() as u64),
+                    chunk_annotations: Vec::new(),
+                    consent: options.consent.clone(),
+                    proxy_chunks: self.proxy_links.snapshot(),
+                    frame_hashes: self.frame_hashes.snapshot(),
+                    activity: self.activity_log.snapshot(),
+                    clock: session_clock,
+                    chunk_map: if options.audio_chunk_duration_ms.is_some() {
+                        self.chunk_map.links()
+                    } else {
+                        Vec::new()
+                    },
+                    keyframes,
+                    moments: self.moments.snapshot(),
+                    title: options.calendar_event.as_ref().map(|event| event.title.clone()),
+                    tags: Vec::new(),
+                    notes: Vec::new(),
+                    calendar_event: options.calendar_event.clone(),
+                    redactions: Vec::new(),
+                    summary: Some(summary.clone()),
+                };
+                let written = match stopped.annotations {
+                    Some(annotations) => annotations.write_manifest(manifest, &dir),
+                    None => manifest.write(&dir),
+                };
+                if let Err(err) = written {
+                    eprintln!("[capture] failed to write manifest: {err}");
+                }
+                Some(dir.to_string_lossy().to_string())
+            } else {
+                options.debug_save.then(|| DEBUG_OUTPUT_DIR.to_string())
+            };
+            summary.output_dir = output_dir.clone();
+            let event = CaptureEvent::SessionStopped {
+                session_id,
+                duration_ms,
+                marker_count: stopped.markers.len(),
+                output_dir,
+            };
+            (event, summary)
+        });
+
+        let idle = self.lock_state().machine.transition(CaptureState::Idle)?;
+        self.emit_transition(idle);
+        let summary = finished.map(|(event, summary)| {
+            self.events.emit(event);
+            summary
+        });
+        Ok(summary)
+    }
+
+    /// Pauses by hand; see [`Self::pause_for`].
+    pub fn pause_capture(&self) -> Result<()> {
+        self.pause_for(PauseReason::User)
+    }
+
+    /// Resumes by hand, lifting every pause reason but `Policy`: a
+    /// denylisted app on screen keeps capture paused until it leaves.
+    pub fn resume_capture(&self) -> Result<()> {
+        let resumed = {
+            let mut inner = self.lock_checked()?;
+            if inner.machine.state() != CaptureState::Paused {
+                return Err(anyhow!("capture is not paused"));
+            }
+            inner.pause_reasons.retain(|reason| *reason == PauseReason::Policy);
+            if !inner.pause_reasons.is_empty() {
+                return Err(anyhow!("capture stays paused while a denylisted app is shown"));
+            }
+            Self::resume_locked(&mut inner)?
+        };
+        self.emit_transition(resumed);
+        Ok(())
+    }
+
+    /// Pauses the session for `reason`. The pipelines stop with the first
+    /// reason; any later one only keeps the session paused until it is
+    /// lifted as well.
+    pub fn pause_for(&self, reason: PauseReason) -> Result<()> {
+        let paused = {
+            let mut inner = self.lock_checked()?;
+            let state = inner.machine.state();
+            if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+                return Err(anyhow!("capture is not running"));
+            }
+            inner.pause_reasons.insert(reason);
+            if state == CaptureState::Paused {
+                return Ok(());
+            }
+            let failed = inner
+                .pipelines()
+                .find_map(|pipeline| pipeline.set_state(gst::State::Paused).err());
+            if let Some(err) = failed {
+                inner.pause_reasons.remove(&reason);
+                return Err(anyhow!("failed to pause pipeline: {err:?}"));
+            }
+            inner.machine.transition(CaptureState::Paused)?
+        };
+        self.emit_transition(paused);
+        Ok(())
+    }
+
+    /// Lifts `reason`; the session resumes once no other reason holds it.
+    pub fn resume_for(&self, reason: PauseReason) -> Result<()> {
+        let resumed = {
+            let mut inner = self.lock_checked()?;
+            if !inner.pause_reasons.remove(&reason)
+                || !inner.pause_reasons.is_empty()
+                || inner.machine.state() != CaptureState::Paused
+            {
+                return Ok(());
+            }
+            Self::resume_locked(&mut inner)?
+        };
+        self.emit_transition(resumed);
+        Ok(())
+    }
+
+    /// Whether `reason` is one of the things keeping the session paused.
+    pub fn paused_for(&self, reason: PauseReason) -> bool {
+        self.lock_state().pause_reasons.contains(&reason)
+    }
+
+    fn resume_locked(inner: &mut ManagerState) -> Result<Transition> {
+        for pipeline in inner.pipelines() {
+            Self::start_pipeline(pipeline, "paused")?;
+        }
+        inner.machine.transition(CaptureState::Running)
+    }
+
+    /// Builds the pipelines for `options` ahead of time, including portal
+    /// negotiation, and parks them in `Paused`. A later `start_capture` with
+    /// the same options only sets them playing, so frames arrive within
+    /// about 100ms instead of after seconds of setup. Other options drop the
+    /// armed pipelines and build fresh ones. Arming is used up by one start.
+    pub fn arm_capture(&self, options: CaptureOptions) -> Result<()> {
+        platform::ensure_supported()?;
+        ensure_gstreamer_initialized()?;
+        if let Some(dir) = &options.output_dir {
+            session::confine(std::path::Path::new(dir))?;
+        }
+        if self.lock_checked()?.machine.state().is_active() {
+            return Err(anyhow!("capture already running"));
+        }
+        self.disarm_capture();
+
+        let (sender, receiver) = mpsc::channel::<CapturedChunk>();
+        let pipelines = self.build_pipelines(&options, sender.clone())?;
+        let failed = pipelines.labelled().find_map(|(label, pipeline)| {
+            let err = pipeline.set_state(gst::State::Paused).err()?;
+            Some(
+                pipeline_error(pipeline, label)
+                    .unwrap_or_else(|| CaptureError::new(format!("failed to arm: {err:?}"), Some(label))),
+            )
+        });
+        if let Some(error) = failed {
+            pipelines.teardown();
+            return Err(error.into());
+        }
+
+        let mut inner = self.lock_state();
+        if inner.machine.state().is_active() {
+            // a capture started while the portal was asking
+            pipelines.teardown();
+            return Err(anyhow!("capture already running"));
+        }
+        if let Some(previous) = inner.armed.replace(ArmedPipelines {
+            options: serde_json::to_value(&options).unwrap_or_default(),
+            sender,
+            receiver,
+            pipelines,
+        }) {
+            previous.pipelines.teardown();
+        }
+        println!("[capture] pipelines armed");
+        Ok(())
+    }
+
+    /// Tears down armed pipelines; `false` when none were armed.
+    pub fn disarm_capture(&self) -> bool {
+        let Some(armed) = self.lock_state().armed.take() else {
+            return false;
+        };
+        armed.pipelines.teardown();
+        println!("[capture] pipelines disarmed");
+        true
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.lock_state().armed.is_some()
+    }
+
+    /// The armed pipelines if they were built for `options`; any others are
+    /// torn down.
+    fn take_armed(&self, options: &CaptureOptions) -> Option<ArmedPipelines> {
+        let armed = self.lock_state().armed.take()?;
+        if serde_json::to_value(options).ok().as_ref() == Some(&armed.options) {
+            return Some(armed);
+        }
+        println!("[capture] options changed since arming, building fresh pipelines");
+        armed.pipelines.teardown();
+        None
+    }
+
+    /// Closes the portal session kept from the last recording once its
+    /// `keep_portal_session_secs` are up.
+    pub fn expire_parked_screen_cast(&self) {
+        #[cfg(target_os = "linux")]
+        {
+            let mut inner = self.lock_state();
+            if inner.parked_screen_cast.as_ref().is_some_and(|(_, until)| Instant::now() >= *until) {
+                inner.parked_screen_cast = None;
+                println!("[capture] closed the kept portal session");
+            }
+        }
+    }
+
+    /// Points `src` at the portal session kept from the last recording, or
+    /// asks the portal for a new one when none is kept or it went away.
+    #[cfg(target_os = "linux")]
+    fn reuse_screen_cast(&self, src: &gst::Element) -> Result<Option<ScreenCastSession>> {
+        let parked = self.lock_state().parked_screen_cast.take();
+        if let Some((mut session, _)) = parked {
+            match session.reattach(src) {
+                Ok(()) => return Ok(Some(session)),
+                Err(err) => eprintln!("[capture] kept portal session is gone, asking again: {err}"),
+            }
+        }
+        portal::attach(src)
+    }
+
+    /// Switches the composited layout of the running session to `scene`.
+    pub fn set_layout_scene(&self, scene: &str) -> Result<()> {
+        {
+            let mut inner = self.lock_checked()?;
+            inner
+                .layout
+                .as_mut()
+                .ok_or_else(|| anyhow!("this session has no layout"))?
+                .set_scene(scene)?;
+        }
+        self.events.emit(CaptureEvent::SceneChanged {
+            scene: scene.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Renegotiates the video caps of the running session in place.
+    fn set_video_quality(&self, quality: VideoQuality) -> Result<()> {
+        let mut inner = self.lock_checked()?;
+        let pipeline = inner
+            .video_pipeline
+            .as_ref()
+            .ok_or_else(|| anyhow!("no video pipeline running"))?;
+        let capsfilter = pipeline
+            .by_name(VIDEO_CAPS_FILTER)
+            .ok_or_else(|| anyhow!("video pipeline has no caps filter"))?;
+        // videoscale's input still carries the native size
+        let native_size = pipeline
+            .by_name("video_scale")
+            .and_then(|scale| scale.static_pad("sink"))
+            .and_then(|pad| pad.current_caps())
+            .and_then(|caps| {
+                let structure = caps.structure(0)?;
+                Some((
+                    structure.get::<i32>("width").ok()?,
+                    structure.get::<i32>("height").ok()?,
+                ))
+            });
+        let size = native_size.and_then(|native| scaled_size(native, quality.scale_percent));
+        capsfilter.set_property("caps", &video_caps(quality.framerate, size, inner.options.hdr));
+        inner.video_quality = quality;
+        println!(
+            "[capture] video quality set to {}fps at {}%",
+            quality.framerate, quality.scale_percent
+        );
+        Ok(())
+    }
+
+    /// Switches the running session between full-quality and ambient
+    /// capture without restarting it: video caps are renegotiated and the
+    /// recording's encoder bitrate is changed in place.
+    pub fn set_capture_profile(&self, profile: CaptureProfile) -> Result<()> {
+        let (recording, bitrate) = {
+            let mut inner = self.lock_checked()?;
+            if !matches!(inner.machine.state(), CaptureState::Running | CaptureState::Paused) {
+                return Err(anyhow!("capture is not running"));
+            }
+            if inner.options.profile == profile {
+                return Ok(());
+            }
+            inner.options.profile = profile;
+            (
+                inner.recording.clone(),
+                inner.options.recording_encoder().video_bitrate_kbps,
+            )
+        };
+        let quality = self.apply_quality_caps()?;
+        if let Some(recording) = recording {
+            recording
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .set_video_bitrate(bitrate);
+        }
+        println!("[capture] switched to the {profile:?} profile");
+        self.events.emit(CaptureEvent::ProfileChanged {
+            profile,
+            framerate: quality.framerate,
+        });
+        Ok(())
+    }
+
+    /// [`Self::set_capture_profile`] with a marker labelled `label` where the
+    /// switch happened, for switches nobody asked for by hand.
+    pub fn switch_profile(&self, profile: CaptureProfile, label: String) -> Result<Marker> {
+        self.set_capture_profile(profile)?;
+        self.add_auto_marker(label)
+    }
+
+    /// The quality the video pipeline currently runs at: the profile's,
+    /// lowered by whichever adaptive or battery cap is in force.
+    pub fn video_quality(&self) -> VideoQuality {
+        self.lock_state().video_quality
+    }
+
+    /// Sets the adaptive controller's ceiling; `None` lifts it. Composes
+    /// with the battery cap rather than replacing it.
+    pub fn set_adaptive_cap(&self, cap: Option<VideoQuality>) -> Result<VideoQuality> {
+        self.lock_checked()?.adaptive_cap = cap;
+        self.apply_quality_caps()
+    }
+
+    /// Sets the battery policy's framerate ceiling; `None` lifts it and
+    /// leaves any adaptive step-down in place.
+    pub fn set_battery_framerate(&self, framerate: Option<u32>) -> Result<VideoQuality> {
+        self.lock_checked()?.battery_framerate = framerate;
+        self.apply_quality_caps()
+    }
+
+    fn apply_quality_caps(&self) -> Result<VideoQuality> {
+        let quality = {
+            let inner = self.lock_checked()?;
+            inner
+                .options
+                .base_video_quality()
+                .capped(inner.adaptive_cap, inner.battery_framerate)
+        };
+        self.set_video_quality(quality)?;
+        Ok(quality)
+    }
+
+    pub fn load_stats(&self) -> &LoadStats {
+        &self.load_stats
+    }
+
+    pub fn latency(&self) -> &LatencyTracker {
+        &self.latency
+    }
+
+    /// Chunk data held by the running session and the fill of its pipeline
+    /// queues; `None` when not capturing.
+    pub fn memory_report(&self) -> Option<MemoryReport> {
+        let inner = self.lock_state();
+        if !matches!(inner.machine.state(), CaptureState::Running | CaptureState::Paused) {
+            return None;
         }
+        let pipelines = [
+            ("video", &inner.video_pipeline),
+            ("system_audio", &inner.system_audio_pipeline),
+            ("mic", &inner.mic_pipeline),
+        ]
+        .into_iter()
+        .filter_map(|(label, pipeline)| Some((label.to_string(), memory::queue_levels(pipeline.as_ref()?))))
+        .collect();
+        drop(inner);
+        Some(self.memory.report(pipelines))
+    }
+
+    pub fn sample_clock(&self) -> &SampleClock {
+        &self.sample_clock
+    }
+
+    pub fn session_elapsed(&self) -> Option<Duration> {
+        self.lock_state()
+            .session_started
+            .map(|started| started.elapsed())
+    }
 
-        let mut inner = self.inner.lock().expect("manager mutex poisoned");
-        inner.status = CaptureState::Running;
+    /// `count` stills of the running capture, `interval_ms` apart.
+    pub fn capture_frames(&self, count: u32, interval_ms: u64, format: FrameFormat) -> Result<Vec<CapturedFrame>> {
+        if self.status() != CaptureState::Running {
+            return Err(anyhow!("capture is not running"));
+        }
+        self.latest_frame.burst(count, Duration::from_millis(interval_ms), format)
+    }
+
+    /// The last `ms` of mic audio, for wake-word and utterance detection.
+    pub fn read_recent_audio(&self, ms: u64) -> Result<RecentAudio> {
+        self.audio_ring.read(ms)
+    }
+
+    #[cfg_attr(not(feature = "wake_word"), allow(dead_code))]
+    pub fn audio_ring(&self) -> &AudioRing {
+        &self.audio_ring
+    }
+
+    /// The running session's HLS preview, to be played from `offset_ms`.
+    pub fn preview_playback(&self, offset_ms: u64) -> Result<PreviewPlayback> {
+        let inner = self.lock_state();
+        let started = inner.session_started.ok_or_else(|| anyhow!("no capture is running"))?;
+        let playlist = inner
+            .preview_playlist
+            .as_ref()
+            .ok_or_else(|| anyhow!("the session was started without preview_playback"))?;
+        if !playlist.exists() {
+            return Err(anyhow!("no preview segment has been written yet"));
+        }
+        let recorded_ms = started.elapsed().as_millis() as u64;
+        Ok(PreviewPlayback {
+            playlist: playlist.to_string_lossy().to_string(),
+            offset_ms: offset_ms.min(recorded_ms),
+            recorded_ms,
+        })
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Cuts every stream's chunk at its next sample instead of waiting out
+    /// the chunk duration, e.g. right after a question was asked.
+    pub fn flush_chunks_now(&self) -> Result<()> {
+        if self.lock_checked()?.machine.state() != CaptureState::Running {
+            return Err(anyhow!("capture is not running"));
+        }
+        self.flush_requests.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
 
-    pub fn stop_capture(&self) -> Result<()> {
-        let mut inner = self.inner.lock().expect("manager mutex poisoned");
-        if !inner.status.is_active() {
-            return Ok(());
+    /// Records the tab a browser extension reports as active.
+    pub fn record_browser_tab(&self, tab: BrowserTab) -> Result<()> {
+        let state = self.lock_checked()?.machine.state();
+        if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+            return Err(anyhow!("no capture session running"));
         }
-        inner.status = CaptureState::Stopping;
-        Self::teardown_pipeline(inner.video_pipeline.take());
-        Self::teardown_pipeline(inner.system_audio_pipeline.take());
-        Self::teardown_pipeline(inner.mic_pipeline.take());
-        inner.video_chunk_buffer = None;
-        inner.system_audio_chunk_buffer = None;
-        inner.mic_chunk_buffer = None;
-        inner.status = CaptureState::Idle;
+        self.browser_timeline.push(tab, unix_now_millis() as u64);
         Ok(())
     }
 
+    /// Adds an annotator to every session started from now on.
+    #[allow(dead_code)]
+    pub fn register_annotator(&self, annotator: Arc<dyn ChunkAnnotator>) {
+        self.annotators
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(annotator);
+    }
+
+    pub fn clipboard_log(&self) -> &ClipboardLog {
+        &self.clipboard_log
+    }
+
+    pub fn window_timeline(&self) -> &WindowTimeline {
+        &self.window_timeline
+    }
+
+    pub fn uploads(&self) -> &UploadSpool {
+        &self.uploads
+    }
+
+    /// Every chunk of every session, for in-process exporters.
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub fn chunk_taps(&self) -> &ChunkTaps {
+        &self.chunk_taps
+    }
+
+    pub fn acks(&self) -> &Arc<AckTracker> {
+        &self.acks
+    }
+
+    pub fn activity(&self) -> &ActivityTracker {
+        &self.activity
+    }
+
+    pub fn screen_lock(&self) -> &LockTracker {
+        &self.screen_lock
+    }
+
+    pub fn displays(&self) -> &DisplayTracker {
+        &self.displays
+    }
+
+    pub fn escalation(&self) -> &EscalationState {
+        &self.escalation
+    }
+
+    pub fn blackout(&self) -> &BlackoutState {
+        &self.blackout
+    }
+
+    pub fn policy(&self) -> &PolicyState {
+        &self.policy
+    }
+
+    pub fn options(&self) -> CaptureOptions {
+        self.lock_state().options.clone()
+    }
+
+    fn emit_transition(&self, transition: Transition) {
+        self.events.emit(CaptureEvent::StateChanged {
+            from: transition.from,
+            state: transition.to,
+        });
+    }
+
     pub fn status(&self) -> CaptureState {
-        self.inner.lock().expect("manager mutex poisoned").status
+        self.lock_state().machine.state()
+    }
+
+    /// Why the last session failed; cleared when the next one starts.
+    pub fn last_error(&self) -> Option<CaptureError> {
+        self.lock_state().last_error.clone()
+    }
+
+    /// Fails the session if one of its pipelines posted an error since the
+    /// last check; outputs are finalized with what was captured so far.
+    pub fn check_pipelines(&self) {
+        let (transition, error) = {
+            let mut inner = self.lock_state();
+            if !matches!(inner.machine.state(), CaptureState::Running | CaptureState::Paused) {
+                return;
+            }
+            let pipelines = [
+                ("video", &inner.video_pipeline),
+                ("system_audio", &inner.system_audio_pipeline),
+                ("mic", &inner.mic_pipeline),
+            ];
+            let Some(mut error) = pipelines
+                .into_iter()
+                .find_map(|(label, pipeline)| pipeline.as_ref().and_then(|pipeline| pipeline_error(pipeline, label)))
+            else {
+                return;
+            };
+            eprintln!("[capture] {error}");
+            inner.pause_reasons.clear();
+            Self::teardown_pipeline(inner.video_pipeline.take());
+            Self::teardown_pipeline(inner.system_audio_pipeline.take());
+            Self::teardown_pipeline(inner.mic_pipeline.take());
+            inner.video_chunk_buffer = None;
+            inner.system_audio_chunk_buffer = None;
+            inner.mic_chunk_buffer = None;
+            inner.layout = None;
+            #[cfg(target_os = "linux")]
+            {
+                inner.screen_cast = None;
+                inner.screencopy = None;
+                inner.inhibitor = None;
+            }
+            inner.followed_window = None;
+            inner.preview_playlist = None;
+            self.latest_frame.clear();
+            self.audio_ring.clear();
+            self.input_activity.set_enabled(false);
+            self.clipboard_log.finish();
+            inner.annotations = None;
+            inner.session_started = None;
+            let mut recordings: Vec<SharedRecording> = inner.sink_recordings.drain(..).collect();
+            recordings.extend(inner.recording.take());
+            for recording in recordings {
+                let state = recording.lock().unwrap_or_else(PoisonError::into_inner);
+                state.write_chapters(unix_now_millis() * 1_000_000);
+            }
+            // the consumer finalizes what was recorded before the failure
+            inner.chunk_sender = None;
+            error.session_id = inner.session_id.take();
+            inner.last_error = Some(error.clone());
+            (inner.machine.transition(CaptureState::Failed), error)
+        };
+        if let Ok(transition) = transition {
+            self.emit_transition(transition);
+        }
+        self.events.emit(CaptureEvent::SessionFailed {
+            session_id: error.session_id.clone(),
+            error: error.to_string(),
+        });
+    }
+
+    pub fn add_marker(&self, label: Option<String>) -> Result<Marker> {
+        let mut inner = self.lock_checked()?;
+        let marker = Self::add_marker_locked(&mut inner, label)?;
+        let moment = match (&inner.options.moments, &inner.session_id) {
+            (Some(settings), Some(session_id)) if inner.options.record_mkv => {
+                Some((settings.clone(), session::session_dir(&inner.options, session_id)))
+            }
+            _ => None,
+        };
+        drop(inner);
+        self.escalation.note_marker();
+        if let Some((settings, session_dir)) = moment {
+            // encoding, the compositor query and the writes stay off the
+            // caller, which may be the UI thread
+            let marker = marker.clone();
+            let frame = Arc::clone(&self.latest_frame);
+            let ring = Arc::clone(&self.audio_ring);
+            let log = Arc::clone(&self.moments);
+            let events = self.events.clone();
+            let spawned = std::thread::Builder::new().name("moment".into()).spawn(move || {
+                match moments::capture(&marker, &settings, &session_dir, &frame, &ring) {
+                    Ok(moment) => {
+                        log.push(moment.clone());
+                        events.emit(CaptureEvent::MomentCaptured { moment });
+                    }
+                    Err(err) => eprintln!("[moments] failed to keep moment for marker {}: {err}", marker.id),
+                }
+            });
+            if let Err(err) = spawned {
+                eprintln!("[moments] failed to start moment capture: {err}");
+            }
+        }
+        Ok(marker)
+    }
+
+    /// A marker the app leaves by itself; unlike [`Self::add_marker`] it
+    /// doesn't count as the user flagging a moment, so it never escalates.
+    pub fn add_auto_marker(&self, label: String) -> Result<Marker> {
+        let mut inner = self.lock_checked()?;
+        Self::add_marker_locked(&mut inner, Some(label))
+    }
+
+    fn add_marker_locked(inner: &mut ManagerState, label: Option<String>) -> Result<Marker> {
+        let started = match (inner.machine.state(), inner.session_started) {
+            (CaptureState::Running, Some(started)) => started,
+            _ => return Err(anyhow!("markers can only be added while capture is running")),
+        };
+        let marker = Marker {
+            id: inner.markers.len() as u64,
+            label,
+            unix_ms: unix_now_millis(),
+            offset_ms: started.elapsed().as_millis() as u64,
+        };
+        println!("[capture] marker {} at {}ms", marker.id, marker.offset_ms);
+        for recording in inner.recording.iter().chain(&inner.sink_recordings) {
+            recording
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .add_marker(marker.clone());
+        }
+        inner.markers.push(marker.clone());
+        Ok(marker)
+    }
+
+    /// Restarts the video source on `window`'s PipeWire node and leaves a
+    /// `focus:` marker, so the manifest shows where each window starts.
+    pub fn follow_window(&self, window: &FocusedWindow) -> Result<Marker> {
+        let mut inner = self.lock_checked()?;
+        if !matches!(inner.options.target, CaptureTarget::FollowFocus { .. })
+            || inner.options.custom_sources.video.is_some()
+        {
+            return Err(anyhow!("capture is not following focus"));
+        }
+        let node_id = window
+            .node_id
+            .ok_or_else(|| anyhow!("window {} has no PipeWire node", window.id))?;
+        let src = inner
+            .video_pipeline
+            .as_ref()
+            .and_then(|pipeline| pipeline.by_name("video_source"))
+            .ok_or_else(|| anyhow!("no video source running"))?;
+        // pipewiresrc only picks up a new target when it reconnects
+        src.set_state(gst::State::Null)?;
+        if !set_target_node(&src, node_id) {
+            return Err(anyhow!("video source can't switch PipeWire nodes"));
+        }
+        src.sync_state_with_parent()?;
+        let marker = Self::add_marker_locked(&mut inner, Some(format!("focus: {}", window.label())))?;
+        inner.followed_window = Some(window.id.clone());
+        drop(inner);
+        println!("[capture] following focus to {} (node {node_id})", window.label());
+        self.events.emit(CaptureEvent::FocusFollowed {
+            window: window.clone(),
+            marker_id: marker.id,
+        });
+        Ok(marker)
+    }
+
+    /// Id of the running session, if any.
+    pub fn session_id(&self) -> Option<String> {
+        self.lock_state().session_id.clone()
+    }
+
+    /// Frame hashes of the running session so far.
+    pub fn frame_hashes(&self) -> Vec<FrameHash> {
+        self.frame_hashes.snapshot()
+    }
+
+    /// Id and start of the running session, if any.
+    pub fn session(&self) -> Option<(String, u128)> {
+        let inner = self.lock_state();
+        Some((inner.session_id.clone()?, inner.session_started_unix_ms))
+    }
+
+    pub fn followed_window(&self) -> Option<String> {
+        self.lock_state().followed_window.clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn markers(&self) -> Vec<Marker> {
+        self.lock_state().markers.clone()
     }
 
     #[allow(dead_code)]
     pub fn set_options(&self, options: CaptureOptions) -> Result<()> {
-        let mut inner = self.inner.lock().expect("manager mutex poisoned");
-        if inner.status.is_active() {
+        let mut inner = self.lock_checked()?;
+        if inner.machine.state().is_active() {
             return Err(anyhow!("stop capture before updating options"));
         }
         inner.options = options;
         Ok(())
     }
 
-    fn configure_pipelines(&self, options: &CaptureOptions) -> Result<()> {
-        // create chunk channel and consumer
-        let (tx, rx) = mpsc::channel::<CapturedChunk>();
+    fn configure_pipelines(&self, options: &CaptureOptions, session_id: &str) -> Result<SessionResources> {
+        let denied_targets = match &options.app_policy {
+            Some(app_policy) => policy::denied_targets(app_policy, &options.target).unwrap_or_else(|err| {
+                eprintln!("[policy] failed to check the target windows, relying on focus: {err}");
+                BTreeMap::new()
+            }),
+            None => BTreeMap::new(),
+        };
+        let blank_denied = options
+            .app_policy
+            .as_ref()
+            .is_some_and(|app_policy| app_policy.action == PolicyAction::Blank);
+        if let Some(app_id) = denied_targets.values().next().filter(|_| !blank_denied) {
+            return Err(anyhow!("the target window shows {app_id}, which app_policy denylists"));
+        }
+        // focus is reported by compositor id, the target is a PipeWire node
+        let blackout_window = match &options.target {
+            CaptureTarget::Window { id } if options.blackout_when_hidden => Some(
+                focus::window_for_node(id)?
+                    .ok_or_else(|| {
+                        anyhow!("window node {id} matches no compositor window, so blackout_when_hidden can't follow it")
+                    })?
+                    .id,
+            ),
+            _ => None,
+        };
+        self.clipboard_log.start(options.clipboard.as_ref())?;
+        // chunk timestamps are relative to this instant in muxed output
+        let origin_unix_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let recording = SharedRecording::default();
+        let captions = options
+            .live_captions
+            .clone()
+            .map(|config| Arc::new(LiveCaptions::new(config)));
+        let mut mkv_writer = if options.record_mkv {
+            Some(RollingMkvWriter::new(
+                session::session_dir(options, session_id),
+                session::tracks_for(options),
+                origin_unix_nanos,
+                options.max_file_size_mb.map(|mb| mb * 1024 * 1024),
+                options.element_overrides.clone(),
+                options.recording_encoder(),
+                captions.clone().filter(|captions| captions.burn_in()),
+                Arc::clone(&recording),
+            )?)
+        } else {
+            None
+        };
+
+        let session_dir = session::session_dir(options, session_id);
+        let mut preview_writer = if options.preview_playback {
+            let _ = std::fs::remove_dir_all(preview::preview_dir(&session_dir));
+            Some(preview::start_writer(
+                &session_dir,
+                &session::tracks_for(options),
+                origin_unix_nanos,
+                &options.element_overrides,
+                captions.as_ref(),
+            )?)
+        } else {
+            None
+        };
+        let preview_playlist = options.preview_playback.then(|| preview::playlist_path(&session_dir));
+
+        let mut matched_overrides = mkv_writer
+            .as_ref()
+            .map(|writer| writer.applied_overrides().to_vec())
+            .unwrap_or_default();
+
+        let tracks = session::tracks_for(options);
+        let sink_context = SinkContext {
+            session_id,
+            session_dir: session::session_dir(options, session_id),
+            tracks: &tracks,
+            origin_unix_nanos,
+            element_overrides: &options.element_overrides,
+            events: &self.events,
+            uploads: &self.uploads,
+            upload_limit_kbps: options.upload_limit_kbps,
+        };
+        let sinks: Vec<SinkHandle> = options
+            .sinks
+            .iter()
+            .filter_map(|config| SinkHandle::start(config, &sink_context))
+            .collect();
+        let sink_recordings: Vec<SharedRecording> =
+            sinks.iter().filter_map(|sink| sink.recording().cloned()).collect();
+
+        // create chunk channel and consumer; armed pipelines already send on theirs
+        let armed = self.take_armed(options);
+        let (tx, rx, armed) = match armed {
+            Some(ArmedPipelines {
+                sender,
+                receiver,
+                pipelines,
+                ..
+            }) => (sender, receiver, Some(pipelines)),
+            None => {
+                let (tx, rx) = mpsc::channel::<CapturedChunk>();
+                (tx, rx, None)
+            }
+        };
         let debug_save = options.debug_save;
-        std::thread::Builder::new()
+        let zstd_level = options
+            .chunk_zstd_level
+            .filter(|_| debug_save || sinks.iter().any(SinkHandle::takes_compressed));
+        let consumer_tuning = options.consumer_thread_tuning.clone();
+        let mut gaps = GapTracker::new(options.gap_tolerance_ms);
+        let events = self.events.clone();
+        let taps = self.chunk_taps.clone();
+        let acks = Arc::clone(&self.acks);
+        let timings = Arc::clone(&self.latency);
+        let activity_log = Arc::clone(&self.activity_log);
+        let anchors = Arc::clone(&self.anchors);
+        let chunk_map = Arc::clone(&self.chunk_map);
+        let memory = Arc::clone(&self.memory);
+        let mut annotators = self
+            .annotators
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        annotators.extend(annotations::from_configs(&options.annotators));
+        if let Some(config) = &options.translation {
+            annotators.push(Arc::new(TranslationAnnotator::from_config(config)));
+        }
+        // last, so it can put speakers on the transcripts of the others
+        #[cfg(feature = "diarization")]
+        if let Some(config) = &options.diarization {
+            annotators.push(Arc::new(Diarizer::new(config.clone())));
+        }
+        let session_annotations =
+            (!annotators.is_empty()).then(|| Arc::new(SessionAnnotations::new(captions.clone())));
+        let annotator_queue = match &session_annotations {
+            Some(records) => Some(AnnotatorQueue::start(annotators, Arc::clone(records))?),
+            None => None,
+        };
+        let disk_writer = if debug_save {
+            Some(DiskWriter::start(PathBuf::from(DEBUG_OUTPUT_DIR), options.debug_write)?)
+        } else {
+            None
+        };
+        let chunk_consumer = std::thread::Builder::new()
             .name("chunk_consumer".into())
             .spawn(move || {
-                if debug_save {
-                    let _ = std::fs::create_dir_all("debug_output");
-                }
-                for chunk in rx {
-                    if debug_save {
-                        // write raw data and metadata
-                        let ts = chunk.start_ts_unix_nanos;
-                        let fname = format!("debug_output/chunk-{}-{}-{}.raw", ts, chunk.id, chunk.kind);
-                        let _ = std::fs::write(&fname, &chunk.data);
-                        let meta_fname = format!("debug_output/chunk-{}-{}-{}.json", ts, chunk.id, chunk.kind);
-                        let _ = std::fs::write(&meta_fname, serde_json::to_string_pretty(&chunk.metadata).unwrap_or_default());
-                        println!("[capture] debug-saved chunk {} -> {}", chunk.id, fname);
+                consumer_tuning.apply_to_current("chunk_consumer");
+                for mut chunk in rx {
+                    memory.dequeued(chunk.data.len());
+                    let source = timings.received(&chunk.kind, chunk.id);
+                    if let Some(gap_ms) = gaps.check(&mut chunk) {
+                        eprintln!(
+                            "[capture] warning: {} chunk {} starts {gap_ms}ms off the previous one",
+                            chunk.kind, chunk.id
+                        );
+                        events.emit(CaptureEvent::ChunkGap {
+                            stream: chunk.kind.clone(),
+                            chunk_id: chunk.id,
+                            gap_ms,
+                        });
+                    }
+                    if let Some(activity) = highlights::score_chunk(&chunk) {
+                        activity_log.push(activity);
+                    }
+                    if let Some(anchor) = ChunkAnchor::from_chunk(&chunk) {
+                        anchors.push(anchor);
+                    }
+                    chunk_map.push(ChunkSpan::of(&chunk));
+                    let chunk = Arc::new(chunk);
+                    let compress_started = Instant::now();
+                    let packed = zstd_level.and_then(|level| match compression::compress(&chunk, level) {
+                        Ok(packed) => Some(Arc::new(packed)),
+                        Err(err) => {
+                            eprintln!("[capture] failed to compress chunk {}, keeping it raw: {err}", chunk.id);
+                            None
+                        }
+                    });
+                    if packed.is_some() {
+                        timings.record(&chunk.kind, latency::COMPRESS, compress_started.elapsed());
+                    }
+                    let offer_started = Instant::now();
+                    for sink in &sinks {
+                        match &packed {
+                            Some(packed) if sink.takes_compressed() => sink.offer(packed),
+                            _ => sink.offer(&chunk),
+                        }
+                    }
+                    taps.offer(&chunk);
+                    acks.offer(&chunk);
+                    timings.record(&chunk.kind, latency::SINKS, offer_started.elapsed());
+                    if let Some(source) = source {
+                        timings.record(&chunk.kind, latency::END_TO_END, source.elapsed());
+                    }
+                    events.emit(CaptureEvent::ChunkReady {
+                        stream: chunk.kind.clone(),
+                        chunk_id: chunk.id,
+                        start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+                        duration_ms: chunk.duration_ms,
+                        bytes: chunk.data.len(),
+                    });
+                    if let Some(queue) = &annotator_queue {
+                        queue.offer(&chunk);
+                    }
+                    if let Some(writer) = mkv_writer.as_mut() {
+                        if let Err(err) = writer.push(&chunk) {
+                            eprintln!("[capture] failed to mux chunk {} kind={}: {err}", chunk.id, chunk.kind);
+                        }
+                    }
+                    if let Some(writer) = preview_writer.as_mut() {
+                        if let Err(err) = writer.push(&chunk) {
+                            eprintln!("[capture] preview playback stopped: {err}");
+                            preview_writer = None;
+                        }
+                    }
+                    if let Some(writer) = &disk_writer {
+                        // raw (or compressed) data and metadata, on the IO thread
+                        writer.write(Arc::clone(packed.as_ref().unwrap_or(&chunk)));
                     } else {
                         println!("[capture] consumed chunk {} kind={} len={}", chunk.id, chunk.kind, chunk.data_len);
                     }
                 }
+                if let Some(writer) = mkv_writer {
+                    if let Err(err) = writer.finish() {
+                        eprintln!("[capture] {err}");
+                    }
+                }
+                // the preview only lives as long as the session
+                if let Some(writer) = preview_writer {
+                    let _ = writer.finish();
+                    let _ = std::fs::remove_dir_all(preview::preview_dir(&session_dir));
+                }
+                for sink in sinks {
+                    sink.close();
+                }
+                if let Some(writer) = disk_writer {
+                    writer.finish();
+                }
             })?;
 
-        let tx_clone_v = tx.clone();
-        let tx_clone_s = tx.clone();
-        let tx_clone_m = tx.clone();
-
-        let video_handles = Self::build_video_pipeline(options, Some(tx_clone_v))?;
-        let system_audio_handles = Self::build_system_audio_pipeline(options, Some(tx_clone_s))?;
-        let mic_handles = if options.capture_mic {
-            Some(Self::build_mic_audio_pipeline(options, Some(tx_clone_m))?)
-        } else {
-            None
+        self.activity.reset();
+        self.screen_lock.reset();
+        self.displays.reset();
+        self.policy.reset(denied_targets, blank_denied);
+        self.blackout.reset(blackout_window);
+        self.sample_clock.reset();
+        self.chunk_stats.reset();
+        self.latency.reset();
+        self.window_timeline.reset();
+        self.browser_timeline.reset();
+        self.acks.reset();
+        self.proxy_links.reset();
+        self.frame_hashes.reset();
+        self.activity_log.reset();
+        self.anchors.reset();
+        self.chunk_map.reset();
+        self.escalation.reset();
+        self.moments.reset();
+        self.memory.reset();
+        self.latest_frame.clear();
+        self.audio_ring
+            .reset(if options.capture_mic { options.audio_ring_ms } else { 0 });
+
+        let built = match armed {
+            Some(pipelines) => {
+                pipelines.restart_chunks();
+                pipelines
+            }
+            None => self.build_pipelines(options, tx.clone())?,
         };
+        let BuiltPipelines {
+            video: video_handles,
+            system_audio: system_audio_handles,
+            mic: mic_handles,
+            matched_overrides: built_overrides,
+        } = built;
+        matched_overrides.extend(built_overrides);
+        overrides::warn_unmatched(&options.element_overrides, &matched_overrides);
 
         Self::start_pipeline(&video_handles.pipeline, "video").map_err(|err| {
             let _ = video_handles.pipeline.set_state(gst::State::Null);
@@ -226,35 +2152,97 @@ impl CaptureManager {
             }
         }
 
-        let VideoPipelineHandles {
-            pipeline: video_pipeline,
-            chunk_buffer: video_chunk_buffer,
-        } = video_handles;
-        let AudioPipelineHandles {
-            pipeline: system_audio_pipeline,
-            chunk_buffer: system_audio_chunk_buffer,
-        } = system_audio_handles;
-        let (mic_pipeline, mic_chunk_buffer) = if let Some(handles) = mic_handles {
-            (Some(handles.pipeline), Some(handles.chunk_buffer))
-        } else {
-            (None, None)
+        #[cfg(target_os = "linux")]
+        {
+            let captured = match (&video_handles.screencopy, &video_handles.screen_cast) {
+                (Some(screencopy), _) => Some(screencopy.output().to_string()),
+                (None, Some(session)) => session
+                    .size
+                    .and_then(|size| self.displays.find(session.position, size)),
+                (None, None) => None,
+            };
+            self.displays.set_captured(captured);
+        }
+
+        #[cfg(target_os = "linux")]
+        let inhibitor = if options.keep_awake {
+            inhibit::acquire()
+                .map_err(|err| eprintln!("[capture] could not keep the display awake: {err}"))
+                .ok()
+        } else {
+            None
+        };
+
+        Ok(SessionResources {
+            video: video_handles,
+            system_audio: system_audio_handles,
+            mic: mic_handles,
+            #[cfg(target_os = "linux")]
+            inhibitor,
+            chunk_sender: tx,
+            chunk_consumer,
+            annotations: session_annotations,
+            preview_playlist,
+            recording: options.record_mkv.then_some(recording),
+            sink_recordings,
+        })
+    }
+
+    /// Builds the pipelines of a session, sending chunks to `sender`, and
+    /// applies element overrides to them.
+    fn build_pipelines(&self, options: &CaptureOptions, sender: mpsc::Sender<CapturedChunk>) -> Result<BuiltPipelines> {
+        let context = ChunkContext {
+            sender: Some(sender),
+            activity: Arc::clone(&self.activity),
+            screen_lock: Arc::clone(&self.screen_lock),
+            load_stats: Arc::clone(&self.load_stats),
+            latency: Arc::clone(&self.latency),
+            sample_clock: Arc::clone(&self.sample_clock),
+            stats: Arc::clone(&self.chunk_stats),
+            windows: Arc::clone(&self.window_timeline),
+            input: Arc::clone(&self.input_activity),
+            tabs: Arc::clone(&self.browser_timeline),
+            proxy: options.proxy.clone(),
+            proxy_links: Arc::clone(&self.proxy_links),
+            frame_hashing: options.frame_hashes,
+            frame_hashes: Arc::clone(&self.frame_hashes),
+            memory: Arc::clone(&self.memory),
+            latest_frame: Arc::clone(&self.latest_frame),
+            audio_ring: Arc::clone(&self.audio_ring),
+            policy: Arc::clone(&self.policy),
+            blackout: Arc::clone(&self.blackout),
+            flush_requests: Arc::clone(&self.flush_requests),
+            overlap_ms: options.overlap_ms,
         };
 
-        let mut inner = self.inner.lock().expect("manager mutex poisoned");
-        inner.video_pipeline = Some(video_pipeline);
-        inner.video_chunk_buffer = Some(video_chunk_buffer);
-        inner.system_audio_pipeline = Some(system_audio_pipeline);
-        inner.system_audio_chunk_buffer = Some(system_audio_chunk_buffer);
-        inner.mic_pipeline = mic_pipeline;
-        inner.mic_chunk_buffer = mic_chunk_buffer;
-        inner.chunk_sender = Some(tx);
-        Ok(())
+        let video = self.build_video_pipeline(options, context.clone())?;
+        let system_audio = Self::build_system_audio_pipeline(options, context.clone())?;
+        let mic = if options.capture_mic {
+            Some(Self::build_mic_audio_pipeline(options, context)?)
+        } else {
+            None
+        };
+        let mut built = BuiltPipelines {
+            video,
+            system_audio,
+            mic,
+            matched_overrides: Vec::new(),
+        };
+        let mut matched = Vec::new();
+        for (_, pipeline) in built.labelled() {
+            matched.extend(overrides::apply(pipeline, &options.element_overrides)?);
+        }
+        built.matched_overrides = matched;
+        Ok(built)
     }
 
     fn start_pipeline(pipeline: &gst::Pipeline, label: &str) -> Result<()> {
-        pipeline
-            .set_state(gst::State::Playing)
-            .map_err(|err| anyhow!("failed to start {label} pipeline: {err:?}"))?;
+        if let Err(err) = pipeline.set_state(gst::State::Playing) {
+            // the bus usually has the real reason, e.g. a busy device
+            let error = pipeline_error(pipeline, label)
+                .unwrap_or_else(|| CaptureError::new(format!("failed to start: {err:?}"), Some(label)));
+            return Err(error.into());
+        }
         Ok(())
     }
 
@@ -265,9 +2253,41 @@ impl CaptureManager {
     }
 }
 
+/// Everything a chunk buffer shares with the rest of the session.
+#[derive(Clone, Default)]
+struct ChunkContext {
+    sender: Option<mpsc::Sender<CapturedChunk>>,
+    activity: Arc<ActivityTracker>,
+    screen_lock: Arc<LockTracker>,
+    load_stats: Arc<LoadStats>,
+    latency: Arc<LatencyTracker>,
+    sample_clock: Arc<SampleClock>,
+    stats: Arc<ChunkStats>,
+    windows: Arc<WindowTimeline>,
+    input: Arc<InputActivity>,
+    tabs: Arc<BrowserTimeline>,
+    proxy: Option<ProxyConfig>,
+    proxy_links: Arc<ProxyLinks>,
+    frame_hashing: bool,
+    frame_hashes: Arc<FrameHashes>,
+    memory: Arc<MemoryStats>,
+    latest_frame: Arc<LatestFrame>,
+    audio_ring: Arc<AudioRing>,
+    policy: Arc<PolicyState>,
+    blackout: Arc<BlackoutState>,
+    flush_requests: Arc<AtomicU64>,
+    overlap_ms: u64,
+}
+
 struct VideoPipelineHandles {
     pipeline: gst::Pipeline,
     chunk_buffer: Arc<Mutex<VideoChunkBuffer>>,
+    layout: Option<LiveLayout>,
+    #[cfg(target_os = "linux")]
+    screen_cast: Option<ScreenCastSession>,
+    #[cfg(target_os = "linux")]
+    screencopy: Option<Screencopy>,
+    followed_window: Option<String>,
 }
 
 struct AudioPipelineHandles {
@@ -275,6 +2295,101 @@ struct AudioPipelineHandles {
     chunk_buffer: Arc<Mutex<AudioChunkBuffer>>,
 }
 
+struct BuiltPipelines {
+    video: VideoPipelineHandles,
+    system_audio: AudioPipelineHandles,
+    mic: Option<AudioPipelineHandles>,
+    matched_overrides: Vec<String>,
+}
+
+impl BuiltPipelines {
+    fn labelled(&self) -> impl Iterator<Item = (&'static str, &gst::Pipeline)> {
+        [("video", &self.video.pipeline), ("system_audio", &self.system_audio.pipeline)]
+            .into_iter()
+            .chain(self.mic.as_ref().map(|mic| ("mic", &mic.pipeline)))
+    }
+
+    /// Starts the first chunk of every stream now rather than when the
+    /// pipelines were built.
+    fn restart_chunks(&self) {
+        self.video.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).restart();
+        self.system_audio.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).restart();
+        if let Some(mic) = &self.mic {
+            mic.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).restart();
+        }
+    }
+
+    fn teardown(self) {
+        for (_, pipeline) in self.labelled() {
+            let _ = pipeline.set_state(gst::State::Null);
+        }
+    }
+}
+
+/// What `configure_pipelines` built for a session, until the manager takes
+/// it over.
+struct SessionResources {
+    video: VideoPipelineHandles,
+    system_audio: AudioPipelineHandles,
+    mic: Option<AudioPipelineHandles>,
+    #[cfg(target_os = "linux")]
+    inhibitor: Option<Inhibitor>,
+    chunk_sender: mpsc::Sender<CapturedChunk>,
+    chunk_consumer: JoinHandle<()>,
+    annotations: Option<Arc<SessionAnnotations>>,
+    preview_playlist: Option<PathBuf>,
+    recording: Option<SharedRecording>,
+    sink_recordings: Vec<SharedRecording>,
+}
+
+impl SessionResources {
+    /// Stops the pipelines and closes every chunk sender, so the consumer
+    /// drains, finalizes the recording and sinks, and exits. The portal
+    /// session and the inhibitor are released as they drop.
+    fn release(self) {
+        let _ = self.video.pipeline.set_state(gst::State::Null);
+        let _ = self.system_audio.pipeline.set_state(gst::State::Null);
+        self.video.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).close();
+        self.system_audio.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).close();
+        if let Some(mic) = &self.mic {
+            let _ = mic.pipeline.set_state(gst::State::Null);
+            mic.chunk_buffer.lock().unwrap_or_else(PoisonError::into_inner).close();
+        }
+    }
+}
+
+/// What `stop_capture` takes out of the manager to finish a session
+/// without holding the state lock.
+struct StoppedSession {
+    video_pipeline: Option<gst::Pipeline>,
+    system_audio_pipeline: Option<gst::Pipeline>,
+    mic_pipeline: Option<gst::Pipeline>,
+    video_chunk_buffer: Option<Arc<Mutex<VideoChunkBuffer>>>,
+    system_audio_chunk_buffer: Option<Arc<Mutex<AudioChunkBuffer>>>,
+    mic_chunk_buffer: Option<Arc<Mutex<AudioChunkBuffer>>>,
+    chunk_sender: Option<mpsc::Sender<CapturedChunk>>,
+    chunk_consumer: Option<JoinHandle<()>>,
+    recording: Option<SharedRecording>,
+    sink_recordings: Vec<SharedRecording>,
+    annotations: Option<Arc<SessionAnnotations>>,
+    session_id: Option<String>,
+    started_unix_ms: u128,
+    started: Option<Instant>,
+    anchor: Option<ClockAnchor>,
+    markers: Vec<Marker>,
+    options: CaptureOptions,
+}
+
+/// Pipelines parked in `Paused` with the channel their chunks go to.
+struct ArmedPipelines {
+    /// The options they were built for, as JSON, since `CaptureOptions`
+    /// isn't comparable.
+    options: serde_json::Value,
+    sender: mpsc::Sender<CapturedChunk>,
+    receiver: mpsc::Receiver<CapturedChunk>,
+    pipelines: BuiltPipelines,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CapturedChunk {
     pub id: u64,
@@ -287,45 +2402,305 @@ pub struct CapturedChunk {
     pub data: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Marker {
+    pub id: u64,
+    pub label: Option<String>,
+    pub unix_ms: u128,
+    pub offset_ms: u64,
+}
+
+/// What put the capture into [`CaptureState::Failed`], kept until the next start.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureError {
+    pub message: String,
+    /// `video`, `system_audio` or `mic`; `None` when the failure wasn't
+    /// inside a pipeline, e.g. an invalid option.
+    pub pipeline: Option<String>,
+    /// The GStreamer element that reported the error.
+    pub element: Option<String>,
+    pub debug: Option<String>,
+    pub session_id: Option<String>,
+    pub unix_ms: u128,
+}
+
+impl CaptureError {
+    fn new(message: String, pipeline: Option<&str>) -> Self {
+        Self {
+            message,
+            pipeline: pipeline.map(str::to_string),
+            element: None,
+            debug: None,
+            session_id: None,
+            unix_ms: unix_now_millis(),
+        }
+    }
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.pipeline, &self.element) {
+            (Some(pipeline), Some(element)) => write!(f, "{pipeline} pipeline failed in {element}: {}", self.message),
+            (Some(pipeline), None) => write!(f, "{pipeline} pipeline failed: {}", self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Takes the first error `pipeline` posted on its bus, if any.
+fn pipeline_error(pipeline: &gst::Pipeline, label: &str) -> Option<CaptureError> {
+    let message = pipeline.bus()?.pop_filtered(&[gst::MessageType::Error])?;
+    let gst::MessageView::Error(err) = message.view() else {
+        return None;
+    };
+    Some(CaptureError {
+        element: err.src().map(|src| src.name().to_string()),
+        debug: err.debug().map(|debug| debug.to_string()),
+        ..CaptureError::new(err.error().to_string(), Some(label))
+    })
+}
+
+fn unix_now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+const DEFAULT_VIDEO_FPS: u32 = 30;
+const VIDEO_CAPS_FILTER: &str = "video_caps";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VideoQuality {
+    pub framerate: u32,
+    pub scale_percent: u32,
+}
+
+impl VideoQuality {
+    /// The lower of this quality and each cap, field by field.
+    fn capped(self, adaptive: Option<VideoQuality>, battery_framerate: Option<u32>) -> VideoQuality {
+        let mut quality = self;
+        if let Some(cap) = adaptive {
+            quality.framerate = quality.framerate.min(cap.framerate);
+            quality.scale_percent = quality.scale_percent.min(cap.scale_percent);
+        }
+        if let Some(framerate) = battery_framerate {
+            quality.framerate = quality.framerate.min(framerate);
+        }
+        quality
+    }
+}
+
+impl Default for VideoQuality {
+    fn default() -> Self {
+        Self {
+            framerate: DEFAULT_VIDEO_FPS,
+            scale_percent: 100,
+        }
+    }
+}
+
+fn video_caps(framerate: u32, size: Option<(i32, i32)>, hdr: HdrMode) -> gst::Caps {
+    let mut caps = gst::Caps::new_empty();
+    let caps_mut = caps.get_mut().expect("fresh caps are writable");
+    for format in hdr.formats() {
+        let mut builder = gst::Structure::builder("video/x-raw")
+            .field("format", *format)
+            .field("framerate", &gst::Fraction::new(framerate.max(1) as i32, 1));
+        if let Some((width, height)) = size {
+            builder = builder.field("width", &width).field("height", &height);
+        }
+        if let Some(colorimetry) = hdr.colorimetry() {
+            builder = builder.field("colorimetry", &colorimetry);
+        }
+        caps_mut.append_structure(builder.build());
+    }
+    caps
+}
+
+/// Points a `pipewiresrc` at `node_id`; returns false if `src` can't be pointed.
+fn set_target_node(src: &gst::Element, node_id: u32) -> bool {
+    if src.find_property("target-node").is_none() {
+        return false;
+    }
+    src.set_property("target-node", &node_id);
+    true
+}
+
+/// `native` scaled to `percent`, or `None` at full size.
+fn scaled_size((width, height): (i32, i32), percent: u32) -> Option<(i32, i32)> {
+    if percent >= 100 {
+        return None;
+    }
+    // keep dimensions even for chroma-subsampled consumers
+    let scaled = |value: i32| ((value * percent as i32 / 100) & !1).max(2);
+    Some((scaled(width), scaled(height)))
+}
+
+/// Adds the focused-window spans overlapping the chunk as `windows`.
+fn attach_windows(metadata: &mut serde_json::Value, windows: &WindowTimeline, start_ts_unix_nanos: u128) {
+    let spans = windows.between((start_ts_unix_nanos / 1_000_000) as u64, unix_now_millis() as u64);
+    if let (Some(object), false) = (metadata.as_object_mut(), spans.is_empty()) {
+        object.insert("windows".to_string(), json!(spans));
+    }
+}
+
+/// Adds the browser tabs active during the chunk as `tabs`.
+fn attach_tabs(metadata: &mut serde_json::Value, tabs: &BrowserTimeline, start_ts_unix_nanos: u128) {
+    let spans = tabs.between((start_ts_unix_nanos / 1_000_000) as u64, unix_now_millis() as u64);
+    if let (Some(object), false) = (metadata.as_object_mut(), spans.is_empty()) {
+        object.insert("tabs".to_string(), json!(spans));
+    }
+}
+
+/// Adds the input counts since the previous chunk as `input`.
+fn attach_input(metadata: &mut serde_json::Value, input: &InputActivity, chunk_start: &mut InputCounts) {
+    let totals = input.totals();
+    let counts = totals.since(*chunk_start);
+    *chunk_start = totals;
+    if let (Some(object), true) = (metadata.as_object_mut(), input.is_enabled()) {
+        object.insert("input".to_string(), json!(counts));
+    }
+}
+
 fn missing_element(name: &str) -> anyhow::Error {
     anyhow!("missing GStreamer element '{name}' — ensure required plugins are installed")
 }
 
+/// Builds a user-supplied gst-launch source description into a bin that
+/// takes the place of the stock source; the standard conversion chain and
+/// appsink are still appended after it.
+fn custom_source(description: &str, name: &str) -> Result<gst::Element> {
+    let bin = gst::parse::bin_from_description_full(description, true, None, gst::ParseFlags::FATAL_ERRORS)
+        .map_err(|err| anyhow!("invalid custom source for {name}: {err}"))?;
+    if bin.static_pad("src").is_none() {
+        return Err(anyhow!("custom source for {name} must leave exactly one unlinked src pad"));
+    }
+    let has_sink = bin
+        .iterate_recurse()
+        .into_iter()
+        .flatten()
+        .any(|element| element.element_flags().contains(gst::ElementFlags::SINK));
+    if has_sink {
+        return Err(anyhow!("custom source for {name} must not contain sink elements"));
+    }
+    bin.set_property("name", name);
+    println!("[capture] using custom {name}: {description}");
+    Ok(bin.upcast())
+}
+
 impl CaptureManager {
-    fn build_video_pipeline(options: &CaptureOptions, sender: Option<mpsc::Sender<CapturedChunk>>) -> Result<VideoPipelineHandles> {
+    fn build_video_pipeline(&self, options: &CaptureOptions, context: ChunkContext) -> Result<VideoPipelineHandles> {
         let pipeline = gst::Pipeline::new();
-        let src = gst::ElementFactory::make("pipewiresrc")
-            .name("video_source")
-            .build()
-            .map_err(|_| missing_element("pipewiresrc"))?;
-        src.set_property("do-timestamp", &true);
-
-        match &options.target {
-            CaptureTarget::FullDisplay => {
-                // Nothing extra — the portal UI will prompt for full display selection.
+        let src = match (&options.custom_sources.video, &options.target) {
+            (Some(description), _) => custom_source(description, "video_source")?,
+            #[cfg(feature = "ndi")]
+            (None, CaptureTarget::Ndi { source }) => ndi::source(source, "video_source")?,
+            (None, CaptureTarget::Device { path }) => devices::v4l2_source(path, "video_source")?,
+            (None, CaptureTarget::Windows { ids, tiling, width, height }) => {
+                layout::tiled_windows(ids, *tiling, *width, *height, "video_source")?
             }
-            CaptureTarget::Window { id } => {
-                if let Ok(node_id) = id.parse::<u32>() {
-                    if src.find_property("target-node").is_some() {
-                        src.set_property("target-node", &node_id);
-                    }
+            (None, target) => Self::build_pipewire_source(target)?,
+        };
+        // the portal hands out the screen the user picked (or restored);
+        // wlroots compositors without one are recorded through screencopy
+        #[cfg(target_os = "linux")]
+        let (src, screen_cast, screencopy) = match (&options.custom_sources.video, &options.target) {
+            // the portal can't be told which screen to share; screencopy can
+            (None, CaptureTarget::FullDisplay) if options.display.is_some() && wlr_screencopy::supported() => {
+                let framerate = options.base_video_quality().framerate;
+                let (screencopy_src, screencopy) =
+                    wlr_screencopy::start(framerate, options.display.as_deref(), "video_source")?;
+                (screencopy_src, None, Some(screencopy))
+            }
+            (None, CaptureTarget::FullDisplay) => match self.reuse_screen_cast(&src)? {
+                Some(session) => (src, Some(session), None),
+                None if wlr_screencopy::supported() => {
+                    let framerate = options.base_video_quality().framerate;
+                    let (screencopy_src, screencopy) = wlr_screencopy::start(framerate, None, "video_source")?;
+                    (screencopy_src, None, Some(screencopy))
                 }
+                None => (src, None, None),
+            },
+            _ => (src, None, None),
+        };
+        // start on the focused window; the focus follower takes over from there
+        let followed_window = match (&options.custom_sources.video, &options.target) {
+            (None, CaptureTarget::FollowFocus { .. }) => match focus::focused_window() {
+                Ok(window) => window.and_then(|window| {
+                    let node_id = window.node_id?;
+                    set_target_node(&src, node_id);
+                    println!("[capture] following focus, starting on {}", window.label());
+                    Some(window.id)
+                }),
+                Err(err) => {
+                    eprintln!("[capture] could not query the focused window: {err}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        let (src, layout) = match &options.layout {
+            Some(config) => {
+                let (composite, live) = layout::build(config, src)?;
+                (composite, Some(live))
             }
-        }
+            None => (src, None),
+        };
 
         let convert = gst::ElementFactory::make("videoconvert")
             .name("video_convert")
             .build()
             .map_err(|_| missing_element("videoconvert"))?;
+        options.hdr.configure_convert(&convert);
         let scale = gst::ElementFactory::make("videoscale")
             .name("video_scale")
             .build()
             .map_err(|_| missing_element("videoscale"))?;
 
-        let caps = gst::Caps::builder("video/x-raw")
-            .field("format", &"RGBA")
-            .field("framerate", &gst::Fraction::new(30, 1))
-            .build();
+        let rate = gst::ElementFactory::make("videorate")
+            .name("video_rate")
+            .build()
+            .map_err(|_| missing_element("videorate"))?;
+        // videorate repeats the last frame across a stall, flagging the copies
+        if let Some(max_ms) = options.freeze_fill_max_ms {
+            rate.set_property("max-duplication-time", max_ms * 1_000_000);
+        }
+        let capsfilter = gst::ElementFactory::make("capsfilter")
+            .name(VIDEO_CAPS_FILTER)
+            .build()
+            .map_err(|_| missing_element("capsfilter"))?;
+        let quality = options.base_video_quality();
+        capsfilter.set_property("caps", &video_caps(quality.framerate, None, options.hdr));
+        if quality.scale_percent < 100 {
+            // the scaled size needs the native one, known once caps arrive
+            let capsfilter = capsfilter.downgrade();
+            let hdr = options.hdr;
+            scale
+                .static_pad("sink")
+                .ok_or_else(|| anyhow!("videoscale has no sink pad"))?
+                .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                    let Some(gst::PadProbeData::Event(event)) = &info.data else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+                    let gst::EventView::Caps(caps) = event.view() else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+                    let native = caps.caps().structure(0).and_then(|structure| {
+                        Some((
+                            structure.get::<i32>("width").ok()?,
+                            structure.get::<i32>("height").ok()?,
+                        ))
+                    });
+                    if let (Some(native), Some(capsfilter)) = (native, capsfilter.upgrade()) {
+                        let size = scaled_size(native, quality.scale_percent);
+                        capsfilter.set_property("caps", &video_caps(quality.framerate, size, hdr));
+                    }
+                    gst::PadProbeReturn::Remove
+                });
+        }
 
         let sink = gst::ElementFactory::make("appsink")
             .name("video_sink")
@@ -336,31 +2711,108 @@ impl CaptureManager {
             .dynamic_cast::<gst_app::AppSink>()
             .map_err(|_| anyhow!("failed to downcast appsink"))?;
 
-        appsink.set_caps(Some(&caps));
         appsink.set_property("emit-signals", &true);
         appsink.set_property("sync", &false);
-        appsink.set_property("max-buffers", &5u32);
-        appsink.set_property("drop", &true);
+        options.buffering.configure_appsink(&appsink, options.buffering.video_max_buffers);
 
-        pipeline.add_many(&[&src, &convert, &scale, &sink])?;
-        gst::Element::link_many(&[&src, &convert, &scale, &sink])?;
+        // crop, rotate and reshape before conversion, on the source's native format
+        let mut chain = vec![src, rate];
+        if let Some(rect) = options.crop {
+            chain.push(geometry::crop(rect)?);
+        }
+        if let Some(degrees) = options.rotation.filter(|degrees| *degrees != 0) {
+            chain.push(geometry::rotate(degrees)?);
+        }
+        if let Some(preset) = options.aspect {
+            chain.push(geometry::aspect(preset, options.aspect_mode)?);
+        }
+        if options.privacy.as_ref().is_some_and(|filter| filter.blur_faces) {
+            chain.extend(privacy::face_blur()?);
+        }
+        // after the privacy filter, so the banner itself is never blurred
+        if let Some(text) = options.consent.as_ref().and_then(|consent| consent.banner.as_deref()) {
+            chain.push(consent::banner(text)?);
+        }
+        chain.extend([convert, scale, capsfilter.clone()]);
+        pipeline.add_many(&chain)?;
+        pipeline.add(&sink)?;
+        gst::Element::link_many(&chain)?;
+        // redact on the capsfilter's output, ahead of the tee and every sink
+        if let Some(filter) = options.privacy.as_ref().filter(|filter| filter.redact_pii) {
+            let pad = capsfilter
+                .static_pad("src")
+                .ok_or_else(|| anyhow!("capsfilter has no src pad"))?;
+            privacy::add_pii_redactor(&pad, filter)?;
+        }
+        if options.app_policy.is_some() {
+            let pad = capsfilter
+                .static_pad("src")
+                .ok_or_else(|| anyhow!("capsfilter has no src pad"))?;
+            context.policy.add_blanking_probe(&pad);
+        }
+        if options.blackout_when_hidden && matches!(options.target, CaptureTarget::Window { .. }) {
+            let pad = capsfilter
+                .static_pad("src")
+                .ok_or_else(|| anyhow!("capsfilter has no src pad"))?;
+            context.blackout.add_probe(&pad);
+        }
+        #[cfg(feature = "ndi")]
+        let has_ndi_output = options.ndi_output.is_some();
+        #[cfg(not(feature = "ndi"))]
+        let has_ndi_output = false;
+        if has_ndi_output || options.shm_output.is_some() {
+            // side outputs hang off a tee; the appsink keeps its own queue so
+            // none of them can stall chunking
+            let tee = gst::ElementFactory::make("tee")
+                .name("video_tee")
+                .build()
+                .map_err(|_| missing_element("tee"))?;
+            let queue = gst::ElementFactory::make("queue")
+                .name("video_capture_queue")
+                .build()
+                .map_err(|_| missing_element("queue"))?;
+            options.buffering.configure_queue(&queue);
+            pipeline.add_many([&tee, &queue])?;
+            gst::Element::link_many([&capsfilter, &tee, &queue, &sink])?;
+            #[cfg(feature = "ndi")]
+            if let Some(sender_name) = &options.ndi_output {
+                ndi::add_sender(&pipeline, &tee, sender_name)?;
+            }
+            if let Some(socket_path) = &options.shm_output {
+                shm::add_output(&pipeline, &tee, socket_path)?;
+            }
+        } else {
+            capsfilter.link(&sink)?;
+        }
 
-        let chunk_buffer = Arc::new(Mutex::new(VideoChunkBuffer::new_with_sender(
+        let load_stats = Arc::clone(&context.load_stats);
+        let chunk_buffer = Arc::new(Mutex::new(VideoChunkBuffer::new_with_context(
             options.chunk_duration(),
             options.debug_save,
-            sender,
+            context,
         )));
         let chunk_buffer_clone = Arc::clone(&chunk_buffer);
 
+        let tuning = options.capture_thread_tuning.clone();
+        let tuned = AtomicBool::new(tuning.is_default());
         let callbacks = gst_app::AppSinkCallbacks::builder()
             .new_sample(move |appsink| {
+                // appsink callbacks run on GStreamer's streaming thread
+                if !tuned.swap(true, Ordering::Relaxed) {
+                    tuning.apply_to_current("video");
+                }
                 let sample = appsink
                     .pull_sample()
                     .map_err(|_| gst::FlowError::Error)?;
+                let started = Instant::now();
+                let age = latency::pipeline_latency(appsink, &sample);
+                let anchor = ClockAnchor::at(appsink);
+                // a panic mid-sample leaves at worst a partial chunk; keep capturing
                 let mut guard = chunk_buffer_clone
                     .lock()
-                    .map_err(|_| gst::FlowError::Error)?;
-                guard.handle_sample(&sample);
+                    .unwrap_or_else(PoisonError::into_inner);
+                guard.handle_sample(&sample, age, anchor);
+                load_stats.record(started.elapsed());
                 Ok(gst::FlowSuccess::Ok)
             })
             .build();
@@ -370,19 +2822,58 @@ impl CaptureManager {
         Ok(VideoPipelineHandles {
             pipeline,
             chunk_buffer,
+            layout,
+            #[cfg(target_os = "linux")]
+            screen_cast,
+            #[cfg(target_os = "linux")]
+            screencopy,
+            followed_window,
         })
     }
 
-    fn build_system_audio_pipeline(options: &CaptureOptions, sender: Option<mpsc::Sender<CapturedChunk>>) -> Result<AudioPipelineHandles> {
+    fn build_pipewire_source(target: &CaptureTarget) -> Result<gst::Element> {
+        let src = gst::ElementFactory::make("pipewiresrc")
+            .name("video_source")
+            .build()
+            .map_err(|_| missing_element("pipewiresrc"))?;
+        src.set_property("do-timestamp", &true);
+
+        match target {
+            CaptureTarget::FullDisplay => {
+                // pointed at the portal's stream by `portal::attach`
+            }
+            CaptureTarget::Window { id } => {
+                if let Ok(node_id) = id.parse::<u32>() {
+                    set_target_node(&src, node_id);
+                }
+            }
+            CaptureTarget::FollowFocus { .. } => {
+                // pointed at the focused window once the pipeline is built
+            }
+            CaptureTarget::Windows { .. } => {
+                // handled by the tiled windows bin
+            }
+            CaptureTarget::Device { .. } => {
+                // handled by the V4L2 source bin
+            }
+            #[cfg(feature = "ndi")]
+            CaptureTarget::Ndi { .. } => {
+                // handled by the NDI source bin
+            }
+        }
+        Ok(src)
+    }
+
+    fn build_system_audio_pipeline(options: &CaptureOptions, context: ChunkContext) -> Result<AudioPipelineHandles> {
         let device = std::env::var("SC_SYSTEM_AUDIO_DEVICE")
             .unwrap_or_else(|_| "@DEFAULT_SINK@.monitor".to_string());
-        Self::build_pulse_audio_pipeline("system_audio_source", "system_audio", Some(device), options, sender)
+        Self::build_pulse_audio_pipeline("system_audio_source", "system_audio", Some(device), options, context)
     }
 
-    fn build_mic_audio_pipeline(options: &CaptureOptions, sender: Option<mpsc::Sender<CapturedChunk>>) -> Result<AudioPipelineHandles> {
+    fn build_mic_audio_pipeline(options: &CaptureOptions, context: ChunkContext) -> Result<AudioPipelineHandles> {
         let device = std::env::var("SC_MIC_AUDIO_DEVICE")
             .unwrap_or_else(|_| "@DEFAULT_SOURCE@".to_string());
-        Self::build_pulse_audio_pipeline("mic_audio_source", "mic", Some(device), options, sender)
+        Self::build_pulse_audio_pipeline("mic_audio_source", "mic", Some(device), options, context)
     }
 
     fn build_pulse_audio_pipeline(
@@ -390,19 +2881,28 @@ impl CaptureManager {
         label: &'static str,
         device: Option<String>,
         options: &CaptureOptions,
-        sender: Option<mpsc::Sender<CapturedChunk>>,
+        context: ChunkContext,
     ) -> Result<AudioPipelineHandles> {
         let pipeline = gst::Pipeline::new();
-        let src = gst::ElementFactory::make("pulsesrc")
-            .name(source_name)
-            .build()
-            .map_err(|_| missing_element("pulsesrc"))?;
-
-        if let Some(device_name) = device {
-            if src.find_property("device").is_some() {
-                src.set_property("device", &device_name);
+        let custom = match label {
+            "mic" => options.custom_sources.mic.as_deref(),
+            _ => options.custom_sources.system_audio.as_deref(),
+        };
+        let src = match custom {
+            Some(description) => custom_source(description, source_name)?,
+            None => {
+                let src = gst::ElementFactory::make("pulsesrc")
+                    .name(source_name)
+                    .build()
+                    .map_err(|_| missing_element("pulsesrc"))?;
+                if let Some(device_name) = device {
+                    if src.find_property("device").is_some() {
+                        src.set_property("device", &device_name);
+                    }
+                }
+                src
             }
-        }
+        };
 
         let convert = gst::ElementFactory::make("audioconvert")
             .name(format!("{source_name}_convert"))
@@ -413,11 +2913,7 @@ impl CaptureManager {
             .build()
             .map_err(|_| missing_element("audioresample"))?;
 
-        let caps = gst::Caps::builder("audio/x-raw")
-            .field("format", &"F32LE")
-            .field("rate", &48_000i32)
-            .field("channels", &2i32)
-            .build();
+        let caps = options.audio.caps();
 
         let sink = gst::ElementFactory::make("appsink")
             .name(format!("{source_name}_sink"))
@@ -431,29 +2927,35 @@ impl CaptureManager {
         appsink.set_caps(Some(&caps));
         appsink.set_property("emit-signals", &true);
         appsink.set_property("sync", &false);
-        appsink.set_property("max-buffers", &20u32);
-        appsink.set_property("drop", &true);
+        options.buffering.configure_appsink(&appsink, options.buffering.audio_max_buffers);
 
         pipeline.add_many(&[&src, &convert, &resample, &sink])?;
         gst::Element::link_many(&[&src, &convert, &resample, &sink])?;
 
-        let chunk_buffer = Arc::new(Mutex::new(AudioChunkBuffer::new_with_sender(
+        let chunk_buffer = Arc::new(Mutex::new(AudioChunkBuffer::new_with_context(
             label,
-            options.chunk_duration(),
+            options.audio_chunk_duration(),
             options.debug_save,
-            sender,
+            context,
         )));
         let chunk_buffer_clone = Arc::clone(&chunk_buffer);
 
+        let tuning = options.capture_thread_tuning.clone();
+        let tuned = AtomicBool::new(tuning.is_default());
         let callbacks = gst_app::AppSinkCallbacks::builder()
             .new_sample(move |appsink| {
+                if !tuned.swap(true, Ordering::Relaxed) {
+                    tuning.apply_to_current(label);
+                }
                 let sample = appsink
                     .pull_sample()
                     .map_err(|_| gst::FlowError::Error)?;
+                let age = latency::pipeline_latency(appsink, &sample);
+                let anchor = ClockAnchor::at(appsink);
                 let mut guard = chunk_buffer_clone
                     .lock()
-                    .map_err(|_| gst::FlowError::Error)?;
-                guard.handle_sample(&sample);
+                    .unwrap_or_else(PoisonError::into_inner);
+                guard.handle_sample(&sample, age, anchor);
                 Ok(gst::FlowSuccess::Ok)
             })
             .build();
@@ -473,19 +2975,55 @@ struct VideoChunkBuffer {
     chunk_start: Instant,
     frames_in_chunk: u64,
     accum: Vec<u8>,
+    /// Taken at the first sample of the current chunk.
+    chunk_anchor: Option<ClockAnchor>,
     start_ts_unix_nanos: u128,
     id_counter: u64,
     sender: Option<mpsc::Sender<CapturedChunk>>,
+    activity: Arc<ActivityTracker>,
+    screen_lock: Arc<LockTracker>,
+    sample_clock: Arc<SampleClock>,
+    stats: Arc<ChunkStats>,
+    windows: Arc<WindowTimeline>,
+    input: Arc<InputActivity>,
+    input_at_chunk_start: InputCounts,
+    tabs: Arc<BrowserTimeline>,
+    proxy: Option<ProxyConfig>,
+    proxy_links: Arc<ProxyLinks>,
+    proxy_warned: bool,
+    frame_hashing: bool,
+    frame_hashes: Arc<FrameHashes>,
+    frame_hash_warned: bool,
+    memory: Arc<MemoryStats>,
+    latest_frame: Arc<LatestFrame>,
+    latency: Arc<LatencyTracker>,
+    newest_sample: Option<SampleTimes>,
+    idle_seen: bool,
+    locked_seen: bool,
+    flush_requests: Arc<AtomicU64>,
+    flush_seen: u64,
+    overlap_ms: u64,
+    /// Bytes at the start of `accum` carried over from the last chunk, and
+    /// how long they play.
+    carried: (usize, u64),
+    /// PTS of the current chunk's first frame.
+    first_pts: Option<gst::ClockTime>,
+    /// Repeated frames in the current chunk.
+    freeze_spans: Vec<FreezeSpan>,
+    /// The last frame was a repeat.
+    frozen: bool,
+    /// The newest sample, whose caps describe a partial chunk sent on close.
+    last_sample: Option<gst::Sample>,
 }
 
 impl VideoChunkBuffer {
     fn new(chunk_duration: Duration, debug_save: bool) -> Self {
-        Self::new_with_sender(chunk_duration, debug_save, None)
+        Self::new_with_context(chunk_duration, debug_save, ChunkContext::default())
     }
-    fn new_with_sender(
+    fn new_with_context(
         chunk_duration: Duration,
         debug_save: bool,
-        sender: Option<mpsc::Sender<CapturedChunk>>,
+        context: ChunkContext,
     ) -> Self {
         Self {
             chunk_duration,
@@ -493,46 +3031,152 @@ impl VideoChunkBuffer {
             chunk_start: Instant::now(),
             frames_in_chunk: 0,
             accum: Vec::new(),
+            chunk_anchor: None,
             start_ts_unix_nanos: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .map(|d| d.as_nanos())
                 .unwrap_or_default(),
             id_counter: 0,
-            sender,
+            sender: context.sender,
+            activity: context.activity,
+            screen_lock: context.screen_lock,
+            sample_clock: context.sample_clock,
+            stats: context.stats,
+            windows: context.windows,
+            input_at_chunk_start: context.input.totals(),
+            input: context.input,
+            tabs: context.tabs,
+            proxy: context.proxy,
+            proxy_links: context.proxy_links,
+            proxy_warned: false,
+            frame_hashing: context.frame_hashing,
+            frame_hashes: context.frame_hashes,
+            frame_hash_warned: false,
+            memory: context.memory,
+            latest_frame: context.latest_frame,
+            latency: context.latency,
+            newest_sample: None,
+            idle_seen: false,
+            locked_seen: false,
+            flush_seen: context.flush_requests.load(Ordering::Relaxed),
+            flush_requests: context.flush_requests,
+            overlap_ms: context.overlap_ms,
+            carried: (0, 0),
+            first_pts: None,
+            freeze_spans: Vec::new(),
+            frozen: false,
+            last_sample: None,
         }
     }
 
-    fn handle_sample(&mut self, sample: &gst::Sample) {
+    /// Restarts the current chunk from now, for pipelines that sat armed
+    /// between being built and their first sample.
+    fn restart(&mut self) {
+        self.chunk_start = Instant::now();
+        self.start_ts_unix_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        self.input_at_chunk_start = self.input.totals();
+        self.flush_seen = self.flush_requests.load(Ordering::Relaxed);
+    }
+
+    /// `age` is how long ago the sample was captured at the source.
+    fn handle_sample(&mut self, sample: &gst::Sample, age: Option<Duration>, anchor: ClockAnchor) {
+        self.chunk_anchor.get_or_insert(anchor);
+        self.sample_clock.touch("video");
+        self.latest_frame.store(sample, unix_now_millis());
+        self.newest_sample = Some(self.latency.arrived("video", age));
         // append buffer bytes to accumulator
         if let Some(buffer) = sample.buffer() {
+            self.track_freeze(buffer);
             if let Ok(map) = buffer.map_readable() {
+                self.activity.observe_frame(map.as_slice());
                 self.accum.extend_from_slice(map.as_slice());
             }
         }
+        self.idle_seen |= self.activity.is_idle();
+        self.locked_seen |= self.screen_lock.is_locked();
         self.frames_in_chunk += 1;
-        if self.chunk_start.elapsed() >= self.chunk_duration {
-            self.flush(sample);
+        self.last_sample = Some(sample.clone());
+        let requested = self.flush_requested();
+        if requested || self.chunk_start.elapsed() >= self.chunk_duration {
+            self.flush(sample, requested);
+        }
+        self.memory.accumulating("video", self.accum.len());
+    }
+
+    /// True once per `flush_chunks_now` call.
+    fn flush_requested(&mut self) -> bool {
+        let current = self.flush_requests.load(Ordering::Relaxed);
+        std::mem::replace(&mut self.flush_seen, current) != current
+    }
+
+    /// Adds `buffer` to the current freeze span when videorate made it by
+    /// repeating the last frame, which it marks with the GAP flag.
+    fn track_freeze(&mut self, buffer: &gst::BufferRef) {
+        let pts = buffer.pts();
+        let first = *self.first_pts.get_or_insert_with(|| pts.unwrap_or_default());
+        if !buffer.flags().contains(gst::BufferFlags::GAP) {
+            self.frozen = false;
+            return;
+        }
+        let offset_ms = pts.map_or(0, |pts| pts.saturating_sub(first).mseconds());
+        let end_ms = offset_ms + buffer.duration().map_or(0, gst::ClockTime::mseconds);
+        match self.freeze_spans.last_mut() {
+            Some(span) if self.frozen => {
+                span.frames += 1;
+                span.duration_ms = end_ms.saturating_sub(span.offset_ms);
+            }
+            _ => self.freeze_spans.push(FreezeSpan {
+                offset_ms,
+                duration_ms: end_ms - offset_ms,
+                frames: 1,
+            }),
         }
+        self.frozen = true;
     }
 
-    fn flush(&mut self, sample: &gst::Sample) {
+    /// `requested` cuts short of the chunk duration.
+    fn flush(&mut self, sample: &gst::Sample, requested: bool) {
         // gather metadata
         let meta = VideoFrameMetadata::from_sample(sample);
         let id = self.id_counter;
         self.id_counter += 1;
-        let duration_ms = self.chunk_duration.as_millis() as u64;
-        let metadata = if let Some(m) = meta {
+        let duration_ms = if requested {
+            self.chunk_start.elapsed().as_millis() as u64
+        } else {
+            self.chunk_duration.as_millis() as u64
+        } + self.carried.1;
+        let mut metadata = if let Some(m) = meta {
             json!({
                 "width": m.width,
                 "height": m.height,
                 "format": m.format,
-                "pts": m.pts.map(|d| d.as_millis())
+                "frame_size": m.frame_size,
+                "colorimetry": m.color.as_ref().map(|color| color.colorimetry.clone()),
+                "color": m.color,
+                "hdr": m.hdr,
+                "pts": m.pts.map(|d| d.as_millis()),
+                "idle": self.idle_seen,
+                "screen_locked": self.locked_seen,
+                "flush_requested": requested,
+                "freeze_fill": self.freeze_spans
             })
         } else {
             json!(null)
         };
+        attach_windows(&mut metadata, &self.windows, self.start_ts_unix_nanos);
+        attach_tabs(&mut metadata, &self.tabs, self.start_ts_unix_nanos);
+        attach_input(&mut metadata, &self.input, &mut self.input_at_chunk_start);
+        if let (Some(object), Some(anchor)) = (metadata.as_object_mut(), self.chunk_anchor.take()) {
+            object.insert("clock_anchor".to_string(), json!(anchor));
+        }
+        if self.overlap_ms > 0 {
+            overlap::mark(&mut metadata, self.carried.0, self.carried.1);
+        }
 
-        let chunk = CapturedChunk {
+        let mut chunk = CapturedChunk {
             id,
             kind: "video".to_string(),
             start_ts_unix_nanos: self.start_ts_unix_nanos,
@@ -541,20 +3185,95 @@ impl VideoChunkBuffer {
             data_len: self.accum.len(),
             data: std::mem::take(&mut self.accum),
         };
+        if self.frame_hashing {
+            match frame_hash::hash_chunk(&chunk) {
+                Ok(hashes) => {
+                    if let Some(metadata) = chunk.metadata.as_object_mut() {
+                        metadata.insert("frame_hashes".to_string(), json!(hashes));
+                    }
+                    self.frame_hashes.extend(&hashes);
+                }
+                Err(err) => {
+                    if !std::mem::replace(&mut self.frame_hash_warned, true) {
+                        eprintln!("[capture] no frame hashes: {err}");
+                    }
+                }
+            }
+        }
+
+        let carry = (self.overlap_ms > 0)
+            .then(|| overlap::tail(&chunk, self.overlap_ms))
+            .flatten();
 
         if let Some(sender) = &self.sender {
+            let proxy = match self.proxy.as_ref().map(|config| proxy::make_proxy(&chunk, config)) {
+                Some(Ok((proxy, link))) => {
+                    self.proxy_links.push(link);
+                    Some(proxy)
+                }
+                Some(Err(err)) => {
+                    if !std::mem::replace(&mut self.proxy_warned, true) {
+                        eprintln!("[capture] no proxy chunks: {err}");
+                    }
+                    None
+                }
+                None => None,
+            };
+            let newest = self.newest_sample.take().unwrap_or_else(|| self.latency.arrived("video", None));
+            self.stats.record(&chunk);
+            self.latency.sent("video", id, newest);
+            self.memory.queued(chunk.data.len());
             let _ = sender.send(chunk);
+            if let Some(proxy) = proxy {
+                self.stats.record(&proxy);
+                self.latency.sent(&proxy.kind, id, newest);
+                self.memory.queued(proxy.data.len());
+                let _ = sender.send(proxy);
+            }
         } else {
             println!("[capture] video chunk ready id={} len={}", id, chunk.data_len);
         }
 
         self.frames_in_chunk = 0;
+        self.idle_seen = false;
+        self.locked_seen = false;
+        self.first_pts = None;
+        self.freeze_spans.clear();
         self.chunk_start = Instant::now();
         self.start_ts_unix_nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_nanos())
             .unwrap_or_default();
+        self.carry_over(carry);
     }
+
+    /// Sends the partial chunk, if frames arrived since the last one, and
+    /// drops this buffer's chunk sender, once its pipeline has stopped.
+    fn close(&mut self) {
+        if self.frames_in_chunk > 0 {
+            if let Some(sample) = self.last_sample.take() {
+                self.flush(&sample, true);
+            }
+        }
+        self.sender = None;
+    }
+
+    /// Starts the next chunk with `carry`, the end of the one just sent.
+    fn carry_over(&mut self, carry: Option<(Vec<u8>, u64)>) {
+        let (data, ms) = carry.unwrap_or_default();
+        self.carried = (data.len(), ms);
+        self.accum = data;
+        self.start_ts_unix_nanos -= ms as u128 * 1_000_000;
+    }
+}
+
+/// Frames repeated from the last good one while the source stalled,
+/// relative to the start of the chunk.
+#[derive(Debug, Clone, Serialize)]
+struct FreezeSpan {
+    offset_ms: u64,
+    duration_ms: u64,
+    frames: u64,
 }
 
 #[derive(Debug)]
@@ -562,6 +3281,9 @@ struct VideoFrameMetadata {
     width: i32,
     height: i32,
     format: Option<String>,
+    frame_size: Option<usize>,
+    color: Option<ColorInfo>,
+    hdr: bool,
     pts: Option<Duration>,
 }
 
@@ -579,11 +3301,15 @@ impl VideoFrameMetadata {
             .buffer()
             .and_then(|buffer| buffer.pts())
             .map(|clock_time| Duration::from_nanos(clock_time.nseconds()));
+        let info = gst_video::VideoInfo::from_caps(caps).ok();
 
         Some(Self {
             width,
             height,
             format,
+            frame_size: info.as_ref().map(|info| info.size()),
+            color: info.as_ref().map(ColorInfo::from_info),
+            hdr: info.as_ref().is_some_and(hdr::is_hdr),
             pts,
         })
     }
@@ -597,21 +3323,44 @@ struct AudioChunkBuffer {
     frames_accumulated: u64,
     last_metadata: Option<AudioFrameMetadata>,
     accum: Vec<u8>,
+    /// Taken at the first sample of the current chunk.
+    chunk_anchor: Option<ClockAnchor>,
     start_ts_unix_nanos: u128,
     id_counter: u64,
     sender: Option<mpsc::Sender<CapturedChunk>>,
+    activity: Arc<ActivityTracker>,
+    screen_lock: Arc<LockTracker>,
+    sample_clock: Arc<SampleClock>,
+    stats: Arc<ChunkStats>,
+    windows: Arc<WindowTimeline>,
+    input: Arc<InputActivity>,
+    input_at_chunk_start: InputCounts,
+    tabs: Arc<BrowserTimeline>,
+    latency: Arc<LatencyTracker>,
+    memory: Arc<MemoryStats>,
+    newest_sample: Option<SampleTimes>,
+    idle_seen: bool,
+    locked_seen: bool,
+    flush_requests: Arc<AtomicU64>,
+    flush_seen: u64,
+    overlap_ms: u64,
+    /// Bytes at the start of `accum` carried over from the last chunk, and
+    /// how long they play.
+    carried: (usize, u64),
+    /// Fed every sample as it arrives; only the mic has one.
+    ring: Option<Arc<AudioRing>>,
 }
 
 impl AudioChunkBuffer {
     fn new(label: &'static str, chunk_duration: Duration, debug_save: bool) -> Self {
-        Self::new_with_sender(label, chunk_duration, debug_save, None)
+        Self::new_with_context(label, chunk_duration, debug_save, ChunkContext::default())
     }
 
-    fn new_with_sender(
+    fn new_with_context(
         label: &'static str,
         chunk_duration: Duration,
         debug_save: bool,
-        sender: Option<mpsc::Sender<CapturedChunk>>,
+        context: ChunkContext,
     ) -> Self {
         Self {
             label,
@@ -621,46 +3370,114 @@ impl AudioChunkBuffer {
             frames_accumulated: 0,
             last_metadata: None,
             accum: Vec::new(),
+            chunk_anchor: None,
             start_ts_unix_nanos: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .map(|d| d.as_nanos())
                 .unwrap_or_default(),
             id_counter: 0,
-            sender,
+            sender: context.sender,
+            activity: context.activity,
+            screen_lock: context.screen_lock,
+            sample_clock: context.sample_clock,
+            stats: context.stats,
+            windows: context.windows,
+            input_at_chunk_start: context.input.totals(),
+            input: context.input,
+            tabs: context.tabs,
+            latency: context.latency,
+            memory: context.memory,
+            newest_sample: None,
+            idle_seen: false,
+            locked_seen: false,
+            flush_seen: context.flush_requests.load(Ordering::Relaxed),
+            flush_requests: context.flush_requests,
+            overlap_ms: context.overlap_ms,
+            carried: (0, 0),
+            ring: (label == "mic").then_some(context.audio_ring),
         }
     }
 
-    fn handle_sample(&mut self, sample: &gst::Sample) {
+    /// Restarts the current chunk from now, for pipelines that sat armed
+    /// between being built and their first sample.
+    fn restart(&mut self) {
+        self.chunk_start = Instant::now();
+        self.start_ts_unix_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        self.input_at_chunk_start = self.input.totals();
+        self.flush_seen = self.flush_requests.load(Ordering::Relaxed);
+    }
+
+    /// `age` is how long ago the sample was captured at the source.
+    fn handle_sample(&mut self, sample: &gst::Sample, age: Option<Duration>, anchor: ClockAnchor) {
+        self.chunk_anchor.get_or_insert(anchor);
+        self.sample_clock.touch(self.label);
+        self.newest_sample = Some(self.latency.arrived(self.label, age));
+        self.idle_seen |= self.activity.is_idle();
+        self.locked_seen |= self.screen_lock.is_locked();
+        let meta = AudioFrameMetadata::from_sample(sample);
         if let Some(buffer) = sample.buffer() {
             if let Ok(map) = buffer.map_readable() {
                 self.accum.extend_from_slice(map.as_slice());
+                if let (Some(ring), Some(meta)) = (&self.ring, &meta) {
+                    ring.push(meta.pcm_format(), map.as_slice(), unix_now_millis() as u64);
+                }
             }
         }
-        if let Some(meta) = AudioFrameMetadata::from_sample(sample) {
+        if let Some(meta) = meta {
             self.frames_accumulated += meta.frames as u64;
             self.last_metadata = Some(meta);
         }
 
-        if self.chunk_start.elapsed() >= self.chunk_duration {
-            self.flush();
+        let requested = self.flush_requested();
+        if requested || self.chunk_start.elapsed() >= self.chunk_duration {
+            self.flush(requested);
         }
+        self.memory.accumulating(self.label, self.accum.len());
+    }
+
+    /// True once per `flush_chunks_now` call.
+    fn flush_requested(&mut self) -> bool {
+        let current = self.flush_requests.load(Ordering::Relaxed);
+        std::mem::replace(&mut self.flush_seen, current) != current
     }
 
-    fn flush(&mut self) {
+    /// `requested` cuts short of the chunk duration.
+    fn flush(&mut self, requested: bool) {
         let id = self.id_counter;
         self.id_counter += 1;
-        let duration_ms = self.chunk_duration.as_millis() as u64;
-        let metadata = if let Some(meta) = self.last_metadata.take() {
+        let duration_ms = if requested {
+            self.chunk_start.elapsed().as_millis() as u64
+        } else {
+            self.chunk_duration.as_millis() as u64
+        } + self.carried.1;
+        let mut metadata = if let Some(meta) = self.last_metadata.take() {
             json!({
                 "rate": meta.rate,
                 "channels": meta.channels,
                 "format": meta.format,
+                "bytes_per_sample": meta.bytes_per_sample,
+                "layout": "interleaved",
                 "frames": meta.frames,
-                "pts_ms": meta.pts.map(|d| d.as_millis())
+                "pts_ms": meta.pts.map(|d| d.as_millis()),
+                "idle": self.idle_seen,
+                "screen_locked": self.locked_seen,
+                "flush_requested": requested
             })
         } else {
             json!(null)
         };
+        attach_windows(&mut metadata, &self.windows, self.start_ts_unix_nanos);
+        attach_tabs(&mut metadata, &self.tabs, self.start_ts_unix_nanos);
+        attach_input(&mut metadata, &self.input, &mut self.input_at_chunk_start);
+        if let (Some(object), Some(anchor)) = (metadata.as_object_mut(), self.chunk_anchor.take()) {
+            object.insert("clock_anchor".to_string(), json!(anchor));
+        }
+        if self.overlap_ms > 0 {
+            overlap::mark(&mut metadata, self.carried.0, self.carried.1);
+        }
 
         let chunk = CapturedChunk {
             id,
@@ -671,8 +3488,15 @@ impl AudioChunkBuffer {
             data_len: self.accum.len(),
             data: std::mem::take(&mut self.accum),
         };
+        let carry = (self.overlap_ms > 0)
+            .then(|| overlap::tail(&chunk, self.overlap_ms))
+            .flatten();
 
         if let Some(sender) = &self.sender {
+            let newest = self.newest_sample.take().unwrap_or_else(|| self.latency.arrived(self.label, None));
+            self.stats.record(&chunk);
+            self.latency.sent(self.label, id, newest);
+            self.memory.queued(chunk.data.len());
             let _ = sender.send(chunk);
         } else if self.debug_save {
             // handled by global consumer thread
@@ -681,11 +3505,31 @@ impl AudioChunkBuffer {
         }
 
         self.frames_accumulated = 0;
+        self.idle_seen = false;
+        self.locked_seen = false;
         self.chunk_start = Instant::now();
         self.start_ts_unix_nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_nanos())
             .unwrap_or_default();
+        self.carry_over(carry);
+    }
+
+    /// Sends the partial chunk, if samples arrived since the last one, and
+    /// drops this buffer's chunk sender, once its pipeline has stopped.
+    fn close(&mut self) {
+        if self.frames_accumulated > 0 {
+            self.flush(true);
+        }
+        self.sender = None;
+    }
+
+    /// Starts the next chunk with `carry`, the end of the one just sent.
+    fn carry_over(&mut self, carry: Option<(Vec<u8>, u64)>) {
+        let (data, ms) = carry.unwrap_or_default();
+        self.carried = (data.len(), ms);
+        self.accum = data;
+        self.start_ts_unix_nanos -= ms as u128 * 1_000_000;
     }
 }
 
@@ -693,7 +3537,9 @@ impl AudioChunkBuffer {
 struct AudioFrameMetadata {
     rate: i32,
     channels: i32,
+    /// Negotiated on the appsink, so it is what the bytes really hold.
     format: Option<String>,
+    bytes_per_sample: u32,
     frames: usize,
     pts: Option<Duration>,
 }
@@ -718,8 +3564,201 @@ impl AudioFrameMetadata {
             rate: info.rate() as i32,
             channels: info.channels() as i32,
             format,
+            bytes_per_sample: info.bpf() / info.channels().max(1),
             frames,
             pts,
         })
     }
+
+    fn pcm_format(&self) -> PcmFormat {
+        PcmFormat {
+            rate: self.rate as u32,
+            channels: self.channels as u32,
+            format: self.format.clone(),
+            bytes_per_sample: self.bytes_per_sample,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty pipelines whose chunk buffers send on `sender`, as a start
+    /// would have built them.
+    fn resources(sender: mpsc::Sender<CapturedChunk>) -> SessionResources {
+        let context = ChunkContext {
+            sender: Some(sender.clone()),
+            ..ChunkContext::default()
+        };
+        let audio = |label| AudioPipelineHandles {
+            pipeline: gst::Pipeline::new(),
+            chunk_buffer: Arc::new(Mutex::new(AudioChunkBuffer::new_with_context(
+                label,
+                Duration::from_secs(1),
+                false,
+                context.clone(),
+            ))),
+        };
+        SessionResources {
+            video: VideoPipelineHandles {
+                pipeline: gst::Pipeline::new(),
+                chunk_buffer: Arc::new(Mutex::new(VideoChunkBuffer::new_with_context(
+                    Duration::from_secs(1),
+                    false,
+                    context.clone(),
+                ))),
+                layout: None,
+                #[cfg(target_os = "linux")]
+                screen_cast: None,
+                #[cfg(target_os = "linux")]
+                screencopy: None,
+                followed_window: None,
+            },
+            system_audio: audio("system_audio"),
+            mic: Some(audio("mic")),
+            #[cfg(target_os = "linux")]
+            inhibitor: None,
+            chunk_sender: sender,
+            chunk_consumer: std::thread::spawn(|| {}),
+            annotations: None,
+            preview_playlist: None,
+            recording: Some(SharedRecording::default()),
+            sink_recordings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn stop_during_start_releases_the_session() {
+        ensure_gstreamer_initialized().unwrap();
+        let manager = CaptureManager::default();
+        manager.lock_state().machine.transition(CaptureState::Starting).unwrap();
+        assert!(manager.stop_capture().unwrap().is_none());
+
+        let (sender, receiver) = mpsc::channel();
+        let adopted = manager.adopt_session(
+            resources(sender),
+            "session-1",
+            ClockAnchor::now(),
+            &CaptureOptions::default(),
+        );
+        assert!(adopted.is_err());
+        assert_eq!(manager.status(), CaptureState::Idle);
+        let inner = manager.lock_state();
+        assert!(inner.video_pipeline.is_none());
+        assert!(inner.chunk_sender.is_none());
+        assert!(inner.recording.is_none());
+        assert!(inner.session_id.is_none());
+        drop(inner);
+        // every sender is gone, so the consumer drains and finalizes
+        assert!(receiver.recv().is_err());
+    }
+
+    fn running_manager() -> CaptureManager {
+        let manager = CaptureManager::default();
+        let mut inner = manager.lock_state();
+        inner.machine.transition(CaptureState::Starting).unwrap();
+        inner.machine.transition(CaptureState::Running).unwrap();
+        drop(inner);
+        manager
+    }
+
+    #[test]
+    fn policy_blocks_while_already_paused_by_idle() {
+        let manager = running_manager();
+        manager.pause_for(PauseReason::Idle).unwrap();
+        assert_eq!(manager.status(), CaptureState::Paused);
+        manager.pause_for(PauseReason::Policy).unwrap();
+
+        // activity returns while the denylisted app is still shown
+        manager.resume_for(PauseReason::Idle).unwrap();
+        assert_eq!(manager.status(), CaptureState::Paused);
+        assert!(manager.paused_for(PauseReason::Policy));
+        assert!(manager.resume_capture().is_err());
+
+        manager.resume_for(PauseReason::Policy).unwrap();
+        assert_eq!(manager.status(), CaptureState::Running);
+    }
+
+    #[test]
+    fn manual_resume_lifts_automatic_pauses() {
+        let manager = running_manager();
+        manager.pause_for(PauseReason::ScreenLock).unwrap();
+        manager.pause_capture().unwrap();
+        manager.resume_capture().unwrap();
+        assert_eq!(manager.status(), CaptureState::Running);
+        // a late unlock finds nothing to lift
+        manager.resume_for(PauseReason::ScreenLock).unwrap();
+        assert_eq!(manager.status(), CaptureState::Running);
+        assert!(manager.resume_for(PauseReason::Idle).is_ok());
+    }
+
+    #[test]
+    fn start_adopts_the_session_while_starting() {
+        ensure_gstreamer_initialized().unwrap();
+        let manager = CaptureManager::default();
+        manager.lock_state().machine.transition(CaptureState::Starting).unwrap();
+        let (sender, _receiver) = mpsc::channel();
+        let running = manager
+            .adopt_session(resources(sender), "session-1", ClockAnchor::now(), &CaptureOptions::default())
+            .unwrap();
+        assert_eq!(running.to, CaptureState::Running);
+        assert_eq!(manager.session_id().as_deref(), Some("session-1"));
+        assert!(manager.lock_state().chunk_sender.is_some());
+    }
+
+    #[test]
+    fn closing_a_buffer_sends_its_partial_chunk() {
+        ensure_gstreamer_initialized().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut buffer = AudioChunkBuffer::new_with_context(
+            "system_audio",
+            Duration::from_secs(60),
+            false,
+            ChunkContext {
+                sender: Some(sender),
+                ..ChunkContext::default()
+            },
+        );
+        let info = gst_audio::AudioInfo::builder(gst_audio::AudioFormat::S16le, 48_000, 2)
+            .build()
+            .unwrap();
+        let sample = gst::Sample::builder()
+            .buffer(&gst::Buffer::from_mut_slice(vec![0u8; 4 * 480]))
+            .caps(&info.to_caps().unwrap())
+            .build();
+        buffer.handle_sample(&sample, None, ClockAnchor::now());
+        assert!(receiver.try_recv().is_err());
+
+        buffer.close();
+        let chunk = receiver.try_recv().unwrap();
+        assert_eq!(chunk.data_len, 4 * 480);
+        assert_eq!(chunk.metadata["frames"], 480);
+        // with the sender gone a consumer would finish here
+        assert!(matches!(receiver.try_recv(), Err(mpsc::TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn battery_and_adaptive_caps_compose() {
+        let base = VideoQuality {
+            framerate: 30,
+            scale_percent: 100,
+        };
+        let adaptive = VideoQuality {
+            framerate: 20,
+            scale_percent: 75,
+        };
+        assert_eq!(base.capped(None, None), base);
+        assert_eq!(base.capped(Some(adaptive), Some(10)).framerate, 10);
+        assert_eq!(base.capped(Some(adaptive), Some(25)), adaptive);
+        // lifting the battery cap keeps the adaptive step-down
+        assert_eq!(base.capped(Some(adaptive), None), adaptive);
+        assert_eq!(
+            base.capped(None, Some(15)),
+            VideoQuality {
+                framerate: 15,
+                scale_percent: 100,
+            }
+        );
+    }
 }
@@ -0,0 +1,77 @@
+use std::sync::{Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+use crate::proxy;
+
+/// Where one chunk sits on the session's wall clock.
+#[derive(Debug, Clone)]
+pub struct ChunkSpan {
+    stream: String,
+    chunk_id: u64,
+    start_unix_ms: u64,
+    duration_ms: u64,
+}
+
+impl ChunkSpan {
+    pub fn of(chunk: &CapturedChunk) -> Self {
+        Self {
+            stream: chunk.kind.clone(),
+            chunk_id: chunk.id,
+            start_unix_ms: (chunk.start_ts_unix_nanos / 1_000_000) as u64,
+            duration_ms: chunk.duration_ms,
+        }
+    }
+
+    fn end_unix_ms(&self) -> u64 {
+        self.start_unix_ms + self.duration_ms
+    }
+}
+
+/// An audio chunk and the video chunks it overlaps in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkLink {
+    pub stream: String,
+    pub chunk_id: u64,
+    pub start_unix_ms: u64,
+    pub duration_ms: u64,
+    pub video_chunk_ids: Vec<u64>,
+}
+
+/// Spans of the running session's chunks, in the order they were consumed.
+#[derive(Default)]
+pub struct ChunkMap {
+    spans: Mutex<Vec<ChunkSpan>>,
+}
+
+impl ChunkMap {
+    pub fn reset(&self) {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn push(&self, span: ChunkSpan) {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner).push(span);
+    }
+
+    /// Every audio chunk with the video chunks it overlaps.
+    pub fn links(&self) -> Vec<ChunkLink> {
+        let spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        let video: Vec<&ChunkSpan> = spans.iter().filter(|span| span.stream == "video").collect();
+        spans
+            .iter()
+            .filter(|span| !proxy::is_video(&span.stream))
+            .map(|audio| ChunkLink {
+                stream: audio.stream.clone(),
+                chunk_id: audio.chunk_id,
+                start_unix_ms: audio.start_unix_ms,
+                duration_ms: audio.duration_ms,
+                video_chunk_ids: video
+                    .iter()
+                    .filter(|video| video.start_unix_ms < audio.end_unix_ms() && audio.start_unix_ms < video.end_unix_ms())
+                    .map(|video| video.chunk_id)
+                    .collect(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+use crate::acks::{AckTracker, ChunkInfo, ChunkKey};
+use crate::capture_manager::CapturedChunk;
+use crate::compression;
+use crate::session::{self, SessionManifest};
+use crate::uploads::UploadSpool;
+
+/// Where a stored chunk was found.
+enum Location {
+    /// Held in memory by a consumer's replay buffer.
+    Retained(Arc<CapturedChunk>),
+    /// A `debug_save` chunk file.
+    Debug(PathBuf),
+    /// A spooled upload.
+    Spool(PathBuf),
+}
+
+pub struct StoredChunk {
+    pub info: ChunkInfo,
+    location: Location,
+}
+
+impl StoredChunk {
+    /// The chunk's raw data, decompressed if it was stored compressed.
+    pub fn data(&self) -> Result<Vec<u8>> {
+        let stored = match &self.location {
+            Location::Retained(chunk) => return Ok(chunk.data.clone()),
+            Location::Debug(path) | Location::Spool(path) => std::fs::read(path)?,
+        };
+        compression::decompress(&self.info.metadata, stored)
+    }
+}
+
+/// Where to look for chunks that are still stored.
+pub struct ChunkStores<'a> {
+    pub output_root: &'a Path,
+    /// Id and start of the running session.
+    pub running: Option<(String, u128)>,
+    pub acks: &'a AckTracker,
+    pub chunk_dir: &'a Path,
+    pub spool: &'a UploadSpool,
+}
+
+impl ChunkStores<'_> {
+    /// Looks the chunk up in the replay buffers (running session only), the
+    /// `debug_save` chunks and the upload spool, in that order.
+    pub fn find(&self, session_id: &str, key: &ChunkKey) -> Result<Option<StoredChunk>> {
+        session::validate_id(session_id)?;
+        let running = self
+            .running
+            .as_ref()
+            .filter(|(id, _)| id == session_id)
+            .map(|(_, started)| *started);
+        if running.is_some() {
+            if let Some(chunk) = self.acks.find(key) {
+                return Ok(Some(StoredChunk {
+                    info: ChunkInfo::of(&chunk),
+                    location: Location::Retained(chunk),
+                }));
+            }
+        }
+
+        // debug chunks carry no session id, so the session's time span decides
+        let span = match running {
+            Some(started) => Some((started * 1_000_000, u128::MAX)),
+            None => SessionManifest::read(&self.output_root.join(session_id))
+                .ok()
+                .map(|manifest| {
+                    let start = manifest.started_unix_ms * 1_000_000;
+                    (start, start + manifest.duration_ms as u128 * 1_000_000)
+                }),
+        };
+        if let (Some((start, end)), true) = (span, self.chunk_dir.exists()) {
+            for entry in std::fs::read_dir(self.chunk_dir)? {
+                let raw = entry?.path();
+                let Some((start_ns, id, stream)) = parse_chunk_name(&raw) else {
+                    continue;
+                };
+                if id != key.chunk_id || stream != key.stream || !(start..=end).contains(&start_ns) {
+                    continue;
+                }
+                let metadata = serde_json::from_slice(&std::fs::read(raw.with_extension("json"))?)?;
+                let bytes = std::fs::metadata(&raw)?.len() as usize;
+                return Ok(Some(StoredChunk {
+                    info: ChunkInfo {
+                        stream,
+                        chunk_id: id,
+                        start_ts_unix_nanos: start_ns,
+                        // not recorded by debug_save
+                        duration_ms: 0,
+                        bytes,
+                        metadata,
+                    },
+                    location: Location::Debug(raw),
+                }));
+            }
+        }
+
+        let spooled = self.spool.pending()?.into_iter().find(|entry| {
+            entry.session_id == session_id && entry.stream == key.stream && entry.chunk_id == key.chunk_id
+        });
+        let Some(entry) = spooled else {
+            return Ok(None);
+        };
+        Ok(Some(StoredChunk {
+            location: Location::Spool(self.spool.data_path(&entry)?),
+            info: ChunkInfo {
+                stream: entry.stream,
+                chunk_id: entry.chunk_id,
+                start_ts_unix_nanos: entry.start_ts_unix_nanos,
+                duration_ms: entry.duration_ms,
+                bytes: entry.bytes,
+                metadata: entry.metadata,
+            },
+        }))
+    }
+
+    pub fn get(&self, session_id: &str, key: &ChunkKey) -> Result<StoredChunk> {
+        self.find(session_id, key)?
+            .ok_or_else(|| anyhow!("{} chunk {} of {session_id} is not stored", key.stream, key.chunk_id))
+    }
+}
+
+/// `chunk-{start_ns}-{id}-{kind}.raw`, as written by `debug_save`.
+pub fn parse_chunk_name(path: &Path) -> Option<(u128, u64, String)> {
+    let stem = path.file_name()?.to_str()?.strip_prefix("chunk-")?.strip_suffix(".raw")?;
+    let mut parts = stem.splitn(3, '-');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_debug_save_names() {
+        assert_eq!(
+            parse_chunk_name(Path::new("/tmp/out/chunk-1700000000000000000-12-video.raw")),
+            Some((1_700_000_000_000_000_000, 12, "video".to_string()))
+        );
+        // stream names may carry dashes of their own
+        assert_eq!(
+            parse_chunk_name(Path::new("chunk-5-0-video-proxy.raw")),
+            Some((5, 0, "video-proxy".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(parse_chunk_name(Path::new("chunk-5-0-video.mp4")), None);
+        assert_eq!(parse_chunk_name(Path::new("manifest.json")), None);
+        assert_eq!(parse_chunk_name(Path::new("chunk-abc-0-video.raw")), None);
+        assert_eq!(parse_chunk_name(Path::new("chunk-5-video.raw")), None);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Opt-in clipboard monitoring for a session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipboardConfig {
+    /// Keep the copied text, not just when the clipboard changed.
+    #[serde(default)]
+    pub capture_text: bool,
+    /// Applied in order to captured text before it is stored.
+    #[serde(default)]
+    pub redact: Vec<RedactRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactRule {
+    /// A regular expression, e.g. `\b\d{13,19}\b` for card numbers.
+    pub pattern: String,
+    #[serde(default = "RedactRule::default_replacement")]
+    pub replacement: String,
+}
+
+impl RedactRule {
+    fn default_replacement() -> String {
+        "[redacted]".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardChange {
+    pub unix_ms: u64,
+    /// MIME types offered, e.g. `text/plain` or `image/png`.
+    pub types: Vec<String>,
+    /// Redacted text, only with `capture_text`.
+    pub text: Option<String>,
+}
+
+struct ActiveLog {
+    capture_text: bool,
+    rules: Vec<(Regex, String)>,
+    last_hash: Option<u64>,
+    changes: Vec<ClipboardChange>,
+}
+
+/// Clipboard changes of the running session, written to its manifest.
+#[derive(Default)]
+pub struct ClipboardLog {
+    active: Mutex<Option<ActiveLog>>,
+}
+
+impl ClipboardLog {
+    /// Starts a fresh log for a session; `None` turns monitoring off.
+    pub fn start(&self, config: Option<&ClipboardConfig>) -> Result<()> {
+        let active = match config {
+            Some(config) => {
+                let rules = config
+                    .redact
+                    .iter()
+                    .map(|rule| {
+                        Regex::new(&rule.pattern)
+                            .map(|regex| (regex, rule.replacement.clone()))
+                            .map_err(|err| anyhow!("invalid clipboard redaction pattern '{}': {err}", rule.pattern))
+                    })
+                    .collect::<Result<_>>()?;
+                Some(ActiveLog {
+                    capture_text: config.capture_text,
+                    rules,
+                    // whatever is on the clipboard at start isn't a change
+                    last_hash: read_clipboard().ok().map(|(types, text)| hash(&types, &text)),
+                    changes: Vec::new(),
+                })
+            }
+            None => None,
+        };
+        *self.active.lock().unwrap_or_else(PoisonError::into_inner) = active;
+        Ok(())
+    }
+
+    /// Ends the session's log and returns what it recorded.
+    pub fn finish(&self) -> Vec<ClipboardChange> {
+        self.active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .map(|log| log.changes)
+            .unwrap_or_default()
+    }
+
+    fn is_active(&self) -> bool {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+    }
+
+    fn observe(&self, types: Vec<String>, text: Option<String>) {
+        let mut active = self.active.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(log) = active.as_mut() else {
+            return;
+        };
+        let current = hash(&types, &text);
+        if log.last_hash.replace(current) == Some(current) {
+            return;
+        }
+        let text = text.filter(|_| log.capture_text).map(|text| {
+            log.rules.iter().fold(text, |text, (regex, replacement)| {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            })
+        });
+        log.changes.push(ClipboardChange {
+            unix_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            types,
+            text,
+        });
+    }
+}
+
+fn hash(types: &[String], text: &Option<String>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    types.hash(&mut hasher);
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn run(program: &str, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("{program} failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// The offered MIME types and, when one is text, the text. Uses `wl-paste`
+/// on Wayland and `xclip` on X11.
+fn read_clipboard() -> Result<(Vec<String>, Option<String>)> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let types = if wayland {
+        run("wl-paste", &["--list-types"])?
+    } else {
+        run("xclip", &["-selection", "clipboard", "-o", "-t", "TARGETS"])?
+    };
+    let types: Vec<String> = String::from_utf8_lossy(&types)
+        .lines()
+        .map(str::to_string)
+        .filter(|mime| !mime.is_empty())
+        .collect();
+    let has_text = types.iter().any(|mime| mime.starts_with("text/") || mime == "UTF8_STRING");
+    let text = if !has_text {
+        None
+    } else if wayland {
+        Some(run("wl-paste", &["--no-newline", "--type", "text"])?)
+    } else {
+        Some(run("xclip", &["-selection", "clipboard", "-o"])?)
+    };
+    Ok((types, text.map(|text| String::from_utf8_lossy(&text).into_owned())))
+}
+
+/// Polls the clipboard while a session with `clipboard` set is running.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("clipboard".into())
+        .spawn(move || {
+            let mut read_failed = false;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let manager = app.state::<CaptureManager>();
+                if manager.status() != CaptureState::Running || !manager.clipboard_log().is_active() {
+                    continue;
+                }
+                match read_clipboard() {
+                    Ok((types, text)) => {
+                        read_failed = false;
+                        manager.clipboard_log().observe(types, text);
+                    }
+                    Err(err) => {
+                        if !std::mem::replace(&mut read_failed, true) {
+                            eprintln!("[clipboard] failed to read the clipboard: {err}");
+                        }
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
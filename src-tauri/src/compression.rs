@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::capture_manager::CapturedChunk;
+
+const ZSTD: &str = "zstd";
+
+/// A copy of `chunk` with zstd-compressed data. The metadata records the
+/// codec and both sizes, so readers know how to restore the raw data.
+pub fn compress(chunk: &CapturedChunk, level: i32) -> Result<CapturedChunk> {
+    let data = zstd::bulk::compress(&chunk.data, level)?;
+    let mut metadata = chunk.metadata.clone();
+    if !metadata.is_object() {
+        metadata = json!({});
+    }
+    metadata["compression"] = json!(ZSTD);
+    metadata["uncompressed_size"] = json!(chunk.data.len());
+    metadata["compressed_size"] = json!(data.len());
+    Ok(CapturedChunk {
+        id: chunk.id,
+        kind: chunk.kind.clone(),
+        start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+        duration_ms: chunk.duration_ms,
+        metadata,
+        data_len: data.len(),
+        data,
+    })
+}
+
+/// Restores data written by [`compress`]; uncompressed data passes through.
+pub fn decompress(metadata: &Value, data: Vec<u8>) -> Result<Vec<u8>> {
+    match metadata["compression"].as_str() {
+        None => Ok(data),
+        Some(ZSTD) => Ok(zstd::decode_all(data.as_slice())?),
+        Some(other) => Err(anyhow!("unsupported chunk compression '{other}'")),
+    }
+}
+
+/// Re-applies the compression recorded in `metadata` to edited raw data and
+/// refreshes the recorded sizes; uncompressed data passes through.
+pub fn recompress(metadata: &mut Value, data: Vec<u8>) -> Result<Vec<u8>> {
+    match metadata["compression"].as_str() {
+        None => Ok(data),
+        Some(ZSTD) => {
+            let packed = zstd::bulk::compress(&data, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+            metadata["uncompressed_size"] = json!(data.len());
+            metadata["compressed_size"] = json!(packed.len());
+            Ok(packed)
+        }
+        Some(other) => Err(anyhow!("unsupported chunk compression '{other}'")),
+    }
+}
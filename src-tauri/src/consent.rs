@@ -0,0 +1,33 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Consent metadata for workplace-compliance deployments, copied into the
+/// session manifest as given.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsentConfig {
+    /// Whether the people on screen or on the call were told about the recording.
+    #[serde(default)]
+    pub participants_notified: bool,
+    /// Deployment-specific fields, e.g. a policy id or a ticket reference.
+    #[serde(default)]
+    pub fields: BTreeMap<String, Value>,
+    /// Burn this text into the top-right corner of every frame, e.g. "● REC".
+    #[serde(default)]
+    pub banner: Option<String>,
+}
+
+pub fn banner(text: &str) -> Result<gst::Element> {
+    gst::ElementFactory::make("textoverlay")
+        .name("consent_banner")
+        .property("text", text)
+        .property_from_str("valignment", "top")
+        .property_from_str("halignment", "right")
+        .property("shaded-background", true)
+        .property("font-desc", "Sans Bold 18")
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'textoverlay' — install gst-plugins-base with Pango"))
+}
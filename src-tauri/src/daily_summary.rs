@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::CaptureManager;
+use crate::jobs::{JobQueue, JobSpec};
+use crate::session;
+
+const SUMMARY_DIR: &str = "summaries";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// One day of sessions, condensed for an LLM prompt.
+#[derive(Debug, Serialize)]
+pub struct DailySummary {
+    /// UTC date, `YYYY-MM-DD`.
+    pub date: String,
+    pub sessions: Vec<SessionEntry>,
+    pub recorded_ms: u64,
+    /// Focused time per app, longest first.
+    pub apps: Vec<AppTime>,
+    pub markers: Vec<MarkerEntry>,
+    /// Annotator output, e.g. transcripts or chunk summaries, in time order.
+    pub notes: Vec<NoteEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionEntry {
+    pub session_id: String,
+    pub started_unix_ms: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppTime {
+    pub app: String,
+    pub focused_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkerEntry {
+    pub session_id: String,
+    pub unix_ms: u64,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteEntry {
+    pub session_id: String,
+    pub unix_ms: u64,
+    pub annotator: String,
+    pub summary: Option<String>,
+    pub labels: Vec<String>,
+}
+
+/// Where the summary of `date` is written under `output_root`; the Markdown
+/// version sits next to it with an `.md` extension.
+pub fn output_path(output_root: &Path, date: &str) -> PathBuf {
+    output_root.join(SUMMARY_DIR).join(format!("{date}.json"))
+}
+
+/// Aggregates the sessions started on `date` (UTC) under `output_root` and
+/// writes the summary as JSON and Markdown.
+pub fn write(output_root: &Path, date: &str) -> Result<PathBuf> {
+    let day_start = parse_date(date)?;
+    let day_end = day_start + DAY_MS;
+    let mut summary = DailySummary {
+        date: date.to_string(),
+        sessions: Vec::new(),
+        recorded_ms: 0,
+        apps: Vec::new(),
+        markers: Vec::new(),
+        notes: Vec::new(),
+    };
+    let mut apps: BTreeMap<String, u64> = BTreeMap::new();
+    let mut manifests = session::list_manifests(output_root)?;
+    manifests.reverse();
+    for (_, manifest) in manifests {
+        let started = manifest.started_unix_ms as u64;
+        if !(day_start..day_end).contains(&started) {
+            continue;
+        }
+        summary.recorded_ms += manifest.duration_ms;
+        for span in &manifest.windows {
+            let app = span.app_id.clone().or_else(|| span.title.clone()).unwrap_or_default();
+            *apps.entry(app).or_default() += span.end_unix_ms.saturating_sub(span.start_unix_ms);
+        }
+        summary.markers.extend(manifest.markers.iter().map(|marker| MarkerEntry {
+            session_id: manifest.session_id.clone(),
+            unix_ms: marker.unix_ms as u64,
+            label: marker.label.clone(),
+        }));
+        summary.notes.extend(manifest.chunk_annotations.iter().map(|record| NoteEntry {
+            session_id: manifest.session_id.clone(),
+            unix_ms: (record.start_ts_unix_nanos / 1_000_000) as u64,
+            annotator: record.annotator.clone(),
+            summary: record.annotation.summary.clone(),
+            labels: record.annotation.labels.clone(),
+        }));
+        summary.sessions.push(SessionEntry {
+            session_id: manifest.session_id,
+            started_unix_ms: started,
+            duration_ms: manifest.duration_ms,
+        });
+    }
+    summary.apps = apps
+        .into_iter()
+        .filter(|(app, _)| !app.is_empty())
+        .map(|(app, focused_ms)| AppTime { app, focused_ms })
+        .collect();
+    summary.apps.sort_by(|a, b| b.focused_ms.cmp(&a.focused_ms));
+    summary.notes.sort_by_key(|note| note.unix_ms);
+
+    let path = output_path(output_root, date);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_vec_pretty(&summary)?)?;
+    std::fs::write(path.with_extension("md"), markdown(&summary))?;
+    println!("[summary] wrote {} ({} sessions)", path.display(), summary.sessions.len());
+    Ok(path)
+}
+
+fn markdown(summary: &DailySummary) -> String {
+    let mut out = format!("# Activity on {}\n\n", summary.date);
+    let _ = writeln!(
+        out,
+        "{} sessions, {} recorded.\n",
+        summary.sessions.len(),
+        minutes(summary.recorded_ms)
+    );
+    if !summary.apps.is_empty() {
+        out.push_str("## Apps\n\n");
+        for app in &summary.apps {
+            let _ = writeln!(out, "- {}: {}", app.app, minutes(app.focused_ms));
+        }
+        out.push('\n');
+    }
+    if !summary.markers.is_empty() {
+        out.push_str("## Markers\n\n");
+        for marker in &summary.markers {
+            let _ = writeln!(
+                out,
+                "- {} {}",
+                time_of_day(marker.unix_ms),
+                marker.label.as_deref().unwrap_or("(unlabelled)")
+            );
+        }
+        out.push('\n');
+    }
+    if !summary.notes.is_empty() {
+        out.push_str("## Notes\n\n");
+        for note in &summary.notes {
+            let mut line = note.summary.clone().unwrap_or_default();
+            if !note.labels.is_empty() {
+                let _ = write!(line, " [{}]", note.labels.join(", "));
+            }
+            let _ = writeln!(out, "- {} ({}) {}", time_of_day(note.unix_ms), note.annotator, line.trim());
+        }
+    }
+    out
+}
+
+fn minutes(ms: u64) -> String {
+    format!("{} min", (ms + 30_000) / 60_000)
+}
+
+/// `HH:MM` UTC.
+fn time_of_day(unix_ms: u64) -> String {
+    let minutes = (unix_ms % DAY_MS) / 60_000;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Milliseconds since the epoch at the start of a `YYYY-MM-DD` UTC date.
+pub fn parse_date(date: &str) -> Result<u64> {
+    let invalid = || anyhow!("invalid date '{date}', expected YYYY-MM-DD");
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<i64>());
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day) as u64 * DAY_MS)
+}
+
+/// Days since 1970-01-01 of a Gregorian date, after Howard Hinnant's
+/// `days_from_civil`.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of `days` since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// The `YYYY-MM-DD` UTC date containing `unix_ms`.
+pub fn format_date(unix_ms: u64) -> String {
+    let (year, month, day) = civil_from_days((unix_ms / DAY_MS) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// With `SC_DAILY_SUMMARY` set, queues a summary of the previous UTC day
+/// once it is over, unless one was already written.
+pub fn spawn_scheduler(app: AppHandle) -> Result<()> {
+    if std::env::var_os("SC_DAILY_SUMMARY").is_none() {
+        return Ok(());
+    }
+    std::thread::Builder::new()
+        .name("daily_summary".into())
+        .spawn(move || {
+            let mut queued: Option<String> = None;
+            loop {
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default();
+                let yesterday = format_date(now_ms.saturating_sub(DAY_MS));
+                let options = app.state::<CaptureManager>().options();
+                let root = session::output_root(&options);
+                if queued.as_ref() != Some(&yesterday) && !output_path(&root, &yesterday).exists() {
+                    let spec = JobSpec::DailySummary {
+                        date: yesterday.clone(),
+                        output_dir: options.output_dir,
+                    };
+                    match app.state::<JobQueue>().enqueue(spec) {
+                        Ok(_) => queued = Some(yesterday),
+                        Err(err) => eprintln!("[summary] failed to queue the daily summary: {err}"),
+                    }
+                }
+                std::thread::sleep(SCHEDULE_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use anyhow::Result;
+use tauri::{AppHandle, Manager};
+use zbus::blocking::{connection, Connection};
+use zbus::fdo;
+use zbus::interface;
+use zbus::names::BusName;
+
+use crate::capture_manager::{CaptureManager, CaptureState};
+use crate::events::CaptureEvent;
+use crate::StartCapturePayload;
+
+const SERVICE_NAME: &str = "org.mvp.ScreenCapture";
+const OBJECT_PATH: &str = "/org/mvp/ScreenCapture";
+
+struct ScreenCaptureService {
+    app: AppHandle,
+}
+
+impl ScreenCaptureService {
+    fn manager(&self) -> tauri::State<'_, CaptureManager> {
+        self.app.state::<CaptureManager>()
+    }
+}
+
+fn failed(err: anyhow::Error) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+#[interface(name = "org.mvp.ScreenCapture")]
+impl ScreenCaptureService {
+    /// `options` is the JSON `start_capture` payload; an empty string uses defaults.
+    fn start(&self, options: &str) -> fdo::Result<()> {
+        let options = if options.trim().is_empty() { "{}" } else { options };
+        let payload: StartCapturePayload = serde_json::from_str(options)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.manager().start_capture(payload.into()).map_err(failed)
+    }
+
+    fn stop(&self) -> fdo::Result<()> {
+        self.manager().stop_capture().map(|_| ()).map_err(failed)
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.manager().pause_capture().map_err(failed)
+    }
+
+    fn resume(&self) -> fdo::Result<()> {
+        self.manager().resume_capture().map_err(failed)
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> String {
+        self.manager().status().as_str().to_string()
+    }
+
+    /// JSON description of why the last session failed; empty when it didn't.
+    #[zbus(property)]
+    fn last_error(&self) -> String {
+        self.manager()
+            .last_error()
+            .and_then(|error| serde_json::to_string(&error).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Registers the service on the session bus and forwards state transitions as
+/// `StateChanged(s)` signals, plus `PropertiesChanged` for `State` and, when
+/// a session starts or fails, `LastError`. The returned connection must be
+/// kept alive.
+pub fn spawn(app: AppHandle) -> Result<Connection> {
+    let service = ScreenCaptureService { app: app.clone() };
+    let conn = connection::Builder::session()?
+        .name(SERVICE_NAME)?
+        .serve_at(OBJECT_PATH, service)?
+        .build()?;
+
+    let signal_conn = conn.clone();
+    let iface = conn
+        .object_server()
+        .interface::<_, ScreenCaptureService>(OBJECT_PATH)?;
+    app.state::<CaptureManager>().events().subscribe(move |event| {
+        let CaptureEvent::StateChanged { state, .. } = event else {
+            return;
+        };
+        if let Err(err) = signal_conn.emit_signal(
+            None::<BusName<'_>>,
+            OBJECT_PATH,
+            SERVICE_NAME,
+            "StateChanged",
+            &(state.as_str(),),
+        ) {
+            eprintln!("[dbus] failed to emit StateChanged: {err}");
+        }
+        let service = iface.get();
+        let ctxt = iface.signal_context();
+        let mut notified = zbus::block_on(service.state_changed(ctxt));
+        // a start clears the last error and a failure sets it
+        if notified.is_ok() && matches!(state, CaptureState::Starting | CaptureState::Failed) {
+            notified = zbus::block_on(service.last_error_changed(ctxt));
+        }
+        if let Err(err) = notified {
+            eprintln!("[dbus] failed to emit PropertiesChanged: {err}");
+        }
+    });
+
+    println!("[dbus] registered {SERVICE_NAME} at {OBJECT_PATH}");
+    Ok(conn)
+}
@@ -0,0 +1,150 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::daily_summary;
+use crate::session::{self, SessionManifest};
+use crate::uploads::UploadSpool;
+
+const WIPE_BLOCK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeletionReport {
+    pub session_id: String,
+    pub files_removed: usize,
+    pub bytes_removed: u64,
+    /// Whether file contents were overwritten before unlinking.
+    pub secure: bool,
+    /// Daily summaries regenerated without the session.
+    pub summaries_rewritten: Vec<String>,
+}
+
+/// Removes everything stored for a session: its directory under the output
+/// root (manifest, recordings, sink files, thumbnails), its `debug_save`
+/// chunks in `chunk_dir`, and its upload spool entries. A daily summary of
+/// the session's day is written again without it. With `secure`, file
+/// contents are overwritten with zeros and synced before each unlink.
+pub fn delete_session(
+    output_root: &Path,
+    session_id: &str,
+    chunk_dir: &Path,
+    spool: &UploadSpool,
+    secure: bool,
+) -> Result<DeletionReport> {
+    session::validate_id(session_id)?;
+    let session_dir = output_root.join(session_id);
+    let manifest = SessionManifest::read(&session_dir).ok();
+    let mut spooled = spool.pending()?;
+    spooled.retain(|entry| entry.session_id == session_id);
+    if manifest.is_none() && !session_dir.exists() && spooled.is_empty() {
+        return Err(anyhow!("unknown session '{session_id}'"));
+    }
+    let mut report = DeletionReport {
+        session_id: session_id.to_string(),
+        secure,
+        ..DeletionReport::default()
+    };
+
+    for entry in &spooled {
+        let data = spool.data_path(entry)?;
+        for path in [data.with_extension("json"), data] {
+            if path.exists() {
+                remove_file(&path, secure, &mut report)?;
+            }
+        }
+    }
+
+    // debug chunks carry no session id, so the session's time span decides
+    if let (Some(manifest), true) = (&manifest, chunk_dir.exists()) {
+        let start_ns = manifest.started_unix_ms * 1_000_000;
+        let end_ns = start_ns + manifest.duration_ms as u128 * 1_000_000;
+        for entry in std::fs::read_dir(chunk_dir)? {
+            let path = entry?.path();
+            let Some(start) = chunk_start_ns(&path) else {
+                continue;
+            };
+            if (start_ns..=end_ns).contains(&start) {
+                remove_file(&path, secure, &mut report)?;
+            }
+        }
+    }
+
+    if session_dir.exists() {
+        remove_tree(&session_dir, secure, &mut report)?;
+    }
+
+    if let Some(manifest) = &manifest {
+        let date = daily_summary::format_date(manifest.started_unix_ms as u64);
+        if daily_summary::output_path(output_root, &date).exists() {
+            daily_summary::write(output_root, &date)?;
+            report.summaries_rewritten.push(date);
+        }
+    }
+
+    println!(
+        "[deletion] removed {session_id}: {} files, {} bytes{}",
+        report.files_removed,
+        report.bytes_removed,
+        if secure { ", overwritten" } else { "" }
+    );
+    Ok(report)
+}
+
+/// Start of a `debug_save` file, `chunk-{start_ns}-{id}-{kind}.raw|json`.
+fn chunk_start_ns(path: &Path) -> Option<u128> {
+    let name = path.file_name()?.to_str()?;
+    if !(name.ends_with(".raw") || name.ends_with(".json")) {
+        return None;
+    }
+    name.strip_prefix("chunk-")?.split('-').next()?.parse().ok()
+}
+
+fn remove_tree(dir: &Path, secure: bool, report: &mut DeletionReport) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_tree(&entry.path(), secure, report)?;
+        } else {
+            remove_file(&entry.path(), secure, report)?;
+        }
+    }
+    std::fs::remove_dir(dir)?;
+    Ok(())
+}
+
+/// Whether another name points at the same inode, as with recordings imported
+/// as hard links by older builds; overwriting would wipe that file too.
+#[cfg(unix)]
+fn is_shared(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn is_shared(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+fn remove_file(path: &Path, secure: bool, report: &mut DeletionReport) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    let len = metadata.len();
+    if secure && !metadata.file_type().is_symlink() && !is_shared(&metadata) {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let zeros = vec![0u8; WIPE_BLOCK];
+        let mut left = len;
+        while left > 0 {
+            let block = left.min(WIPE_BLOCK as u64) as usize;
+            file.write_all(&zeros[..block])?;
+            left -= block as u64;
+        }
+        file.sync_all()?;
+    }
+    std::fs::remove_file(path)?;
+    report.files_removed += 1;
+    report.bytes_removed += len;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+
+use crate::capture_manager::ensure_gstreamer_initialized;
+
+/// A V4L2 video input such as a UVC capture card.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureDevice {
+    pub name: String,
+    pub path: String,
+    /// Distinct media types the device offers, e.g. `video/x-raw` or `image/jpeg`.
+    pub formats: Vec<String>,
+}
+
+pub fn list_video_devices() -> Result<Vec<CaptureDevice>> {
+    ensure_gstreamer_initialized()?;
+    let monitor = gst::DeviceMonitor::new();
+    monitor.add_filter(Some("Video/Source"), None);
+    monitor
+        .start()
+        .map_err(|err| anyhow!("failed to start device monitor: {err}"))?;
+    let devices = monitor
+        .devices()
+        .into_iter()
+        .filter_map(|device| {
+            let properties = device.properties()?;
+            // the v4l2 provider reports `device.path`, PipeWire's reports `api.v4l2.path`
+            let path = properties
+                .get::<String>("device.path")
+                .or_else(|_| properties.get::<String>("api.v4l2.path"))
+                .ok()?;
+            let mut formats: Vec<String> = device
+                .caps()
+                .map(|caps| caps.iter().map(|structure| structure.name().to_string()).collect())
+                .unwrap_or_default();
+            formats.sort_unstable();
+            formats.dedup();
+            Some(CaptureDevice {
+                name: device.display_name().to_string(),
+                path,
+                formats,
+            })
+        })
+        .collect();
+    monitor.stop();
+    Ok(devices)
+}
+
+/// Source bin for the V4L2 device at `path`. Raw formats are preferred;
+/// devices that only offer MJPEG (common for HDMI capture dongles) get a
+/// decoder so the rest of the pipeline always sees raw video.
+pub fn v4l2_source(path: &str, name: &str) -> Result<gst::Element> {
+    let src = gst::ElementFactory::make("v4l2src")
+        .name(format!("{name}_v4l2"))
+        .property("device", path)
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'v4l2src' — ensure required plugins are installed"))?;
+    src.set_property("do-timestamp", true);
+
+    let offered = probe_caps(&src).map_err(|err| anyhow!("capture device {path}: {err}"))?;
+    let has = |media: &str| offered.iter().any(|structure| structure.name() == media);
+    let description = if has("video/x-raw") {
+        "capsfilter caps=video/x-raw"
+    } else if has("image/jpeg") {
+        "capsfilter caps=image/jpeg ! jpegdec"
+    } else {
+        return Err(anyhow!("capture device {path} offers neither raw video nor MJPEG"));
+    };
+    println!("[devices] {path}: using {description}");
+
+    let tail = gst::parse::bin_from_description(description, true)
+        .map_err(|err| anyhow!("failed to build decoder for {path}: {err}"))?;
+    let bin = gst::Bin::with_name(name);
+    bin.add_many([&src, tail.upcast_ref()])?;
+    src.link(&tail)?;
+    let tail_src = tail
+        .static_pad("src")
+        .ok_or_else(|| anyhow!("decoder for {path} has no src pad"))?;
+    bin.add_pad(&gst::GhostPad::with_target(&tail_src)?)?;
+    Ok(bin.upcast())
+}
+
+/// Opens the device briefly to read the formats it supports.
+fn probe_caps(src: &gst::Element) -> Result<gst::Caps> {
+    src.set_state(gst::State::Ready)
+        .map_err(|_| anyhow!("cannot open device"))?;
+    let caps = src
+        .static_pad("src")
+        .map(|pad| pad.query_caps(None))
+        .ok_or_else(|| anyhow!("device has no src pad"));
+    let _ = src.set_state(gst::State::Null);
+    caps
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::annotations::{Annotation, ChunkAnnotator};
+use crate::capture_manager::CapturedChunk;
+use crate::proxy;
+use crate::translation;
+
+const NAME: &str = "diarization";
+/// Analysis window; long enough for two periods of a low voice.
+const FRAME_MS: u64 = 40;
+/// Silence shorter than this doesn't split a speech segment.
+const MAX_PAUSE_MS: u64 = 300;
+/// Segments shorter than this are too short to judge a voice by and go to
+/// the speaker before them.
+const MIN_SEGMENT_MS: u64 = 400;
+const MIN_PITCH_HZ: f32 = 70.0;
+const MAX_PITCH_HZ: f32 = 400.0;
+/// Frames quieter than this are never speech, whatever the noise floor.
+const MIN_SPEECH_RMS: f32 = 0.005;
+/// Scales of the voice features; a distance under 1 is the same speaker.
+const PITCH_SCALE: f32 = 0.12;
+const ZCR_SCALE: f32 = 0.04;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiarizationConfig {
+    /// Voices beyond this many are given to the closest known speaker.
+    #[serde(default = "DiarizationConfig::default_max_speakers")]
+    pub max_speakers: usize,
+}
+
+impl DiarizationConfig {
+    pub const fn default_max_speakers() -> usize {
+        6
+    }
+}
+
+/// A stretch of one speaker, relative to the start of the chunk.
+#[derive(Debug, Clone, Serialize)]
+struct Turn {
+    speaker: String,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+/// What a voice sounds like: log pitch and zero-crossing rate, averaged over
+/// everything the speaker said so far.
+#[derive(Debug, Clone, Copy)]
+struct Voice {
+    log_pitch: f32,
+    zcr: f32,
+    frames: u32,
+}
+
+impl Voice {
+    fn distance(&self, other: &Voice) -> f32 {
+        ((self.log_pitch - other.log_pitch) / PITCH_SCALE).hypot((self.zcr - other.zcr) / ZCR_SCALE)
+    }
+
+    fn absorb(&mut self, other: &Voice) {
+        let total = (self.frames + other.frames) as f32;
+        self.log_pitch = (self.log_pitch * self.frames as f32 + other.log_pitch * other.frames as f32) / total;
+        self.zcr = (self.zcr * self.frames as f32 + other.zcr * other.frames as f32) / total;
+        self.frames += other.frames;
+    }
+}
+
+/// Labels who speaks when in audio chunks, by clustering voices on pitch
+/// and timbre. Speaker ids hold for the whole session. Transcript segments
+/// from annotators listed before it get a `speaker` each.
+pub struct Diarizer {
+    config: DiarizationConfig,
+    /// Known voices per stream; `speaker_<n>` is index `n - 1`.
+    voices: Mutex<BTreeMap<String, Vec<Voice>>>,
+}
+
+impl Diarizer {
+    pub fn new(config: DiarizationConfig) -> Self {
+        Self {
+            config,
+            voices: Mutex::default(),
+        }
+    }
+
+    /// The speaker whose voice is closest to `voice`, adding a new one if
+    /// none is close and there is room.
+    fn speaker(&self, stream: &str, voice: Voice) -> String {
+        let mut streams = self.voices.lock().unwrap_or_else(PoisonError::into_inner);
+        let voices = streams.entry(stream.to_string()).or_default();
+        let closest = voices
+            .iter()
+            .enumerate()
+            .map(|(index, known)| (index, known.distance(&voice)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let index = match closest {
+            Some((index, distance)) if distance < 1.0 || voices.len() >= self.config.max_speakers.max(1) => {
+                voices[index].absorb(&voice);
+                index
+            }
+            _ => {
+                voices.push(voice);
+                voices.len() - 1
+            }
+        };
+        format!("speaker_{}", index + 1)
+    }
+
+    fn turns(&self, chunk: &CapturedChunk) -> Result<Vec<Turn>> {
+        let rate = chunk.metadata["rate"].as_u64().filter(|rate| *rate > 0).ok_or_else(|| anyhow!("no sample rate"))?;
+        let samples = mono_samples(chunk)?;
+        let frame_len = (rate * FRAME_MS / 1000) as usize;
+        if frame_len == 0 {
+            return Ok(Vec::new());
+        }
+        let frames: Vec<&[f32]> = samples.chunks_exact(frame_len).collect();
+        let levels: Vec<f32> = frames.iter().map(|frame| rms(frame)).collect();
+        let mut sorted = levels.clone();
+        sorted.sort_by(f32::total_cmp);
+        // twice the median level separates speech from room noise in most calls
+        let threshold = sorted
+            .get(sorted.len() / 2)
+            .map_or(MIN_SPEECH_RMS, |median| (median * 2.0).max(MIN_SPEECH_RMS));
+
+        // runs of speech frames, allowing short pauses inside
+        let max_gap = (MAX_PAUSE_MS / FRAME_MS) as usize;
+        let mut segments: Vec<(usize, usize)> = Vec::new();
+        for (index, _) in levels.iter().enumerate().filter(|(_, level)| **level >= threshold) {
+            match segments.last_mut() {
+                Some((_, end)) if index - *end <= max_gap => *end = index + 1,
+                _ => segments.push((index, index + 1)),
+            }
+        }
+
+        let mut turns: Vec<Turn> = Vec::new();
+        for (start, end) in segments {
+            let (start_ms, end_ms) = (start as u64 * FRAME_MS, end as u64 * FRAME_MS);
+            let voice = (end_ms - start_ms >= MIN_SEGMENT_MS)
+                .then(|| voice_of(&frames[start..end], &levels[start..end], threshold, rate as f32))
+                .flatten();
+            let speaker = match (voice, turns.last()) {
+                (Some(voice), _) => self.speaker(&chunk.kind, voice),
+                (None, Some(previous)) => previous.speaker.clone(),
+                (None, None) => continue,
+            };
+            match turns.last_mut() {
+                Some(previous) if previous.speaker == speaker => previous.end_ms = end_ms,
+                _ => turns.push(Turn {
+                    speaker,
+                    start_ms,
+                    end_ms,
+                }),
+            }
+        }
+        Ok(turns)
+    }
+}
+
+impl ChunkAnnotator for Diarizer {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn wants(&self, chunk: &CapturedChunk) -> bool {
+        !proxy::is_video(&chunk.kind)
+    }
+
+    fn annotate(&self, chunk: &CapturedChunk) -> Result<Option<Annotation>> {
+        self.annotate_after(chunk, &[])
+    }
+
+    fn annotate_after(&self, chunk: &CapturedChunk, earlier: &[Annotation]) -> Result<Option<Annotation>> {
+        let turns = self.turns(chunk)?;
+        if turns.is_empty() {
+            return Ok(None);
+        }
+        let mut labels: Vec<String> = turns.iter().map(|turn| turn.speaker.clone()).collect();
+        labels.sort();
+        labels.dedup();
+
+        // transcript segments, e.g. `{ "start_ms", "end_ms", "text" }` from a
+        // Whisper-style annotator, each given the speaker it overlaps most
+        let segments: Vec<Value> = translation::transcript(earlier)
+            .and_then(|transcript| transcript.extra["segments"].as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|segment| {
+                let start_ms = segment["start_ms"].as_u64()?;
+                let end_ms = segment["end_ms"].as_u64()?;
+                let speaker = turns
+                    .iter()
+                    .map(|turn| (turn, turn.end_ms.min(end_ms).saturating_sub(turn.start_ms.max(start_ms))))
+                    .filter(|(_, overlap)| *overlap > 0)
+                    .max_by_key(|(_, overlap)| *overlap)
+                    .map(|(turn, _)| turn.speaker.clone());
+                let mut segment = segment.clone();
+                segment["speaker"] = json!(speaker);
+                Some(segment)
+            })
+            .collect();
+        let summary = (!segments.is_empty()).then(|| {
+            segments
+                .iter()
+                .filter_map(|segment| {
+                    let text = segment["text"].as_str()?.trim();
+                    let speaker = segment["speaker"].as_str().unwrap_or("unknown");
+                    (!text.is_empty()).then(|| format!("{speaker}: {text}"))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+        Ok(Some(Annotation {
+            summary,
+            labels,
+            extra: json!({ "turns": turns, "segments": segments }),
+            ..Annotation::default()
+        }))
+    }
+}
+
+/// The chunk's interleaved samples mixed down to one channel.
+fn mono_samples(chunk: &CapturedChunk) -> Result<Vec<f32>> {
+    let channels = chunk.metadata["channels"].as_u64().unwrap_or(1).max(1) as usize;
+    let samples: Vec<f32> = match chunk.metadata["format"].as_str() {
+        Some("F32LE") => chunk
+            .data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect(),
+        Some("S16LE") => chunk
+            .data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+            .collect(),
+        format => return Err(anyhow!("unsupported sample format {format:?}")),
+    };
+    Ok(samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect())
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    (frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len().max(1) as f32).sqrt()
+}
+
+/// The average voice over the speech frames of a segment; `None` when no
+/// frame has a clear pitch, e.g. for music or noise.
+fn voice_of(frames: &[&[f32]], levels: &[f32], threshold: f32, rate: f32) -> Option<Voice> {
+    let (mut log_pitch, mut zcr, mut count) = (0.0, 0.0, 0u32);
+    for (frame, _) in frames.iter().zip(levels).filter(|(_, level)| **level >= threshold) {
+        let Some(pitch) = pitch(frame, rate) else {
+            continue;
+        };
+        log_pitch += pitch.ln();
+        zcr += frame.windows(2).filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0)).count() as f32 / frame.len() as f32;
+        count += 1;
+    }
+    (count > 0).then(|| Voice {
+        log_pitch: log_pitch / count as f32,
+        zcr: zcr / count as f32,
+        frames: count,
+    })
+}
+
+/// Fundamental frequency by autocorrelation, when the frame is voiced.
+fn pitch(frame: &[f32], rate: f32) -> Option<f32> {
+    let min_lag = (rate / MAX_PITCH_HZ) as usize;
+    let max_lag = ((rate / MIN_PITCH_HZ) as usize).min(frame.len() - 1);
+    let energy: f32 = frame.iter().map(|sample| sample * sample).sum();
+    if energy <= 0.0 || min_lag >= max_lag {
+        return None;
+    }
+    let (lag, correlation) = (min_lag..=max_lag)
+        .map(|lag| {
+            let sum: f32 = frame.iter().zip(&frame[lag..]).map(|(a, b)| a * b).sum();
+            (lag, sum / energy)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    // weakly periodic frames are unvoiced sounds or noise
+    (correlation > 0.3).then(|| rate / lag as f32)
+}
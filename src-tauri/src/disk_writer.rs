@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+
+/// Direct IO wants buffer addresses, file offsets and lengths on the
+/// device's logical block size; 4 KiB covers the common ones.
+const ALIGN: usize = 4096;
+
+/// How `debug_save` gets chunks onto disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DiskWriteSettings {
+    /// Size of each write handed to the kernel, rounded to whole 4 KiB blocks.
+    #[serde(default = "DiskWriteSettings::default_buffer_kb")]
+    pub buffer_kb: u32,
+    /// Chunks waiting for the IO thread; once that many are queued the
+    /// consumer waits rather than holding more in memory.
+    #[serde(default = "DiskWriteSettings::default_queue_chunks")]
+    pub queue_chunks: usize,
+    /// Writes around the page cache (`O_DIRECT`, Linux only). Filesystems
+    /// that refuse it get buffered writes that are dropped from the cache
+    /// once on disk.
+    #[serde(default)]
+    pub direct_io: bool,
+}
+
+impl DiskWriteSettings {
+    pub const fn default_buffer_kb() -> u32 {
+        8 * 1024
+    }
+
+    pub const fn default_queue_chunks() -> usize {
+        8
+    }
+
+    fn buffer_len(&self) -> usize {
+        (self.buffer_kb as usize * 1024).max(ALIGN) / ALIGN * ALIGN
+    }
+}
+
+impl Default for DiskWriteSettings {
+    fn default() -> Self {
+        Self {
+            buffer_kb: Self::default_buffer_kb(),
+            queue_chunks: Self::default_queue_chunks(),
+            direct_io: false,
+        }
+    }
+}
+
+/// A heap buffer whose usable part starts on an [`ALIGN`] boundary.
+struct AlignedBuffer {
+    raw: Vec<u8>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let raw = vec![0u8; len + ALIGN];
+        let offset = raw.as_ptr().align_offset(ALIGN);
+        Self { raw, offset, len }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.raw[self.offset..self.offset + self.len]
+    }
+}
+
+/// Writes `debug_save` chunks on its own thread, so a multi-hundred-MB chunk
+/// doesn't hold up the consumer loop.
+pub struct DiskWriter {
+    sender: Option<SyncSender<Arc<CapturedChunk>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DiskWriter {
+    pub fn start(dir: PathBuf, settings: DiskWriteSettings) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let (sender, receiver) = mpsc::sync_channel::<Arc<CapturedChunk>>(settings.queue_chunks.max(1));
+        let handle = std::thread::Builder::new()
+            .name("debug_writer".into())
+            .spawn(move || {
+                let mut io = ChunkIo::new(settings);
+                for chunk in receiver {
+                    let name = format!("chunk-{}-{}-{}", chunk.start_ts_unix_nanos, chunk.id, chunk.kind);
+                    let data_path = dir.join(format!("{name}.raw"));
+                    if let Err(err) = io.write(&data_path, &chunk.data) {
+                        eprintln!("[disk_writer] failed to write {}: {err}", data_path.display());
+                        continue;
+                    }
+                    let meta = serde_json::to_string_pretty(&chunk.metadata).unwrap_or_default();
+                    let _ = std::fs::write(dir.join(format!("{name}.json")), meta);
+                    println!("[capture] debug-saved chunk {} -> {}", chunk.id, data_path.display());
+                }
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queues `chunk`; only waits when the IO thread is `queue_chunks` behind.
+    pub fn write(&self, chunk: Arc<CapturedChunk>) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(chunk);
+        }
+    }
+
+    /// Waits for the queued chunks to reach disk.
+    pub fn finish(mut self) {
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct ChunkIo {
+    settings: DiskWriteSettings,
+    buffer: Option<AlignedBuffer>,
+    direct_failed: bool,
+}
+
+impl ChunkIo {
+    fn new(settings: DiskWriteSettings) -> Self {
+        Self {
+            settings,
+            buffer: None,
+            direct_failed: false,
+        }
+    }
+
+    fn write(&mut self, path: &Path, data: &[u8]) -> Result<()> {
+        if self.settings.direct_io {
+            match open_direct(path) {
+                Ok(file) => return self.write_direct(file, data),
+                Err(err) => {
+                    if !std::mem::replace(&mut self.direct_failed, true) {
+                        eprintln!("[disk_writer] direct IO unavailable, writing through the page cache: {err}");
+                    }
+                }
+            }
+        }
+        let mut file = File::create(path)?;
+        for batch in data.chunks(self.settings.buffer_len()) {
+            file.write_all(batch)?;
+        }
+        if self.settings.direct_io {
+            file.sync_data()?;
+            // the chunk is on disk either way; a full page cache is only slower
+            if let Err(err) = drop_from_cache(&file) {
+                eprintln!("[disk_writer] failed to drop {} from the page cache: {err}", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies `data` through the aligned buffer, padding the last block and
+    /// trimming the file back to the real length afterwards.
+    fn write_direct(&mut self, mut file: File, data: &[u8]) -> Result<()> {
+        let len = self.settings.buffer_len();
+        let buffer = self.buffer.get_or_insert_with(|| AlignedBuffer::new(len)).as_mut_slice();
+        for batch in data.chunks(len) {
+            let padded = batch.len().div_ceil(ALIGN) * ALIGN;
+            buffer[..batch.len()].copy_from_slice(batch);
+            buffer[batch.len()..padded].fill(0);
+            file.write_all(&buffer[..padded])?;
+        }
+        file.set_len(data.len() as u64)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_direct(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_direct(_path: &Path) -> std::io::Result<File> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "direct IO is only supported on Linux",
+    ))
+}
+
+/// Asks the kernel to drop `file`'s already synced pages, so chunks that are
+/// only written once don't push everything else out of the cache.
+#[cfg(target_os = "linux")]
+fn drop_from_cache(file: &File) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the descriptor belongs to `file`, which is open for the whole call
+    let err = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    // posix_fadvise returns the error number instead of setting errno
+    if err != 0 {
+        return Err(std::io::Error::from_raw_os_error(err));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_from_cache(_file: &File) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, PauseReason};
+use crate::events::CaptureEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Display {
+    /// Connector name, e.g. `DP-1`; unnamed displays get `display-<n>`.
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl Display {
+    /// Whether a rectangle in compositor (logical) or physical coordinates,
+    /// as the ScreenCast portal reports streams, is this display.
+    pub fn matches(&self, position: Option<(i32, i32)>, size: (i32, i32)) -> bool {
+        let logical = |value: i64| (value as f64 / self.scale_factor).round() as i64;
+        let physical = (self.x as i64, self.y as i64, self.width as i64, self.height as i64);
+        let scaled = (logical(physical.0), logical(physical.1), logical(physical.2), logical(physical.3));
+        [physical, scaled].into_iter().any(|(x, y, width, height)| {
+            (width, height) == (size.0 as i64, size.1 as i64)
+                && position.is_none_or(|(px, py)| (x, y) == (px as i64, py as i64))
+        })
+    }
+}
+
+/// What to do when the display being captured is disconnected.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayLostAction {
+    /// Pause until the display comes back.
+    #[default]
+    Pause,
+    /// Restart capture, as a new session, on a display that is still there.
+    /// Where no display can be picked without the portal, pause instead.
+    Retarget,
+}
+
+/// The last seen display list and which one the session captures.
+#[derive(Default)]
+pub struct DisplayTracker {
+    displays: Mutex<Vec<Display>>,
+    captured: Mutex<Option<String>>,
+}
+
+impl DisplayTracker {
+    pub fn reset(&self) {
+        *self.captured.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub fn list(&self) -> Vec<Display> {
+        self.displays.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn captured(&self) -> Option<String> {
+        self.captured.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn set_captured(&self, name: Option<String>) {
+        if let Some(name) = &name {
+            println!("[displays] capturing display {name}");
+        }
+        *self.captured.lock().unwrap_or_else(PoisonError::into_inner) = name;
+    }
+
+    /// The display showing a portal stream with this position and size.
+    pub fn find(&self, position: Option<(i32, i32)>, size: (i32, i32)) -> Option<String> {
+        self.displays
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|display| display.matches(position, size))
+            .map(|display| display.name.clone())
+    }
+
+    /// Returns true when the list changed.
+    fn replace(&self, displays: Vec<Display>) -> bool {
+        let mut current = self.displays.lock().unwrap_or_else(PoisonError::into_inner);
+        if *current == displays {
+            return false;
+        }
+        *current = displays;
+        true
+    }
+}
+
+fn connected(app: &AppHandle) -> Result<Vec<Display>> {
+    Ok(app
+        .available_monitors()?
+        .into_iter()
+        .enumerate()
+        .map(|(index, monitor)| Display {
+            name: monitor
+                .name()
+                .cloned()
+                .unwrap_or_else(|| format!("display-{index}")),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+        })
+        .collect())
+}
+
+/// Polls the connected displays, keeping the cached list current and
+/// reacting when the captured one goes away.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("display_monitor".into())
+        .spawn(move || {
+            let mut backend_failed = false;
+            loop {
+                poll(&app, &mut backend_failed);
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle, backend_failed: &mut bool) {
+    let displays = match connected(app) {
+        Ok(displays) => {
+            *backend_failed = false;
+            displays
+        }
+        Err(err) => {
+            if !std::mem::replace(backend_failed, true) {
+                eprintln!("[displays] failed to list displays: {err}");
+            }
+            return;
+        }
+    };
+    let manager = app.state::<CaptureManager>();
+    let tracker = manager.displays();
+    if !tracker.replace(displays.clone()) {
+        return;
+    }
+    println!("[displays] {} display(s) connected", displays.len());
+    manager.events().emit(CaptureEvent::DisplaysChanged {
+        displays: displays.clone(),
+    });
+
+    let Some(captured) = tracker.captured() else {
+        return;
+    };
+    let state = manager.status();
+    if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+        return;
+    }
+    let present = displays.iter().any(|display| display.name == captured);
+    if present {
+        if manager.paused_for(PauseReason::DisplayLost) {
+            println!("[displays] {captured} is back, resuming");
+            if let Err(err) = manager.resume_for(PauseReason::DisplayLost) {
+                eprintln!("[displays] failed to resume: {err}");
+            }
+        }
+        return;
+    }
+    if manager.paused_for(PauseReason::DisplayLost) {
+        return;
+    }
+    let action = manager.options().display_lost_action;
+    println!("[displays] captured display {captured} disconnected, action={action:?}");
+    manager.events().emit(CaptureEvent::DisplayLost {
+        name: captured.clone(),
+        action,
+    });
+    let pause = || {
+        if let Err(err) = manager.pause_for(PauseReason::DisplayLost) {
+            eprintln!("[displays] failed to pause: {err}");
+        }
+    };
+    match action {
+        Some(DisplayLostAction::Pause) => pause(),
+        Some(DisplayLostAction::Retarget) => {
+            let Some(display) = retarget_display(&displays) else {
+                println!("[displays] no display can be picked without the portal, pausing instead");
+                pause();
+                return;
+            };
+            let mut options = manager.options();
+            options.display = Some(display.clone());
+            if let Err(err) = manager.stop_capture() {
+                eprintln!("[displays] failed to stop for retarget: {err}");
+                return;
+            }
+            println!("[displays] retargeting to {display}");
+            if let Err(err) = manager.start_capture(options) {
+                eprintln!("[displays] failed to restart on {display}: {err}");
+            }
+        }
+        None => {}
+    }
+}
+
+/// A connected display a restarted session can be pointed at. Only
+/// screencopy honours `display`; the portal would restore the lost monitor
+/// or show its picker, so there is none to offer there.
+#[cfg(target_os = "linux")]
+fn retarget_display(displays: &[Display]) -> Option<String> {
+    if !crate::wlr_screencopy::supported() {
+        return None;
+    }
+    displays.first().map(|display| display.name.clone())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn retarget_display(_displays: &[Display]) -> Option<String> {
+    None
+}
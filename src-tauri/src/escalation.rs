@@ -0,0 +1,213 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureProfile, CaptureState};
+use crate::events::CaptureEvent;
+use crate::focus;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// When an ambient session steps up to the full profile on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationSettings {
+    /// Escalate while a conferencing app is in a meeting.
+    #[serde(default = "EscalationSettings::default_meetings")]
+    pub meetings: bool,
+    /// Escalate for this long after a marker is added; 0 turns it off.
+    #[serde(default = "EscalationSettings::default_marker_hold_secs")]
+    pub marker_hold_secs: u64,
+    /// Escalate while the focused window's app id or title contains one of
+    /// these, case-insensitively.
+    #[serde(default)]
+    pub apps: Vec<String>,
+    /// How long every trigger must stay quiet before the session drops back
+    /// to ambient, so a brief lull doesn't flap the profile.
+    #[serde(default = "EscalationSettings::default_release_after_secs")]
+    pub release_after_secs: u64,
+}
+
+impl EscalationSettings {
+    pub const fn default_meetings() -> bool {
+        true
+    }
+
+    pub const fn default_marker_hold_secs() -> u64 {
+        300
+    }
+
+    pub const fn default_release_after_secs() -> u64 {
+        30
+    }
+}
+
+impl Default for EscalationSettings {
+    fn default() -> Self {
+        Self {
+            meetings: Self::default_meetings(),
+            marker_hold_secs: Self::default_marker_hold_secs(),
+            apps: Vec::new(),
+            release_after_secs: Self::default_release_after_secs(),
+        }
+    }
+}
+
+/// What escalated the session.
+#[derive(Debug, Clone, Copy)]
+enum EscalationTrigger {
+    Meeting,
+    Marker,
+    App,
+}
+
+impl EscalationTrigger {
+    fn label(self) -> &'static str {
+        match self {
+            EscalationTrigger::Meeting => "meeting",
+            EscalationTrigger::Marker => "marker",
+            EscalationTrigger::App => "app",
+        }
+    }
+}
+
+/// Trigger state shared between the manager, the meeting events and the
+/// escalation thread.
+#[derive(Default)]
+pub struct EscalationState {
+    in_meeting: AtomicBool,
+    last_marker: Mutex<Option<Instant>>,
+    /// Set while this engine holds the session at full; the time is when a
+    /// trigger was last active.
+    escalated: Mutex<Option<Instant>>,
+}
+
+impl EscalationState {
+    /// Forgets the previous session's marker and escalation; an ongoing
+    /// meeting carries over.
+    pub fn reset(&self) {
+        *self.last_marker.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        *self.escalated.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub fn note_marker(&self) {
+        *self.last_marker.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+
+    fn observe(&self, event: &CaptureEvent) {
+        match event {
+            CaptureEvent::MeetingDetected { .. } => self.in_meeting.store(true, Ordering::Relaxed),
+            CaptureEvent::MeetingEnded { .. } => self.in_meeting.store(false, Ordering::Relaxed),
+            _ => {}
+        }
+    }
+}
+
+/// Switches running sessions with `escalation` set between the ambient and
+/// full profiles as triggers come and go, leaving a marker at each switch.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    {
+        let handle = app.clone();
+        app.state::<CaptureManager>().events().subscribe(move |event| {
+            handle.state::<CaptureManager>().escalation().observe(event);
+        });
+    }
+    std::thread::Builder::new()
+        .name("escalation".into())
+        .spawn(move || {
+            let mut focus_failed = false;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                poll(&app, &mut focus_failed);
+            }
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle, focus_failed: &mut bool) {
+    let manager = app.state::<CaptureManager>();
+    let options = manager.options();
+    let Some(settings) = options.escalation.as_ref().filter(|_| manager.status() == CaptureState::Running) else {
+        return;
+    };
+    let state = manager.escalation();
+    let mut escalated = state.escalated.lock().unwrap_or_else(PoisonError::into_inner);
+    if escalated.is_some() && options.profile != CaptureProfile::Full {
+        // switched back by hand; that wins until the next trigger
+        *escalated = None;
+    }
+    if escalated.is_none() && options.profile != CaptureProfile::Ambient {
+        return;
+    }
+
+    let trigger = active_trigger(state, settings, focus_failed);
+    match (trigger, *escalated) {
+        (Some(trigger), None) => {
+            match manager.switch_profile(CaptureProfile::Full, format!("escalate:{}", trigger.label())) {
+                Ok(_) => {
+                    println!("[escalation] escalated to full for a {}", trigger.label());
+                    *escalated = Some(Instant::now());
+                }
+                Err(err) => eprintln!("[escalation] failed to escalate: {err}"),
+            }
+        }
+        (Some(_), Some(_)) => *escalated = Some(Instant::now()),
+        (None, Some(last_active)) if last_active.elapsed() >= Duration::from_secs(settings.release_after_secs) => {
+            match manager.switch_profile(CaptureProfile::Ambient, "deescalate".to_string()) {
+                Ok(_) => {
+                    println!("[escalation] back to ambient");
+                    *escalated = None;
+                }
+                Err(err) => eprintln!("[escalation] failed to return to ambient: {err}"),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn active_trigger(
+    state: &EscalationState,
+    settings: &EscalationSettings,
+    focus_failed: &mut bool,
+) -> Option<EscalationTrigger> {
+    if settings.meetings && state.in_meeting.load(Ordering::Relaxed) {
+        return Some(EscalationTrigger::Meeting);
+    }
+    let marker_held = state
+        .last_marker
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some_and(|at| at.elapsed() < Duration::from_secs(settings.marker_hold_secs));
+    if marker_held {
+        return Some(EscalationTrigger::Marker);
+    }
+    if settings.apps.is_empty() {
+        return None;
+    }
+    let window = match focus::focused_window() {
+        Ok(window) => {
+            *focus_failed = false;
+            window?
+        }
+        Err(err) => {
+            if !std::mem::replace(focus_failed, true) {
+                eprintln!("[escalation] failed to query the focused window: {err}");
+            }
+            return None;
+        }
+    };
+    let values: Vec<String> = [window.app_id, window.title]
+        .into_iter()
+        .flatten()
+        .map(|value| value.to_lowercase())
+        .collect();
+    settings
+        .apps
+        .iter()
+        .map(|pattern| pattern.to_lowercase())
+        .any(|pattern| values.iter().any(|value| value.contains(&pattern)))
+        .then_some(EscalationTrigger::App)
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::Serialize;
+
+use crate::capture_manager::{CaptureOptions, CaptureProfile, CaptureState};
+use crate::displays::{Display, DisplayLostAction};
+use crate::focus::FocusedWindow;
+use crate::jobs::JobState;
+use crate::latency::StageTiming;
+use crate::memory::MemoryReport;
+use crate::moments::Moment;
+use crate::meetings::DetectedBy;
+use crate::policy::PolicyAction;
+use crate::sinks::SinkStatus;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureEvent {
+    StateChanged {
+        from: CaptureState,
+        state: CaptureState,
+    },
+    SessionStarted {
+        session_id: String,
+        started_unix_ms: u128,
+        options: CaptureOptions,
+    },
+    SessionStopped {
+        session_id: String,
+        duration_ms: u64,
+        marker_count: usize,
+        output_dir: Option<String>,
+    },
+    SessionFailed {
+        session_id: Option<String>,
+        error: String,
+    },
+    IdleChanged {
+        idle: bool,
+    },
+    ScreenLockChanged {
+        locked: bool,
+    },
+    /// A display was connected, disconnected or reconfigured.
+    DisplaysChanged {
+        displays: Vec<Display>,
+    },
+    /// The display being captured was disconnected.
+    DisplayLost {
+        name: String,
+        action: Option<DisplayLostAction>,
+    },
+    /// A denylisted app entered (`blocked`) or left the capture.
+    AppBlocked {
+        blocked: bool,
+        app_id: Option<String>,
+        action: PolicyAction,
+    },
+    /// The window target lost (`hidden`) or regained focus; its frames are
+    /// black in between.
+    WindowHidden {
+        hidden: bool,
+    },
+    /// A marker's frame, audio snippet and window were kept.
+    MomentCaptured {
+        moment: Moment,
+    },
+    CaptureDegraded {
+        reason: String,
+        framerate: Option<u32>,
+        paused: bool,
+    },
+    CaptureRestored {
+        reason: String,
+    },
+    SceneChanged {
+        scene: String,
+    },
+    ProfileChanged {
+        profile: CaptureProfile,
+        framerate: u32,
+    },
+    ChunkGap {
+        stream: String,
+        chunk_id: u64,
+        gap_ms: i64,
+    },
+    /// A chunk left the consumer; consumers that track delivery ack it with
+    /// `ack_chunks`.
+    ChunkReady {
+        stream: String,
+        chunk_id: u64,
+        start_ts_unix_nanos: u128,
+        duration_ms: u64,
+        bytes: usize,
+    },
+    /// A spooled chunk was accepted by its upload server; `attempts` counts
+    /// this one.
+    UploadCompleted {
+        session_id: String,
+        stream: String,
+        chunk_id: u64,
+        url: String,
+        bytes: usize,
+        attempts: u32,
+    },
+    SinkStatusChanged {
+        sink: String,
+        status: SinkStatus,
+        error: Option<String>,
+    },
+    FocusFollowed {
+        window: FocusedWindow,
+        marker_id: u64,
+    },
+    Heartbeat {
+        state: CaptureState,
+        elapsed_ms: u64,
+        last_sample_age_ms: BTreeMap<String, u64>,
+    },
+    /// Per-stream stage timings over the last few seconds, for tuning.
+    LatencyStats {
+        streams: BTreeMap<String, BTreeMap<String, StageTiming>>,
+    },
+    /// Where chunk data is held right now, to see why memory climbs.
+    MemoryStats {
+        report: MemoryReport,
+    },
+    /// A conferencing app became active. `template` is the recording
+    /// template started for it, if one was configured and could start.
+    MeetingDetected {
+        app: String,
+        detected_by: DetectedBy,
+        recording: bool,
+        template: Option<String>,
+    },
+    MeetingEnded {
+        app: String,
+    },
+    /// The enrolled wake phrase was spoken; `marker_id` or `template` say
+    /// what it did.
+    #[cfg(feature = "wake_word")]
+    WakeWordHeard {
+        phrase: String,
+        distance: f32,
+        marker_id: Option<u64>,
+        template: Option<String>,
+    },
+    JobProgress {
+        job_id: u64,
+        state: JobState,
+        progress: f32,
+        error: Option<String>,
+    },
+    /// A thumbnail queued by a search or listing was written to `path`.
+    ThumbnailReady {
+        session_id: String,
+        offset_ms: u64,
+        path: String,
+    },
+}
+
+impl CaptureEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptureEvent::StateChanged { .. } => "state_changed",
+            CaptureEvent::SessionStarted { .. } => "session_started",
+            CaptureEvent::SessionStopped { .. } => "session_stopped",
+            CaptureEvent::SessionFailed { .. } => "session_failed",
+            CaptureEvent::IdleChanged { .. } => "idle_changed",
+            CaptureEvent::ScreenLockChanged { .. } => "screen_lock_changed",
+            CaptureEvent::DisplaysChanged { .. } => "displays_changed",
+            CaptureEvent::DisplayLost { .. } => "display_lost",
+            CaptureEvent::AppBlocked { .. } => "app_blocked",
+            CaptureEvent::WindowHidden { .. } => "window_hidden",
+            CaptureEvent::MomentCaptured { .. } => "moment_captured",
+            CaptureEvent::CaptureDegraded { .. } => "capture_degraded",
+            CaptureEvent::CaptureRestored { .. } => "capture_restored",
+            CaptureEvent::SceneChanged { .. } => "scene_changed",
+            CaptureEvent::ProfileChanged { .. } => "profile_changed",
+            CaptureEvent::ChunkGap { .. } => "chunk_gap",
+            CaptureEvent::ChunkReady { .. } => "chunk_ready",
+            CaptureEvent::UploadCompleted { .. } => "upload_completed",
+            CaptureEvent::SinkStatusChanged { .. } => "sink_status_changed",
+            CaptureEvent::FocusFollowed { .. } => "focus_followed",
+            CaptureEvent::Heartbeat { .. } => "heartbeat",
+            CaptureEvent::LatencyStats { .. } => "latency_stats",
+            CaptureEvent::MemoryStats { .. } => "memory_stats",
+            CaptureEvent::MeetingDetected { .. } => "meeting_detected",
+            CaptureEvent::MeetingEnded { .. } => "meeting_ended",
+            #[cfg(feature = "wake_word")]
+            CaptureEvent::WakeWordHeard { .. } => "wake_word_heard",
+            CaptureEvent::JobProgress { .. } => "job_progress",
+            CaptureEvent::ThumbnailReady { .. } => "thumbnail_ready",
+        }
+    }
+}
+
+type Listener = Arc<dyn Fn(&CaptureEvent) + Send + Sync>;
+
+/// Fan-out point for capture lifecycle events. Integrations (D-Bus, UI, ...)
+/// subscribe once at startup; the manager emits without knowing who listens.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    listeners: Arc<Mutex<Vec<Listener>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self, listener: impl Fn(&CaptureEvent) + Send + Sync + 'static) {
+        self.listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::new(listener));
+    }
+
+    pub fn emit(&self, event: CaptureEvent) {
+        // clone the list so listeners may call back into the bus
+        let listeners = self
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for listener in listeners {
+            listener(&event);
+        }
+    }
+}
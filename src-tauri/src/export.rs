@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::annotations::ChunkRecord;
+use crate::proxy;
+use crate::search;
+use crate::session::{self, SessionManifest, MANIFEST_FILE};
+use crate::translation::TRANSLATION_LABEL;
+
+const EXPORT_DIR: &str = "exports";
+/// Length of a cue for a chunk without segments when the chunk length is
+/// unknown.
+const DEFAULT_CUE_MS: u64 = 5_000;
+
+/// How the text files in the bundle are written; the manifest is always JSON.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    /// Plain Markdown, for pasting straight into an LLM prompt.
+    Markdown,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataExport {
+    pub path: String,
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// One timestamped line of text from an annotation.
+struct Line {
+    offset_ms: u64,
+    source: String,
+    text: String,
+}
+
+/// One SRT file of a session's subtitles.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleTrack {
+    /// ISO 639-1 code, or `None` for a transcript whose language wasn't found.
+    pub language: Option<String>,
+    pub translation: bool,
+    pub path: String,
+    pub cues: usize,
+}
+
+/// A timed line of subtitle text, relative to the session start.
+struct Cue {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+}
+
+/// Zips everything but the media of a finished session: the manifest,
+/// transcripts (audio annotations), screen text (video annotations, e.g.
+/// OCR), markers and notes, and a thumbnail per marker.
+pub fn export_session_metadata(root: &Path, session_id: &str, format: ExportFormat) -> Result<MetadataExport> {
+    session::validate_id(session_id)?;
+    let dir = root.join(session_id);
+    let manifest = SessionManifest::read(&dir)
+        .map_err(|err| anyhow!("session '{session_id}' has no readable manifest: {err}"))?;
+    let started_ms = manifest.started_unix_ms as u64;
+
+    let mut transcript = Vec::new();
+    let mut screen_text = Vec::new();
+    for record in &manifest.chunk_annotations {
+        let Some(line) = annotation_line(record, started_ms) else {
+            continue;
+        };
+        if proxy::is_video(&record.stream) {
+            screen_text.push(line);
+        } else {
+            transcript.push(line);
+        }
+    }
+    transcript.sort_by_key(|line| line.offset_ms);
+    screen_text.sort_by_key(|line| line.offset_ms);
+
+    let mut entries: Vec<(String, Vec<u8>)> = vec![(MANIFEST_FILE.to_string(), std::fs::read(dir.join(MANIFEST_FILE))?)];
+    let extension = format.extension();
+    for (name, lines) in [("transcript", &transcript), ("screen_text", &screen_text)] {
+        if !lines.is_empty() {
+            entries.push((format!("{name}.{extension}"), render_lines(name, lines, format)?));
+        }
+    }
+    let mut events: Vec<Line> = manifest
+        .markers
+        .iter()
+        .map(|marker| Line {
+            offset_ms: marker.offset_ms,
+            source: "marker".into(),
+            text: marker.label.clone().unwrap_or_default(),
+        })
+        .chain(manifest.notes.iter().map(|note| Line {
+            offset_ms: note.offset_ms.unwrap_or(0),
+            source: "note".into(),
+            text: note.text.clone(),
+        }))
+        .collect();
+    events.sort_by_key(|line| line.offset_ms);
+    if !events.is_empty() {
+        entries.push((format!("markers.{extension}"), render_lines("markers", &events, format)?));
+    }
+    if let Some(recording) = &manifest.recording {
+        for marker in &manifest.markers {
+            if let Some(png) = search::thumbnail(&dir, recording, marker.offset_ms) {
+                entries.push((format!("thumbnails/marker-{}-{}ms.png", marker.id, marker.offset_ms), std::fs::read(png)?));
+            }
+        }
+    }
+
+    let out_dir = dir.join(EXPORT_DIR);
+    std::fs::create_dir_all(&out_dir)?;
+    let path = out_dir.join(format!("{session_id}-metadata.zip"));
+    let mut zip = ZipWriter::new(File::create(&path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut files = Vec::new();
+    for (name, data) in entries {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(&data)?;
+        files.push(name);
+    }
+    zip.finish()?;
+    let bytes = std::fs::metadata(&path)?.len();
+    println!("[export] {session_id}: {} files -> {}", files.len(), path.display());
+    Ok(MetadataExport {
+        path: path.to_string_lossy().to_string(),
+        files,
+        bytes,
+    })
+}
+
+fn annotation_line(record: &ChunkRecord, started_ms: u64) -> Option<Line> {
+    let annotation = &record.annotation;
+    let text = match (&annotation.summary, annotation.extra.as_str()) {
+        (Some(summary), _) => summary.clone(),
+        (None, Some(extra)) => extra.to_string(),
+        (None, None) => return None,
+    };
+    Some(Line {
+        offset_ms: ((record.start_ts_unix_nanos / 1_000_000) as u64).saturating_sub(started_ms),
+        source: format!("{}/{}", record.annotator, record.stream),
+        text,
+    })
+}
+
+fn render_lines(title: &str, lines: &[Line], format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Json => {
+            let lines: Vec<_> = lines
+                .iter()
+                .map(|line| json!({ "offset_ms": line.offset_ms, "source": line.source, "text": line.text }))
+                .collect();
+            Ok(serde_json::to_vec_pretty(&lines)?)
+        }
+        ExportFormat::Markdown => {
+            let mut out = format!("# {title}\n\n");
+            for line in lines {
+                let seconds = line.offset_ms / 1000;
+                writeln!(
+                    out,
+                    "- **{:02}:{:02}:{:02}** ({}) {}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60,
+                    line.source,
+                    line.text.replace('\n', " ")
+                )?;
+            }
+            Ok(out.into_bytes())
+        }
+    }
+}
+
+/// Writes the transcript of a finished session as SRT, plus one SRT per
+/// translation language, to `exports/<id>.<language>.srt`, so players can
+/// offer the translation as a second subtitle track.
+pub fn export_subtitles(root: &Path, session_id: &str) -> Result<Vec<SubtitleTrack>> {
+    session::validate_id(session_id)?;
+    let dir = root.join(session_id);
+    let manifest = SessionManifest::read(&dir)
+        .map_err(|err| anyhow!("session '{session_id}' has no readable manifest: {err}"))?;
+    let started_ms = manifest.started_unix_ms as u64;
+    let chunk_ms = |stream: &str| {
+        manifest
+            .summary
+            .as_ref()
+            .and_then(|summary| summary.streams.get(stream))
+            .and_then(|totals| totals.duration_ms.checked_div(totals.chunks))
+            .filter(|chunk_ms| *chunk_ms > 0)
+            .unwrap_or(DEFAULT_CUE_MS)
+    };
+
+    // the first annotation of an audio chunk is its transcript; translations
+    // are told apart by their label
+    let mut transcribed: Vec<(u64, &str)> = Vec::new();
+    let mut tracks: BTreeMap<(bool, Option<String>), Vec<Cue>> = BTreeMap::new();
+    let mut transcript_language: BTreeMap<String, usize> = BTreeMap::new();
+    for record in manifest.chunk_annotations.iter().filter(|record| !proxy::is_video(&record.stream)) {
+        let annotation = &record.annotation;
+        let translation = annotation.labels.iter().any(|label| label == TRANSLATION_LABEL);
+        if !translation {
+            if transcribed.contains(&(record.chunk_id, record.stream.as_str())) {
+                continue;
+            }
+            transcribed.push((record.chunk_id, record.stream.as_str()));
+            if let Some(language) = &annotation.language {
+                *transcript_language.entry(language.clone()).or_default() += 1;
+            }
+        }
+        let offset_ms = ((record.start_ts_unix_nanos / 1_000_000) as u64).saturating_sub(started_ms);
+        let language = translation.then(|| annotation.language.clone()).flatten();
+        let cues = tracks.entry((translation, language)).or_default();
+        match annotation.extra["segments"].as_array() {
+            Some(segments) => cues.extend(segments.iter().filter_map(|segment| {
+                Some(Cue {
+                    start_ms: offset_ms + segment["start_ms"].as_u64()?,
+                    end_ms: offset_ms + segment["end_ms"].as_u64()?,
+                    text: segment["text"].as_str()?.trim().to_string(),
+                })
+            })),
+            None => cues.extend(annotation.summary.as_ref().map(|summary| Cue {
+                start_ms: offset_ms,
+                end_ms: offset_ms + chunk_ms(&record.stream),
+                text: summary.trim().to_string(),
+            })),
+        }
+    }
+    let spoken = transcript_language
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(language, _)| language);
+
+    let out_dir = dir.join(EXPORT_DIR);
+    std::fs::create_dir_all(&out_dir)?;
+    let mut written = Vec::new();
+    for ((translation, language), mut cues) in tracks {
+        cues.retain(|cue| !cue.text.is_empty());
+        if cues.is_empty() {
+            continue;
+        }
+        cues.sort_by_key(|cue| cue.start_ms);
+        let language = if translation { language } else { spoken.clone() };
+        let path = out_dir.join(format!(
+            "{session_id}.{}.srt",
+            language.as_deref().unwrap_or(if translation { "translation" } else { "transcript" })
+        ));
+        std::fs::write(&path, render_srt(&cues)?)?;
+        println!("[export] {session_id}: {} subtitle cues -> {}", cues.len(), path.display());
+        written.push(SubtitleTrack {
+            language,
+            translation,
+            path: path.to_string_lossy().to_string(),
+            cues: cues.len(),
+        });
+    }
+    if written.is_empty() {
+        return Err(anyhow!("session '{session_id}' has no transcribed audio"));
+    }
+    Ok(written)
+}
+
+fn render_srt(cues: &[Cue]) -> Result<String> {
+    let timestamp = |ms: u64| {
+        format!(
+            "{:02}:{:02}:{:02},{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    };
+    let mut out = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        writeln!(
+            out,
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            timestamp(cue.start_ms),
+            timestamp(cue.end_ms.max(cue.start_ms + 1)),
+            cue.text
+        )?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_ms: u64, end_ms: u64, text: &str) -> Cue {
+        Cue {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn numbers_cues_and_formats_timestamps() {
+        let srt = render_srt(&[cue(0, 1500, "hello"), cue(3_723_004, 3_725_000, "later")]).unwrap();
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:01,500\nhello\n\n2\n01:02:03,004 --> 01:02:05,000\nlater\n\n"
+        );
+    }
+
+    #[test]
+    fn empty_cue_still_ends_after_it_starts() {
+        let srt = render_srt(&[cue(2000, 2000, "blip")]).unwrap();
+        assert!(srt.contains("00:00:02,000 --> 00:00:02,001"));
+    }
+}
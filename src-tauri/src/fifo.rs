@@ -0,0 +1,66 @@
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+
+/// Our copy of the original stdout, shared by every stream written there.
+static STREAM_FD: OnceCell<i32> = OnceCell::new();
+
+/// Hands stdout over to a stream. The returned descriptor is the original
+/// stdout; fd 1 itself is pointed at stderr, so the app's own log lines
+/// can't end up inside the stream.
+pub fn take_stdout() -> Result<i32> {
+    STREAM_FD
+        .get_or_try_init(|| {
+            std::io::stdout().flush()?;
+            // SAFETY: plain descriptor calls on fds that are open for the whole process
+            let fd = unsafe { libc::dup(libc::STDOUT_FILENO) };
+            if fd < 0 {
+                return Err(anyhow!("failed to duplicate stdout: {}", std::io::Error::last_os_error()));
+            }
+            // SAFETY: as above
+            if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+                return Err(anyhow!("failed to redirect logs to stderr: {}", std::io::Error::last_os_error()));
+            }
+            eprintln!("[fifo] stdout now carries the stream; logs continue on stderr");
+            Ok(fd)
+        })
+        .copied()
+}
+
+/// Creates a FIFO at `path` unless something already exists there, which
+/// is then written to as is (an existing FIFO, or a regular file).
+pub fn ensure_fifo(path: &Path) -> Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `c_path` is a valid NUL-terminated string for the duration of the call
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } < 0 {
+        return Err(anyhow!(
+            "failed to create FIFO {}: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    println!("[fifo] created {}", path.display());
+    Ok(())
+}
+
+/// Opens the FIFO at `path` for the stream, or `None` when `path` is a
+/// regular file. A write-only open would block until a reader attaches, and
+/// it happens while capture is starting; read-write never blocks on Linux.
+/// Writes still wait for a reader once the pipe buffer is full, but on the
+/// sink's own thread.
+pub fn open_fifo(path: &Path) -> Result<Option<File>> {
+    ensure_fifo(path)?;
+    if !std::fs::metadata(path)?.file_type().is_fifo() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::OpenOptions::new().read(true).write(true).open(path)?))
+}
@@ -0,0 +1,405 @@
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, CaptureTarget};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// How long one `pw-dump` listing of window nodes is reused; focus is
+/// queried several times a second and nodes rarely come and go.
+const NODE_CACHE_TTL: Duration = Duration::from_secs(2);
+/// Stream node properties portals use to name the window they share.
+const NODE_WINDOW_ID_KEYS: [&str; 3] = ["window.id", "window.address", "window.handle"];
+const NODE_TITLE_KEYS: [&str; 2] = ["window.title", "media.title"];
+
+/// The window that currently has keyboard focus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FocusedWindow {
+    /// Compositor-specific window id.
+    pub id: String,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// PipeWire node the window is shared as, when the backend knows it.
+    #[serde(default)]
+    pub node_id: Option<u32>,
+}
+
+impl FocusedWindow {
+    /// Short human-readable name for logs and markers.
+    pub fn label(&self) -> &str {
+        self.app_id
+            .as_deref()
+            .or(self.title.as_deref())
+            .unwrap_or(&self.id)
+    }
+}
+
+/// A stretch of time one window held focus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSpan {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub start_unix_ms: u64,
+    pub end_unix_ms: u64,
+}
+
+impl WindowSpan {
+    fn shows(&self, window: &FocusedWindow) -> bool {
+        self.app_id == window.app_id && self.title == window.title
+    }
+
+    /// Case-insensitive match on the app id or title.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [&self.app_id, &self.title]
+            .into_iter()
+            .flatten()
+            .any(|value| value.to_lowercase().contains(&query))
+    }
+}
+
+/// Which window had focus when, sampled once a second during capture.
+/// Shared with the chunk buffers, which attach the overlapping spans.
+#[derive(Default)]
+pub struct WindowTimeline {
+    spans: Mutex<Vec<WindowSpan>>,
+}
+
+impl WindowTimeline {
+    pub fn reset(&self) {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Extends the current span while the same window keeps focus; more than
+    /// one missed sample ends it.
+    fn observe(&self, window: Option<&FocusedWindow>, now_ms: u64) {
+        let mut spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(window) = window else {
+            return;
+        };
+        let max_gap_ms = 2 * SAMPLE_INTERVAL.as_millis() as u64;
+        if let Some(last) = spans.last_mut() {
+            if last.shows(window) && now_ms.saturating_sub(last.end_unix_ms) <= max_gap_ms {
+                last.end_unix_ms = now_ms;
+                return;
+            }
+        }
+        spans.push(WindowSpan {
+            app_id: window.app_id.clone(),
+            title: window.title.clone(),
+            start_unix_ms: now_ms,
+            end_unix_ms: now_ms,
+        });
+    }
+
+    /// Spans overlapping `start_ms..=end_ms`.
+    pub fn between(&self, start_ms: u64, end_ms: u64) -> Vec<WindowSpan> {
+        self.spans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|span| span.start_unix_ms <= end_ms && span.end_unix_ms >= start_ms)
+            .cloned()
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<WindowSpan> {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Asks the compositor which window is focused. `SC_FOCUSED_WINDOW_COMMAND`
+/// (a shell command printing a [`FocusedWindow`] as JSON) takes precedence
+/// over the built-in sway and Hyprland backends; `Ok(None)` means no backend
+/// is available.
+pub fn focused_window() -> Result<Option<FocusedWindow>> {
+    let window = if let Ok(command) = std::env::var("SC_FOCUSED_WINDOW_COMMAND") {
+        let output = run("sh", &["-c", &command])?;
+        if output.trim().is_empty() {
+            return Ok(None);
+        }
+        serde_json::from_str(&output)?
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        let tree: Value = serde_json::from_str(&run("swaymsg", &["-t", "get_tree", "-r"])?)?;
+        let mut windows = Vec::new();
+        sway_windows(&tree, &mut windows);
+        match windows.into_iter().find_map(|(window, focused)| focused.then_some(window)) {
+            Some(window) => window,
+            None => return Ok(None),
+        }
+    } else if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let window: Value = serde_json::from_str(&run("hyprctl", &["activewindow", "-j"])?)?;
+        match hyprland_window(&window) {
+            Some(window) => window,
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(with_node(window, &window_nodes())))
+}
+
+/// Every window the compositor manages, with the PipeWire node of those that
+/// are shared. `SC_WINDOWS_COMMAND` prints them as a JSON array of
+/// [`FocusedWindow`]s and takes precedence over sway and Hyprland.
+pub fn list_windows() -> Result<Vec<FocusedWindow>> {
+    let windows: Vec<FocusedWindow> = if let Ok(command) = std::env::var("SC_WINDOWS_COMMAND") {
+        serde_json::from_str(&run("sh", &["-c", &command])?)?
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        let tree: Value = serde_json::from_str(&run("swaymsg", &["-t", "get_tree", "-r"])?)?;
+        let mut windows = Vec::new();
+        sway_windows(&tree, &mut windows);
+        windows.into_iter().map(|(window, _)| window).collect()
+    } else if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let clients: Value = serde_json::from_str(&run("hyprctl", &["clients", "-j"])?)?;
+        clients
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(hyprland_window)
+            .collect()
+    } else {
+        return Err(anyhow!("no window backend; run sway or Hyprland, or set SC_WINDOWS_COMMAND"));
+    };
+    let nodes = window_nodes();
+    Ok(windows.into_iter().map(|window| with_node(window, &nodes)).collect())
+}
+
+/// The compositor window shared as PipeWire node `node_id`, as used by
+/// [`CaptureTarget::Window`].
+pub fn window_for_node(node_id: &str) -> Result<Option<FocusedWindow>> {
+    Ok(list_windows()?
+        .into_iter()
+        .find(|window| window.node_id.is_some_and(|node| node.to_string() == node_id)))
+}
+
+/// A PipeWire video source and the window it says it shares.
+#[derive(Debug, Clone)]
+struct WindowNode {
+    id: u32,
+    window_id: Option<String>,
+    title: Option<String>,
+}
+
+static NODE_CACHE: Mutex<Option<(Instant, Vec<WindowNode>)>> = Mutex::new(None);
+
+/// Video source nodes from `pw-dump`, cached for [`NODE_CACHE_TTL`]. Without
+/// PipeWire there are none, and windows simply stay without a node.
+fn window_nodes() -> Vec<WindowNode> {
+    let mut cache = NODE_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((listed, nodes)) = cache.as_ref() {
+        if listed.elapsed() < NODE_CACHE_TTL {
+            return nodes.clone();
+        }
+    }
+    let nodes = match run("pw-dump", &[]).and_then(|output| Ok(serde_json::from_str::<Value>(&output)?)) {
+        Ok(dump) => parse_window_nodes(&dump),
+        Err(err) => {
+            if cache.is_none() {
+                eprintln!("[focus] failed to list PipeWire nodes: {err}");
+            }
+            Vec::new()
+        }
+    };
+    *cache = Some((Instant::now(), nodes.clone()));
+    nodes
+}
+
+fn parse_window_nodes(dump: &Value) -> Vec<WindowNode> {
+    let prop = |props: &Value, keys: &[&str]| {
+        keys.iter().find_map(|key| match &props[*key] {
+            Value::String(value) => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        })
+    };
+    dump.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|object| object["type"] == "PipeWire:Interface:Node")
+        .filter(|object| object["info"]["props"]["media.class"] == "Video/Source")
+        .filter_map(|object| {
+            let props = &object["info"]["props"];
+            Some(WindowNode {
+                id: u32::try_from(object["id"].as_u64()?).ok()?,
+                window_id: prop(props, &NODE_WINDOW_ID_KEYS),
+                title: prop(props, &NODE_TITLE_KEYS),
+            })
+        })
+        .collect()
+}
+
+/// Fills in `node_id` from the node naming this window by id, or else the
+/// only node carrying its title.
+fn with_node(mut window: FocusedWindow, nodes: &[WindowNode]) -> FocusedWindow {
+    if window.node_id.is_some() {
+        return window;
+    }
+    window.node_id = nodes
+        .iter()
+        .find(|node| node.window_id.as_deref() == Some(window.id.as_str()))
+        .map(|node| node.id)
+        .or_else(|| {
+            let title = window.title.as_deref()?;
+            let mut titled = nodes.iter().filter(|node| node.title.as_deref() == Some(title));
+            match (titled.next(), titled.next()) {
+                (Some(node), None) => Some(node.id),
+                _ => None,
+            }
+        });
+    window
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Application windows in a sway tree, each with whether it has focus.
+fn sway_windows(node: &Value, windows: &mut Vec<(FocusedWindow, bool)>) {
+    // containers holding a client have a pid; splits and workspaces don't
+    if (node["type"] == "con" || node["type"] == "floating_con") && node["pid"].is_number() {
+        let app_id = node["app_id"]
+            .as_str()
+            .or_else(|| node["window_properties"]["class"].as_str());
+        windows.push((
+            FocusedWindow {
+                id: node["id"].to_string(),
+                app_id: app_id.map(str::to_string),
+                title: node["name"].as_str().map(str::to_string),
+                node_id: None,
+            },
+            node["focused"].as_bool() == Some(true),
+        ));
+    }
+    for child in ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+    {
+        sway_windows(child, windows);
+    }
+}
+
+fn hyprland_window(window: &Value) -> Option<FocusedWindow> {
+    Some(FocusedWindow {
+        id: window["address"].as_str()?.to_string(),
+        app_id: window["class"].as_str().map(str::to_string),
+        title: window["title"].as_str().map(str::to_string),
+        node_id: None,
+    })
+}
+
+/// Drives [`CaptureTarget::FollowFocus`]: switches the video source to the
+/// focused window once focus has rested on it for the configured dwell time.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("focus_follow".into())
+        .spawn(move || {
+            let mut follower = Follower::default();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                follower.poll(&app);
+            }
+        })?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Follower {
+    /// A newly focused window and when it got focus.
+    candidate: Option<(FocusedWindow, Instant)>,
+    backend_failed: bool,
+}
+
+impl Follower {
+    fn poll(&mut self, app: &AppHandle) {
+        let manager = app.state::<CaptureManager>();
+        let min_dwell = match manager.options().target {
+            CaptureTarget::FollowFocus { min_dwell_ms } if manager.status() == CaptureState::Running => {
+                Duration::from_millis(min_dwell_ms)
+            }
+            _ => {
+                self.candidate = None;
+                return;
+            }
+        };
+        let window = match focused_window() {
+            Ok(Some(window)) => window,
+            Ok(None) => return,
+            Err(err) => {
+                if !std::mem::replace(&mut self.backend_failed, true) {
+                    eprintln!("[focus] failed to query the focused window: {err}");
+                }
+                return;
+            }
+        };
+        self.backend_failed = false;
+        if window.node_id.is_none() || manager.followed_window().as_ref() == Some(&window.id) {
+            self.candidate = None;
+            return;
+        }
+        match &self.candidate {
+            Some((candidate, since)) if candidate.id == window.id => {
+                if since.elapsed() < min_dwell {
+                    return;
+                }
+            }
+            _ => {
+                self.candidate = Some((window, Instant::now()));
+                return;
+            }
+        }
+        self.candidate = None;
+        if let Err(err) = manager.follow_window(&window) {
+            eprintln!("[focus] failed to switch to {}: {err}", window.label());
+        }
+    }
+}
+
+/// Samples the focused window into the manager's [`WindowTimeline`] while a
+/// session with `track_active_window` runs.
+pub fn spawn_sampler(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("window_sampler".into())
+        .spawn(move || {
+            let mut backend_failed = false;
+            loop {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                let manager = app.state::<CaptureManager>();
+                if manager.status() != CaptureState::Running || !manager.options().track_active_window {
+                    continue;
+                }
+                match focused_window() {
+                    Ok(window) => {
+                        backend_failed = false;
+                        let now_ms = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|d| d.as_millis() as u64)
+                            .unwrap_or_default();
+                        manager.window_timeline().observe(window.as_ref(), now_ms);
+                    }
+                    Err(err) => {
+                        if !std::mem::replace(&mut backend_failed, true) {
+                            eprintln!("[focus] failed to sample the focused window: {err}");
+                        }
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+use crate::session;
+
+/// Side of the luma thumbnail the DCT runs on.
+const THUMB_SIZE: usize = 32;
+/// Side of the low-frequency block the hash bits come from.
+const HASH_SIZE: usize = 8;
+/// Points averaged per thumbnail cell along each axis; enough to smooth
+/// out text and cursors without reading every pixel.
+const CELL_SAMPLES: usize = 4;
+/// One frame is hashed per this much of a chunk.
+const SAMPLE_INTERVAL_MS: u64 = 1_000;
+/// Hashes this many bits apart or fewer are usually the same screen.
+pub const DEFAULT_MAX_DISTANCE: u32 = 10;
+const MAX_MATCHES: usize = 200;
+
+/// Perceptual hash of one sampled frame. `hash` is 16 hex digits, since a
+/// `u64` doesn't survive a trip through JavaScript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameHash {
+    pub chunk_id: u64,
+    pub unix_ms: u64,
+    /// Position of the frame within its chunk.
+    pub offset_ms: u64,
+    pub hash: String,
+}
+
+/// The hashes of the running session, in chunk order.
+#[derive(Default)]
+pub struct FrameHashes {
+    hashes: Mutex<Vec<FrameHash>>,
+}
+
+impl FrameHashes {
+    pub fn reset(&self) {
+        self.hashes.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn extend(&self, hashes: &[FrameHash]) {
+        self.hashes.lock().unwrap_or_else(PoisonError::into_inner).extend_from_slice(hashes);
+    }
+
+    pub fn snapshot(&self) -> Vec<FrameHash> {
+        self.hashes.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarFrame {
+    pub session_id: String,
+    pub chunk_id: u64,
+    pub unix_ms: u64,
+    /// Position in the session's recording.
+    pub offset_ms: u64,
+    pub hash: String,
+    /// Differing bits; 0 is the same picture.
+    pub distance: u32,
+}
+
+/// Hashes about one frame per second of a video chunk. A frame whose hash
+/// equals the one sampled before it is left out, so a still screen costs a
+/// single entry per chunk. Only 4-byte packed formats are handled, as for
+/// proxies.
+pub fn hash_chunk(chunk: &CapturedChunk) -> Result<Vec<FrameHash>> {
+    let meta = &chunk.metadata;
+    let format = meta["format"].as_str().unwrap_or_default();
+    let (r, g, b) = match format {
+        "RGBA" | "RGBx" => (0, 1, 2),
+        "BGRA" | "BGRx" => (2, 1, 0),
+        _ => return Err(anyhow!("no frame hashes for {format:?} frames")),
+    };
+    let width = meta["width"].as_u64().unwrap_or_default() as usize;
+    let height = meta["height"].as_u64().unwrap_or_default() as usize;
+    let frame_size = meta["frame_size"].as_u64().unwrap_or_default() as usize;
+    if width == 0 || height == 0 || frame_size < width * height * 4 {
+        return Err(anyhow!("chunk metadata has no usable frame size"));
+    }
+    let stride = frame_size / height;
+    let frames = chunk.data.len() / frame_size;
+    if frames == 0 {
+        return Ok(Vec::new());
+    }
+    let frame_ms = chunk.duration_ms / frames as u64;
+    let step = (SAMPLE_INTERVAL_MS / frame_ms.max(1)).max(1) as usize;
+    let start_unix_ms = (chunk.start_ts_unix_nanos / 1_000_000) as u64;
+
+    let mut hashes: Vec<FrameHash> = Vec::new();
+    let mut previous = None;
+    for (index, frame) in chunk.data.chunks_exact(frame_size).enumerate().step_by(step) {
+        let hash = phash(&luma_thumbnail(frame, width, height, stride, (r, g, b)));
+        if previous.replace(hash) == Some(hash) {
+            continue;
+        }
+        let offset_ms = index as u64 * frame_ms;
+        hashes.push(FrameHash {
+            chunk_id: chunk.id,
+            unix_ms: start_unix_ms + offset_ms,
+            offset_ms,
+            hash: format!("{hash:016x}"),
+        });
+    }
+    Ok(hashes)
+}
+
+/// Parses a hash as written by [`hash_chunk`].
+pub fn parse_hash(hash: &str) -> Result<u64> {
+    u64::from_str_radix(hash.trim(), 16).map_err(|_| anyhow!("'{hash}' is not a hex frame hash"))
+}
+
+/// Frames under `root` within `max_distance` bits of `hash`, closest first
+/// and newest first among equals. `live` adds the running session, which
+/// has no manifest yet.
+pub fn find_similar_frames(
+    root: &Path,
+    hash: &str,
+    max_distance: u32,
+    live: Option<(&str, u64, &[FrameHash])>,
+) -> Result<Vec<SimilarFrame>> {
+    let wanted = parse_hash(hash)?;
+    let mut matches = Vec::new();
+    let mut collect = |session_id: &str, started_unix_ms: u64, hashes: &[FrameHash]| {
+        for entry in hashes {
+            let Ok(value) = parse_hash(&entry.hash) else {
+                continue;
+            };
+            let distance = (value ^ wanted).count_ones();
+            if distance <= max_distance {
+                matches.push(SimilarFrame {
+                    session_id: session_id.to_string(),
+                    chunk_id: entry.chunk_id,
+                    unix_ms: entry.unix_ms,
+                    offset_ms: entry.unix_ms.saturating_sub(started_unix_ms),
+                    hash: entry.hash.clone(),
+                    distance,
+                });
+            }
+        }
+    };
+    if let Some((session_id, started_unix_ms, hashes)) = live {
+        collect(session_id, started_unix_ms, hashes);
+    }
+    for (_, manifest) in session::list_manifests(root)? {
+        if live.is_some_and(|(session_id, _, _)| session_id == manifest.session_id) {
+            continue;
+        }
+        collect(&manifest.session_id, manifest.started_unix_ms as u64, &manifest.frame_hashes);
+    }
+    matches.sort_by(|a, b| a.distance.cmp(&b.distance).then(b.unix_ms.cmp(&a.unix_ms)));
+    matches.truncate(MAX_MATCHES);
+    Ok(matches)
+}
+
+/// Box-filters a frame down to a `THUMB_SIZE` square of luma, reading a
+/// grid of points per cell rather than every pixel.
+fn luma_thumbnail(
+    frame: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    (r, g, b): (usize, usize, usize),
+) -> Vec<f32> {
+    let mut thumb = Vec::with_capacity(THUMB_SIZE * THUMB_SIZE);
+    for cell_y in 0..THUMB_SIZE {
+        for cell_x in 0..THUMB_SIZE {
+            let mut sum = 0.0;
+            for sy in 0..CELL_SAMPLES {
+                let y = ((cell_y * CELL_SAMPLES + sy) * height / (THUMB_SIZE * CELL_SAMPLES)).min(height - 1);
+                for sx in 0..CELL_SAMPLES {
+                    let x = ((cell_x * CELL_SAMPLES + sx) * width / (THUMB_SIZE * CELL_SAMPLES)).min(width - 1);
+                    let pixel = &frame[y * stride + x * 4..];
+                    sum += 0.299 * pixel[r] as f32 + 0.587 * pixel[g] as f32 + 0.114 * pixel[b] as f32;
+                }
+            }
+            thumb.push(sum / (CELL_SAMPLES * CELL_SAMPLES) as f32);
+        }
+    }
+    thumb
+}
+
+/// DCT-based perceptual hash: one bit per low-frequency coefficient, set
+/// when it is above the median. The DC term is left out of the median, as
+/// it only tracks overall brightness.
+fn phash(thumb: &[f32]) -> u64 {
+    let n = THUMB_SIZE;
+    let basis: Vec<f32> = (0..HASH_SIZE)
+        .flat_map(|k| {
+            (0..n).map(move |i| (std::f32::consts::PI / n as f32 * (i as f32 + 0.5) * k as f32).cos())
+        })
+        .collect();
+    // rows first, then columns, keeping only the low frequencies of each
+    let mut rows = vec![0.0f32; n * HASH_SIZE];
+    for y in 0..n {
+        for k in 0..HASH_SIZE {
+            rows[y * HASH_SIZE + k] = (0..n).map(|x| thumb[y * n + x] * basis[k * n + x]).sum();
+        }
+    }
+    let mut coefficients = [0.0f32; HASH_SIZE * HASH_SIZE];
+    for k in 0..HASH_SIZE {
+        for u in 0..HASH_SIZE {
+            coefficients[k * HASH_SIZE + u] = (0..n).map(|y| rows[y * HASH_SIZE + u] * basis[k * n + y]).sum();
+        }
+    }
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value > median)
+        .fold(0u64, |hash, (bit, _)| hash | 1 << bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A smooth but uneven `THUMB_SIZE` square, so no coefficient sits at
+    /// the median by accident.
+    fn pattern() -> Vec<f32> {
+        (0..THUMB_SIZE * THUMB_SIZE)
+            .map(|i| {
+                let (x, y) = ((i % THUMB_SIZE) as f32, (i / THUMB_SIZE) as f32);
+                128.0 + 60.0 * (x * 0.3 + y * 0.17).sin()
+                    + 40.0 * (x * 0.11 - y * 0.29).cos()
+                    + 20.0 * (x * y * 0.02).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ignores_overall_brightness() {
+        let brighter: Vec<f32> = pattern().iter().map(|luma| luma + 40.0).collect();
+        assert_eq!(phash(&pattern()), phash(&brighter));
+    }
+
+    #[test]
+    fn small_changes_stay_close() {
+        let mut touched = pattern();
+        for luma in touched.iter_mut().step_by(37) {
+            *luma += 6.0;
+        }
+        assert!((phash(&pattern()) ^ phash(&touched)).count_ones() <= 4);
+    }
+
+    #[test]
+    fn different_frames_are_far_apart() {
+        let inverted: Vec<f32> = pattern().iter().map(|luma| 255.0 - luma).collect();
+        assert!((phash(&pattern()) ^ phash(&inverted)).count_ones() >= 32);
+    }
+}
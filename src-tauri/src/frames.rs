@@ -0,0 +1,118 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use gstreamer as gst;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+
+const MAX_BURST: u32 = 60;
+/// Keeps a burst to a minute at most, since it holds a command thread.
+const MAX_BURST_INTERVAL: Duration = Duration::from_secs(1);
+const ENCODE_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameFormat {
+    #[default]
+    Jpeg,
+    Png,
+}
+
+impl FrameFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "jpg",
+            FrameFormat::Png => "png",
+        }
+    }
+
+    fn caps(self) -> gst::Caps {
+        match self {
+            FrameFormat::Jpeg => gst::Caps::builder("image/jpeg").build(),
+            FrameFormat::Png => gst::Caps::builder("image/png").build(),
+        }
+    }
+}
+
+/// One still, encoded and base64'd so it can go straight into a data URL
+/// or a model request.
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturedFrame {
+    pub unix_ms: u128,
+    pub width: i32,
+    pub height: i32,
+    pub format: FrameFormat,
+    pub data_base64: String,
+}
+
+/// The newest frame that reached the video appsink.
+#[derive(Default)]
+pub struct LatestFrame {
+    sample: Mutex<Option<(gst::Sample, u128)>>,
+}
+
+impl LatestFrame {
+    pub fn store(&self, sample: &gst::Sample, unix_ms: u128) {
+        *self.sample.lock().unwrap_or_else(PoisonError::into_inner) = Some((sample.clone(), unix_ms));
+    }
+
+    pub fn clear(&self) {
+        *self.sample.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    fn get(&self) -> Option<(gst::Sample, u128)> {
+        self.sample.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// The newest frame encoded as `format`, with when it arrived.
+    pub fn still(&self, format: FrameFormat) -> Result<(Vec<u8>, u128)> {
+        let (sample, unix_ms) = self.get().ok_or_else(|| anyhow!("no video frame captured yet"))?;
+        Ok((encode_bytes(&sample, format)?, unix_ms))
+    }
+
+    /// Encodes the newest frame `count` times, `interval` apart. Screen
+    /// sources only deliver frames on change, so a still screen yields the
+    /// same frame (and timestamp) more than once.
+    pub fn burst(&self, count: u32, interval: Duration, format: FrameFormat) -> Result<Vec<CapturedFrame>> {
+        if count == 0 || count > MAX_BURST {
+            return Err(anyhow!("frame count must be between 1 and {MAX_BURST}"));
+        }
+        if interval > MAX_BURST_INTERVAL {
+            return Err(anyhow!("frame interval must be at most {} ms", MAX_BURST_INTERVAL.as_millis()));
+        }
+        let mut frames = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            if index > 0 {
+                std::thread::sleep(interval);
+            }
+            let (sample, unix_ms) = self.get().ok_or_else(|| anyhow!("no video frame captured yet"))?;
+            frames.push(encode(&sample, unix_ms, format)?);
+        }
+        Ok(frames)
+    }
+}
+
+fn encode_bytes(sample: &gst::Sample, format: FrameFormat) -> Result<Vec<u8>> {
+    let encoded = gst_video::convert_sample(sample, &format.caps(), gst::ClockTime::from_seconds(ENCODE_TIMEOUT_SECS))
+        .map_err(|err| anyhow!("failed to encode frame: {err}"))?;
+    let buffer = encoded.buffer().ok_or_else(|| anyhow!("encoded frame is empty"))?;
+    let map = buffer.map_readable()?;
+    Ok(map.as_slice().to_vec())
+}
+
+fn encode(sample: &gst::Sample, unix_ms: u128, format: FrameFormat) -> Result<CapturedFrame> {
+    let (width, height) = sample
+        .caps()
+        .and_then(|caps| caps.structure(0))
+        .and_then(|structure| Some((structure.get::<i32>("width").ok()?, structure.get::<i32>("height").ok()?)))
+        .unwrap_or_default();
+    Ok(CapturedFrame {
+        unix_ms,
+        width,
+        height,
+        format,
+        data_base64: base64::engine::general_purpose::STANDARD.encode(encode_bytes(sample, format)?),
+    })
+}
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Region of the source frame to keep, in source pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A `videocrop` keeping `rect`. videocrop is configured by margins, so the
+/// right and bottom ones are derived once the source size is negotiated.
+pub fn crop(rect: Rect) -> Result<gst::Element> {
+    if rect.width == 0 || rect.height == 0 {
+        return Err(anyhow!("crop rectangle must have a non-zero size"));
+    }
+    let crop = gst::ElementFactory::make("videocrop")
+        .name("video_crop")
+        .property("left", rect.x as i32)
+        .property("top", rect.y as i32)
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'videocrop' — ensure required plugins are installed"))?;
+    on_input_size(&crop, move |crop, width, height| {
+        let right = width - (rect.x + rect.width) as i32;
+        let bottom = height - (rect.y + rect.height) as i32;
+        if right < 0 || bottom < 0 {
+            eprintln!("[capture] crop {rect:?} exceeds the {width}x{height} source; clamping");
+        }
+        crop.set_property("right", right.max(0));
+        crop.set_property("bottom", bottom.max(0));
+    })?;
+    Ok(crop)
+}
+
+/// Calls `configure` with the frame size whenever new caps reach `element`,
+/// before the element itself sees them.
+fn on_input_size(element: &gst::Element, configure: impl Fn(&gst::Element, i32, i32) + Send + Sync + 'static) -> Result<()> {
+    let sink_pad = element
+        .static_pad("sink")
+        .ok_or_else(|| anyhow!("{} has no sink pad", element.name()))?;
+    let target = element.clone();
+    sink_pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        let Some(gst::PadProbeData::Event(event)) = &info.data else {
+            return gst::PadProbeReturn::Ok;
+        };
+        let gst::EventView::Caps(caps) = event.view() else {
+            return gst::PadProbeReturn::Ok;
+        };
+        let Some(structure) = caps.caps().structure(0) else {
+            return gst::PadProbeReturn::Ok;
+        };
+        if let (Ok(width), Ok(height)) = (structure.get::<i32>("width"), structure.get::<i32>("height")) {
+            configure(&target, width, height);
+        }
+        gst::PadProbeReturn::Ok
+    });
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AspectPreset {
+    #[serde(rename = "16:9")]
+    Landscape,
+    #[serde(rename = "9:16")]
+    Portrait,
+    #[serde(rename = "1:1")]
+    Square,
+}
+
+impl AspectPreset {
+    fn ratio(self) -> (i32, i32) {
+        match self {
+            AspectPreset::Landscape => (16, 9),
+            AspectPreset::Portrait => (9, 16),
+            AspectPreset::Square => (1, 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectMode {
+    /// Keep the whole picture and add black bars.
+    #[default]
+    Pad,
+    /// Fill the frame and cut off what doesn't fit.
+    Crop,
+}
+
+/// Brings frames to `preset`'s aspect ratio: `aspectratiocrop` for
+/// [`AspectMode::Crop`], or a `videobox` with negative margins (borders) for
+/// [`AspectMode::Pad`], sized from the negotiated input.
+pub fn aspect(preset: AspectPreset, mode: AspectMode) -> Result<gst::Element> {
+    let (num, den) = preset.ratio();
+    let factory = match mode {
+        AspectMode::Crop => "aspectratiocrop",
+        AspectMode::Pad => "videobox",
+    };
+    let element = gst::ElementFactory::make(factory)
+        .name("video_aspect")
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element '{factory}' — ensure required plugins are installed"))?;
+    match mode {
+        AspectMode::Crop => element.set_property("aspect-ratio", gst::Fraction::new(num, den)),
+        AspectMode::Pad => {
+            element.set_property_from_str("fill", "black");
+            on_input_size(&element, move |videobox, width, height| {
+                let (mut pad_x, mut pad_y) = (0, 0);
+                if width * den > height * num {
+                    // too wide: letterbox, keeping the height even
+                    pad_y = ((width * den / num + 1) & !1) - height;
+                } else {
+                    // too tall: pillarbox
+                    pad_x = ((height * num / den + 1) & !1) - width;
+                }
+                videobox.set_property("left", -(pad_x / 2));
+                videobox.set_property("right", -(pad_x - pad_x / 2));
+                videobox.set_property("top", -(pad_y / 2));
+                videobox.set_property("bottom", -(pad_y - pad_y / 2));
+            })?;
+        }
+    }
+    Ok(element)
+}
+
+/// A `videoflip` rotating clockwise by `degrees` (0, 90, 180 or 270).
+pub fn rotate(degrees: u16) -> Result<gst::Element> {
+    let method = match degrees {
+        0 => "none",
+        90 => "clockwise",
+        180 => "rotate-180",
+        270 => "counterclockwise",
+        other => return Err(anyhow!("unsupported rotation {other}; use 0, 90, 180 or 270")),
+    };
+    let flip = gst::ElementFactory::make("videoflip")
+        .name("video_flip")
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'videoflip' — ensure required plugins are installed"))?;
+    flip.set_property_from_str("method", method);
+    Ok(flip)
+}
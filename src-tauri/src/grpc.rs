@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::acks::{AckTracker, ChunkKey};
+use crate::capture_manager::CapturedChunk;
+use crate::http_api;
+use crate::proxy;
+use crate::sinks::ChunkTaps;
+
+mod pb {
+    tonic::include_proto!("screencapture.v1");
+}
+
+use pb::chunk_export_server::{ChunkExport, ChunkExportServer};
+
+const DEFAULT_PORT: u16 = 47_801;
+/// Chunks a slow client may fall behind by before it starts missing some.
+const CLIENT_BACKLOG_CHUNKS: usize = 64;
+
+pub struct GrpcConfig {
+    pub port: u16,
+    pub token: Option<String>,
+}
+
+impl GrpcConfig {
+    /// The server stays disabled unless `SC_GRPC_PORT` is set; clients must
+    /// send `authorization: Bearer <SC_GRPC_TOKEN>` when a token is set.
+    pub fn from_env() -> Option<Self> {
+        let port = std::env::var("SC_GRPC_PORT").ok()?;
+        let port = port.parse().unwrap_or(DEFAULT_PORT);
+        let token = std::env::var("SC_GRPC_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        Some(Self { port, token })
+    }
+}
+
+struct ChunkExportService {
+    chunks: broadcast::Sender<Arc<CapturedChunk>>,
+    acks: Arc<AckTracker>,
+    token: Option<String>,
+}
+
+impl ChunkExportService {
+    fn authorize<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if let Some(token) = &self.token {
+            let provided = request
+                .metadata()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok());
+            if !http_api::bearer_matches(provided, token) {
+                return Err(Status::unauthenticated("missing or invalid token"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl pb::SubscribeRequest {
+    fn matches(&self, chunk: &CapturedChunk) -> bool {
+        let is_video = proxy::is_video(&chunk.kind);
+        let media_ok = match self.media() {
+            pb::MediaFilter::All => true,
+            pb::MediaFilter::Video => is_video,
+            pb::MediaFilter::Audio => !is_video,
+        };
+        media_ok && (self.streams.is_empty() || self.streams.contains(&chunk.kind))
+    }
+}
+
+fn to_message(chunk: &CapturedChunk) -> pb::Chunk {
+    pb::Chunk {
+        id: chunk.id,
+        stream: chunk.kind.clone(),
+        start_ts_unix_nanos: chunk.start_ts_unix_nanos as u64,
+        duration_ms: chunk.duration_ms,
+        metadata_json: chunk.metadata.to_string(),
+        data: chunk.data.clone(),
+    }
+}
+
+type ChunkStream = Pin<Box<dyn Stream<Item = Result<pb::Chunk, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl ChunkExport for ChunkExportService {
+    type SubscribeStream = ChunkStream;
+
+    async fn subscribe(&self, request: Request<pb::SubscribeRequest>) -> Result<Response<ChunkStream>, Status> {
+        self.authorize(&request)?;
+        let filter = request.into_inner();
+        println!("[grpc] client subscribed (media {:?}, streams {:?})", filter.media(), filter.streams);
+        // subscribe before taking the backlog, so no chunk falls in between
+        let live = BroadcastStream::new(self.chunks.subscribe());
+        let backlog = match filter.consumer_id.as_str() {
+            "" => Vec::new(),
+            consumer => self.acks.connect(consumer),
+        };
+        let replayed: HashSet<ChunkKey> = backlog.iter().map(|chunk| ChunkKey::of(chunk)).collect();
+        let replay_filter = filter.clone();
+        let replay = tokio_stream::iter(backlog)
+            .filter(move |chunk| replay_filter.matches(chunk))
+            .map(|chunk| Ok(to_message(&chunk)));
+        let live = live.filter_map(move |item| match item {
+            Ok(chunk) => (filter.matches(&chunk) && !replayed.contains(&ChunkKey::of(&chunk))).then(|| Ok(to_message(&chunk))),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                eprintln!("[grpc] slow client skipped {missed} chunks");
+                None
+            }
+        });
+        Ok(Response::new(Box::pin(replay.chain(live))))
+    }
+
+    async fn ack(&self, request: Request<pb::AckRequest>) -> Result<Response<pb::AckResponse>, Status> {
+        self.authorize(&request)?;
+        let request = request.into_inner();
+        let keys: Vec<ChunkKey> = request
+            .chunks
+            .into_iter()
+            .map(|chunk| ChunkKey {
+                stream: chunk.stream,
+                chunk_id: chunk.id,
+            })
+            .collect();
+        let released = self.acks.ack(&request.consumer_id, &keys);
+        Ok(Response::new(pb::AckResponse {
+            released: released as u32,
+        }))
+    }
+}
+
+/// Serves chunks from `taps` on 127.0.0.1. Each client gets its own bounded
+/// backlog, so a slow one only loses chunks itself and never holds back capture.
+/// Clients subscribing with a `consumer_id` get unacked chunks from `acks`
+/// re-delivered when they reconnect.
+pub fn spawn(taps: &ChunkTaps, acks: Arc<AckTracker>, config: GrpcConfig) -> Result<()> {
+    let (chunks, _) = broadcast::channel(CLIENT_BACKLOG_CHUNKS);
+    let sender = chunks.clone();
+    taps.subscribe(move |chunk| {
+        // fails only while nobody is subscribed
+        let _ = sender.send(Arc::clone(chunk));
+    });
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("grpc")
+        .enable_all()
+        .build()?;
+    let addr = ([127, 0, 0, 1], config.port).into();
+    let service = ChunkExportService {
+        chunks,
+        acks,
+        token: config.token,
+    };
+    std::thread::Builder::new()
+        .name("grpc".into())
+        .spawn(move || {
+            println!("[grpc] listening on {addr}");
+            let served = runtime.block_on(
+                tonic::transport::Server::builder()
+                    .add_service(ChunkExportServer::new(service))
+                    .serve(addr),
+            );
+            if let Err(err) = served {
+                eprintln!("[grpc] server stopped: {err}");
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use gstreamer as gst;
+
+/// Warnings and errors from every category.
+const DEFAULT_THRESHOLDS: &str = "*:2";
+
+/// Routes GStreamer's own debug output into the app's log as `[gst]` lines,
+/// so pipeline issues reported by users come with GStreamer's side of the
+/// story. `SC_GST_LOG` takes `GST_DEBUG` syntax (e.g. `*:2,pipewiresrc:5`)
+/// and `off` disables the bridge. When `GST_DEBUG` is set, GStreamer's
+/// default output is left alone.
+pub fn install() {
+    if std::env::var_os("GST_DEBUG").is_some() {
+        return;
+    }
+    let thresholds = std::env::var("SC_GST_LOG").unwrap_or_else(|_| DEFAULT_THRESHOLDS.to_string());
+    if thresholds.trim().is_empty() || thresholds.trim() == "off" {
+        return;
+    }
+    gst::log::remove_default_log_function();
+    gst::debug_set_threshold_from_string(&thresholds, true);
+    gst::debug_set_active(true);
+    let _ = gst::log::add_log_function(|category, level, file, _function, line, object, message| {
+        let Some(message) = message.get() else {
+            return;
+        };
+        let object = object.map(|object| format!(" <{object}>")).unwrap_or_default();
+        let line = format!(
+            "[gst] {} {}{object}: {message} ({file}:{line})",
+            level.name().trim(),
+            category.name()
+        );
+        match level {
+            gst::DebugLevel::Error | gst::DebugLevel::Warning => eprintln!("{line}"),
+            _ => println!("{line}"),
+        }
+    });
+    println!("[gst] forwarding GStreamer logs at '{thresholds}'");
+}
@@ -0,0 +1,96 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+
+/// 10-bit 4:2:0, the format HDR sources are kept in when `HdrMode::Auto` applies.
+pub const HDR_FORMAT: &str = "P010_10LE";
+const SDR_FORMAT: &str = "RGBA";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HdrMode {
+    /// Always 8-bit RGBA; HDR sources are passed through without conversion.
+    #[default]
+    Off,
+    /// Keep 10-bit video and its colorimetry when the source delivers it;
+    /// 8-bit sources still come out as RGBA.
+    Auto,
+    /// Convert HDR sources to SDR sRGB (transfer and primaries remapped,
+    /// highlights clipped) so they don't look washed out.
+    TonemapSdr,
+}
+
+impl HdrMode {
+    /// Output formats to offer after the converter, in order of preference.
+    /// videoconvert picks the one closest to its input, so a 10-bit source
+    /// negotiates P010 and an 8-bit one RGBA.
+    pub fn formats(self) -> &'static [&'static str] {
+        match self {
+            HdrMode::Auto => &[HDR_FORMAT, SDR_FORMAT],
+            HdrMode::Off | HdrMode::TonemapSdr => &[SDR_FORMAT],
+        }
+    }
+
+    pub fn colorimetry(self) -> Option<&'static str> {
+        match self {
+            HdrMode::TonemapSdr => Some("sRGB"),
+            HdrMode::Off | HdrMode::Auto => None,
+        }
+    }
+
+    /// videoconvert leaves the transfer function alone by default, which is
+    /// what makes PQ/HLG content look washed out as SDR.
+    pub fn configure_convert(self, convert: &gst::Element) {
+        if self == HdrMode::TonemapSdr {
+            convert.set_property_from_str("gamma-mode", "remap");
+            convert.set_property_from_str("primaries-mode", "fast");
+        }
+    }
+}
+
+/// Negotiated colour description of the video, recorded per chunk and in the
+/// manifest so exports can be tagged to match what was on screen.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColorInfo {
+    /// GStreamer colorimetry string, e.g. `bt709` or `bt2100-pq`.
+    pub colorimetry: String,
+    pub range: String,
+    pub matrix: String,
+    pub transfer: String,
+    pub primaries: String,
+    pub chroma_site: String,
+}
+
+impl ColorInfo {
+    pub fn from_info(info: &gst_video::VideoInfo) -> Self {
+        let colorimetry = info.colorimetry();
+        let range = match colorimetry.range() {
+            gst_video::VideoColorRange::Range0_255 => "full",
+            gst_video::VideoColorRange::Range16_235 => "limited",
+            _ => "unknown",
+        };
+        Self {
+            colorimetry: colorimetry.to_string(),
+            range: range.to_string(),
+            matrix: format!("{:?}", colorimetry.matrix()).to_lowercase(),
+            transfer: format!("{:?}", colorimetry.transfer()).to_lowercase(),
+            primaries: format!("{:?}", colorimetry.primaries()).to_lowercase(),
+            chroma_site: info.chroma_site().to_str().to_string(),
+        }
+    }
+
+    /// Reads the colour description from caps that have been fully negotiated.
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        gst_video::VideoInfo::from_caps(caps).ok().map(|info| Self::from_info(&info))
+    }
+}
+
+/// True for PQ/HLG transfer or more than 8 bits per component.
+pub fn is_hdr(info: &gst_video::VideoInfo) -> bool {
+    let transfer = info.colorimetry().transfer();
+    matches!(
+        transfer,
+        gst_video::VideoTransferFunction::Smpte2084 | gst_video::VideoTransferFunction::AribStdB67
+    ) || info.format_info().depth().first().is_some_and(|depth| *depth > 8)
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::CaptureManager;
+use crate::events::CaptureEvent;
+
+const INTERVAL: Duration = Duration::from_secs(1);
+const PIPELINE_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Last time each stream (by chunk kind) delivered a sample.
+#[derive(Default)]
+pub struct SampleClock {
+    last: Mutex<BTreeMap<&'static str, Instant>>,
+}
+
+impl SampleClock {
+    pub fn touch(&self, stream: &'static str) {
+        if let Ok(mut last) = self.last.lock() {
+            last.insert(stream, Instant::now());
+        }
+    }
+
+    pub fn reset(&self) {
+        if let Ok(mut last) = self.last.lock() {
+            last.clear();
+        }
+    }
+
+    pub fn ages_ms(&self) -> BTreeMap<String, u64> {
+        self.last
+            .lock()
+            .map(|last| {
+                last.iter()
+                    .map(|(stream, at)| (stream.to_string(), at.elapsed().as_millis() as u64))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("heartbeat".into())
+        .spawn(move || loop {
+            std::thread::sleep(INTERVAL);
+            let manager = app.state::<CaptureManager>();
+            let state = manager.status();
+            let Some(elapsed) = manager.session_elapsed() else {
+                continue;
+            };
+            manager.events().emit(CaptureEvent::Heartbeat {
+                state,
+                elapsed_ms: elapsed.as_millis() as u64,
+                last_sample_age_ms: manager.sample_clock().ages_ms(),
+            });
+        })?;
+    Ok(())
+}
+
+/// Polls the capture pipelines for runtime errors, so a failure mid-session
+/// ends in `Failed` with `last_error` set instead of going unnoticed.
+pub fn spawn_pipeline_watch(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("pipeline_watch".into())
+        .spawn(move || loop {
+            std::thread::sleep(PIPELINE_CHECK_INTERVAL);
+            let manager = app.state::<CaptureManager>();
+            manager.check_pipelines();
+            manager.expire_parked_screen_cast();
+        })?;
+    Ok(())
+}
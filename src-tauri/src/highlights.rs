@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+use crate::proxy;
+use crate::session::{SessionManifest, TrackMedia};
+
+const HIGHLIGHTS_FILE: &str = "highlights.mkv";
+/// Every Nth byte of a frame is compared with the frame before it.
+const FRAME_SAMPLE_STRIDE: usize = 4096;
+/// Share of sampled bytes that must differ for a frame to count as changed.
+const FRAME_CHANGE_RATIO: f64 = 0.01;
+/// Keystrokes, clicks and scrolls per second that count as full activity.
+const FULL_INPUT_RATE: f64 = 2.0;
+/// Audio analysis window for voice activity.
+const VAD_FRAME_MS: u64 = 30;
+/// Windows louder than this (about -40 dBFS) count as speech.
+const SPEECH_RMS: f32 = 0.01;
+/// How much before and after a marker is kept.
+const MARKER_LEAD_MS: u64 = 5_000;
+const MARKER_TAIL_MS: u64 = 10_000;
+/// Skips shorter than this are kept in, since a jump that small is only
+/// jarring.
+const MIN_SKIP_MS: u64 = 3_000;
+
+/// How busy one chunk was: the share of changed frames or input for video,
+/// the share of speech for audio. Idle or locked video chunks score 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkActivity {
+    pub stream: String,
+    pub chunk_id: u64,
+    pub start_unix_ms: u64,
+    pub duration_ms: u64,
+    pub score: f32,
+}
+
+/// Activity of the running session's chunks, in the order they were consumed.
+#[derive(Default)]
+pub struct ActivityLog {
+    chunks: Mutex<Vec<ChunkActivity>>,
+}
+
+impl ActivityLog {
+    pub fn reset(&self) {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn push(&self, activity: ChunkActivity) {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).push(activity);
+    }
+
+    pub fn snapshot(&self) -> Vec<ChunkActivity> {
+        self.chunks.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Scores a raw chunk; `None` for proxies and audio in an unknown format.
+pub fn score_chunk(chunk: &CapturedChunk) -> Option<ChunkActivity> {
+    let score = match chunk.kind.as_str() {
+        "video" => video_score(chunk),
+        proxy::PROXY_STREAM => return None,
+        _ => speech_share(chunk)?,
+    };
+    Some(ChunkActivity {
+        stream: chunk.kind.clone(),
+        chunk_id: chunk.id,
+        start_unix_ms: (chunk.start_ts_unix_nanos / 1_000_000) as u64,
+        duration_ms: chunk.duration_ms,
+        score,
+    })
+}
+
+fn video_score(chunk: &CapturedChunk) -> f32 {
+    let meta = &chunk.metadata;
+    if meta["idle"].as_bool() == Some(true) || meta["screen_locked"].as_bool() == Some(true) {
+        return 0.0;
+    }
+    let frame_size = meta["frame_size"].as_u64().unwrap_or_default() as usize;
+    let changes = if frame_size == 0 {
+        0.0
+    } else {
+        let samples: Vec<Vec<u8>> = chunk
+            .data
+            .chunks_exact(frame_size)
+            .map(|frame| frame.iter().step_by(FRAME_SAMPLE_STRIDE).copied().collect())
+            .collect();
+        let changed = samples
+            .windows(2)
+            .filter(|pair| {
+                let differing = pair[0].iter().zip(&pair[1]).filter(|(a, b)| a != b).count();
+                differing as f64 > pair[0].len() as f64 * FRAME_CHANGE_RATIO
+            })
+            .count();
+        changed as f64 / samples.len().saturating_sub(1).max(1) as f64
+    };
+    let input_events: u64 = meta["input"]
+        .as_object()
+        .map(|counts| counts.values().filter_map(|count| count.as_u64()).sum())
+        .unwrap_or_default();
+    let input = input_events as f64 * 1000.0 / chunk.duration_ms.max(1) as f64 / FULL_INPUT_RATE;
+    changes.max(input).min(1.0) as f32
+}
+
+/// Share of the chunk's audio windows loud enough to be speech.
+fn speech_share(chunk: &CapturedChunk) -> Option<f32> {
+    let meta = &chunk.metadata;
+    let rate = meta["rate"].as_u64().filter(|rate| *rate > 0)?;
+    let channels = meta["channels"].as_u64().unwrap_or(1).max(1) as usize;
+    let samples: Vec<f32> = match meta["format"].as_str() {
+        Some("F32LE") => chunk
+            .data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect(),
+        Some("S16LE") => chunk
+            .data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+            .collect(),
+        _ => return None,
+    };
+    let window = (rate * VAD_FRAME_MS / 1000) as usize * channels;
+    if window == 0 {
+        return None;
+    }
+    let windows = samples.chunks_exact(window);
+    let total = windows.len();
+    let speech = windows
+        .filter(|window| {
+            let power = window.iter().map(|sample| sample * sample).sum::<f32>() / window.len() as f32;
+            power.sqrt() >= SPEECH_RMS
+        })
+        .count();
+    Some(speech as f32 / total.max(1) as f32)
+}
+
+/// The stretches of the session worth keeping, as `(start_ms, end_ms)`
+/// offsets into the recording: chunks scoring at least `min_score`, widened
+/// by `padding_ms`, and the time around markers.
+fn plan(manifest: &SessionManifest, min_score: f32, padding_ms: u64) -> Vec<(u64, u64)> {
+    let started_ms = manifest.started_unix_ms as u64;
+    let busy = manifest
+        .activity
+        .iter()
+        .filter(|chunk| chunk.score >= min_score)
+        .map(|chunk| {
+            let start = chunk.start_unix_ms.saturating_sub(started_ms);
+            (start.saturating_sub(padding_ms), start + chunk.duration_ms + padding_ms)
+        });
+    let marked = manifest
+        .markers
+        .iter()
+        .map(|marker| (marker.offset_ms.saturating_sub(MARKER_LEAD_MS), marker.offset_ms + MARKER_TAIL_MS));
+    let mut ranges: Vec<(u64, u64)> = busy.chain(marked).collect();
+    ranges.sort();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges {
+        let end = end.min(manifest.duration_ms);
+        if start >= end {
+            continue;
+        }
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end + MIN_SKIP_MS => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Where a highlights job writes when it is given no output.
+pub fn default_output(session_dir: &Path) -> PathBuf {
+    session_dir.join(HIGHLIGHTS_FILE)
+}
+
+/// Re-encodes the kept stretches of the session's recording back to back
+/// into `output`. Decoded buffers outside them are dropped and the rest are
+/// shifted down, so the output plays without gaps.
+pub fn build_pipeline(session_dir: &Path, min_score: f32, padding_ms: u64, output: &Path) -> Result<gst::Pipeline> {
+    let manifest = SessionManifest::read(session_dir)
+        .map_err(|err| anyhow!("{} has no readable manifest: {err}", session_dir.display()))?;
+    let input = match manifest.recording_parts.as_slice() {
+        [] => return Err(anyhow!("session '{}' has no MKV recording", manifest.session_id)),
+        [single] => session_dir.join(single),
+        _ => return Err(anyhow!("highlights need a recording in one file; this session was split by max_file_size_mb")),
+    };
+    if manifest.activity.is_empty() && manifest.markers.is_empty() {
+        return Err(anyhow!("session '{}' has no activity scores or markers", manifest.session_id));
+    }
+    let ranges = plan(&manifest, min_score, padding_ms);
+    if ranges.is_empty() {
+        return Err(anyhow!("nothing in session '{}' scores above {min_score}", manifest.session_id));
+    }
+    let kept_ms: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+    println!(
+        "[highlights] keeping {} stretches, {kept_ms}ms of {}ms",
+        ranges.len(),
+        manifest.duration_ms
+    );
+
+    let audio_tracks = manifest
+        .tracks
+        .iter()
+        .filter(|track| track.media == TrackMedia::Audio)
+        .count();
+    let mut description = "filesrc name=input ! matroskademux name=demux \
+         matroskamux name=mux ! filesink name=output \
+         demux.video_0 ! queue ! decodebin ! videoconvert ! identity name=cut_video \
+         ! x264enc speed-preset=veryfast ! h264parse ! queue ! mux. "
+        .to_string();
+    for index in 0..audio_tracks {
+        description.push_str(&format!(
+            "demux.audio_{index} ! queue ! decodebin ! audioconvert ! audioresample \
+             ! identity name=cut_audio_{index} ! opusenc ! queue ! mux. "
+        ));
+    }
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("highlights description did not produce a pipeline"))?;
+    let element = |name: &str| {
+        pipeline
+            .by_name(name)
+            .ok_or_else(|| anyhow!("highlights pipeline has no '{name}' element"))
+    };
+    element("input")?.set_property("location", input.to_string_lossy().to_string());
+    element("output")?.set_property("location", output.to_string_lossy().to_string());
+
+    // each kept stretch with how much was cut before it
+    let mut cut_ms = 0;
+    let mut previous_end = 0;
+    let shifts: Arc<Vec<(u64, u64, u64)>> = Arc::new(
+        ranges
+            .iter()
+            .map(|(start, end)| {
+                cut_ms += start - previous_end;
+                previous_end = *end;
+                (*start, *end, cut_ms)
+            })
+            .collect(),
+    );
+    let cut_names =
+        std::iter::once("cut_video".to_string()).chain((0..audio_tracks).map(|index| format!("cut_audio_{index}")));
+    for name in cut_names {
+        let pad = element(&name)?
+            .static_pad("src")
+            .ok_or_else(|| anyhow!("'{name}' has no src pad"))?;
+        let shifts = Arc::clone(&shifts);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(buffer) = info.buffer_mut() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(pts) = buffer.pts() else {
+                return gst::PadProbeReturn::Drop;
+            };
+            let at_ms = pts.mseconds();
+            let Some((_, _, cut_ms)) = shifts.iter().find(|(start, end, _)| (*start..*end).contains(&at_ms)) else {
+                return gst::PadProbeReturn::Drop;
+            };
+            buffer.make_mut().set_pts(pts.saturating_sub(gst::ClockTime::from_mseconds(*cut_ms)));
+            gst::PadProbeReturn::Ok
+        });
+    }
+    Ok(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn activity(start_unix_ms: u64, score: f32) -> serde_json::Value {
+        json!({
+            "stream": "video",
+            "chunk_id": 0,
+            "start_unix_ms": start_unix_ms,
+            "duration_ms": 5_000,
+            "score": score,
+        })
+    }
+
+    fn marker(offset_ms: u64) -> serde_json::Value {
+        json!({ "id": 0, "label": null, "unix_ms": 1_000_000 + offset_ms, "offset_ms": offset_ms })
+    }
+
+    fn manifest(activity: Vec<serde_json::Value>, markers: Vec<serde_json::Value>) -> SessionManifest {
+        serde_json::from_value(json!({
+            "session_id": "s",
+            "started_unix_ms": 1_000_000,
+            "duration_ms": 120_000,
+            "activity": activity,
+            "markers": markers,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keeps_busy_chunks_with_padding_and_merges_short_skips() {
+        let manifest = manifest(
+            vec![activity(1_010_000, 0.8), activity(1_018_000, 0.9), activity(1_040_000, 0.1)],
+            Vec::new(),
+        );
+        assert_eq!(plan(&manifest, 0.5, 1_000), vec![(9_000, 24_000)]);
+        assert_eq!(plan(&manifest, 0.95, 1_000), Vec::new());
+    }
+
+    #[test]
+    fn keeps_the_time_around_markers_within_the_recording() {
+        let manifest = manifest(vec![activity(1_000_000, 1.0)], vec![marker(60_000), marker(118_000)]);
+        assert_eq!(
+            plan(&manifest, 0.5, 2_000),
+            vec![(0, 7_000), (55_000, 70_000), (113_000, 120_000)]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::browser::BrowserTab;
+use crate::capture_manager::CaptureManager;
+use crate::StartCapturePayload;
+
+const DEFAULT_PORT: u16 = 47_800;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Request line and headers together; a client that never sends the blank
+/// line can't make a connection buffer without bound.
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 64;
+/// Connections served at once; further ones are turned away with a 503.
+const MAX_CONNECTIONS: usize = 16;
+
+pub struct HttpApiConfig {
+    pub port: u16,
+    pub token: String,
+}
+
+impl HttpApiConfig {
+    /// The server stays disabled unless `SC_HTTP_API_TOKEN` is set.
+    pub fn from_env() -> Option<Self> {
+        let token = std::env::var("SC_HTTP_API_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())?;
+        let port = std::env::var("SC_HTTP_API_PORT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        Some(Self { port, token })
+    }
+}
+
+/// Accepts connections on one thread and serves each on its own, so a slow
+/// or stalled client can't hold up the others.
+pub fn spawn(app: AppHandle, config: HttpApiConfig) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", config.port))?;
+    println!("[http_api] listening on 127.0.0.1:{}", config.port);
+    let config = Arc::new(config);
+    let active = Arc::new(AtomicUsize::new(0));
+    std::thread::Builder::new()
+        .name("http_api".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                if active.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                    active.fetch_sub(1, Ordering::AcqRel);
+                    let _ = write_response(&mut stream, 503, &json!({ "error": "too many connections" }));
+                    continue;
+                }
+                let (app, config, served) = (app.clone(), Arc::clone(&config), Arc::clone(&active));
+                let spawned = std::thread::Builder::new()
+                    .name("http_api_conn".into())
+                    .spawn(move || {
+                        if let Err(err) = handle_connection(&app, &config, stream) {
+                            eprintln!("[http_api] request failed: {err}");
+                        }
+                        served.fetch_sub(1, Ordering::AcqRel);
+                    });
+                if let Err(err) = spawned {
+                    active.fetch_sub(1, Ordering::AcqRel);
+                    eprintln!("[http_api] failed to spawn a connection thread: {err}");
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Whether `provided` is `Bearer <token>`, comparing every byte so the time
+/// taken doesn't reveal how much of the token was right.
+pub(crate) fn bearer_matches(provided: Option<&str>, token: &str) -> bool {
+    let expected = format!("Bearer {token}");
+    let Some(provided) = provided else {
+        return false;
+    };
+    if provided.len() != expected.len() {
+        return false;
+    }
+    let diff = provided
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+    std::hint::black_box(diff) == 0
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MarkerPayload {
+    #[serde(default)]
+    label: Option<String>,
+}
+
+fn handle_connection(app: &AppHandle, config: &HttpApiConfig, mut stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let request = read_request(&mut stream)?;
+
+    if !bearer_matches(request.authorization.as_deref(), &config.token) {
+        return write_response(&mut stream, 401, &json!({ "error": "unauthorized" }));
+    }
+
+    let (status, body) = route(app, &request);
+    write_response(&mut stream, status, &body)
+}
+
+fn route(app: &AppHandle, request: &Request) -> (u16, Value) {
+    let manager = app.state::<CaptureManager>();
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => Ok(json!({
+            "state": manager.status(),
+            "last_error": manager.last_error(),
+            "memory": manager.memory_report(),
+        })),
+        ("POST", "/start") => parse_body::<StartCapturePayload>(&request.body)
+            .and_then(|payload| manager.start_capture(payload.into()))
+            .map(|_| json!({ "state": manager.status() })),
+        ("POST", "/stop") => manager
+            .stop_capture()
+            .map(|summary| json!({ "state": manager.status(), "summary": summary })),
+        ("POST", "/marker") => parse_body::<MarkerPayload>(&request.body)
+            .and_then(|payload| manager.add_marker(payload.label))
+            .map(|marker| json!({ "marker": marker })),
+        ("POST", "/flush") => manager.flush_chunks_now().map(|_| json!({ "flushed": true })),
+        // pushed by the companion browser extension on every tab switch
+        ("POST", "/browser-tab") => parse_body::<BrowserTab>(&request.body)
+            .and_then(|tab| manager.record_browser_tab(tab))
+            .map(|_| json!({ "recorded": true })),
+        _ => return (404, json!({ "error": "not found" })),
+    };
+
+    match result {
+        Ok(body) => (200, body),
+        Err(err) => (409, json!({ "error": err.to_string() })),
+    }
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &[u8]) -> Result<T> {
+    let body = if body.iter().all(u8::is_ascii_whitespace) {
+        b"{}".as_slice()
+    } else {
+        body
+    };
+    serde_json::from_slice(body).map_err(|err| anyhow!("invalid request body: {err}"))
+}
+
+fn read_request(stream: impl Read) -> Result<Request> {
+    let mut reader = BufReader::new(stream.take(MAX_HEADER_BYTES));
+    let request_line = read_header_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| anyhow!("empty request"))?.to_string();
+    let path = parts.next().ok_or_else(|| anyhow!("missing request path"))?.to_string();
+
+    let mut authorization = None;
+    let mut content_length = 0usize;
+    for count in 0.. {
+        let line = read_header_line(&mut reader)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(anyhow!("too many request headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(anyhow!("request body too large ({content_length} bytes)"));
+    }
+    // the header limit no longer applies; the body is bounded on its own
+    reader.get_mut().set_limit(content_length as u64);
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+/// One line of the request head, or an error once the head outgrows
+/// `MAX_HEADER_BYTES`; an empty string when the client closed early.
+fn read_header_line<R: Read>(reader: &mut BufReader<std::io::Take<R>>) -> Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.ends_with('\n') && reader.get_ref().limit() == 0 {
+        return Err(anyhow!("request headers too large"));
+    }
+    Ok(line)
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Conflict",
+    };
+    let payload = serde_json::to_vec(body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        payload.len()
+    )?;
+    stream.write_all(&payload)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_headers_and_body() {
+        let raw = b"POST /markers HTTP/1.1\r\nAuthorization: Bearer t\r\nContent-Length: 2\r\n\r\n{}";
+        let request = read_request(&raw[..]).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/markers");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer t"));
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[test]
+    fn rejects_an_endless_header() {
+        let mut raw = b"GET /status HTTP/1.1\r\nX-Padding: ".to_vec();
+        raw.resize(MAX_HEADER_BYTES as usize * 2, b'a');
+        assert!(read_request(&raw[..]).is_err());
+    }
+
+    #[test]
+    fn rejects_too_many_headers() {
+        let mut raw = b"GET /status HTTP/1.1\r\n".to_vec();
+        for _ in 0..=MAX_HEADERS {
+            raw.extend_from_slice(b"X-A: b\r\n");
+        }
+        raw.extend_from_slice(b"\r\n");
+        assert!(read_request(&raw[..]).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+#[cfg(target_os = "linux")]
+use crate::bus::{self, Bus};
+use crate::capture_manager::{CaptureManager, CaptureState, PauseReason};
+use crate::events::CaptureEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Every Nth byte of a frame is sampled when comparing consecutive frames.
+const FRAME_SAMPLE_STRIDE: usize = 4096;
+/// Fraction of sampled bytes that must differ for a frame to count as a change.
+const SIGNIFICANT_CHANGE_RATIO: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleAction {
+    #[default]
+    Pause,
+    Tag,
+}
+
+/// Shared between the video appsink callback (screen changes) and the idle
+/// monitor thread (input idle time), and read by chunk buffers for tagging.
+#[derive(Default)]
+pub struct ActivityTracker {
+    last_screen_change_ms: AtomicU64,
+    previous_frame_samples: Mutex<Vec<u8>>,
+    idle: AtomicBool,
+}
+
+impl ActivityTracker {
+    pub fn reset(&self) {
+        self.last_screen_change_ms.store(unix_now_ms(), Ordering::Relaxed);
+        self.previous_frame_samples
+            .lock()
+            .map(|mut samples| samples.clear())
+            .ok();
+        self.idle.store(false, Ordering::Relaxed);
+    }
+
+    pub fn observe_frame(&self, frame: &[u8]) {
+        let samples: Vec<u8> = frame.iter().step_by(FRAME_SAMPLE_STRIDE).copied().collect();
+        let Ok(mut previous) = self.previous_frame_samples.lock() else {
+            return;
+        };
+        let changed = previous.len() != samples.len() || {
+            let differing = previous.iter().zip(&samples).filter(|(a, b)| a != b).count();
+            differing as f64 > samples.len() as f64 * SIGNIFICANT_CHANGE_RATIO
+        };
+        if changed {
+            self.last_screen_change_ms.store(unix_now_ms(), Ordering::Relaxed);
+            *previous = samples;
+        }
+    }
+
+    pub fn screen_idle_for(&self) -> Duration {
+        let last = self.last_screen_change_ms.load(Ordering::Relaxed);
+        Duration::from_millis(unix_now_ms().saturating_sub(last))
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    /// Returns true when the flag actually flipped.
+    fn set_idle(&self, idle: bool) -> bool {
+        self.idle.swap(idle, Ordering::Relaxed) != idle
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Input idle time from the compositor, when it exposes one (GNOME/Mutter).
+#[cfg(target_os = "linux")]
+fn input_idle_for() -> Option<Duration> {
+    let reply = bus::call(
+        Bus::Session,
+        "org.gnome.Mutter.IdleMonitor",
+        "/org/gnome/Mutter/IdleMonitor/Core",
+        "org.gnome.Mutter.IdleMonitor",
+        "GetIdletime",
+        &(),
+    )?;
+    let idle_ms: u64 = reply.body().deserialize().ok()?;
+    Some(Duration::from_millis(idle_ms))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn input_idle_for() -> Option<Duration> {
+    None
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("idle_monitor".into())
+        .spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            poll(&app);
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle) {
+    let manager = app.state::<CaptureManager>();
+    let options = manager.options();
+    let Some(timeout) = options.idle_timeout_secs.map(Duration::from_secs) else {
+        return;
+    };
+    let activity = manager.activity();
+    let state = manager.status();
+    if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+        return;
+    }
+
+    let input_idle = input_idle_for();
+    let input_active = input_idle.is_some_and(|idle| idle < timeout);
+    let now_idle = !input_active && activity.screen_idle_for() >= timeout;
+    // without an input signal a paused pipeline could never notice activity again
+    let action = if input_idle.is_some() {
+        options.idle_action
+    } else {
+        IdleAction::Tag
+    };
+
+    if now_idle && state == CaptureState::Running {
+        if !activity.set_idle(true) {
+            return;
+        }
+        println!("[idle] no activity for {}s, action={action:?}", timeout.as_secs());
+        if action == IdleAction::Pause {
+            if let Err(err) = manager.pause_for(PauseReason::Idle) {
+                eprintln!("[idle] failed to pause: {err}");
+            }
+        }
+        manager.events().emit(CaptureEvent::IdleChanged { idle: true });
+    } else if !now_idle && activity.set_idle(false) {
+        println!("[idle] activity resumed");
+        if let Err(err) = manager.resume_for(PauseReason::Idle) {
+            eprintln!("[idle] failed to resume: {err}");
+        }
+        manager.events().emit(CaptureEvent::IdleChanged { idle: false });
+    }
+}
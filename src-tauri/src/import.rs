@@ -0,0 +1,171 @@
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use gstreamer_pbutils::prelude::*;
+use serde::Serialize;
+
+use crate::capture_manager::{ensure_gstreamer_initialized, Marker};
+use crate::search::Thumbnails;
+use crate::session::{SessionManifest, SessionSummary, TrackInfo, TrackMedia};
+
+const DISCOVER_TIMEOUT_SECS: u64 = 30;
+/// Thumbnails queued up front, spread evenly over the recording.
+const THUMBNAILS: u64 = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedFile {
+    pub session_id: String,
+    pub session_dir: String,
+    pub duration_ms: u64,
+    pub tracks: Vec<TrackInfo>,
+    /// Offsets of the thumbnails being rendered; each is announced with
+    /// `thumbnail_ready` once written.
+    pub thumbnail_offsets_ms: Vec<u64>,
+}
+
+/// Registers an existing MP4/MKV as a session under `root`: the file is
+/// copied into a new session directory, its tracks probed with the
+/// discoverer, and thumbnails queued, so it shows up in the library and in
+/// search like a recording of our own.
+pub fn import_file(root: &Path, path: &Path, thumbnails: &Thumbnails) -> Result<ImportedFile> {
+    ensure_gstreamer_initialized()?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .filter(|extension| matches!(extension.as_str(), "mp4" | "mkv" | "mov" | "webm"))
+        .ok_or_else(|| anyhow!("{} is not an MP4, MOV, MKV or WebM file", path.display()))?;
+    let path = path.canonicalize()?;
+
+    let uri = gst::glib::filename_to_uri(&path, None)?;
+    let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(DISCOVER_TIMEOUT_SECS))?;
+    let info = discoverer
+        .discover_uri(&uri)
+        .map_err(|err| anyhow!("could not probe {}: {err}", path.display()))?;
+    let duration_ms = info.duration().map_or(0, |duration| duration.mseconds());
+    let mut tracks = Vec::new();
+    for (media, stream) in info
+        .video_streams()
+        .into_iter()
+        .map(|stream| (TrackMedia::Video, stream.upcast::<gst_pbutils::DiscovererStreamInfo>()))
+        .chain(
+            info.audio_streams()
+                .into_iter()
+                .map(|stream| (TrackMedia::Audio, stream.upcast())),
+        )
+    {
+        let index = tracks.len() as u32;
+        let same_media = tracks.iter().filter(|track: &&TrackInfo| track.media == media).count();
+        let stream_name = match (media, same_media) {
+            (TrackMedia::Video, 0) => "video".to_string(),
+            (TrackMedia::Video, n) => format!("video_{n}"),
+            (TrackMedia::Audio, n) => format!("audio_{n}"),
+        };
+        tracks.push(TrackInfo {
+            index,
+            stream: stream_name,
+            media,
+            codec: stream.caps().map_or_else(|| "unknown".to_string(), |caps| codec_name(&caps)),
+            color: None,
+        });
+    }
+    if tracks.is_empty() {
+        return Err(anyhow!("{} has no audio or video tracks", path.display()));
+    }
+
+    // the file was last written when the recording ended
+    let ended_unix_ms = std::fs::metadata(&path)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_millis();
+    let started_unix_ms = ended_unix_ms.saturating_sub(duration_ms as u128);
+    let session_id = format!("import-{started_unix_ms}");
+    let session_dir = root.join(&session_id);
+    if session_dir.exists() {
+        return Err(anyhow!("{} was already imported as {session_id}", path.display()));
+    }
+    std::fs::create_dir_all(&session_dir)?;
+    let recording = format!("recording.{extension}");
+    // a copy, never a link: secure deletion overwrites the session's files
+    std::fs::copy(&path, session_dir.join(&recording))?;
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let manifest = SessionManifest {
+        session_id: session_id.clone(),
+        started_unix_ms,
+        duration_ms,
+        recording: Some(recording.clone()),
+        recording_parts: vec![recording.clone()],
+        tracks: tracks.clone(),
+        markers: vec![Marker {
+            id: 0,
+            label: Some(format!("imported from {file_name}")),
+            unix_ms: started_unix_ms,
+            offset_ms: 0,
+        }],
+        windows: Vec::new(),
+        clipboard: Vec::new(),
+        tabs: Vec::new(),
+        chunk_annotations: Vec::new(),
+        consent: None,
+        proxy_chunks: Vec::new(),
+        frame_hashes: Vec::new(),
+        activity: Vec::new(),
+        clock: None,
+        chunk_map: Vec::new(),
+        keyframes: Vec::new(),
+        moments: Vec::new(),
+        title: None,
+        tags: Vec::new(),
+        notes: Vec::new(),
+        calendar_event: None,
+        redactions: Vec::new(),
+        summary: Some(SessionSummary {
+            session_id: session_id.clone(),
+            duration_ms,
+            output_dir: Some(session_dir.to_string_lossy().to_string()),
+            files: vec![recording.clone()],
+            ..SessionSummary::default()
+        }),
+    };
+    manifest.write(&session_dir)?;
+
+    let has_video = tracks.iter().any(|track| track.media == TrackMedia::Video);
+    let thumbnail_offsets_ms: Vec<u64> = if has_video {
+        (0..THUMBNAILS).map(|n| duration_ms * n / THUMBNAILS).collect()
+    } else {
+        Vec::new()
+    };
+    for &offset_ms in &thumbnail_offsets_ms {
+        thumbnails.get(&session_dir, &session_id, &recording, offset_ms);
+    }
+    println!(
+        "[import] {} -> {session_id}: {duration_ms}ms, {} track(s)",
+        path.display(),
+        tracks.len()
+    );
+    Ok(ImportedFile {
+        session_id,
+        session_dir: session_dir.to_string_lossy().to_string(),
+        duration_ms,
+        tracks,
+        thumbnail_offsets_ms,
+    })
+}
+
+/// `video/x-h264` -> `h264`, `audio/mpeg` -> `mpeg`.
+fn codec_name(caps: &gst::Caps) -> String {
+    caps.structure(0)
+        .map(|structure| {
+            let name = structure.name().as_str();
+            let subtype = name.split_once('/').map_or(name, |(_, subtype)| subtype);
+            subtype.strip_prefix("x-").unwrap_or(subtype).to_string()
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
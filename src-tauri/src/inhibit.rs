@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, Value};
+
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+/// Portal flags: suspend (4) and idle (8), which covers the screen saver.
+const INHIBIT_SUSPEND_AND_IDLE: u32 = 4 | 8;
+const REASON: &str = "Screen recording in progress";
+
+enum Held {
+    /// The portal's request object; the inhibition lasts until it is closed.
+    Portal(OwnedObjectPath),
+    /// A cookie from the older `org.freedesktop.ScreenSaver` interface.
+    ScreenSaver(u32),
+}
+
+/// Keeps the screen saver and automatic suspend away while alive, so a
+/// session doesn't end up recording a locked screen.
+pub struct Inhibitor {
+    conn: Connection,
+    held: Held,
+}
+
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        let released = match &self.held {
+            Held::Portal(handle) => {
+                Proxy::new(&self.conn, PORTAL_DEST, handle.clone(), "org.freedesktop.portal.Request")
+                    .and_then(|request| request.call_method("Close", &()))
+            }
+            Held::ScreenSaver(cookie) => screen_saver(&self.conn)
+                .and_then(|proxy| proxy.call_method("UnInhibit", &(cookie,))),
+        };
+        if let Err(err) = released {
+            eprintln!("[inhibit] failed to release inhibitor: {err}");
+        }
+    }
+}
+
+/// Tries the Inhibit portal, then the ScreenSaver interface for desktops
+/// without one.
+pub fn acquire() -> Result<Inhibitor> {
+    let conn = Connection::session()?;
+    let options: HashMap<&str, Value> = HashMap::from([("reason", Value::from(REASON))]);
+    let portal: zbus::Result<OwnedObjectPath> = Proxy::new(&conn, PORTAL_DEST, PORTAL_PATH, "org.freedesktop.portal.Inhibit")
+        .and_then(|proxy| proxy.call("Inhibit", &("", INHIBIT_SUSPEND_AND_IDLE, options)));
+    let held = match portal {
+        Ok(handle) => Held::Portal(handle),
+        Err(err) => {
+            eprintln!("[inhibit] Inhibit portal unavailable, trying ScreenSaver: {err}");
+            let cookie: u32 = screen_saver(&conn)?.call("Inhibit", &("screen-capture", REASON))?;
+            Held::ScreenSaver(cookie)
+        }
+    };
+    println!("[inhibit] screen saver and suspend inhibited");
+    Ok(Inhibitor { conn, held })
+}
+
+fn screen_saver(conn: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        conn,
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+    )
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// Input event counts; key contents are never recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct InputCounts {
+    pub keystrokes: u64,
+    pub clicks: u64,
+    pub scrolls: u64,
+}
+
+impl InputCounts {
+    pub fn since(self, earlier: InputCounts) -> InputCounts {
+        InputCounts {
+            keystrokes: self.keystrokes.saturating_sub(earlier.keystrokes),
+            clicks: self.clicks.saturating_sub(earlier.clicks),
+            scrolls: self.scrolls.saturating_sub(earlier.scrolls),
+        }
+    }
+}
+
+/// Running totals of keyboard and pointer activity, fed by one reader thread
+/// per input device. Readers are only started once a session asks for input
+/// tracking and only count while it is enabled; chunk buffers diff the totals
+/// at their chunk boundaries.
+#[derive(Default)]
+pub struct InputActivity {
+    enabled: AtomicBool,
+    readers_started: AtomicBool,
+    keystrokes: AtomicU64,
+    clicks: AtomicU64,
+    scrolls: AtomicU64,
+}
+
+impl InputActivity {
+    pub fn set_enabled(self: &Arc<Self>, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if enabled && !self.readers_started.swap(true, Ordering::Relaxed) {
+            start_readers(self);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn totals(&self) -> InputCounts {
+        InputCounts {
+            keystrokes: self.keystrokes.load(Ordering::Relaxed),
+            clicks: self.clicks.load(Ordering::Relaxed),
+            scrolls: self.scrolls.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn count(&self, counter: &AtomicU64) {
+        if self.is_enabled() {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn start_readers(activity: &Arc<InputActivity>) {
+    let devices = match std::fs::read_dir("/dev/input") {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("event"))
+            })
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            eprintln!("[input] cannot list input devices: {err}");
+            return;
+        }
+    };
+    let mut opened = 0;
+    for path in devices {
+        let file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            // devices the user can't read are skipped; see the README
+            Err(_) => continue,
+        };
+        let activity = Arc::clone(activity);
+        let spawned = std::thread::Builder::new()
+            .name("input_reader".into())
+            .spawn(move || evdev::read_events(file, &activity));
+        match spawned {
+            Ok(_) => opened += 1,
+            Err(err) => eprintln!("[input] failed to watch {}: {err}", path.display()),
+        }
+    }
+    if opened == 0 {
+        eprintln!("[input] no readable input devices; add the user to the `input` group");
+    } else {
+        println!("[input] counting activity on {opened} input devices");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn start_readers(_activity: &Arc<InputActivity>) {
+    eprintln!("[input] input activity tracking is only supported on Linux");
+}
+
+#[cfg(target_os = "linux")]
+mod evdev {
+    use std::fs::File;
+    use std::io::Read;
+
+    use super::InputActivity;
+
+    /// `struct input_event`: a timeval, whose size depends on the target,
+    /// then type, code and value.
+    const EVENT_SIZE: usize = std::mem::size_of::<libc::input_event>();
+    const TIME_SIZE: usize = EVENT_SIZE - 8;
+    const EV_KEY: u16 = 0x01;
+    const EV_REL: u16 = 0x02;
+    const KEY_MAX_KEYBOARD: u16 = 0xff;
+    const BTN_MOUSE: std::ops::RangeInclusive<u16> = 0x110..=0x117;
+    const REL_HWHEEL: u16 = 0x06;
+    const REL_WHEEL: u16 = 0x08;
+    const KEY_PRESS: i32 = 1;
+
+    pub(super) fn read_events(mut file: File, activity: &InputActivity) {
+        let mut event = [0u8; EVENT_SIZE];
+        while file.read_exact(&mut event).is_ok() {
+            let fields = &event[TIME_SIZE..];
+            let kind = u16::from_ne_bytes([fields[0], fields[1]]);
+            let code = u16::from_ne_bytes([fields[2], fields[3]]);
+            let value = i32::from_ne_bytes([fields[4], fields[5], fields[6], fields[7]]);
+            match kind {
+                // only presses count; repeats and releases don't
+                EV_KEY if value == KEY_PRESS && code <= KEY_MAX_KEYBOARD => activity.count(&activity.keystrokes),
+                EV_KEY if value == KEY_PRESS && BTN_MOUSE.contains(&code) => activity.count(&activity.clicks),
+                EV_REL if code == REL_WHEEL || code == REL_HWHEEL => activity.count(&activity.scrolls),
+                _ => {}
+            }
+        }
+        // device unplugged; a replugged one is picked up on the next app start
+    }
+}
@@ -0,0 +1,378 @@
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::{ensure_gstreamer_initialized, CaptureOptions};
+use crate::daily_summary;
+use crate::events::{CaptureEvent, EventBus};
+use crate::highlights;
+use crate::session;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    TranscodeH265 {
+        input: String,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    Gif {
+        input: String,
+        #[serde(default)]
+        output: Option<String>,
+        #[serde(default = "JobSpec::default_gif_fps")]
+        fps: u32,
+        #[serde(default = "JobSpec::default_gif_width")]
+        width: u32,
+    },
+    /// Renders an SRT/ASS file into the picture; the output carries video only.
+    BurnSubtitles {
+        input: String,
+        subtitles: String,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    /// Aggregates the sessions of a UTC `date` (`YYYY-MM-DD`) into JSON and
+    /// Markdown under `<output_dir>/summaries/`.
+    DailySummary {
+        date: String,
+        #[serde(default)]
+        output_dir: Option<String>,
+    },
+    /// Cuts a recorded session down to its busy stretches and markers; the
+    /// output defaults to `highlights.mkv` in the session directory.
+    Highlights {
+        session_id: String,
+        #[serde(default)]
+        output_dir: Option<String>,
+        #[serde(default)]
+        output: Option<String>,
+        /// Chunks scoring below this are skipped, from 0 (keep anything
+        /// scored) to 1.
+        #[serde(default = "JobSpec::default_min_score")]
+        min_score: f32,
+        /// Kept on either side of every busy chunk.
+        #[serde(default = "JobSpec::default_padding_ms")]
+        padding_ms: u64,
+    },
+}
+
+impl JobSpec {
+    pub const fn default_gif_fps() -> u32 {
+        10
+    }
+
+    pub const fn default_gif_width() -> u32 {
+        640
+    }
+
+    pub const fn default_min_score() -> f32 {
+        0.2
+    }
+
+    pub const fn default_padding_ms() -> u64 {
+        2_000
+    }
+
+    /// The media file a pipeline job reads; `None` for jobs without one.
+    fn input(&self) -> Option<&str> {
+        match self {
+            JobSpec::TranscodeH265 { input, .. }
+            | JobSpec::Gif { input, .. }
+            | JobSpec::BurnSubtitles { input, .. } => Some(input),
+            JobSpec::DailySummary { .. } | JobSpec::Highlights { .. } => None,
+        }
+    }
+
+    /// Refuses a job that would read or write outside the sessions root.
+    fn check_paths(&self) -> Result<()> {
+        if let JobSpec::Highlights { session_id, .. } = self {
+            session::validate_id(session_id)?;
+        }
+        if let JobSpec::BurnSubtitles { subtitles, .. } = self {
+            session::confine(Path::new(subtitles))?;
+        }
+        if let Some(input) = self.input() {
+            session::confine(Path::new(input))?;
+        }
+        session::confine(&self.output())?;
+        Ok(())
+    }
+
+    fn output(&self) -> PathBuf {
+        let (output, suffix) = match self {
+            JobSpec::TranscodeH265 { output, .. } => (output, "h265.mkv"),
+            JobSpec::Gif { output, .. } => (output, "gif"),
+            JobSpec::BurnSubtitles { output, .. } => (output, "subtitled.mkv"),
+            JobSpec::DailySummary { date, output_dir } => {
+                return daily_summary::output_path(&summary_root(output_dir), date);
+            }
+            JobSpec::Highlights {
+                session_id,
+                output_dir,
+                output,
+                ..
+            } => {
+                return output
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| highlights::default_output(&summary_root(output_dir).join(session_id)));
+            }
+        };
+        output
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(self.input().unwrap_or_default()).with_extension(suffix))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub spec: JobSpec,
+    pub state: JobState,
+    pub progress: f32,
+    pub output: String,
+    pub error: Option<String>,
+}
+
+/// Runs heavy post-steps one at a time on a background thread, so they never
+/// hold up the capture manager or a new recording.
+pub struct JobQueue {
+    jobs: Arc<Mutex<Vec<JobStatus>>>,
+    sender: mpsc::Sender<u64>,
+}
+
+impl JobQueue {
+    pub fn new(events: EventBus) -> Result<Self> {
+        let jobs: Arc<Mutex<Vec<JobStatus>>> = Arc::default();
+        let (sender, receiver) = mpsc::channel::<u64>();
+        let worker_jobs = Arc::clone(&jobs);
+        std::thread::Builder::new()
+            .name("job_worker".into())
+            .spawn(move || {
+                for id in receiver {
+                    run_job(&worker_jobs, &events, id);
+                }
+            })?;
+        Ok(Self { jobs, sender })
+    }
+
+    pub fn enqueue(&self, spec: JobSpec) -> Result<JobStatus> {
+        spec.check_paths()?;
+        let status = {
+            let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+            let status = JobStatus {
+                id: jobs.len() as u64,
+                output: spec.output().to_string_lossy().to_string(),
+                spec,
+                state: JobState::Queued,
+                progress: 0.0,
+                error: None,
+            };
+            jobs.push(status.clone());
+            status
+        };
+        self.sender
+            .send(status.id)
+            .map_err(|_| anyhow!("job worker is not running"))?;
+        Ok(status)
+    }
+
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id as usize)
+            .cloned()
+    }
+}
+
+fn update(jobs: &Mutex<Vec<JobStatus>>, events: &EventBus, id: u64, apply: impl FnOnce(&mut JobStatus)) {
+    let status = {
+        let mut jobs = jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(status) = jobs.get_mut(id as usize) else {
+            return;
+        };
+        apply(status);
+        status.clone()
+    };
+    events.emit(CaptureEvent::JobProgress {
+        job_id: status.id,
+        state: status.state,
+        progress: status.progress,
+        error: status.error,
+    });
+}
+
+fn run_job(jobs: &Mutex<Vec<JobStatus>>, events: &EventBus, id: u64) {
+    let Some(spec) = jobs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(id as usize)
+        .map(|status| status.spec.clone())
+    else {
+        return;
+    };
+    update(jobs, events, id, |status| status.state = JobState::Running);
+
+    let result = match &spec {
+        JobSpec::DailySummary { date, output_dir } => daily_summary::write(&summary_root(output_dir), date).map(|_| ()),
+        _ => run_media_job(jobs, events, id, &spec),
+    };
+
+    update(jobs, events, id, |status| match result {
+        Ok(()) => {
+            status.state = JobState::Completed;
+            status.progress = 1.0;
+        }
+        Err(err) => {
+            eprintln!("[jobs] job {id} failed: {err}");
+            status.state = JobState::Failed;
+            status.error = Some(err.to_string());
+        }
+    });
+}
+
+fn summary_root(output_dir: &Option<String>) -> PathBuf {
+    crate::session::output_root(&CaptureOptions {
+        output_dir: output_dir.clone(),
+        ..CaptureOptions::default()
+    })
+}
+
+fn run_media_job(jobs: &Mutex<Vec<JobStatus>>, events: &EventBus, id: u64, spec: &JobSpec) -> Result<()> {
+    ensure_gstreamer_initialized()
+        .and_then(|_| build_pipeline(spec))
+        .and_then(|pipeline| {
+            run_pipeline(&pipeline, |progress| {
+                update(jobs, events, id, |status| status.progress = progress);
+            })
+        })
+}
+
+fn build_pipeline(spec: &JobSpec) -> Result<gst::Pipeline> {
+    if let JobSpec::Highlights {
+        session_id,
+        output_dir,
+        min_score,
+        padding_ms,
+        ..
+    } = spec
+    {
+        session::validate_id(session_id)?;
+        let session_dir = summary_root(output_dir).join(session_id);
+        return highlights::build_pipeline(&session_dir, *min_score, *padding_ms, &spec.output());
+    }
+    let input = spec.input().ok_or_else(|| anyhow!("job has no media input"))?;
+    if !Path::new(input).exists() {
+        return Err(anyhow!("input file {input} does not exist"));
+    }
+    let description = match spec {
+        JobSpec::TranscodeH265 { .. } => {
+            "uritranscodebin name=transcode profile=video/x-matroska:video/x-h265:audio/x-opus".to_string()
+        }
+        JobSpec::Gif { fps, width, .. } => format!(
+            "filesrc name=input ! decodebin ! videoconvert ! videorate ! videoscale \
+             ! video/x-raw,framerate={fps}/1,width={width},pixel-aspect-ratio=1/1 \
+             ! gifenc ! filesink name=output"
+        ),
+        JobSpec::BurnSubtitles { .. } => "filesrc name=input ! decodebin ! videoconvert \
+             ! subtitleoverlay name=overlay ! videoconvert ! x264enc ! h264parse \
+             ! matroskamux ! filesink name=output \
+             filesrc name=subtitles ! subparse ! overlay.subtitle_sink"
+            .to_string(),
+        JobSpec::DailySummary { .. } => return Err(anyhow!("daily summaries don't run a pipeline")),
+        JobSpec::Highlights { .. } => return Err(anyhow!("highlights are planned from the session manifest")),
+    };
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("job description did not produce a pipeline"))?;
+
+    let output = spec.output();
+    let set_location = |name: &str, path: &Path| -> Result<()> {
+        pipeline
+            .by_name(name)
+            .ok_or_else(|| anyhow!("job pipeline has no '{name}' element"))?
+            .set_property("location", path.to_string_lossy().to_string());
+        Ok(())
+    };
+    match spec {
+        JobSpec::TranscodeH265 { input, .. } => {
+            let transcode = pipeline
+                .by_name("transcode")
+                .ok_or_else(|| anyhow!("job pipeline has no transcoder"))?;
+            transcode.set_property("source-uri", file_uri(Path::new(input))?);
+            transcode.set_property("dest-uri", file_uri(&output)?);
+        }
+        JobSpec::Gif { input, .. } => {
+            set_location("input", Path::new(input))?;
+            set_location("output", &output)?;
+        }
+        JobSpec::BurnSubtitles { input, subtitles, .. } => {
+            set_location("input", Path::new(input))?;
+            set_location("subtitles", Path::new(subtitles))?;
+            set_location("output", &output)?;
+        }
+        JobSpec::DailySummary { .. } | JobSpec::Highlights { .. } => {}
+    }
+    Ok(pipeline)
+}
+
+fn file_uri(path: &Path) -> Result<String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    gst::glib::filename_to_uri(&absolute, None)
+        .map(|uri| uri.to_string())
+        .map_err(|err| anyhow!("invalid path {}: {err}", absolute.display()))
+}
+
+fn run_pipeline(pipeline: &gst::Pipeline, mut on_progress: impl FnMut(f32)) -> Result<()> {
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|err| anyhow!("failed to start job pipeline: {err:?}"))?;
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("job pipeline has no bus"))?;
+
+    let result = loop {
+        let message = bus.timed_pop_filtered(
+            gst::ClockTime::from_mseconds(PROGRESS_INTERVAL.as_millis() as u64),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        match message.as_ref().map(|message| message.view()) {
+            Some(gst::MessageView::Eos(..)) => break Ok(()),
+            Some(gst::MessageView::Error(err)) => break Err(anyhow!("{}", err.error())),
+            _ => {
+                let position = pipeline.query_position::<gst::ClockTime>();
+                let duration = pipeline.query_duration::<gst::ClockTime>();
+                if let (Some(position), Some(duration)) = (position, duration) {
+                    if duration.nseconds() > 0 {
+                        on_progress((position.nseconds() as f64 / duration.nseconds() as f64).min(1.0) as f32);
+                    }
+                }
+            }
+        }
+    };
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
@@ -0,0 +1,126 @@
+/// Fewer words than this say too little to tell languages apart.
+const MIN_WORDS: usize = 3;
+/// Share of letters a non-Latin script needs to decide the language.
+const SCRIPT_SHARE: f64 = 0.3;
+
+/// Common short words per language; the language whose words make up most
+/// of the text wins.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "are", "to", "of", "that", "it", "you", "we", "this", "with", "for", "have", "not"]),
+    ("es", &["el", "la", "los", "las", "que", "de", "y", "es", "en", "por", "para", "una", "con", "no", "pero"]),
+    ("fr", &["le", "la", "les", "est", "et", "que", "des", "une", "pour", "pas", "vous", "nous", "dans", "avec", "ce"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ich", "wir", "sie", "ein", "eine", "mit", "auf", "zu", "es"]),
+    ("it", &["il", "la", "che", "di", "e", "è", "non", "per", "una", "sono", "con", "del", "questo", "lo", "gli"]),
+    ("pt", &["o", "a", "os", "que", "de", "e", "é", "não", "para", "uma", "com", "em", "você", "isso", "mas"]),
+    ("nl", &["de", "het", "een", "en", "is", "dat", "niet", "ik", "wij", "je", "van", "met", "op", "voor", "zijn"]),
+    ("sv", &["och", "är", "att", "det", "som", "en", "inte", "jag", "vi", "på", "med", "för", "har", "den", "till"]),
+    ("pl", &["i", "w", "nie", "to", "jest", "się", "na", "że", "z", "do", "tak", "jak", "ale", "co", "po"]),
+    ("tr", &["ve", "bir", "bu", "da", "de", "için", "ne", "ben", "biz", "çok", "ama", "gibi", "var", "mi", "değil"]),
+];
+
+/// ISO 639-1 code of the language `text` is most likely written in, from
+/// its script and, for Latin text, its most common words. `None` when the
+/// text is too short or nothing stands out.
+pub fn detect(text: &str) -> Option<&'static str> {
+    if let Some(code) = by_script(text) {
+        return Some(code);
+    }
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(code, stopwords)| {
+            let hits = words.iter().filter(|word| stopwords.contains(&word.as_str())).count();
+            (*code, hits)
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+    match scores.as_slice() {
+        // a tie says nothing, e.g. "de la" is Spanish and French alike
+        [(code, best), (_, second), ..] if *best > 0 && best > second => Some(*code),
+        _ => None,
+    }
+}
+
+/// Languages told apart by their alphabet alone.
+fn by_script(text: &str) -> Option<&'static str> {
+    let mut letters = 0usize;
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    let (mut kana, mut ukrainian) = (false, false);
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        let code = match c as u32 {
+            0x3040..=0x30FF => {
+                kana = true;
+                "ja"
+            }
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => "ko",
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => "zh",
+            0x0400..=0x04FF => {
+                ukrainian |= matches!(c, 'і' | 'ї' | 'є' | 'ґ' | 'І' | 'Ї' | 'Є' | 'Ґ');
+                "ru"
+            }
+            0x0600..=0x06FF => "ar",
+            0x0900..=0x097F => "hi",
+            0x0370..=0x03FF => "el",
+            0x0590..=0x05FF => "he",
+            0x0E00..=0x0E7F => "th",
+            _ => continue,
+        };
+        match counts.iter_mut().find(|(seen, _)| *seen == code) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((code, 1)),
+        }
+    }
+    // Japanese mixes kana with kanji; letters only Ukrainian has settle
+    // Cyrillic text
+    let mut merged: Vec<(&'static str, usize)> = Vec::new();
+    for (code, count) in counts {
+        let code = match code {
+            "zh" if kana => "ja",
+            "ru" if ukrainian => "uk",
+            code => code,
+        };
+        match merged.iter_mut().find(|(seen, _)| *seen == code) {
+            Some((_, total)) => *total += count,
+            None => merged.push((code, count)),
+        }
+    }
+    let (code, count) = merged.into_iter().max_by_key(|(_, count)| *count)?;
+    (count as f64 >= letters as f64 * SCRIPT_SHARE).then_some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin_text_by_stopwords() {
+        assert_eq!(detect("this is the plan and we have to ship it"), Some("en"));
+        assert_eq!(detect("el informe es para los clientes y no para el equipo"), Some("es"));
+        assert_eq!(detect("wir sind nicht fertig und das ist ein problem"), Some("de"));
+    }
+
+    #[test]
+    fn non_latin_text_by_script() {
+        assert_eq!(detect("Привет, как дела"), Some("ru"));
+        assert_eq!(detect("Привіт, як справи"), Some("uk"));
+        assert_eq!(detect("今日はいい天気ですね"), Some("ja"));
+        assert_eq!(detect("今天天气很好"), Some("zh"));
+        assert_eq!(detect("안녕하세요"), Some("ko"));
+    }
+
+    #[test]
+    fn too_short_or_ambiguous() {
+        assert_eq!(detect("ok then"), None);
+        assert_eq!(detect("de la"), None);
+        assert_eq!(detect("lorem ipsum dolor sit amet"), None);
+        assert_eq!(detect(""), None);
+    }
+}
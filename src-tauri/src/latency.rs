@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState};
+use crate::events::CaptureEvent;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Source timestamp to appsink, per sample.
+pub const PIPELINE: &str = "pipeline";
+/// Newest frame at the appsink to the chunk leaving its buffer.
+pub const ASSEMBLE: &str = "assemble";
+/// Waiting in the channel for the chunk consumer.
+pub const QUEUE: &str = "queue";
+pub const COMPRESS: &str = "compress";
+/// Offering the chunk to sinks, taps and ack buffers.
+pub const SINKS: &str = "sinks";
+/// The chunk's newest frame's source timestamp to hand-off to sinks.
+pub const END_TO_END: &str = "end_to_end";
+
+/// One stage's timings over a report window.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageTiming {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    #[serde(skip)]
+    total: Duration,
+}
+
+impl StageTiming {
+    fn add(&mut self, took: Duration) {
+        self.count += 1;
+        self.total += took;
+        self.mean_ms = self.total.as_secs_f64() * 1_000.0 / self.count as f64;
+        self.max_ms = self.max_ms.max(took.as_secs_f64() * 1_000.0);
+    }
+}
+
+/// When a buffer's newest sample reached the appsink, and when it was
+/// captured at the source.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleTimes {
+    arrived: Instant,
+    source: Instant,
+}
+
+struct InFlight {
+    source: Instant,
+    sent: Instant,
+}
+
+/// Per-stream, per-stage timings of the capture path, reported every few
+/// seconds as `latency_stats` events.
+#[derive(Default)]
+pub struct LatencyTracker {
+    window: Mutex<BTreeMap<String, BTreeMap<&'static str, StageTiming>>>,
+    in_flight: Mutex<HashMap<(String, u64), InFlight>>,
+}
+
+impl LatencyTracker {
+    pub fn record(&self, stream: &str, stage: &'static str, took: Duration) {
+        self.window
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(stream.to_string())
+            .or_default()
+            .entry(stage)
+            .or_default()
+            .add(took);
+    }
+
+    /// A sample reached `stream`'s appsink, `age` after its source timestamp.
+    pub fn arrived(&self, stream: &str, age: Option<Duration>) -> SampleTimes {
+        let arrived = Instant::now();
+        if let Some(age) = age {
+            self.record(stream, PIPELINE, age);
+        }
+        SampleTimes {
+            arrived,
+            source: age.and_then(|age| arrived.checked_sub(age)).unwrap_or(arrived),
+        }
+    }
+
+    /// A chunk left its buffer; `newest` is its newest sample.
+    pub fn sent(&self, stream: &str, chunk_id: u64, newest: SampleTimes) {
+        self.record(stream, ASSEMBLE, newest.arrived.elapsed());
+        self.in_flight.lock().unwrap_or_else(PoisonError::into_inner).insert(
+            (stream.to_string(), chunk_id),
+            InFlight {
+                source: newest.source,
+                sent: Instant::now(),
+            },
+        );
+    }
+
+    /// The consumer picked the chunk up; returns its source time.
+    pub fn received(&self, stream: &str, chunk_id: u64) -> Option<Instant> {
+        let entry = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(stream.to_string(), chunk_id))?;
+        self.record(stream, QUEUE, entry.sent.elapsed());
+        Some(entry.source)
+    }
+
+    /// Timings since the last call.
+    pub fn take(&self) -> BTreeMap<String, BTreeMap<String, StageTiming>> {
+        std::mem::take(&mut *self.window.lock().unwrap_or_else(PoisonError::into_inner))
+            .into_iter()
+            .map(|(stream, stages)| {
+                let stages = stages.into_iter().map(|(stage, timing)| (stage.to_string(), timing)).collect();
+                (stream, stages)
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.window.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        self.in_flight.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
+
+/// How far the pipeline clock has moved past the sample's source timestamp.
+pub fn pipeline_latency(appsink: &gst_app::AppSink, sample: &gst::Sample) -> Option<Duration> {
+    let now = appsink.clock()?.time()?;
+    let pts = sample.buffer()?.pts()?;
+    let running = sample.segment()?.downcast_ref::<gst::ClockTime>()?.to_running_time(pts)?;
+    let age = now.checked_sub(appsink.base_time()?)?.checked_sub(running)?;
+    Some(Duration::from_nanos(age.nseconds()))
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("latency_stats".into())
+        .spawn(move || loop {
+            std::thread::sleep(REPORT_INTERVAL);
+            let manager = app.state::<CaptureManager>();
+            let streams = manager.latency().take();
+            if manager.status() != CaptureState::Running || streams.is_empty() {
+                continue;
+            }
+            manager.events().emit(CaptureEvent::LatencyStats { streams });
+        })?;
+    Ok(())
+}
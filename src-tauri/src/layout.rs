@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::devices;
+
+/// The captured screen; always available to scenes under this id.
+pub const SCREEN_SOURCE: &str = "screen";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LayoutSource {
+    /// A V4L2 camera, e.g. `/dev/video0`.
+    Camera { path: String },
+    /// A still PNG overlay such as a logo or lower third.
+    Image { path: String },
+}
+
+/// Where one source sits in a scene; `width`/`height` of `None` keep its
+/// native size, and higher `zorder` draws on top.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneItem {
+    pub source: String,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub width: Option<i32>,
+    #[serde(default)]
+    pub height: Option<i32>,
+    #[serde(default)]
+    pub zorder: u32,
+    #[serde(default = "SceneItem::default_alpha")]
+    pub alpha: f64,
+}
+
+impl SceneItem {
+    pub const fn default_alpha() -> f64 {
+        1.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub items: Vec<SceneItem>,
+}
+
+/// Scenes composed from the screen plus extra sources; the first scene is
+/// shown at start and `set_layout` switches between them while recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutConfig {
+    #[serde(default = "LayoutConfig::default_width")]
+    pub width: i32,
+    #[serde(default = "LayoutConfig::default_height")]
+    pub height: i32,
+    #[serde(default)]
+    pub sources: BTreeMap<String, LayoutSource>,
+    pub scenes: Vec<Scene>,
+}
+
+impl LayoutConfig {
+    pub const fn default_width() -> i32 {
+        1920
+    }
+
+    pub const fn default_height() -> i32 {
+        1080
+    }
+
+    fn scene(&self, name: &str) -> Result<&Scene> {
+        self.scenes
+            .iter()
+            .find(|scene| scene.name == name)
+            .ok_or_else(|| anyhow!("unknown scene '{name}'"))
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.scenes.is_empty() {
+            return Err(anyhow!("layout needs at least one scene"));
+        }
+        for scene in &self.scenes {
+            for item in &scene.items {
+                if item.source != SCREEN_SOURCE && !self.sources.contains_key(&item.source) {
+                    return Err(anyhow!("scene '{}' uses unknown source '{}'", scene.name, item.source));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a [`crate::capture_manager::CaptureTarget::Windows`] capture arranges
+/// its windows; each gets an equal cell and is letterboxed inside it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowTiling {
+    /// Side by side, left to right in `ids` order.
+    #[default]
+    Row,
+    /// Stacked top to bottom.
+    Column,
+    /// As square a grid as the count allows, filled row by row.
+    Grid,
+}
+
+impl WindowTiling {
+    /// Position and size of window `index` of `count` on the canvas.
+    fn cell(self, index: usize, count: usize, width: i32, height: i32) -> (i32, i32, i32, i32) {
+        let (columns, rows) = match self {
+            WindowTiling::Row => (count, 1),
+            WindowTiling::Column => (1, count),
+            WindowTiling::Grid => {
+                let columns = (count as f64).sqrt().ceil() as usize;
+                (columns, count.div_ceil(columns))
+            }
+        };
+        // even sizes for chroma-subsampled consumers
+        let cell_width = (width / columns as i32) & !1;
+        let cell_height = (height / rows as i32) & !1;
+        let (column, row) = ((index % columns) as i32, (index / columns) as i32);
+        (column * cell_width, row * cell_height, cell_width, cell_height)
+    }
+}
+
+/// The compositor of a running session and the pad each source feeds.
+pub struct LiveLayout {
+    config: LayoutConfig,
+    pads: HashMap<String, gst::Pad>,
+}
+
+impl LiveLayout {
+    /// Moves every source to its place in `name`; sources the scene doesn't
+    /// use are hidden rather than removed, so switching never renegotiates.
+    pub fn set_scene(&mut self, name: &str) -> Result<()> {
+        let scene = self.config.scene(name)?;
+        for (source, pad) in &self.pads {
+            match scene.items.iter().find(|item| &item.source == source) {
+                Some(item) => {
+                    pad.set_property("xpos", item.x);
+                    pad.set_property("ypos", item.y);
+                    pad.set_property("width", item.width.unwrap_or(0));
+                    pad.set_property("height", item.height.unwrap_or(0));
+                    pad.set_property("zorder", item.zorder);
+                    pad.set_property("alpha", item.alpha);
+                }
+                None => pad.set_property("alpha", 0.0f64),
+            }
+        }
+        println!("[layout] switched to scene '{name}'");
+        Ok(())
+    }
+}
+
+fn make(factory: &str, name: String) -> Result<gst::Element> {
+    gst::ElementFactory::make(factory)
+        .name(name)
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element '{factory}' — ensure required plugins are installed"))
+}
+
+/// A source bin showing only the PipeWire window nodes `ids`, tiled on a
+/// `width`×`height` canvas; the rest of the screen is never captured.
+pub fn tiled_windows(ids: &[String], tiling: WindowTiling, width: i32, height: i32, name: &str) -> Result<gst::Element> {
+    if ids.is_empty() {
+        return Err(anyhow!("the windows target needs at least one window id"));
+    }
+    let bin = gst::Bin::with_name(name);
+    let compositor = make("compositor", format!("{name}_compositor"))?;
+    compositor.set_property_from_str("background", "black");
+    let canvas = make("capsfilter", format!("{name}_canvas"))?;
+    canvas.set_property(
+        "caps",
+        gst::Caps::builder("video/x-raw")
+            .field("width", width)
+            .field("height", height)
+            .build(),
+    );
+    bin.add_many([&compositor, &canvas])?;
+    compositor.link(&canvas)?;
+
+    for (index, id) in ids.iter().enumerate() {
+        let node_id: u32 = id
+            .parse()
+            .map_err(|_| anyhow!("window id '{id}' is not a PipeWire node id"))?;
+        let src = make("pipewiresrc", format!("{name}_{index}"))?;
+        src.set_property("do-timestamp", true);
+        src.set_property("target-node", node_id);
+        let (x, y, cell_width, cell_height) = tiling.cell(index, ids.len(), width, height);
+        let fit = make("capsfilter", format!("{name}_{index}_fit"))?;
+        fit.set_property(
+            "caps",
+            gst::Caps::builder("video/x-raw")
+                .field("width", cell_width)
+                .field("height", cell_height)
+                .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+                .build(),
+        );
+        // videoscale letterboxes to keep each window's aspect ratio
+        let chain = [
+            src,
+            make("videoconvert", format!("{name}_{index}_convert"))?,
+            make("videoscale", format!("{name}_{index}_scale"))?,
+            fit,
+        ];
+        bin.add_many(&chain)?;
+        gst::Element::link_many(&chain)?;
+        let pad = compositor
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| anyhow!("compositor refused a pad for window {id}"))?;
+        pad.set_property("xpos", x);
+        pad.set_property("ypos", y);
+        chain[3]
+            .static_pad("src")
+            .ok_or_else(|| anyhow!("window {id} has no output"))?
+            .link(&pad)
+            .map_err(|err| anyhow!("failed to link window {id}: {err:?}"))?;
+    }
+    println!("[layout] tiling {} windows ({tiling:?})", ids.len());
+
+    let canvas_src = canvas
+        .static_pad("src")
+        .ok_or_else(|| anyhow!("window canvas has no src pad"))?;
+    bin.add_pad(&gst::GhostPad::with_target(&canvas_src)?)?;
+    Ok(bin.upcast())
+}
+
+/// Wraps `screen` and the configured sources into a bin whose output is the
+/// composited canvas; it takes the place of the video source.
+pub fn build(config: &LayoutConfig, screen: gst::Element) -> Result<(gst::Element, LiveLayout)> {
+    config.validate()?;
+    let bin = gst::Bin::with_name("video_layout");
+    let compositor = make("compositor", "layout_compositor".into())?;
+    compositor.set_property_from_str("background", "black");
+    let canvas = make("capsfilter", "layout_canvas".into())?;
+    canvas.set_property(
+        "caps",
+        gst::Caps::builder("video/x-raw")
+            .field("width", config.width)
+            .field("height", config.height)
+            .build(),
+    );
+    bin.add_many([&compositor, &canvas])?;
+    compositor.link(&canvas)?;
+
+    let mut inputs: Vec<(String, Vec<gst::Element>)> = vec![(SCREEN_SOURCE.to_string(), vec![screen])];
+    for (id, source) in &config.sources {
+        let chain = match source {
+            LayoutSource::Camera { path } => vec![devices::v4l2_source(path, &format!("layout_{id}"))?],
+            LayoutSource::Image { path } => {
+                let src = make("filesrc", format!("layout_{id}"))?;
+                src.set_property("location", path);
+                let freeze = make("imagefreeze", format!("layout_{id}_freeze"))?;
+                // keep pace with the live screen instead of racing ahead
+                freeze.set_property("is-live", true);
+                vec![src, make("pngdec", format!("layout_{id}_dec"))?, freeze]
+            }
+        };
+        inputs.push((id.clone(), chain));
+    }
+
+    let mut pads = HashMap::new();
+    for (id, mut chain) in inputs {
+        chain.push(make("videoconvert", format!("layout_{id}_convert"))?);
+        bin.add_many(&chain)?;
+        gst::Element::link_many(&chain)?;
+        let pad = compositor
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| anyhow!("compositor refused a pad for '{id}'"))?;
+        chain
+            .last()
+            .and_then(|last| last.static_pad("src"))
+            .ok_or_else(|| anyhow!("layout source '{id}' has no output"))?
+            .link(&pad)
+            .map_err(|err| anyhow!("failed to link layout source '{id}': {err:?}"))?;
+        pads.insert(id, pad);
+    }
+
+    let canvas_src = canvas
+        .static_pad("src")
+        .ok_or_else(|| anyhow!("layout canvas has no src pad"))?;
+    bin.add_pad(&gst::GhostPad::with_target(&canvas_src)?)?;
+
+    let mut live = LiveLayout {
+        config: config.clone(),
+        pads,
+    };
+    live.set_scene(&config.scenes[0].name)?;
+    Ok((bin.upcast(), live))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_and_column_split_one_axis() {
+        assert_eq!(WindowTiling::Row.cell(1, 3, 1920, 1080), (640, 0, 640, 1080));
+        assert_eq!(WindowTiling::Column.cell(2, 3, 1920, 1080), (0, 720, 1920, 360));
+    }
+
+    #[test]
+    fn grid_fills_row_by_row() {
+        assert_eq!(WindowTiling::Grid.cell(0, 5, 1920, 1080), (0, 0, 640, 540));
+        assert_eq!(WindowTiling::Grid.cell(4, 5, 1920, 1080), (640, 540, 640, 540));
+        assert_eq!(WindowTiling::Grid.cell(3, 4, 1920, 1080), (960, 540, 960, 540));
+    }
+
+    #[test]
+    fn cells_have_even_sizes() {
+        assert_eq!(WindowTiling::Row.cell(2, 3, 1000, 999), (664, 0, 332, 998));
+    }
+}
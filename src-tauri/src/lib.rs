@@ -1,7 +1,104 @@
+mod acks;
+mod adaptive;
+mod anchors;
+mod annotations;
+mod app_dirs;
+mod audio_ring;
+mod bandwidth;
+mod benchmark;
+mod blackout;
+mod browser;
+#[cfg(target_os = "linux")]
+mod bus;
+mod calendar;
+mod captions;
 mod capture_manager;
+mod chunk_map;
+mod chunk_store;
+mod clipboard;
+mod compression;
+mod consent;
+mod daily_summary;
+#[cfg(target_os = "linux")]
+mod dbus_service;
+mod deletion;
+mod devices;
+mod disk_writer;
+#[cfg(feature = "diarization")]
+mod diarization;
+mod displays;
+mod escalation;
+mod events;
+mod export;
+#[cfg(target_os = "linux")]
+mod fifo;
+mod focus;
+mod frame_hash;
+mod frames;
+mod geometry;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod gst_log;
+mod hdr;
+mod heartbeat;
+mod highlights;
+mod http_api;
+mod idle;
+mod import;
+#[cfg(target_os = "linux")]
+mod inhibit;
+mod input;
+mod jobs;
+mod language;
+mod latency;
+mod layout;
+mod library;
+mod meetings;
+mod memory;
+mod moments;
+mod muxer;
+#[cfg(feature = "ndi")]
+mod ndi;
+mod notifications;
+mod overlap;
+mod overrides;
+mod permissions;
+mod platform;
+mod policy;
+#[cfg(target_os = "linux")]
+mod portal;
+mod power;
+mod preview;
+mod privacy;
+mod proxy;
+mod redaction;
+mod remux;
+mod screen_lock;
+mod search;
+mod session;
+mod shm;
+mod sinks;
+mod state_machine;
+mod stitch;
+mod templates;
+mod threading;
+mod translation;
+mod uploads;
+#[cfg(feature = "wake_word")]
+mod wake_word;
+mod webhooks;
+#[cfg(target_os = "linux")]
+mod wlr_screencopy;
 
-use capture_manager::{CaptureManager, CaptureOptions, CaptureState, CaptureTarget};
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use capture_manager::{AmbientSettings, AudioSettings, BufferingSettings, CaptureError, CaptureManager, CaptureOptions, CaptureProfile, CaptureState, CaptureTarget, CustomSources, Marker, TargetSettings};
+use idle::IdleAction;
+use jobs::{JobQueue, JobSpec, JobStatus};
+use power::BatteryPolicy;
+use threading::ThreadTuning;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -14,6 +111,22 @@ fn greet(name: &str) -> String {
 enum CaptureTargetPayload {
     FullDisplay,
     Window { id: String },
+    Windows {
+        ids: Vec<String>,
+        #[serde(default)]
+        tiling: layout::WindowTiling,
+        #[serde(default = "layout::LayoutConfig::default_width")]
+        width: i32,
+        #[serde(default = "layout::LayoutConfig::default_height")]
+        height: i32,
+    },
+    Device { path: String },
+    #[cfg(feature = "ndi")]
+    Ndi { source: String },
+    FollowFocus {
+        #[serde(default = "CaptureTarget::default_min_dwell_ms")]
+        min_dwell_ms: u64,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,11 +134,123 @@ struct StartCapturePayload {
     #[serde(default = "CaptureOptions::default_chunk_ms")]
     chunk_duration_ms: u64,
     #[serde(default)]
+    overlap_ms: u64,
+    #[serde(default)]
+    audio_chunk_duration_ms: Option<u64>,
+    #[serde(default)]
     capture_mic: bool,
     #[serde(default)]
+    audio_ring_ms: u64,
+    #[serde(default)]
+    audio: AudioSettings,
+    #[serde(default)]
+    buffering: BufferingSettings,
+    #[serde(default)]
+    profile: CaptureProfile,
+    #[serde(default)]
+    ambient: AmbientSettings,
+    #[serde(default)]
+    escalation: Option<escalation::EscalationSettings>,
+    #[serde(default)]
+    moments: Option<moments::MomentSettings>,
+    #[serde(default)]
     debug_save: bool,
+    #[serde(default)]
+    debug_write: disk_writer::DiskWriteSettings,
     #[serde(default = "CaptureTargetPayload::default_full_display")]
     target: CaptureTargetPayload,
+    #[serde(default)]
+    idle_timeout_secs: Option<u64>,
+    #[serde(default)]
+    idle_action: IdleAction,
+    #[serde(default)]
+    battery_policy: Option<BatteryPolicy>,
+    #[serde(default)]
+    adaptive_quality: bool,
+    #[serde(default)]
+    capture_thread_tuning: ThreadTuning,
+    #[serde(default)]
+    consumer_thread_tuning: ThreadTuning,
+    #[serde(default)]
+    record_mkv: bool,
+    #[serde(default)]
+    max_file_size_mb: Option<u64>,
+    #[serde(default)]
+    output_dir: Option<String>,
+    #[serde(default = "CaptureOptions::default_gap_tolerance_ms")]
+    gap_tolerance_ms: u64,
+    #[serde(default)]
+    freeze_fill_max_ms: Option<u64>,
+    #[serde(default)]
+    element_overrides: overrides::ElementOverrides,
+    #[serde(default)]
+    crop: Option<geometry::Rect>,
+    #[serde(default)]
+    rotation: Option<u16>,
+    #[serde(default)]
+    aspect: Option<geometry::AspectPreset>,
+    #[serde(default)]
+    aspect_mode: geometry::AspectMode,
+    #[serde(default)]
+    hdr: hdr::HdrMode,
+    #[serde(default)]
+    layout: Option<layout::LayoutConfig>,
+    #[serde(default)]
+    custom_sources: CustomSources,
+    #[serde(default)]
+    sinks: Vec<sinks::SinkConfig>,
+    #[serde(default)]
+    upload_limit_kbps: Option<u64>,
+    #[serde(default)]
+    shm_output: Option<String>,
+    #[serde(default)]
+    chunk_zstd_level: Option<i32>,
+    #[serde(default)]
+    track_active_window: bool,
+    #[serde(default)]
+    track_input_activity: bool,
+    #[serde(default)]
+    clipboard: Option<clipboard::ClipboardConfig>,
+    #[serde(default)]
+    annotators: Vec<annotations::AnnotatorConfig>,
+    #[serde(default)]
+    proxy: Option<proxy::ProxyConfig>,
+    #[serde(default)]
+    frame_hashes: bool,
+    #[serde(default)]
+    preview_playback: bool,
+    #[serde(default)]
+    consent: Option<consent::ConsentConfig>,
+    #[serde(default)]
+    privacy: Option<privacy::PrivacyFilter>,
+    #[serde(default = "CaptureOptions::default_keep_awake")]
+    keep_awake: bool,
+    #[serde(default)]
+    keep_portal_session_secs: Option<u64>,
+    #[serde(default)]
+    screen_lock_action: Option<screen_lock::LockAction>,
+    #[serde(default)]
+    display_lost_action: Option<displays::DisplayLostAction>,
+    #[serde(default)]
+    display: Option<String>,
+    #[serde(default)]
+    live_captions: Option<captions::CaptionConfig>,
+    #[serde(default)]
+    translation: Option<translation::TranslationConfig>,
+    #[serde(default)]
+    calendar_event: Option<calendar::CalendarEvent>,
+    #[serde(default)]
+    app_policy: Option<policy::AppPolicy>,
+    #[serde(default)]
+    blackout_when_hidden: bool,
+    #[serde(default)]
+    target_settings: BTreeMap<String, TargetSettings>,
+    #[cfg(feature = "ndi")]
+    #[serde(default)]
+    ndi_output: Option<String>,
+    #[cfg(feature = "diarization")]
+    #[serde(default)]
+    diarization: Option<diarization::DiarizationConfig>,
 }
 
 impl CaptureTargetPayload {
@@ -37,6 +262,13 @@ impl CaptureTargetPayload {
         match self {
             CaptureTargetPayload::FullDisplay => CaptureTarget::FullDisplay,
             CaptureTargetPayload::Window { id } => CaptureTarget::Window { id },
+            CaptureTargetPayload::Windows { ids, tiling, width, height } => {
+                CaptureTarget::Windows { ids, tiling, width, height }
+            }
+            CaptureTargetPayload::Device { path } => CaptureTarget::Device { path },
+            #[cfg(feature = "ndi")]
+            CaptureTargetPayload::Ndi { source } => CaptureTarget::Ndi { source },
+            CaptureTargetPayload::FollowFocus { min_dwell_ms } => CaptureTarget::FollowFocus { min_dwell_ms },
         }
     }
 }
@@ -45,9 +277,66 @@ impl From<StartCapturePayload> for CaptureOptions {
     fn from(payload: StartCapturePayload) -> Self {
         CaptureOptions {
             chunk_duration_ms: payload.chunk_duration_ms,
+            overlap_ms: payload.overlap_ms,
+            audio_chunk_duration_ms: payload.audio_chunk_duration_ms,
             capture_mic: payload.capture_mic,
+            audio_ring_ms: payload.audio_ring_ms,
+            audio: payload.audio,
+            buffering: payload.buffering,
+            profile: payload.profile,
+            ambient: payload.ambient,
+            escalation: payload.escalation,
+            moments: payload.moments,
             debug_save: payload.debug_save,
+            debug_write: payload.debug_write,
             target: payload.target.into_target(),
+            idle_timeout_secs: payload.idle_timeout_secs,
+            idle_action: payload.idle_action,
+            battery_policy: payload.battery_policy,
+            adaptive_quality: payload.adaptive_quality,
+            capture_thread_tuning: payload.capture_thread_tuning,
+            consumer_thread_tuning: payload.consumer_thread_tuning,
+            record_mkv: payload.record_mkv,
+            max_file_size_mb: payload.max_file_size_mb,
+            output_dir: payload.output_dir,
+            gap_tolerance_ms: payload.gap_tolerance_ms,
+            freeze_fill_max_ms: payload.freeze_fill_max_ms,
+            element_overrides: payload.element_overrides,
+            crop: payload.crop,
+            rotation: payload.rotation,
+            aspect: payload.aspect,
+            aspect_mode: payload.aspect_mode,
+            hdr: payload.hdr,
+            layout: payload.layout,
+            custom_sources: payload.custom_sources,
+            sinks: payload.sinks,
+            upload_limit_kbps: payload.upload_limit_kbps,
+            shm_output: payload.shm_output,
+            chunk_zstd_level: payload.chunk_zstd_level,
+            track_active_window: payload.track_active_window,
+            track_input_activity: payload.track_input_activity,
+            clipboard: payload.clipboard,
+            annotators: payload.annotators,
+            proxy: payload.proxy,
+            frame_hashes: payload.frame_hashes,
+            preview_playback: payload.preview_playback,
+            consent: payload.consent,
+            privacy: payload.privacy,
+            keep_awake: payload.keep_awake,
+            keep_portal_session_secs: payload.keep_portal_session_secs,
+            screen_lock_action: payload.screen_lock_action,
+            display_lost_action: payload.display_lost_action,
+            display: payload.display,
+            live_captions: payload.live_captions,
+            translation: payload.translation,
+            calendar_event: payload.calendar_event,
+            app_policy: payload.app_policy,
+            blackout_when_hidden: payload.blackout_when_hidden,
+            target_settings: payload.target_settings,
+            #[cfg(feature = "ndi")]
+            ndi_output: payload.ndi_output,
+            #[cfg(feature = "diarization")]
+            diarization: payload.diarization,
         }
     }
 }
@@ -62,14 +351,549 @@ fn start_capture(
         .map_err(|err| err.to_string())
 }
 
+/// Builds the pipelines for `payload` now so the next `start_capture` with
+/// the same payload starts at once.
+#[tauri::command(async)]
+fn arm_capture(
+    manager: tauri::State<CaptureManager>,
+    payload: StartCapturePayload,
+) -> Result<(), String> {
+    manager
+        .arm_capture(payload.into())
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn disarm_capture(manager: tauri::State<CaptureManager>) -> bool {
+    manager.disarm_capture()
+}
+
 #[tauri::command]
-fn stop_capture(manager: tauri::State<CaptureManager>) -> Result<(), String> {
+fn stop_capture(manager: tauri::State<CaptureManager>) -> Result<Option<session::SessionSummary>, String> {
     manager.stop_capture().map_err(|err| err.to_string())
 }
 
 #[tauri::command]
-fn capture_status(manager: tauri::State<CaptureManager>) -> CaptureState {
-    manager.status()
+fn pause_capture(manager: tauri::State<CaptureManager>) -> Result<(), String> {
+    manager.pause_capture().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn resume_capture(manager: tauri::State<CaptureManager>) -> Result<(), String> {
+    manager.resume_capture().map_err(|err| err.to_string())
+}
+
+#[derive(Serialize)]
+struct CaptureStatus {
+    state: CaptureState,
+    last_error: Option<CaptureError>,
+    /// Pipelines are armed for a quick start.
+    armed: bool,
+    /// Set while capturing.
+    memory: Option<memory::MemoryReport>,
+}
+
+#[tauri::command]
+fn capture_status(manager: tauri::State<CaptureManager>) -> CaptureStatus {
+    CaptureStatus {
+        state: manager.status(),
+        last_error: manager.last_error(),
+        armed: manager.is_armed(),
+        memory: manager.memory_report(),
+    }
+}
+
+#[tauri::command]
+fn add_marker(
+    manager: tauri::State<CaptureManager>,
+    label: Option<String>,
+) -> Result<Marker, String> {
+    manager.add_marker(label).map_err(|err| err.to_string())
+}
+
+/// Cuts the current chunk of every stream now instead of at the chunk duration.
+#[tauri::command]
+fn flush_chunks_now(manager: tauri::State<CaptureManager>) -> Result<(), String> {
+    manager.flush_chunks_now().map_err(|err| err.to_string())
+}
+
+/// A burst of encoded stills from the running capture, for cheap visual context.
+#[tauri::command(async)]
+fn capture_frames(
+    manager: tauri::State<CaptureManager>,
+    count: u32,
+    interval_ms: u64,
+    format: Option<frames::FrameFormat>,
+) -> Result<Vec<frames::CapturedFrame>, String> {
+    manager
+        .capture_frames(count, interval_ms, format.unwrap_or_default())
+        .map_err(|err| err.to_string())
+}
+
+/// The last `ms` of mic PCM, for a wake-word or utterance detector.
+#[tauri::command]
+fn read_recent_audio(manager: tauri::State<CaptureManager>, ms: u64) -> Result<audio_ring::RecentAudio, String> {
+    manager.read_recent_audio(ms).map_err(|err| err.to_string())
+}
+
+/// Records the last `ms` (default 2000) of mic audio as an example of the
+/// wake phrase.
+#[tauri::command(async)]
+fn enroll_wake_word(app: tauri::AppHandle, phrase: String, ms: Option<u64>) -> Result<(), String> {
+    #[cfg(feature = "wake_word")]
+    {
+        wake_word::enroll(&app, phrase, ms.unwrap_or(2_000)).map_err(|err| err.to_string())
+    }
+    #[cfg(not(feature = "wake_word"))]
+    {
+        let _ = (app, phrase, ms);
+        Err("built without the wake_word feature".to_string())
+    }
+}
+
+/// Where to play the running session from, while it keeps recording.
+#[tauri::command]
+fn start_preview_playback(
+    manager: tauri::State<CaptureManager>,
+    offset_ms: u64,
+) -> Result<preview::PreviewPlayback, String> {
+    manager.preview_playback(offset_ms).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn set_layout(manager: tauri::State<CaptureManager>, scene: String) -> Result<(), String> {
+    manager.set_layout_scene(&scene).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn set_capture_profile(manager: tauri::State<CaptureManager>, profile: CaptureProfile) -> Result<(), String> {
+    manager.set_capture_profile(profile).map_err(|err| err.to_string())
+}
+
+/// Which capture features work on this platform and machine.
+#[tauri::command]
+fn capture_capabilities() -> platform::PlatformCapabilities {
+    platform::probe()
+}
+
+#[derive(Serialize)]
+struct CaptureTargets {
+    displays: Vec<displays::Display>,
+    devices: Vec<devices::CaptureDevice>,
+}
+
+/// Connected displays, kept current by the display monitor, and video devices.
+#[tauri::command]
+fn list_capture_targets(manager: tauri::State<CaptureManager>) -> Result<CaptureTargets, String> {
+    Ok(CaptureTargets {
+        displays: manager.displays().list(),
+        devices: devices::list_video_devices().map_err(|err| err.to_string())?,
+    })
+}
+
+#[tauri::command]
+fn list_capture_devices() -> Result<Vec<devices::CaptureDevice>, String> {
+    devices::list_video_devices().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn list_saved_screen_permissions() -> Result<Vec<permissions::SavedScreenPermission>, String> {
+    permissions::list().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn clear_screen_permission(token: String) -> Result<bool, String> {
+    permissions::clear(&token).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn list_templates() -> Result<Vec<templates::RecordingTemplate>, String> {
+    templates::list().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn save_template(template: templates::RecordingTemplate) -> Result<(), String> {
+    templates::save(template).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn delete_template(name: String) -> Result<bool, String> {
+    templates::delete(&name).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn start_from_template(manager: tauri::State<CaptureManager>, name: String) -> Result<(), String> {
+    let options = templates::resolve(&name, manager.displays()).map_err(|err| err.to_string())?;
+    manager.start_capture(options).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn enqueue_job(queue: tauri::State<JobQueue>, spec: JobSpec) -> Result<JobStatus, String> {
+    queue.enqueue(spec).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn job_status(queue: tauri::State<JobQueue>, job_id: u64) -> Result<JobStatus, String> {
+    queue
+        .status(job_id)
+        .ok_or_else(|| format!("unknown job {job_id}"))
+}
+
+#[tauri::command]
+fn pending_uploads(manager: tauri::State<CaptureManager>) -> Result<Vec<uploads::PendingUpload>, String> {
+    manager.uploads().pending().map_err(|err| err.to_string())
+}
+
+/// Registers `consumer` for chunk acks and returns its unacked chunks, so a
+/// reconnecting frontend can catch up on what it missed.
+#[tauri::command]
+fn connect_chunk_consumer(manager: tauri::State<CaptureManager>, consumer: String) -> Vec<acks::ChunkInfo> {
+    manager
+        .acks()
+        .connect(&consumer)
+        .iter()
+        .map(|chunk| acks::ChunkInfo::of(chunk))
+        .collect()
+}
+
+#[tauri::command]
+fn ack_chunks(manager: tauri::State<CaptureManager>, consumer: String, chunks: Vec<acks::ChunkKey>) -> usize {
+    manager.acks().ack(&consumer, &chunks)
+}
+
+#[tauri::command]
+fn disconnect_chunk_consumer(manager: tauri::State<CaptureManager>, consumer: String) -> bool {
+    manager.acks().forget(&consumer)
+}
+
+/// The output root for a command's `output_dir`, refused unless it lies
+/// inside the sessions root.
+fn output_root(output_dir: Option<String>) -> Result<std::path::PathBuf, String> {
+    let options = CaptureOptions {
+        output_dir,
+        ..CaptureOptions::default()
+    };
+    session::confine(&session::output_root(&options)).map_err(|err| err.to_string())
+}
+
+fn chunk_stores<'a>(manager: &'a CaptureManager, output_root: &'a std::path::Path) -> chunk_store::ChunkStores<'a> {
+    chunk_store::ChunkStores {
+        output_root,
+        running: manager.session(),
+        acks: manager.acks(),
+        chunk_dir: std::path::Path::new(capture_manager::DEBUG_OUTPUT_DIR),
+        spool: manager.uploads(),
+    }
+}
+
+/// Describes a stored chunk; `stream` defaults to `video`.
+#[tauri::command(async)]
+fn get_chunk(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    chunk_id: u64,
+    stream: Option<String>,
+    output_dir: Option<String>,
+) -> Result<acks::ChunkInfo, String> {
+    let root = output_root(output_dir)?;
+    let key = acks::ChunkKey {
+        stream: stream.unwrap_or_else(|| "video".to_string()),
+        chunk_id,
+    };
+    chunk_stores(&manager, &root)
+        .get(&session_id, &key)
+        .map(|chunk| chunk.info)
+        .map_err(|err| err.to_string())
+}
+
+/// The raw (decompressed) data of a stored chunk, as a binary response.
+#[tauri::command(async)]
+fn get_chunk_data(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    chunk_id: u64,
+    stream: Option<String>,
+    output_dir: Option<String>,
+) -> Result<tauri::ipc::Response, String> {
+    let root = output_root(output_dir)?;
+    let key = acks::ChunkKey {
+        stream: stream.unwrap_or_else(|| "video".to_string()),
+        chunk_id,
+    };
+    chunk_stores(&manager, &root)
+        .get(&session_id, &key)
+        .and_then(|chunk| chunk.data())
+        .map(tauri::ipc::Response::new)
+        .map_err(|err| err.to_string())
+}
+
+/// Sessions whose focused-window timeline mentions `query`.
+#[tauri::command(async)]
+fn find_sessions_by_window(query: String, output_dir: Option<String>) -> Result<Vec<session::SessionManifest>, String> {
+    let root = output_root(output_dir)?;
+    session::find_by_window(&root, &query).map_err(|err| err.to_string())
+}
+
+/// Registers an existing MP4/MKV recording as a session.
+#[tauri::command(async)]
+fn import_file(
+    thumbnails: tauri::State<search::Thumbnails>,
+    path: String,
+    output_dir: Option<String>,
+) -> Result<import::ImportedFile, String> {
+    let root = output_root(output_dir)?;
+    let path = session::confine(std::path::Path::new(&path)).map_err(|err| err.to_string())?;
+    import::import_file(&root, &path, &thumbnails)
+        .map_err(|err| err.to_string())
+}
+
+/// A page of past sessions for the library view, newest first.
+#[tauri::command(async)]
+fn list_sessions(
+    thumbnails: tauri::State<search::Thumbnails>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+    filters: Option<library::SessionFilters>,
+    output_dir: Option<String>,
+) -> Result<library::SessionPage, String> {
+    let root = output_root(output_dir)?;
+    library::list_sessions(
+        &root,
+        page.unwrap_or(0),
+        page_size,
+        &filters.unwrap_or_default(),
+        &thumbnails,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// A window of one stream's chunks in a past session.
+#[tauri::command(async)]
+fn chunk_timeline(
+    session_id: String,
+    stream: Option<String>,
+    offset: Option<u64>,
+    limit: Option<usize>,
+    output_dir: Option<String>,
+) -> Result<library::ChunkTimeline, String> {
+    let root = output_root(output_dir)?;
+    library::chunk_timeline(
+        &root,
+        &session_id,
+        stream.as_deref().unwrap_or("video"),
+        offset.unwrap_or(0),
+        limit,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Replaces a finished session's tags in the library.
+#[tauri::command(async)]
+fn set_session_tags(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    tags: Vec<String>,
+    output_dir: Option<String>,
+) -> Result<Vec<String>, String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    library::set_session_tags(&root, &session_id, tags).map_err(|err| err.to_string())
+}
+
+#[tauri::command(async)]
+fn rename_session(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    title: String,
+    output_dir: Option<String>,
+) -> Result<(), String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    library::rename_session(&root, &session_id, &title).map_err(|err| err.to_string())
+}
+
+#[tauri::command(async)]
+fn add_session_note(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    text: String,
+    offset_ms: Option<u64>,
+    output_dir: Option<String>,
+) -> Result<session::SessionNote, String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    library::add_session_note(&root, &session_id, &text, offset_ms).map_err(|err| err.to_string())
+}
+
+/// The output root holding `session_id`; the running session's manifest
+/// isn't written until it stops, so it is refused.
+fn finished_session_root(
+    manager: &CaptureManager,
+    session_id: &str,
+    output_dir: Option<String>,
+) -> Result<std::path::PathBuf, String> {
+    if manager.session_id().as_deref() == Some(session_id) {
+        return Err(format!("session '{session_id}' is still recording"));
+    }
+    output_root(output_dir)
+}
+
+/// Zips a finished session's manifest, transcripts, screen text, markers
+/// and thumbnails, without the media, for external analysis.
+#[tauri::command(async)]
+fn export_session_metadata(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    format: Option<export::ExportFormat>,
+    output_dir: Option<String>,
+) -> Result<export::MetadataExport, String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    export::export_session_metadata(&root, &session_id, format.unwrap_or_default()).map_err(|err| err.to_string())
+}
+
+/// SRT files of a finished session's transcript and its translations.
+#[tauri::command(async)]
+fn export_subtitles(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    output_dir: Option<String>,
+) -> Result<Vec<export::SubtitleTrack>, String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    export::export_subtitles(&root, &session_id).map_err(|err| err.to_string())
+}
+
+/// PNG of what was on screen at wall-clock `timestamp_ms` of a finished
+/// session, e.g. for a search hit.
+#[tauri::command(async)]
+fn extract_frame(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    timestamp_ms: u64,
+    output_dir: Option<String>,
+) -> Result<search::ExtractedFrame, String> {
+    let root = finished_session_root(&manager, &session_id, output_dir)?;
+    search::extract_frame(&root, &session_id, timestamp_ms).map_err(|err| err.to_string())
+}
+
+/// Matches in markers, window titles, tabs, clipboard text and annotations.
+#[tauri::command(async)]
+fn search_recordings(
+    thumbnails: tauri::State<search::Thumbnails>,
+    query: String,
+    time_range: Option<search::TimeRange>,
+    output_dir: Option<String>,
+) -> Result<Vec<search::SearchHit>, String> {
+    let root = output_root(output_dir)?;
+    search::search_recordings(
+        &root,
+        &query,
+        time_range.unwrap_or_default(),
+        &thumbnails,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Frames under the output root, the running session included, that look
+/// like the screen hashed as `hash`; see the `frame_hashes` option.
+#[tauri::command(async)]
+fn find_similar_frames(
+    manager: tauri::State<CaptureManager>,
+    hash: String,
+    max_distance: Option<u32>,
+    output_dir: Option<String>,
+) -> Result<Vec<frame_hash::SimilarFrame>, String> {
+    let root = output_root(output_dir)?;
+    let hashes = manager.frame_hashes();
+    let live = manager.session();
+    frame_hash::find_similar_frames(
+        &root,
+        &hash,
+        max_distance.unwrap_or(frame_hash::DEFAULT_MAX_DISTANCE),
+        live.as_ref().map(|(session_id, started_unix_ms)| {
+            (session_id.as_str(), *started_unix_ms as u64, hashes.as_slice())
+        }),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Measures encoder throughput per resolution to recommend settings for
+/// this machine. Refused while capturing, since both would skew each other.
+#[tauri::command(async)]
+fn run_benchmark(
+    manager: tauri::State<CaptureManager>,
+    request: Option<benchmark::BenchmarkRequest>,
+) -> Result<benchmark::BenchmarkReport, String> {
+    if !matches!(manager.status(), CaptureState::Idle | CaptureState::Failed) {
+        return Err("stop capturing before running the benchmark".to_string());
+    }
+    benchmark::run(&request.unwrap_or_default()).map_err(|err| err.to_string())
+}
+
+/// Deletes a session everywhere it is stored; the running one is refused.
+#[tauri::command(async)]
+fn delete_session(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    secure: bool,
+    output_dir: Option<String>,
+) -> Result<deletion::DeletionReport, String> {
+    if manager.session_id().as_deref() == Some(session_id.as_str()) {
+        return Err(format!("session '{session_id}' is still recording"));
+    }
+    let root = output_root(output_dir)?;
+    deletion::delete_session(
+        &root,
+        &session_id,
+        std::path::Path::new(capture_manager::DEBUG_OUTPUT_DIR),
+        manager.uploads(),
+        secure,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Silences (or beeps over) `start_ms..end_ms` of a session's stored audio chunks.
+#[tauri::command(async)]
+fn redact_range(
+    manager: tauri::State<CaptureManager>,
+    session_id: String,
+    start_ms: u64,
+    end_ms: u64,
+    fill: Option<redaction::RedactFill>,
+    output_dir: Option<String>,
+) -> Result<redaction::RedactionReport, String> {
+    session::validate_id(&session_id).map_err(|err| err.to_string())?;
+    let root = output_root(output_dir)?;
+    redaction::redact_range(
+        &root.join(&session_id),
+        std::path::Path::new(capture_manager::DEBUG_OUTPUT_DIR),
+        manager.uploads(),
+        start_ms,
+        end_ms,
+        fill.unwrap_or_default(),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Raw chunks live under the sessions root or, with `debug_save`, in
+/// `debug_output/`; other directories are refused.
+fn raw_chunk_dir(dir: &str) -> Result<std::path::PathBuf, String> {
+    let dir = std::path::Path::new(dir);
+    session::confine(dir)
+        .or_else(|_| session::confine_to(dir, std::path::Path::new(capture_manager::DEBUG_OUTPUT_DIR)))
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command(async)]
+fn remux_raw_chunks(dir: String) -> Result<Vec<remux::RemuxOutput>, String> {
+    let dir = raw_chunk_dir(&dir)?;
+    remux::remux_raw_chunks(&dir).map_err(|err| err.to_string())
+}
+
+#[tauri::command(async)]
+fn stitch_raw_chunks(dir: String, gap_tolerance_ms: Option<u64>) -> Result<Vec<remux::RemuxOutput>, String> {
+    let dir = raw_chunk_dir(&dir)?;
+    remux::stitch_raw_chunks(
+        &dir,
+        gap_tolerance_ms.unwrap_or(remux::DEFAULT_GAP_TOLERANCE_MS),
+    )
+    .map_err(|err| err.to_string())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -77,11 +901,164 @@ pub fn run() {
     tauri::Builder::default()
         .manage(CaptureManager::default())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            // stores below read their files from here, so resolve it first
+            app_dirs::init(app.handle())?;
+            let handle = app.handle().clone();
+            app.state::<CaptureManager>().events().subscribe(move |event| {
+                let name = format!("capture://{}", event.name().replace('_', "-"));
+                if let Err(err) = handle.emit(&name, event) {
+                    eprintln!("[capture] failed to emit {name}: {err}");
+                }
+            });
+            let job_queue = JobQueue::new(app.state::<CaptureManager>().events().clone())?;
+            app.manage(job_queue);
+            let thumbnails = search::Thumbnails::new(app.state::<CaptureManager>().events().clone())?;
+            app.manage(thumbnails);
+            if let Err(err) = daily_summary::spawn_scheduler(app.handle().clone()) {
+                eprintln!("[summary] failed to start scheduler: {err}");
+            }
+            if let Err(err) = heartbeat::spawn(app.handle().clone()) {
+                eprintln!("[heartbeat] failed to start: {err}");
+            }
+            if let Err(err) = heartbeat::spawn_pipeline_watch(app.handle().clone()) {
+                eprintln!("[heartbeat] failed to start pipeline watch: {err}");
+            }
+            if let Some(config) = http_api::HttpApiConfig::from_env() {
+                if let Err(err) = http_api::spawn(app.handle().clone(), config) {
+                    eprintln!("[http_api] failed to start: {err}");
+                }
+            }
+            if let Err(err) = idle::spawn(app.handle().clone()) {
+                eprintln!("[idle] failed to start monitor: {err}");
+            }
+            if let Err(err) = screen_lock::spawn(app.handle().clone()) {
+                eprintln!("[screen_lock] failed to start monitor: {err}");
+            }
+            if let Err(err) = displays::spawn(app.handle().clone()) {
+                eprintln!("[displays] failed to start monitor: {err}");
+            }
+            if let Err(err) = policy::spawn(app.handle().clone()) {
+                eprintln!("[policy] failed to start: {err}");
+            }
+            if let Err(err) = blackout::spawn(app.handle().clone()) {
+                eprintln!("[blackout] failed to start: {err}");
+            }
+            if let Err(err) = power::spawn(app.handle().clone()) {
+                eprintln!("[power] failed to start monitor: {err}");
+            }
+            if let Err(err) = adaptive::spawn(app.handle().clone()) {
+                eprintln!("[adaptive] failed to start controller: {err}");
+            }
+            if let Err(err) = latency::spawn(app.handle().clone()) {
+                eprintln!("[latency] failed to start reporter: {err}");
+            }
+            if let Err(err) = memory::spawn(app.handle().clone()) {
+                eprintln!("[memory] failed to start reporter: {err}");
+            }
+            if let Err(err) = focus::spawn(app.handle().clone()) {
+                eprintln!("[focus] failed to start follower: {err}");
+            }
+            if let Err(err) = focus::spawn_sampler(app.handle().clone()) {
+                eprintln!("[focus] failed to start window sampler: {err}");
+            }
+            if let Err(err) = clipboard::spawn(app.handle().clone()) {
+                eprintln!("[clipboard] failed to start monitor: {err}");
+            }
+            if let Err(err) = meetings::spawn(app.handle().clone()) {
+                eprintln!("[meetings] failed to start detector: {err}");
+            }
+            if let Err(err) = escalation::spawn(app.handle().clone()) {
+                eprintln!("[escalation] failed to start: {err}");
+            }
+            if let Err(err) = calendar::spawn(app.handle().clone()) {
+                eprintln!("[calendar] failed to start scheduler: {err}");
+            }
+            #[cfg(feature = "wake_word")]
+            if let Err(err) = wake_word::spawn(app.handle().clone()) {
+                eprintln!("[wake_word] failed to start: {err}");
+            }
+            let manager = app.state::<CaptureManager>();
+            if let Err(err) = manager.uploads().start(manager.events().clone()) {
+                eprintln!("[uploads] failed to start uploader: {err}");
+            }
+            if notifications::enabled_from_env() {
+                notifications::install(app.handle());
+            }
+            #[cfg(feature = "grpc")]
+            if let Some(config) = grpc::GrpcConfig::from_env() {
+                let manager = app.state::<CaptureManager>();
+                if let Err(err) = grpc::spawn(manager.chunk_taps(), std::sync::Arc::clone(manager.acks()), config) {
+                    eprintln!("[grpc] failed to start: {err}");
+                }
+            }
+            if let Some(config) = webhooks::WebhookConfig::from_env() {
+                let manager = app.state::<CaptureManager>();
+                if let Err(err) = webhooks::spawn(manager.events(), config) {
+                    eprintln!("[webhooks] failed to start: {err}");
+                }
+            }
+            #[cfg(target_os = "linux")]
+            match dbus_service::spawn(app.handle().clone()) {
+                Ok(conn) => {
+                    app.manage(conn);
+                }
+                Err(err) => eprintln!("[dbus] failed to register service: {err}"),
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             start_capture,
             stop_capture,
-            capture_status
+            arm_capture,
+            disarm_capture,
+            pause_capture,
+            resume_capture,
+            capture_status,
+            add_marker,
+            set_layout,
+            set_capture_profile,
+            start_preview_playback,
+            capture_frames,
+            read_recent_audio,
+            enroll_wake_word,
+            run_benchmark,
+            flush_chunks_now,
+            capture_capabilities,
+            list_capture_targets,
+            list_capture_devices,
+            list_saved_screen_permissions,
+            clear_screen_permission,
+            list_templates,
+            save_template,
+            delete_template,
+            start_from_template,
+            enqueue_job,
+            job_status,
+            pending_uploads,
+            connect_chunk_consumer,
+            ack_chunks,
+            disconnect_chunk_consumer,
+            get_chunk,
+            get_chunk_data,
+            find_sessions_by_window,
+            list_sessions,
+            import_file,
+            chunk_timeline,
+            set_session_tags,
+            rename_session,
+            add_session_note,
+            export_session_metadata,
+            export_subtitles,
+            search_recordings,
+            extract_frame,
+            find_similar_frames,
+            redact_range,
+            delete_session,
+            remux_raw_chunks,
+            stitch_raw_chunks
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,302 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::annotations::Annotation;
+use crate::capture_manager::Marker;
+use crate::search::{Thumbnails, TimeRange};
+use crate::session::{self, SessionManifest, SessionNote};
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+const DEFAULT_TIMELINE_LIMIT: usize = 200;
+
+/// Narrows `list_sessions`; unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SessionFilters {
+    /// Sessions that started inside this range.
+    #[serde(default)]
+    pub time_range: TimeRange,
+    /// App id or window title seen during the session.
+    #[serde(default)]
+    pub window: Option<String>,
+    #[serde(default)]
+    pub min_duration_ms: Option<u64>,
+    #[serde(default)]
+    pub has_markers: Option<bool>,
+    /// Sessions carrying this tag, matched case-insensitively.
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl SessionFilters {
+    fn matches(&self, manifest: &SessionManifest) -> bool {
+        self.time_range.contains(manifest.started_unix_ms as u64)
+            && self
+                .window
+                .as_deref()
+                .is_none_or(|query| manifest.windows.iter().any(|span| span.matches(query)))
+            && self.min_duration_ms.is_none_or(|min| manifest.duration_ms >= min)
+            && self
+                .has_markers
+                .is_none_or(|wanted| wanted == !manifest.markers.is_empty())
+            && self.tag.as_deref().is_none_or(|wanted| {
+                manifest.tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted))
+            })
+    }
+}
+
+/// One row of the library view.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEntry {
+    pub session_id: String,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub note_count: usize,
+    pub started_unix_ms: u128,
+    pub duration_ms: u64,
+    /// Everything in the session directory: recording, manifest, thumbnails.
+    pub size_bytes: u64,
+    pub chunks: u64,
+    pub marker_count: usize,
+    pub recording: Option<String>,
+    /// PNG from the middle of the recording, once rendered; listing a
+    /// session queues it.
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionEntry>,
+    pub page: usize,
+    pub page_size: usize,
+    /// Matching sessions over all pages.
+    pub total: usize,
+}
+
+/// The matching sessions under `root`, newest first, one page at a time.
+/// Thumbnails are only queued for the page being returned.
+pub fn list_sessions(
+    root: &Path,
+    page: usize,
+    page_size: Option<usize>,
+    filters: &SessionFilters,
+    thumbnails: &Thumbnails,
+) -> Result<SessionPage> {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let matching: Vec<_> = session::list_manifests(root)?
+        .into_iter()
+        .filter(|(_, manifest)| filters.matches(manifest))
+        .collect();
+    let total = matching.len();
+    let sessions = matching
+        .into_iter()
+        .skip(page.saturating_mul(page_size))
+        .take(page_size)
+        .map(|(dir, manifest)| entry(&dir, &manifest, thumbnails))
+        .collect();
+    Ok(SessionPage {
+        sessions,
+        page,
+        page_size,
+        total,
+    })
+}
+
+fn entry(dir: &Path, manifest: &SessionManifest, thumbnails: &Thumbnails) -> SessionEntry {
+    let thumbnail = manifest
+        .recording
+        .as_deref()
+        .and_then(|recording| thumbnails.get(dir, &manifest.session_id, recording, manifest.duration_ms / 2));
+    SessionEntry {
+        session_id: manifest.session_id.clone(),
+        title: manifest.title.clone(),
+        tags: manifest.tags.clone(),
+        note_count: manifest.notes.len(),
+        started_unix_ms: manifest.started_unix_ms,
+        duration_ms: manifest.duration_ms,
+        size_bytes: dir_size(dir),
+        chunks: manifest.summary.as_ref().map_or(0, |summary| summary.chunks),
+        marker_count: manifest.markers.len(),
+        recording: manifest.recording.clone(),
+        thumbnail,
+    }
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map_or(0, |metadata| metadata.len()),
+        })
+        .sum()
+}
+
+/// Replaces the session's tags, trimmed and without duplicates.
+pub fn set_session_tags(root: &Path, session_id: &str, tags: Vec<String>) -> Result<Vec<String>> {
+    update(root, session_id, |manifest| {
+        let mut cleaned: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !cleaned.iter().any(|seen| seen.eq_ignore_ascii_case(tag)) {
+                cleaned.push(tag.to_string());
+            }
+        }
+        manifest.tags = cleaned.clone();
+        Ok(cleaned)
+    })
+}
+
+/// Sets the session's title; an empty one clears it.
+pub fn rename_session(root: &Path, session_id: &str, title: &str) -> Result<()> {
+    update(root, session_id, |manifest| {
+        let title = title.trim();
+        manifest.title = (!title.is_empty()).then(|| title.to_string());
+        Ok(())
+    })
+}
+
+pub fn add_session_note(root: &Path, session_id: &str, text: &str, offset_ms: Option<u64>) -> Result<SessionNote> {
+    update(root, session_id, |manifest| {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(anyhow!("note is empty"));
+        }
+        if let Some(offset_ms) = offset_ms.filter(|offset_ms| *offset_ms > manifest.duration_ms) {
+            return Err(anyhow!(
+                "offset {offset_ms}ms is past the end of the {}ms session",
+                manifest.duration_ms
+            ));
+        }
+        let note = SessionNote {
+            id: manifest.notes.iter().map(|note| note.id + 1).max().unwrap_or(0),
+            text: text.to_string(),
+            unix_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis())
+                .unwrap_or_default(),
+            offset_ms,
+        };
+        manifest.notes.push(note.clone());
+        Ok(note)
+    })
+}
+
+/// Reads, edits and rewrites one session's manifest.
+fn update<T>(root: &Path, session_id: &str, edit: impl FnOnce(&mut SessionManifest) -> Result<T>) -> Result<T> {
+    session::validate_id(session_id)?;
+    let dir = root.join(session_id);
+    let mut manifest = SessionManifest::read(&dir)
+        .map_err(|err| anyhow!("session '{session_id}' has no readable manifest: {err}"))?;
+    let result = edit(&mut manifest)?;
+    manifest.write(&dir)?;
+    Ok(result)
+}
+
+/// One chunk on a session's timeline, with what happened while it ran.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineChunk {
+    pub chunk_id: u64,
+    pub stream: String,
+    pub offset_ms: u64,
+    pub duration_ms: u64,
+    /// True when the timing is spread evenly from the stream's totals
+    /// because no record of this chunk's own start was kept.
+    pub estimated: bool,
+    pub markers: Vec<Marker>,
+    /// The app focused when the chunk started.
+    pub app_id: Option<String>,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkTimeline {
+    pub session_id: String,
+    pub stream: String,
+    /// Chunks of `stream` in the session.
+    pub total: u64,
+    pub chunks: Vec<TimelineChunk>,
+}
+
+/// Chunks `offset..offset + limit` of one stream, so long sessions load a
+/// window at a time.
+pub fn chunk_timeline(
+    root: &Path,
+    session_id: &str,
+    stream: &str,
+    offset: u64,
+    limit: Option<usize>,
+) -> Result<ChunkTimeline> {
+    session::validate_id(session_id)?;
+    let manifest = SessionManifest::read(&root.join(session_id))
+        .map_err(|err| anyhow!("session '{session_id}' has no readable manifest: {err}"))?;
+    let totals = manifest
+        .summary
+        .as_ref()
+        .and_then(|summary| summary.streams.get(stream))
+        .cloned()
+        .unwrap_or_default();
+    let average_ms = totals.duration_ms.checked_div(totals.chunks).unwrap_or(0);
+    let started_ms = manifest.started_unix_ms as u64;
+    let limit = limit.unwrap_or(DEFAULT_TIMELINE_LIMIT) as u64;
+
+    let chunks = (offset..totals.chunks.min(offset.saturating_add(limit)))
+        .map(|chunk_id| {
+            let recorded_start = manifest
+                .chunk_annotations
+                .iter()
+                .filter(|record| record.stream == stream)
+                .map(|record| (record.chunk_id, record.start_ts_unix_nanos))
+                .chain(
+                    manifest
+                        .proxy_chunks
+                        .iter()
+                        .filter(|_| stream == "video")
+                        .map(|link| (link.chunk_id, link.start_ts_unix_nanos)),
+                )
+                .find(|(id, _)| *id == chunk_id)
+                .map(|(_, start_ns)| (start_ns / 1_000_000) as u64);
+            let offset_ms = recorded_start
+                .map(|start_ms| start_ms.saturating_sub(started_ms))
+                .unwrap_or(chunk_id * average_ms);
+            let start_ms = started_ms + offset_ms;
+            let end_ms = start_ms + average_ms;
+            TimelineChunk {
+                chunk_id,
+                stream: stream.to_string(),
+                offset_ms,
+                duration_ms: average_ms,
+                estimated: recorded_start.is_none(),
+                markers: manifest
+                    .markers
+                    .iter()
+                    .filter(|marker| (start_ms..end_ms).contains(&(marker.unix_ms as u64)))
+                    .cloned()
+                    .collect(),
+                app_id: manifest
+                    .windows
+                    .iter()
+                    .find(|span| span.start_unix_ms <= start_ms && start_ms < span.end_unix_ms)
+                    .and_then(|span| span.app_id.clone()),
+                annotations: manifest
+                    .chunk_annotations
+                    .iter()
+                    .filter(|record| record.chunk_id == chunk_id && record.stream == stream)
+                    .map(|record| record.annotation.clone())
+                    .collect(),
+            }
+        })
+        .collect();
+    Ok(ChunkTimeline {
+        session_id: manifest.session_id.clone(),
+        stream: stream.to_string(),
+        total: totals.chunks,
+        chunks,
+    })
+}
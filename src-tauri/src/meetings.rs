@@ -0,0 +1,230 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::CaptureManager;
+use crate::events::CaptureEvent;
+use crate::focus;
+use crate::templates;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a meeting app must go unseen before the meeting counts as over;
+/// switching away from its window for a moment doesn't end it.
+const END_GRACE: Duration = Duration::from_secs(60);
+
+/// A conferencing app and how to recognise it.
+struct KnownApp {
+    name: &'static str,
+    /// Case-insensitive regex matched against the focused window's app id
+    /// and title.
+    window: &'static str,
+    /// Lowercase substrings matched against the application name and binary
+    /// of streams recording from a mic. Browser-based apps can't be told
+    /// apart here.
+    audio: &'static [&'static str],
+}
+
+const KNOWN_APPS: &[KnownApp] = &[
+    KnownApp {
+        name: "Zoom",
+        window: r"zoom meeting|zoom workplace|us\.zoom\.xos",
+        audio: &["zoom"],
+    },
+    KnownApp {
+        name: "Google Meet",
+        // only a call's tab carries its `abc-defg-hij` code; the landing page
+        // and other "Meet - " titles don't
+        window: r"^meet [-–] [a-z]{3}-[a-z]{4}-[a-z]{3}\b|meet\.google\.com/[a-z]{3}-[a-z]{4}-[a-z]{3}\b",
+        audio: &[],
+    },
+    KnownApp {
+        name: "Microsoft Teams",
+        window: r"microsoft teams|teams-for-linux",
+        audio: &["teams"],
+    },
+];
+
+/// `KNOWN_APPS` window patterns, in the same order.
+static WINDOW_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    KNOWN_APPS
+        .iter()
+        .map(|app| Regex::new(&format!("(?i){}", app.window)).expect("known app patterns are valid"))
+        .collect()
+});
+
+/// Where a meeting was noticed.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectedBy {
+    Window,
+    Audio,
+}
+
+/// Meeting detection is off unless `SC_MEETING_DETECTION=1`, since it polls
+/// `pactl` every few seconds.
+fn enabled_from_env() -> bool {
+    matches!(
+        std::env::var("SC_MEETING_DETECTION").as_deref(),
+        Ok("1") | Ok("true") | Ok("on")
+    )
+}
+
+/// `SC_MEETING_TEMPLATE` names a recording template to start when a meeting
+/// is detected and nothing is being recorded.
+fn auto_template() -> Option<String> {
+    std::env::var("SC_MEETING_TEMPLATE").ok().filter(|name| !name.is_empty())
+}
+
+fn window_app(values: &[String]) -> Option<&'static KnownApp> {
+    KNOWN_APPS
+        .iter()
+        .zip(WINDOW_PATTERNS.iter())
+        .find(|(_, pattern)| values.iter().any(|value| pattern.is_match(value)))
+        .map(|(app, _)| app)
+}
+
+fn audio_app(values: &[String]) -> Option<&'static KnownApp> {
+    KNOWN_APPS.iter().find(|app| {
+        values.iter().any(|value| {
+            let value = value.to_lowercase();
+            app.audio.iter().any(|pattern| value.contains(pattern))
+        })
+    })
+}
+
+/// Application names and binaries of the streams currently recording from
+/// a source, as reported by `pactl`.
+#[cfg(target_os = "linux")]
+fn recording_apps() -> Result<Vec<String>> {
+    let output = std::process::Command::new("pactl")
+        .args(["list", "source-outputs"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "pactl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once(" = ")?;
+            matches!(key, "application.name" | "application.process.binary")
+                .then(|| value.trim_matches('"').to_string())
+        })
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn recording_apps() -> Result<Vec<String>> {
+    Ok(Vec::new())
+}
+
+/// Watches for conferencing apps becoming active, so a meeting isn't left
+/// unrecorded.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    if !enabled_from_env() {
+        return Ok(());
+    }
+    std::thread::Builder::new()
+        .name("meeting_detector".into())
+        .spawn(move || {
+            let mut detector = Detector::default();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                detector.poll(&app);
+            }
+        })?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Detector {
+    /// The meeting app in progress and when it was last seen.
+    active: Option<(&'static str, Instant)>,
+    focus_failed: bool,
+    audio_failed: bool,
+}
+
+impl Detector {
+    fn poll(&mut self, app: &AppHandle) {
+        let manager = app.state::<CaptureManager>();
+        let Some((known, detected_by)) = self.detect() else {
+            if let Some((name, last_seen)) = self.active {
+                if last_seen.elapsed() >= END_GRACE {
+                    self.active = None;
+                    println!("[meetings] {name} meeting ended");
+                    manager.events().emit(CaptureEvent::MeetingEnded { app: name.to_string() });
+                }
+            }
+            return;
+        };
+        let ongoing = self.active.is_some_and(|(name, _)| name == known.name);
+        self.active = Some((known.name, Instant::now()));
+        if ongoing {
+            return;
+        }
+        println!("[meetings] {} meeting detected via {detected_by:?}", known.name);
+
+        // a recording that is already running is left alone
+        let template = auto_template().filter(|_| !manager.status().is_active());
+        let started = template.as_deref().and_then(|name| {
+            let result = templates::resolve(name, manager.displays()).and_then(|options| manager.start_capture(options));
+            match result {
+                Ok(()) => {
+                    println!("[meetings] started template {name}");
+                    Some(name.to_string())
+                }
+                Err(err) => {
+                    eprintln!("[meetings] failed to start template {name}: {err}");
+                    None
+                }
+            }
+        });
+        manager.events().emit(CaptureEvent::MeetingDetected {
+            app: known.name.to_string(),
+            detected_by,
+            recording: manager.status().is_active(),
+            template: started,
+        });
+    }
+
+    fn detect(&mut self) -> Option<(&'static KnownApp, DetectedBy)> {
+        let window = match focus::focused_window() {
+            Ok(window) => {
+                self.focus_failed = false;
+                window
+            }
+            Err(err) => {
+                if !std::mem::replace(&mut self.focus_failed, true) {
+                    eprintln!("[meetings] failed to query the focused window: {err}");
+                }
+                None
+            }
+        };
+        let window_values: Vec<String> = window
+            .into_iter()
+            .flat_map(|window| [window.app_id, window.title])
+            .flatten()
+            .collect();
+        if let Some(known) = window_app(&window_values) {
+            return Some((known, DetectedBy::Window));
+        }
+        match recording_apps() {
+            Ok(apps) => {
+                self.audio_failed = false;
+                audio_app(&apps).map(|known| (known, DetectedBy::Audio))
+            }
+            Err(err) => {
+                if !std::mem::replace(&mut self.audio_failed, true) {
+                    eprintln!("[meetings] failed to list audio streams: {err}");
+                }
+                None
+            }
+        }
+    }
+}
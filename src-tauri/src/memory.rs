@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::Result;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState};
+use crate::events::CaptureEvent;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where the capture path holds chunk data: in the chunk buffers while a
+/// chunk fills, and in the channel to the consumer until it picks them up.
+#[derive(Default)]
+pub struct MemoryStats {
+    queued_chunks: AtomicU64,
+    queued_bytes: AtomicU64,
+    accumulated: Mutex<BTreeMap<String, usize>>,
+}
+
+impl MemoryStats {
+    pub fn reset(&self) {
+        self.queued_chunks.store(0, Ordering::Relaxed);
+        self.queued_bytes.store(0, Ordering::Relaxed);
+        self.accumulated.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// `stream`'s chunk buffer now holds `bytes`.
+    pub fn accumulating(&self, stream: &str, bytes: usize) {
+        let mut accumulated = self.accumulated.lock().unwrap_or_else(PoisonError::into_inner);
+        match accumulated.get_mut(stream) {
+            Some(held) => *held = bytes,
+            None => {
+                accumulated.insert(stream.to_string(), bytes);
+            }
+        }
+    }
+
+    /// A chunk of `bytes` went into the consumer channel.
+    pub fn queued(&self, bytes: usize) {
+        self.queued_chunks.fetch_add(1, Ordering::Relaxed);
+        self.queued_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// The consumer took a chunk of `bytes` off the channel.
+    pub fn dequeued(&self, bytes: usize) {
+        // saturating, since a reset can land between a send and its receipt
+        let _ = self
+            .queued_chunks
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |chunks| Some(chunks.saturating_sub(1)));
+        let _ = self.queued_bytes.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+            Some(queued.saturating_sub(bytes as u64))
+        });
+    }
+
+    pub fn report(&self, pipelines: BTreeMap<String, Vec<QueueLevel>>) -> MemoryReport {
+        let accumulated_bytes = self.accumulated.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        MemoryReport {
+            queued_chunks: self.queued_chunks.load(Ordering::Relaxed),
+            queued_bytes: self.queued_bytes.load(Ordering::Relaxed),
+            accumulated_total_bytes: accumulated_bytes.values().sum(),
+            accumulated_bytes,
+            pipelines,
+            rss_bytes: rss_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryReport {
+    /// Chunks sent to the consumer and not yet picked up.
+    pub queued_chunks: u64,
+    pub queued_bytes: u64,
+    /// Bytes per stream waiting in chunk buffers for the chunk to fill.
+    pub accumulated_bytes: BTreeMap<String, usize>,
+    pub accumulated_total_bytes: usize,
+    /// Fill of every queue element, per pipeline.
+    pub pipelines: BTreeMap<String, Vec<QueueLevel>>,
+    /// Resident memory of the whole process; Linux only.
+    pub rss_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueLevel {
+    pub element: String,
+    pub buffers: u32,
+    pub bytes: u32,
+    pub time_ms: u64,
+    /// Limits of the queue; 0 is unlimited.
+    pub max_buffers: u32,
+    pub max_bytes: u32,
+    pub max_time_ms: u64,
+}
+
+/// Current fill of the `queue` and `queue2` elements in `pipeline`.
+pub fn queue_levels(pipeline: &gst::Pipeline) -> Vec<QueueLevel> {
+    pipeline
+        .iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter(|element| {
+            element
+                .factory()
+                .is_some_and(|factory| matches!(factory.name().as_str(), "queue" | "queue2"))
+        })
+        .map(|element| QueueLevel {
+            element: element.name().to_string(),
+            buffers: element.property("current-level-buffers"),
+            bytes: element.property("current-level-bytes"),
+            time_ms: element.property::<u64>("current-level-time") / 1_000_000,
+            max_buffers: element.property("max-size-buffers"),
+            max_bytes: element.property("max-size-bytes"),
+            max_time_ms: element.property::<u64>("max-size-time") / 1_000_000,
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf takes no pointers; it returns -1 on failure, which the
+    // conversion below rejects
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("memory_stats".into())
+        .spawn(move || loop {
+            std::thread::sleep(REPORT_INTERVAL);
+            let manager = app.state::<CaptureManager>();
+            if manager.status() != CaptureState::Running {
+                continue;
+            }
+            if let Some(report) = manager.memory_report() {
+                manager.events().emit(CaptureEvent::MemoryStats { report });
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::audio_ring::{AudioRing, PcmFormat};
+use crate::capture_manager::Marker;
+use crate::focus::{self, FocusedWindow};
+use crate::frames::{FrameFormat, LatestFrame};
+
+pub const MOMENTS_DIR: &str = "moments";
+const MOMENT_FILE: &str = "moment.json";
+
+/// What to keep of the session at each marker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MomentSettings {
+    /// Mic audio leading up to the marker, taken from the audio ring.
+    #[serde(default = "MomentSettings::default_audio_ms")]
+    pub audio_ms: u64,
+    #[serde(default)]
+    pub frame_format: FrameFormat,
+}
+
+impl MomentSettings {
+    pub const fn default_audio_ms() -> u64 {
+        5_000
+    }
+}
+
+impl Default for MomentSettings {
+    fn default() -> Self {
+        Self {
+            audio_ms: Self::default_audio_ms(),
+            frame_format: FrameFormat::default(),
+        }
+    }
+}
+
+/// The screen, mic and focused window at a marker, kept in
+/// `moments/<marker id>/` apart from the chunks. Paths are relative to the
+/// session directory; parts that weren't available are `None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Moment {
+    pub marker_id: u64,
+    pub label: Option<String>,
+    pub unix_ms: u128,
+    pub offset_ms: u64,
+    #[serde(default)]
+    pub frame: Option<String>,
+    #[serde(default)]
+    pub audio: Option<String>,
+    #[serde(default)]
+    pub window: Option<FocusedWindow>,
+}
+
+/// The running session's moments, for the manifest.
+#[derive(Default)]
+pub struct MomentLog {
+    moments: Mutex<Vec<Moment>>,
+}
+
+impl MomentLog {
+    pub fn reset(&self) {
+        self.moments.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn push(&self, moment: Moment) {
+        self.moments.lock().unwrap_or_else(PoisonError::into_inner).push(moment);
+    }
+
+    pub fn snapshot(&self) -> Vec<Moment> {
+        self.moments.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Writes the moment for `marker` under `session_dir`: the newest frame, the
+/// last `audio_ms` of the mic ring as WAV, and the focused window.
+pub fn capture(
+    marker: &Marker,
+    settings: &MomentSettings,
+    session_dir: &Path,
+    frame: &LatestFrame,
+    ring: &AudioRing,
+) -> Result<Moment> {
+    let relative = Path::new(MOMENTS_DIR).join(marker.id.to_string());
+    let dir = session_dir.join(&relative);
+    std::fs::create_dir_all(&dir)?;
+
+    let frame = match frame.still(settings.frame_format) {
+        Ok((bytes, _)) => {
+            let name = format!("frame.{}", settings.frame_format.extension());
+            std::fs::write(dir.join(&name), bytes)?;
+            Some(relative.join(name).to_string_lossy().to_string())
+        }
+        Err(err) => {
+            eprintln!("[moments] no frame for marker {}: {err}", marker.id);
+            None
+        }
+    };
+    // without `audio_ring_ms` there is simply no audio to keep
+    let audio = match ring.recent(settings.audio_ms) {
+        Ok((format, data, _)) if !data.is_empty() => {
+            std::fs::write(dir.join("audio.wav"), wav(&format, &data))?;
+            Some(relative.join("audio.wav").to_string_lossy().to_string())
+        }
+        _ => None,
+    };
+    let window = focus::focused_window().unwrap_or_else(|err| {
+        eprintln!("[moments] failed to query the focused window: {err}");
+        None
+    });
+
+    let moment = Moment {
+        marker_id: marker.id,
+        label: marker.label.clone(),
+        unix_ms: marker.unix_ms,
+        offset_ms: marker.offset_ms,
+        frame,
+        audio,
+        window,
+    };
+    std::fs::write(dir.join(MOMENT_FILE), serde_json::to_vec_pretty(&moment)?)?;
+    println!("[moments] kept moment for marker {}", marker.id);
+    Ok(moment)
+}
+
+/// `data` behind a RIFF header, so any player opens the snippet.
+fn wav(format: &PcmFormat, data: &[u8]) -> Vec<u8> {
+    // IEEE float for F32LE, integer PCM otherwise
+    let format_tag: u16 = if format.format.as_deref() == Some("F32LE") { 3 } else { 1 };
+    let channels = format.channels.max(1) as u16;
+    let block_align = channels * format.bytes_per_sample as u16;
+    let mut out = Vec::with_capacity(44 + data.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&format_tag.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&format.rate.to_le_bytes());
+    out.extend_from_slice(&(format.rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&(format.bytes_per_sample as u16 * 8).to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    }
+
+    #[test]
+    fn writes_a_pcm_header() {
+        let format = PcmFormat {
+            rate: 48_000,
+            channels: 2,
+            format: Some("S16LE".to_string()),
+            bytes_per_sample: 2,
+        };
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let out = wav(&format, &data);
+        assert_eq!(out.len(), 44 + data.len());
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(u32_at(&out, 4), 36 + data.len() as u32);
+        assert_eq!(&out[8..16], b"WAVEfmt ");
+        assert_eq!(u16_at(&out, 20), 1);
+        assert_eq!(u16_at(&out, 22), 2);
+        assert_eq!(u32_at(&out, 24), 48_000);
+        assert_eq!(u32_at(&out, 28), 48_000 * 4);
+        assert_eq!(u16_at(&out, 32), 4);
+        assert_eq!(u16_at(&out, 34), 16);
+        assert_eq!(&out[36..40], b"data");
+        assert_eq!(u32_at(&out, 40), data.len() as u32);
+        assert_eq!(&out[44..], &data);
+    }
+
+    #[test]
+    fn marks_float_samples() {
+        let format = PcmFormat {
+            rate: 16_000,
+            channels: 0,
+            format: Some("F32LE".to_string()),
+            bytes_per_sample: 4,
+        };
+        let out = wav(&format, &[0; 16]);
+        assert_eq!(u16_at(&out, 20), 3);
+        // no channel count means mono
+        assert_eq!(u16_at(&out, 22), 1);
+        assert_eq!(u16_at(&out, 34), 32);
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+fn make(factory: &str, name: String) -> Result<gst::Element> {
+    gst::ElementFactory::make(factory)
+        .name(name)
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element '{factory}' — install gst-plugin-ndi and the NDI runtime"))
+}
+
+/// A source bin receiving the video of the NDI stream `ndi_name`. The demuxer
+/// only exposes its pads once the stream is discovered, so the bin's ghost
+/// pad is retargeted when the video pad appears.
+pub fn source(ndi_name: &str, name: &str) -> Result<gst::Element> {
+    let bin = gst::Bin::with_name(name);
+    let src = make("ndisrc", format!("{name}_ndi"))?;
+    src.set_property("ndi-name", ndi_name);
+    let demux = make("ndisrcdemux", format!("{name}_demux"))?;
+    bin.add_many([&src, &demux])?;
+    src.link(&demux)?;
+
+    let ghost = gst::GhostPad::builder(gst::PadDirection::Src).name("src").build();
+    bin.add_pad(&ghost)?;
+    let target = ghost.clone();
+    demux.connect_pad_added(move |_, pad| {
+        if pad.name().starts_with("video") {
+            if let Err(err) = target.set_target(Some(pad)) {
+                eprintln!("[ndi] failed to expose NDI video pad: {err}");
+            }
+        }
+    });
+    println!("[ndi] receiving from NDI source '{ndi_name}'");
+    Ok(bin.upcast())
+}
+
+/// Branches the video off `tee` into an NDI sender published as `sender_name`.
+pub fn add_sender(pipeline: &gst::Pipeline, tee: &gst::Element, sender_name: &str) -> Result<()> {
+    let queue = make("queue", "ndi_sender_queue".into())?;
+    // never let a slow NDI receiver hold back the recording
+    queue.set_property_from_str("leaky", "downstream");
+    let convert = make("videoconvert", "ndi_convert".into())?;
+    let sink = make("ndisink", "ndi_sink".into())?;
+    sink.set_property("ndi-name", sender_name);
+    sink.set_property("sync", false);
+
+    pipeline.add_many([&queue, &convert, &sink])?;
+    gst::Element::link_many([tee, &queue, &convert, &sink])?;
+    println!("[ndi] publishing capture as NDI sender '{sender_name}'");
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::capture_manager::CaptureManager;
+use crate::events::CaptureEvent;
+
+/// Notifications are on by default; `SC_NOTIFICATIONS=0` turns them off.
+pub fn enabled_from_env() -> bool {
+    !matches!(
+        std::env::var("SC_NOTIFICATIONS").as_deref(),
+        Ok("0") | Ok("false") | Ok("off")
+    )
+}
+
+pub fn install(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<CaptureManager>().events().subscribe(move |event| {
+        let (title, body) = match event {
+            CaptureEvent::SessionStopped {
+                duration_ms,
+                output_dir,
+                ..
+            } => {
+                let mut body = format!("Recorded {}", format_duration(*duration_ms));
+                if let Some(dir) = output_dir {
+                    body.push_str(&format!(" — saved to {dir}"));
+                }
+                ("Recording finished", body)
+            }
+            CaptureEvent::SessionFailed { error, .. } => ("Recording failed", error.clone()),
+            CaptureEvent::MeetingDetected {
+                app,
+                recording: false,
+                ..
+            } => ("Meeting detected", format!("{app} looks active — start recording?")),
+            _ => return,
+        };
+        if let Err(err) = handle.notification().builder().title(title).body(body).show() {
+            eprintln!("[notifications] failed to show notification: {err}");
+        }
+    });
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    let total_secs = duration_ms / 1000;
+    let (hours, minutes, seconds) = (total_secs / 3600, (total_secs / 60) % 60, total_secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
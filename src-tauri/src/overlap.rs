@@ -0,0 +1,124 @@
+use serde_json::json;
+
+use crate::capture_manager::CapturedChunk;
+use crate::proxy;
+
+/// How much of the start of `chunk` repeats the end of the chunk before it.
+pub fn overlap_ms(chunk: &CapturedChunk) -> u64 {
+    chunk.metadata["overlap_ms"].as_u64().unwrap_or_default()
+}
+
+/// The end of `chunk` covering at least `overlap_ms`, in whole frames, with
+/// the time it covers. `None` when the chunk has nothing to carry over or its
+/// metadata doesn't say how its bytes split into frames.
+pub fn tail(chunk: &CapturedChunk, overlap_ms: u64) -> Option<(Vec<u8>, u64)> {
+    let meta = &chunk.metadata;
+    let (frame_bytes, frame_ms) = if proxy::is_video(&chunk.kind) {
+        let frame_size = meta["frame_size"].as_u64().filter(|size| *size > 0)?;
+        let frames = chunk.data.len() as u64 / frame_size;
+        (frame_size, chunk.duration_ms as f64 / frames.max(1) as f64)
+    } else {
+        let rate = meta["rate"].as_u64().filter(|rate| *rate > 0)?;
+        let channels = meta["channels"].as_u64().unwrap_or(1).max(1);
+        let bytes_per_sample = meta["bytes_per_sample"].as_u64().filter(|bytes| *bytes > 0)?;
+        (channels * bytes_per_sample, 1000.0 / rate as f64)
+    };
+    let frames = (chunk.data.len() as u64 / frame_bytes).min((overlap_ms as f64 / frame_ms).ceil() as u64);
+    if frames == 0 {
+        return None;
+    }
+    let bytes = (frames * frame_bytes) as usize;
+    let tail = chunk.data[chunk.data.len() - bytes..].to_vec();
+    Some((tail, (frames as f64 * frame_ms).round() as u64))
+}
+
+/// Records in `metadata` that the chunk starts with `bytes` (`ms` long)
+/// carried over from the chunk before.
+pub fn mark(metadata: &mut serde_json::Value, bytes: usize, ms: u64) {
+    if let Some(object) = metadata.as_object_mut() {
+        object.insert("overlap_ms".to_string(), json!(ms));
+        object.insert("overlap_bytes".to_string(), json!(bytes));
+    }
+}
+
+/// `chunk` without the stretch it repeats from the chunk before, for
+/// outputs that play chunks back to back; `None` when it has no overlap.
+pub fn trim(chunk: &CapturedChunk) -> Option<CapturedChunk> {
+    let bytes = chunk.metadata["overlap_bytes"].as_u64().filter(|bytes| *bytes > 0)? as usize;
+    let overlap_ms = overlap_ms(chunk);
+    let data = chunk.data.get(bytes..).unwrap_or_default().to_vec();
+    let mut metadata = chunk.metadata.clone();
+    if let Some(object) = metadata.as_object_mut() {
+        object.remove("overlap_ms");
+        object.remove("overlap_bytes");
+    }
+    Some(CapturedChunk {
+        id: chunk.id,
+        kind: chunk.kind.clone(),
+        start_ts_unix_nanos: chunk.start_ts_unix_nanos + overlap_ms as u128 * 1_000_000,
+        duration_ms: chunk.duration_ms.saturating_sub(overlap_ms),
+        metadata,
+        data_len: data.len(),
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &str, metadata: serde_json::Value, data: Vec<u8>) -> CapturedChunk {
+        CapturedChunk {
+            id: 3,
+            kind: kind.to_string(),
+            start_ts_unix_nanos: 10_000_000_000,
+            duration_ms: 1000,
+            metadata,
+            data_len: data.len(),
+            data,
+        }
+    }
+
+    #[test]
+    fn audio_tail_is_whole_samples() {
+        let audio = chunk(
+            "audio",
+            json!({ "rate": 1000, "channels": 2, "bytes_per_sample": 2 }),
+            (0..4000).map(|byte| byte as u8).collect(),
+        );
+        let (bytes, ms) = tail(&audio, 100).unwrap();
+        assert_eq!(ms, 100);
+        assert_eq!(bytes, audio.data[3600..]);
+    }
+
+    #[test]
+    fn video_tail_rounds_up_to_whole_frames() {
+        let video = chunk("video", json!({ "frame_size": 16 }), (0..64).collect());
+        let (bytes, ms) = tail(&video, 300).unwrap();
+        assert_eq!(ms, 500);
+        assert_eq!(bytes, (32..64).collect::<Vec<u8>>());
+        // never more than the chunk holds
+        assert_eq!(tail(&video, 5000).unwrap().1, 1000);
+    }
+
+    #[test]
+    fn no_tail_without_frame_layout_or_overlap() {
+        assert!(tail(&chunk("audio", json!({}), vec![0; 100]), 100).is_none());
+        assert!(tail(&chunk("video", json!({ "frame_size": 16 }), vec![0; 64]), 0).is_none());
+    }
+
+    #[test]
+    fn trim_drops_the_marked_overlap() {
+        let mut metadata = json!({ "frame_size": 16 });
+        mark(&mut metadata, 32, 500);
+        let video = chunk("video", metadata, (0..64).collect());
+        assert_eq!(overlap_ms(&video), 500);
+        let trimmed = trim(&video).unwrap();
+        assert_eq!(trimmed.data, (32..64).collect::<Vec<u8>>());
+        assert_eq!(trimmed.data_len, 32);
+        assert_eq!(trimmed.duration_ms, 500);
+        assert_eq!(trimmed.start_ts_unix_nanos, 10_500_000_000);
+        assert_eq!(trimmed.metadata, json!({ "frame_size": 16 }));
+        assert!(trim(&trimmed).is_none());
+    }
+}
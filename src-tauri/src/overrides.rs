@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use serde_json::Value;
+
+/// Element name -> property name -> value, e.g.
+/// `{"video_enc": {"tune": "zerolatency"}, "mic_audio_source": {"buffer-time": 20000}}`.
+pub type ElementOverrides = HashMap<String, HashMap<String, Value>>;
+
+/// Sets the overrides for every element of `pipeline` they name and returns
+/// the element names that were found. Names that belong to another pipeline
+/// are skipped; a bad property or value is an error.
+pub fn apply(pipeline: &gst::Pipeline, overrides: &ElementOverrides) -> Result<Vec<String>> {
+    let mut applied = Vec::new();
+    for (element_name, properties) in overrides {
+        let Some(element) = pipeline.by_name(element_name) else {
+            continue;
+        };
+        for (property, value) in properties {
+            set_property(&element, property, value)
+                .map_err(|err| anyhow!("override {element_name}.{property}: {err}"))?;
+            println!("[overrides] {element_name}.{property} = {value}");
+        }
+        applied.push(element_name.clone());
+    }
+    Ok(applied)
+}
+
+fn set_property(element: &gst::Element, property: &str, value: &Value) -> Result<()> {
+    let pspec = element
+        .find_property(property)
+        .ok_or_else(|| anyhow!("no such property"))?;
+    let flags = pspec.flags();
+    if !flags.contains(glib::ParamFlags::WRITABLE) || flags.contains(glib::ParamFlags::CONSTRUCT_ONLY) {
+        return Err(anyhow!("property is not writable"));
+    }
+    // go through the GStreamer string syntax so enums, flags, caps and
+    // numbers of any width are all handled the same way
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let parsed = glib::Value::deserialize(&text, pspec.value_type())
+        .map_err(|_| anyhow!("cannot parse {value} as {}", pspec.value_type().name()))?;
+    element.set_property_from_value(property, &parsed);
+    Ok(())
+}
+
+/// Warns about override targets that matched no element in any pipeline.
+pub fn warn_unmatched(overrides: &ElementOverrides, matched: &[String]) {
+    for name in overrides.keys().filter(|name| !matched.contains(name)) {
+        eprintln!("[overrides] no pipeline element named '{name}'");
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs;
+
+const DEFAULT_STORE_FILE: &str = "screen_permissions.json";
+
+/// A portal restore token: lets the next capture reuse the screen the user
+/// picked instead of showing the picker again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedScreenPermission {
+    pub token: String,
+    /// What the selection covers, e.g. `monitor`.
+    pub source: String,
+    pub saved_unix_ms: u128,
+}
+
+/// Kept in the app data directory; `SC_SCREEN_PERMISSIONS_FILE` overrides it.
+fn store_path() -> Result<PathBuf> {
+    match std::env::var_os("SC_SCREEN_PERMISSIONS_FILE") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_dirs::data_file(DEFAULT_STORE_FILE),
+    }
+}
+
+pub fn list() -> Result<Vec<SavedScreenPermission>> {
+    let path = store_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Tokens grant screen access, so the file is readable by the owner only.
+fn write(permissions: &[SavedScreenPermission]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(store_path()?)?
+        .write_all(&serde_json::to_vec_pretty(permissions)?)?;
+    Ok(())
+}
+
+/// The token to offer the portal for `source`, if one was saved.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn latest(source: &str) -> Option<String> {
+    list()
+        .ok()?
+        .into_iter()
+        .find(|permission| permission.source == source)
+        .map(|permission| permission.token)
+}
+
+/// Portal tokens are single-use, so a new one replaces the previous token
+/// for the same source.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn save(source: &str, token: &str) -> Result<()> {
+    let mut permissions = list().unwrap_or_default();
+    permissions.retain(|permission| permission.source != source);
+    permissions.push(SavedScreenPermission {
+        token: token.to_string(),
+        source: source.to_string(),
+        saved_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default(),
+    });
+    write(&permissions)
+}
+
+/// Forgets `token`, so the next capture shows the picker again. Returns
+/// whether it was saved.
+pub fn clear(token: &str) -> Result<bool> {
+    let mut permissions = list()?;
+    let before = permissions.len();
+    permissions.retain(|permission| permission.token != token);
+    if permissions.len() == before {
+        return Ok(false);
+    }
+    write(&permissions)?;
+    #[cfg(target_os = "linux")]
+    crate::portal::revoke(token);
+    Ok(true)
+}
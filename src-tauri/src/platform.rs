@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use serde::Serialize;
+
+use crate::capture_manager::ensure_gstreamer_initialized;
+
+/// Why mobile builds can't capture yet; they need MediaProjection (Android)
+/// or ReplayKit (iOS) backends, which live outside GStreamer.
+const MOBILE_UNSUPPORTED: &str = "no capture backend for this platform yet";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capability {
+    pub feature: &'static str,
+    pub supported: bool,
+    /// Set when `supported` is false.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformCapabilities {
+    /// `linux`, `android`, `ios`, ...
+    pub platform: &'static str,
+    pub features: Vec<Capability>,
+}
+
+/// Each feature with the GStreamer element it depends on.
+const FEATURES: [(&str, &str); 5] = [
+    ("screen_capture", "pipewiresrc"),
+    ("window_capture", "pipewiresrc"),
+    ("system_audio", "pulsesrc"),
+    ("microphone", "pulsesrc"),
+    ("capture_devices", "v4l2src"),
+];
+
+/// What this build can capture on this machine, so callers can hide what
+/// isn't there instead of finding out from a failed start.
+pub fn probe() -> PlatformCapabilities {
+    let platform = std::env::consts::OS;
+    let unavailable = unavailable_reason();
+    let features = FEATURES
+        .iter()
+        .map(|&(feature, element)| {
+            let reason = match &unavailable {
+                Some(reason) => Some(reason.clone()),
+                None if gst::ElementFactory::find(element).is_none() => {
+                    Some(format!("GStreamer element '{element}' is not installed"))
+                }
+                None => None,
+            };
+            Capability {
+                feature,
+                supported: reason.is_none(),
+                reason,
+            }
+        })
+        .collect();
+    PlatformCapabilities { platform, features }
+}
+
+/// Fails early, with the reason, where no capture can work at all.
+pub fn ensure_supported() -> Result<()> {
+    if cfg!(mobile) {
+        return Err(anyhow!(
+            "capture is not supported on {}: {MOBILE_UNSUPPORTED}",
+            std::env::consts::OS
+        ));
+    }
+    Ok(())
+}
+
+fn unavailable_reason() -> Option<String> {
+    if let Err(err) = ensure_supported() {
+        return Some(err.to_string());
+    }
+    ensure_gstreamer_initialized()
+        .err()
+        .map(|err| format!("GStreamer is unavailable: {err}"))
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::Result;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, CaptureTarget, PauseReason};
+use crate::events::CaptureEvent;
+use crate::focus::{self, FocusedWindow};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    #[default]
+    Pause,
+    /// Keep recording, with every video frame replaced by black.
+    Blank,
+}
+
+/// Applications that must never be recorded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppPolicy {
+    /// App ids (Wayland app id or X11 class), matched case-insensitively.
+    #[serde(default)]
+    pub denylist: Vec<String>,
+    #[serde(default)]
+    pub action: PolicyAction,
+}
+
+impl AppPolicy {
+    pub fn denies(&self, window: &FocusedWindow) -> bool {
+        window.app_id.as_deref().is_some_and(|app_id| {
+            self.denylist
+                .iter()
+                .any(|denied| denied.eq_ignore_ascii_case(app_id))
+        })
+    }
+}
+
+/// Shared between the policy thread and the video pipeline's blanking probe.
+#[derive(Default)]
+pub struct PolicyState {
+    blanked: Arc<AtomicBool>,
+    /// The app currently blocked, if any.
+    blocked: Mutex<Option<String>>,
+    /// Windows seen showing a denylisted app, by PipeWire node id as in
+    /// [`CaptureTarget::Window`], so a window target stays blocked after it
+    /// loses focus.
+    denied_windows: Mutex<BTreeMap<String, String>>,
+}
+
+impl PolicyState {
+    /// Starts a session knowing `denied` target windows up front; with
+    /// `blank`, frames are black from the first one.
+    pub fn reset(&self, denied: BTreeMap<String, String>, blank: bool) {
+        self.blanked.store(blank && !denied.is_empty(), Ordering::Relaxed);
+        *self.blocked.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        *self.denied_windows.lock().unwrap_or_else(PoisonError::into_inner) = denied;
+    }
+
+    /// Blacks out every buffer leaving `pad` while the policy blanks capture.
+    pub fn add_blanking_probe(&self, pad: &gst::Pad) {
+        let blanked = Arc::clone(&self.blanked);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            if !blanked.load(Ordering::Relaxed) {
+                return gst::PadProbeReturn::Ok;
+            }
+            if let Some(gst::PadProbeData::Buffer(buffer)) = &mut info.data {
+                if let Ok(mut map) = buffer.make_mut().map_writable() {
+                    map.as_mut_slice().fill(0);
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+/// The windows of a `window` or `windows` target that show a denylisted app,
+/// by node id, so the session can be refused or blanked before it records.
+pub fn denied_targets(policy: &AppPolicy, target: &CaptureTarget) -> Result<BTreeMap<String, String>> {
+    let ids: Vec<&String> = match target {
+        CaptureTarget::Window { id } => vec![id],
+        CaptureTarget::Windows { ids, .. } => ids.iter().collect(),
+        _ => return Ok(BTreeMap::new()),
+    };
+    if policy.denylist.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(focus::list_windows()?
+        .into_iter()
+        .filter(|window| policy.denies(window))
+        .filter_map(|window| {
+            let node_id = window.node_id?.to_string();
+            ids.contains(&&node_id).then(|| (node_id, window.label().to_string()))
+        })
+        .collect())
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("app_policy".into())
+        .spawn(move || {
+            let mut backend_failed = false;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                poll(&app, &mut backend_failed);
+            }
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle, backend_failed: &mut bool) {
+    let manager = app.state::<CaptureManager>();
+    let options = manager.options();
+    let Some(policy) = options.app_policy.filter(|policy| !policy.denylist.is_empty()) else {
+        return;
+    };
+    if !matches!(manager.status(), CaptureState::Running | CaptureState::Paused) {
+        return;
+    }
+    let focused = match focus::focused_window() {
+        Ok(focused) => {
+            *backend_failed = false;
+            focused
+        }
+        Err(err) => {
+            if !std::mem::replace(backend_failed, true) {
+                eprintln!("[policy] failed to query the focused window: {err}");
+            }
+            return;
+        }
+    };
+    let state = manager.policy();
+    let mut denied_windows = state.denied_windows.lock().unwrap_or_else(PoisonError::into_inner);
+    let denied_focus = focused.as_ref().filter(|window| policy.denies(window));
+    if let Some((window, node_id)) = denied_focus.and_then(|window| Some((window, window.node_id?))) {
+        denied_windows.insert(node_id.to_string(), window.label().to_string());
+    }
+    // what the capture shows: a fixed window, or whatever has focus on screen
+    let blocked_app = match &options.target {
+        CaptureTarget::Window { id } => denied_windows.get(id).cloned(),
+        CaptureTarget::Windows { ids, .. } => ids.iter().find_map(|id| denied_windows.get(id).cloned()),
+        CaptureTarget::FullDisplay | CaptureTarget::FollowFocus { .. } => {
+            denied_focus.map(|window| window.label().to_string())
+        }
+        _ => None,
+    };
+    drop(denied_windows);
+
+    let mut blocked = state.blocked.lock().unwrap_or_else(PoisonError::into_inner);
+    if *blocked == blocked_app {
+        return;
+    }
+    match (&*blocked, &blocked_app) {
+        (None, Some(app_id)) => {
+            println!("[policy] {app_id} is denylisted, action={:?}", policy.action);
+            let _ = manager.add_auto_marker(format!("blocked: {app_id}"));
+            match policy.action {
+                // also while already paused, e.g. for idleness, so resuming
+                // from that doesn't show the app
+                PolicyAction::Pause => {
+                    if let Err(err) = manager.pause_for(PauseReason::Policy) {
+                        eprintln!("[policy] failed to pause: {err}");
+                    }
+                }
+                PolicyAction::Blank => state.blanked.store(true, Ordering::Relaxed),
+            }
+        }
+        (Some(app_id), None) => {
+            println!("[policy] {app_id} left the capture");
+            state.blanked.store(false, Ordering::Relaxed);
+            if let Err(err) = manager.resume_for(PauseReason::Policy) {
+                eprintln!("[policy] failed to resume: {err}");
+            }
+        }
+        // one denylisted app replaced another; capture stays blocked
+        _ => {}
+    }
+    let app_id = blocked_app.clone().or_else(|| blocked.clone());
+    let now_blocked = blocked_app.is_some();
+    *blocked = blocked_app;
+    drop(blocked);
+    manager.events().emit(CaptureEvent::AppBlocked {
+        blocked: now_blocked,
+        app_id,
+        action: policy.action,
+    });
+}
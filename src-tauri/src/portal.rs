@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{DeserializeDict, DynamicType, ObjectPath, OwnedFd, OwnedObjectPath, OwnedValue, Type, Value};
+
+use crate::permissions;
+
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREEN_CAST: &str = "org.freedesktop.portal.ScreenCast";
+const MONITOR_SOURCE: u32 = 1;
+const PERSIST_UNTIL_REVOKED: u32 = 2;
+/// Key under which monitor selections are saved in [`permissions`].
+const MONITOR_PERMISSION: &str = "monitor";
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+fn handle_token() -> String {
+    format!("sc{}_{}", std::process::id(), NEXT_TOKEN.fetch_add(1, Ordering::Relaxed))
+}
+
+#[derive(DeserializeDict, Type)]
+#[zvariant(signature = "dict")]
+struct CreateSessionResults {
+    session_handle: String,
+}
+
+#[derive(DeserializeDict, Type)]
+#[zvariant(signature = "dict")]
+struct StartResults {
+    streams: Option<Vec<(u32, HashMap<String, OwnedValue>)>>,
+    restore_token: Option<String>,
+}
+
+/// A granted screen cast; the stream stays available while this is alive.
+pub struct ScreenCastSession {
+    conn: Connection,
+    session: OwnedObjectPath,
+    /// Where the granted monitor sits, as the portal reports it.
+    pub position: Option<(i32, i32)>,
+    pub size: Option<(i32, i32)>,
+    node_id: u32,
+    // pipewiresrc duplicates the descriptor, but keep ours open for the session
+    _remote: OwnedFd,
+}
+
+impl Drop for ScreenCastSession {
+    fn drop(&mut self) {
+        let closed = Proxy::new(&self.conn, PORTAL_DEST, self.session.clone(), "org.freedesktop.portal.Session")
+            .and_then(|session| session.call_method("Close", &()));
+        if let Err(err) = closed {
+            eprintln!("[portal] failed to close screen cast session: {err}");
+        }
+    }
+}
+
+/// Calls a ScreenCast method and waits for the portal's `Response` signal,
+/// subscribing before the call so the reply can't be missed.
+fn request<B, R>(conn: &Connection, method: &str, token: &str, body: &B) -> Result<R>
+where
+    B: Serialize + DynamicType,
+    R: DeserializeOwned + Type,
+{
+    let sender = conn
+        .unique_name()
+        .ok_or_else(|| anyhow!("session bus connection has no name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request = Proxy::new(
+        conn,
+        PORTAL_DEST,
+        format!("{PORTAL_PATH}/request/{sender}/{token}"),
+        "org.freedesktop.portal.Request",
+    )?;
+    let mut responses = request.receive_signal("Response")?;
+    Proxy::new(conn, PORTAL_DEST, PORTAL_PATH, SCREEN_CAST)?.call_method(method, body)?;
+    let message = responses
+        .next()
+        .ok_or_else(|| anyhow!("portal dropped the {method} request"))?;
+    let (code, results): (u32, R) = message.body().deserialize()?;
+    match code {
+        0 => Ok(results),
+        1 => Err(anyhow!("screen sharing was cancelled")),
+        _ => Err(anyhow!("screen cast portal {method} failed ({code})")),
+    }
+}
+
+/// Asks the ScreenCast portal for a monitor and points `src` (a
+/// `pipewiresrc`) at the granted stream. A saved restore token skips the
+/// picker; the new token the portal hands out is saved for next time.
+///
+/// Returns `Ok(None)` when no portal is available, in which case `src` keeps
+/// connecting to the default PipeWire daemon as before.
+pub fn attach(src: &gst::Element) -> Result<Option<ScreenCastSession>> {
+    let (conn, session_handle) = match create_session() {
+        Ok(created) => created,
+        Err(err) => {
+            eprintln!("[portal] screen cast portal unavailable, using default PipeWire source: {err}");
+            return Ok(None);
+        }
+    };
+    let session = ObjectPath::try_from(session_handle)?;
+
+    let select_token = handle_token();
+    let mut select: HashMap<&str, Value> = HashMap::from([
+        ("handle_token", Value::from(select_token.as_str())),
+        ("types", Value::from(MONITOR_SOURCE)),
+        ("multiple", Value::from(false)),
+        ("persist_mode", Value::from(PERSIST_UNTIL_REVOKED)),
+    ]);
+    let saved = permissions::latest(MONITOR_PERMISSION);
+    if let Some(token) = &saved {
+        select.insert("restore_token", Value::from(token.as_str()));
+    }
+    request::<_, HashMap<String, OwnedValue>>(&conn, "SelectSources", &select_token, &(&session, select))?;
+
+    let start_token = handle_token();
+    let start: HashMap<&str, Value> = HashMap::from([("handle_token", Value::from(start_token.as_str()))]);
+    let started: StartResults = request(&conn, "Start", &start_token, &(&session, "", start))?;
+    if let Some(token) = &started.restore_token {
+        if let Err(err) = permissions::save(MONITOR_PERMISSION, token) {
+            eprintln!("[portal] failed to save restore token: {err}");
+        }
+    }
+    let (node_id, properties) = started
+        .streams
+        .and_then(|streams| streams.into_iter().next())
+        .ok_or_else(|| anyhow!("screen cast portal granted no stream"))?;
+
+    let remote: OwnedFd = Proxy::new(&conn, PORTAL_DEST, PORTAL_PATH, SCREEN_CAST)?
+        .call("OpenPipeWireRemote", &(&session, HashMap::<&str, Value>::new()))?;
+    src.set_property("fd", remote.as_raw_fd());
+    src.set_property("path", node_id.to_string());
+    println!(
+        "[portal] capturing PipeWire node {node_id}{}",
+        if saved.is_some() { " (restored selection)" } else { "" }
+    );
+    Ok(Some(ScreenCastSession {
+        conn,
+        session: session.into(),
+        position: pair(&properties, "position"),
+        size: pair(&properties, "size"),
+        node_id,
+        _remote: remote,
+    }))
+}
+
+impl ScreenCastSession {
+    /// Points another `pipewiresrc` at the granted stream, over a fresh
+    /// PipeWire remote; no picker and no new grant.
+    pub fn reattach(&mut self, src: &gst::Element) -> Result<()> {
+        let remote: OwnedFd = Proxy::new(&self.conn, PORTAL_DEST, PORTAL_PATH, SCREEN_CAST)?
+            .call("OpenPipeWireRemote", &(&self.session, HashMap::<&str, Value>::new()))?;
+        src.set_property("fd", remote.as_raw_fd());
+        src.set_property("path", self.node_id.to_string());
+        self._remote = remote;
+        println!("[portal] capturing PipeWire node {} (kept session)", self.node_id);
+        Ok(())
+    }
+}
+
+/// An `(ii)` stream property such as `position` or `size`.
+fn pair(properties: &HashMap<String, OwnedValue>, key: &str) -> Option<(i32, i32)> {
+    match &**properties.get(key)? {
+        Value::Structure(fields) => match fields.fields() {
+            [Value::I32(first), Value::I32(second)] => Some((*first, *second)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn create_session() -> Result<(Connection, String)> {
+    let conn = Connection::session()?;
+    let token = handle_token();
+    let options: HashMap<&str, Value> = HashMap::from([
+        ("handle_token", Value::from(token.as_str())),
+        ("session_handle_token", Value::from(handle_token())),
+    ]);
+    let created: CreateSessionResults = request(&conn, "CreateSession", &token, &(options,))?;
+    Ok((conn, created.session_handle))
+}
+
+/// Best-effort removal of the portal's own record of `token`, so it can't be
+/// restored even if the token leaked.
+pub fn revoke(token: &str) {
+    let deleted = Connection::session().and_then(|conn| {
+        Proxy::new(
+            &conn,
+            "org.freedesktop.impl.portal.PermissionStore",
+            "/org/freedesktop/impl/portal/PermissionStore",
+            "org.freedesktop.impl.portal.PermissionStore",
+        )?
+        .call_method("Delete", &("screencast", token))
+        .map(|_| ())
+    });
+    if let Err(err) = deleted {
+        eprintln!("[portal] could not remove the token from the permission store: {err}");
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::capture_manager::{CaptureManager, CaptureState, PauseReason};
+use crate::events::CaptureEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryAction {
+    #[default]
+    ReduceFramerate,
+    Pause,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryPolicy {
+    #[serde(default = "BatteryPolicy::default_threshold_percent")]
+    pub threshold_percent: u8,
+    #[serde(default)]
+    pub action: BatteryAction,
+    #[serde(default = "BatteryPolicy::default_reduced_framerate")]
+    pub reduced_framerate: u32,
+}
+
+impl BatteryPolicy {
+    pub const fn default_threshold_percent() -> u8 {
+        20
+    }
+
+    pub const fn default_reduced_framerate() -> u32 {
+        5
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PowerStatus {
+    on_battery: bool,
+    capacity_percent: u8,
+}
+
+/// Reads the first battery under sysfs; `None` on desktops without one.
+fn read_power_status() -> Option<PowerStatus> {
+    let entries = std::fs::read_dir(POWER_SUPPLY_DIR).ok()?;
+    let mut battery = None;
+    let mut ac_online = None;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read_trimmed(&path.join("type")).as_deref() {
+            Some("Battery") if battery.is_none() => battery = Some(path),
+            Some("Mains") => {
+                ac_online = read_trimmed(&path.join("online")).map(|value| value == "1");
+            }
+            _ => {}
+        }
+    }
+    let battery = battery?;
+    let capacity_percent = read_trimmed(&battery.join("capacity"))?.parse().ok()?;
+    let on_battery = match ac_online {
+        Some(online) => !online,
+        None => read_trimmed(&battery.join("status")).as_deref() == Some("Discharging"),
+    };
+    Some(PowerStatus {
+        on_battery,
+        capacity_percent,
+    })
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("power_monitor".into())
+        .spawn(move || {
+            let mut degraded: Option<BatteryAction> = None;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                degraded = poll(&app, degraded);
+            }
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle, degraded: Option<BatteryAction>) -> Option<BatteryAction> {
+    let manager = app.state::<CaptureManager>();
+    let state = manager.status();
+    if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+        return None;
+    }
+    let Some(policy) = manager.options().battery_policy else {
+        return None;
+    };
+    let Some(power) = read_power_status() else {
+        return degraded;
+    };
+    let low = power.on_battery && power.capacity_percent <= policy.threshold_percent;
+
+    match (low, degraded) {
+        (true, None) => {
+            let reason = format!(
+                "on battery at {}% (threshold {}%)",
+                power.capacity_percent, policy.threshold_percent
+            );
+            // the cap composes with the target's rate and any adaptive step-down
+            let applied = match policy.action {
+                BatteryAction::ReduceFramerate => manager
+                    .set_battery_framerate(Some(policy.reduced_framerate))
+                    .map(|quality| Some(quality.framerate)),
+                BatteryAction::Pause => manager.pause_for(PauseReason::Battery).map(|_| None),
+            };
+            let framerate = match applied {
+                Ok(framerate) => framerate,
+                Err(err) => {
+                    eprintln!("[power] failed to degrade capture: {err}");
+                    return None;
+                }
+            };
+            println!("[power] degrading capture: {reason}");
+            manager.events().emit(CaptureEvent::CaptureDegraded {
+                reason,
+                framerate,
+                paused: policy.action == BatteryAction::Pause,
+            });
+            Some(policy.action)
+        }
+        (false, Some(action)) => {
+            let restored = match action {
+                BatteryAction::ReduceFramerate => manager.set_battery_framerate(None).map(|_| ()),
+                BatteryAction::Pause => manager.resume_for(PauseReason::Battery),
+            };
+            if let Err(err) = restored {
+                eprintln!("[power] failed to restore capture: {err}");
+                return degraded;
+            }
+            println!("[power] power restored, capture back to normal");
+            manager.events().emit(CaptureEvent::CaptureRestored {
+                reason: "no longer on low battery".to_string(),
+            });
+            None
+        }
+        _ => degraded,
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::captions::LiveCaptions;
+use crate::muxer::{EncoderSettings, MuxTarget, MuxWriter, HLS_PLAYLIST};
+use crate::overrides::ElementOverrides;
+use crate::session::TrackInfo;
+
+const PREVIEW_DIR: &str = "preview";
+
+/// Where a player can pick up the running session.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewPlayback {
+    /// HLS playlist that keeps growing until the session stops.
+    pub playlist: String,
+    /// Position to seek to, clamped to what has been recorded.
+    pub offset_ms: u64,
+    /// Length of the session so far; the playlist trails it by a segment or two.
+    pub recorded_ms: u64,
+}
+
+pub fn preview_dir(session_dir: &Path) -> PathBuf {
+    session_dir.join(PREVIEW_DIR)
+}
+
+pub fn playlist_path(session_dir: &Path) -> PathBuf {
+    preview_dir(session_dir).join(HLS_PLAYLIST)
+}
+
+/// An HLS writer for the session's chunks. Speed matters more than size
+/// here, since it encodes next to the real outputs. `captions` are drawn
+/// over the video as they come in.
+pub fn start_writer(
+    session_dir: &Path,
+    tracks: &[TrackInfo],
+    origin_unix_nanos: u128,
+    element_overrides: &ElementOverrides,
+    captions: Option<&Arc<LiveCaptions>>,
+) -> Result<MuxWriter> {
+    let encoder = EncoderSettings {
+        speed_preset: Some("ultrafast".into()),
+        ..EncoderSettings::default()
+    };
+    MuxWriter::with_captions(
+        MuxTarget::Hls(&preview_dir(session_dir)),
+        tracks,
+        origin_unix_nanos,
+        element_overrides,
+        &encoder,
+        captions,
+    )
+}
@@ -0,0 +1,273 @@
+use std::process::Command;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Rect;
+
+/// Emails and 13-19 digit card numbers, optionally grouped by spaces or dashes.
+const BUILTIN_PII_PATTERNS: &[&str] = &[
+    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+    r"\b(?:\d[ -]?){12,18}\d\b",
+];
+/// Side of the pixelation blocks, in frame pixels.
+const PIXEL_BLOCK: usize = 16;
+/// Extra margin around detected text, since OCR boxes hug the glyphs.
+const REGION_MARGIN: u32 = 4;
+/// A scan older than this many intervals no longer vouches for the frames,
+/// which are blanked until the next one lands.
+const STALE_INTERVALS: u32 = 2;
+
+/// Blurs sensitive content before frames reach any output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyFilter {
+    /// Blur faces with OpenCV's `faceblur` (gst-plugins-bad).
+    #[serde(default)]
+    pub blur_faces: bool,
+    /// Pixelate on-screen text matching PII patterns, found by OCR with the
+    /// `tesseract` CLI.
+    #[serde(default)]
+    pub redact_pii: bool,
+    /// Regexes matched in addition to the built-in email and card patterns.
+    #[serde(default)]
+    pub pii_patterns: Vec<String>,
+    #[serde(default = "PrivacyFilter::default_ocr_interval_ms")]
+    pub ocr_interval_ms: u64,
+}
+
+impl PrivacyFilter {
+    pub const fn default_ocr_interval_ms() -> u64 {
+        2_000
+    }
+}
+
+/// `faceblur` only takes RGB, so it gets its own converter.
+pub fn face_blur() -> Result<[gst::Element; 2]> {
+    let convert = gst::ElementFactory::make("videoconvert")
+        .name("privacy_convert")
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'videoconvert' — ensure required plugins are installed"))?;
+    let blur = gst::ElementFactory::make("faceblur")
+        .name("privacy_faceblur")
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element 'faceblur' — install the OpenCV plugin from gst-plugins-bad"))?;
+    Ok([convert, blur])
+}
+
+struct Frame {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+/// What the latest finished OCR pass found, and when it finished.
+#[derive(Default)]
+struct Scan {
+    regions: Vec<Rect>,
+    finished: Option<Instant>,
+}
+
+/// Pixelates PII on every buffer leaving `pad`. OCR runs on a copy of a
+/// frame every `ocr_interval_ms` on its own thread; the regions it finds are
+/// applied until the next pass. It fails closed: frames are black before the
+/// first pass finishes, while passes lag (tesseract failing or too slow), and
+/// in formats it can't read.
+pub fn add_pii_redactor(pad: &gst::Pad, filter: &PrivacyFilter) -> Result<()> {
+    let patterns = BUILTIN_PII_PATTERNS
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .chain(filter.pii_patterns.iter().cloned())
+        .map(|pattern| Regex::new(&pattern).map_err(|err| anyhow!("invalid PII pattern '{pattern}': {err}")))
+        .collect::<Result<Vec<_>>>()?;
+    let scan: Arc<Mutex<Scan>> = Arc::default();
+    let frames = spawn_ocr(patterns, Arc::clone(&scan))?;
+    let interval = Duration::from_millis(filter.ocr_interval_ms.max(100));
+    let stale_after = interval * STALE_INTERVALS;
+    let mut blanking = false;
+    let mut last_sample: Option<Instant> = None;
+    let mut warned_format = false;
+
+    pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+        let video = pad
+            .current_caps()
+            .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok());
+        let Some(gst::PadProbeData::Buffer(buffer)) = &mut info.data else {
+            return gst::PadProbeReturn::Ok;
+        };
+        let buffer = buffer.make_mut();
+        let Ok(mut map) = buffer.map_writable() else {
+            return gst::PadProbeReturn::Ok;
+        };
+        let Some(video) = video.filter(|video| video.format() == gst_video::VideoFormat::Rgba) else {
+            if !std::mem::replace(&mut warned_format, true) {
+                eprintln!("[privacy] PII redaction needs RGBA frames; blanking them instead");
+            }
+            map.as_mut_slice().fill(0);
+            return gst::PadProbeReturn::Ok;
+        };
+        let width = video.width() as usize;
+        let height = video.height() as usize;
+        let stride = video.stride()[0] as usize;
+
+        if last_sample.is_none_or(|at| at.elapsed() >= interval) {
+            last_sample = Some(Instant::now());
+            // a busy OCR thread just misses this frame
+            let _ = frames.try_send(Frame {
+                data: map.as_slice().to_vec(),
+                width,
+                height,
+                stride,
+            });
+        }
+        let scan = scan.lock().unwrap_or_else(PoisonError::into_inner);
+        let vouched = scan.finished.is_some_and(|finished| finished.elapsed() < stale_after);
+        if std::mem::replace(&mut blanking, !vouched) == vouched {
+            if vouched {
+                println!("[privacy] OCR caught up; redacting matches only");
+            } else {
+                eprintln!("[privacy] no recent OCR pass; blanking frames until one lands");
+            }
+        }
+        if !vouched {
+            map.as_mut_slice().fill(0);
+            return gst::PadProbeReturn::Ok;
+        }
+        for region in &scan.regions {
+            pixelate(map.as_mut_slice(), width, height, stride, region);
+        }
+        gst::PadProbeReturn::Ok
+    });
+    Ok(())
+}
+
+fn pixelate(data: &mut [u8], width: usize, height: usize, stride: usize, region: &Rect) {
+    let x0 = (region.x as usize).min(width);
+    let y0 = (region.y as usize).min(height);
+    let x1 = (x0 + region.width as usize).min(width);
+    let y1 = (y0 + region.height as usize).min(height);
+    for block_y in (y0..y1).step_by(PIXEL_BLOCK) {
+        for block_x in (x0..x1).step_by(PIXEL_BLOCK) {
+            let source = block_y * stride + block_x * 4;
+            let pixel: [u8; 4] = data[source..source + 4].try_into().unwrap_or_default();
+            for y in block_y..(block_y + PIXEL_BLOCK).min(y1) {
+                for x in block_x..(block_x + PIXEL_BLOCK).min(x1) {
+                    let offset = y * stride + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&pixel);
+                }
+            }
+        }
+    }
+}
+
+fn spawn_ocr(patterns: Vec<Regex>, scan: Arc<Mutex<Scan>>) -> Result<SyncSender<Frame>> {
+    let (sender, receiver) = mpsc::sync_channel::<Frame>(1);
+    std::thread::Builder::new()
+        .name("privacy_ocr".into())
+        .spawn(move || {
+            let image = std::env::temp_dir().join(format!("sc-privacy-{}.ppm", std::process::id()));
+            let mut failed = false;
+            for frame in receiver {
+                let found = write_ppm(&image, &frame).and_then(|_| ocr_lines(&image));
+                match found {
+                    Ok(lines) => {
+                        failed = false;
+                        let matched: Vec<Rect> = lines
+                            .into_iter()
+                            .filter(|(text, _)| patterns.iter().any(|pattern| pattern.is_match(text)))
+                            .map(|(_, rect)| rect)
+                            .collect();
+                        *scan.lock().unwrap_or_else(PoisonError::into_inner) = Scan {
+                            regions: matched,
+                            finished: Some(Instant::now()),
+                        };
+                    }
+                    Err(err) => {
+                        if !std::mem::replace(&mut failed, true) {
+                            eprintln!("[privacy] OCR failed: {err}");
+                        }
+                    }
+                }
+            }
+            let _ = std::fs::remove_file(&image);
+        })?;
+    Ok(sender)
+}
+
+/// tesseract reads PNM, which is trivial to write from RGBA.
+fn write_ppm(path: &std::path::Path, frame: &Frame) -> Result<()> {
+    let mut ppm = format!("P6\n{} {}\n255\n", frame.width, frame.height).into_bytes();
+    ppm.reserve(frame.width * frame.height * 3);
+    for row in frame.data.chunks(frame.stride).take(frame.height) {
+        for pixel in row[..frame.width * 4].chunks_exact(4) {
+            ppm.extend_from_slice(&pixel[..3]);
+        }
+    }
+    std::fs::write(path, ppm)?;
+    Ok(())
+}
+
+/// Text lines with their bounding boxes, from tesseract's TSV output.
+/// Whole lines are matched, so numbers split into groups still match.
+fn ocr_lines(image: &std::path::Path) -> Result<Vec<(String, Rect)>> {
+    let output = Command::new("tesseract").arg(image).args(["stdout", "tsv"]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let mut lines: Vec<((u32, u32, u32), String, Rect)> = Vec::new();
+    for row in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        // level page block par line word left top width height conf text
+        let fields: Vec<&str> = row.split('\t').collect();
+        if fields.len() < 12 || fields[0] != "5" || fields[11].trim().is_empty() {
+            continue;
+        }
+        let number = |index: usize| fields[index].parse::<u32>().unwrap_or_default();
+        let key = (number(2), number(3), number(4));
+        let word = Rect {
+            x: number(6),
+            y: number(7),
+            width: number(8),
+            height: number(9),
+        };
+        match lines.last_mut().filter(|(line_key, _, _)| *line_key == key) {
+            Some((_, text, rect)) => {
+                text.push(' ');
+                text.push_str(fields[11]);
+                *rect = union(*rect, word);
+            }
+            None => lines.push((key, fields[11].to_string(), word)),
+        }
+    }
+    Ok(lines
+        .into_iter()
+        .map(|(_, text, rect)| {
+            let x = rect.x.saturating_sub(REGION_MARGIN);
+            let y = rect.y.saturating_sub(REGION_MARGIN);
+            let padded = Rect {
+                x,
+                y,
+                width: rect.x + rect.width + REGION_MARGIN - x,
+                height: rect.y + rect.height + REGION_MARGIN - y,
+            };
+            (text, padded)
+        })
+        .collect())
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Rect {
+        x,
+        y,
+        width: (a.x + a.width).max(b.x + b.width) - x,
+        height: (a.y + a.height).max(b.y + b.height) - y,
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::capture_manager::CapturedChunk;
+
+/// Stream name of proxy chunks; each carries the id of its full-quality chunk.
+pub const PROXY_STREAM: &str = "video_proxy";
+
+/// Whether `stream` carries video frames, full quality or proxy.
+pub fn is_video(stream: &str) -> bool {
+    stream == "video" || stream == PROXY_STREAM
+}
+
+/// A low-res, low-framerate copy of every video chunk, for fast upload or
+/// model input next to the full-quality outputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(default = "ProxyConfig::default_width")]
+    pub width: u32,
+    #[serde(default = "ProxyConfig::default_framerate")]
+    pub framerate: u32,
+}
+
+impl ProxyConfig {
+    pub const fn default_width() -> u32 {
+        640
+    }
+
+    pub const fn default_framerate() -> u32 {
+        2
+    }
+}
+
+/// Ties a proxy chunk to the full-quality video chunk with the same id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyLink {
+    pub chunk_id: u64,
+    pub start_ts_unix_nanos: u128,
+    pub width: u32,
+    pub height: u32,
+    pub frames: usize,
+    pub bytes: usize,
+}
+
+#[derive(Default)]
+pub struct ProxyLinks {
+    links: Mutex<Vec<ProxyLink>>,
+}
+
+impl ProxyLinks {
+    pub fn reset(&self) {
+        self.links.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn push(&self, link: ProxyLink) {
+        self.links.lock().unwrap_or_else(PoisonError::into_inner).push(link);
+    }
+
+    pub fn snapshot(&self) -> Vec<ProxyLink> {
+        self.links.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Downscales `chunk` (nearest neighbour) and drops frames down to the proxy
+/// framerate. Only 4-byte packed formats are handled, which covers the SDR
+/// capture format; 10-bit HDR chunks get no proxy.
+pub fn make_proxy(chunk: &CapturedChunk, config: &ProxyConfig) -> Result<(CapturedChunk, ProxyLink)> {
+    let meta = &chunk.metadata;
+    let format = meta["format"].as_str().unwrap_or_default();
+    if !matches!(format, "RGBA" | "RGBx" | "BGRA" | "BGRx") {
+        return Err(anyhow!("no proxy for {format:?} frames"));
+    }
+    let width = meta["width"].as_u64().unwrap_or_default() as usize;
+    let height = meta["height"].as_u64().unwrap_or_default() as usize;
+    let frame_size = meta["frame_size"].as_u64().unwrap_or_default() as usize;
+    if width == 0 || height == 0 || frame_size < width * height * 4 {
+        return Err(anyhow!("chunk metadata has no usable frame size"));
+    }
+    let stride = frame_size / height;
+    let frames = chunk.data.len() / frame_size;
+
+    let out_width = (config.width as usize).clamp(2, width) & !1;
+    let out_height = ((height * out_width / width).max(2)) & !1;
+    let source_fps = frames as u64 * 1000 / chunk.duration_ms.max(1);
+    let step = (source_fps / u64::from(config.framerate.max(1))).max(1) as usize;
+
+    let mut data = Vec::with_capacity(frames.div_ceil(step) * out_width * out_height * 4);
+    let mut kept = 0;
+    for frame in chunk.data.chunks_exact(frame_size).step_by(step) {
+        for y in 0..out_height {
+            let row = &frame[(y * height / out_height) * stride..];
+            for x in 0..out_width {
+                let offset = (x * width / out_width) * 4;
+                data.extend_from_slice(&row[offset..offset + 4]);
+            }
+        }
+        kept += 1;
+    }
+
+    let link = ProxyLink {
+        chunk_id: chunk.id,
+        start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+        width: out_width as u32,
+        height: out_height as u32,
+        frames: kept,
+        bytes: data.len(),
+    };
+    let proxy = CapturedChunk {
+        id: chunk.id,
+        kind: PROXY_STREAM.to_string(),
+        start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+        duration_ms: chunk.duration_ms,
+        metadata: json!({
+            "width": out_width,
+            "height": out_height,
+            "format": format,
+            "frame_size": out_width * out_height * 4,
+            "frames": kept,
+            "source_chunk_id": chunk.id,
+            "source_width": width,
+            "source_height": height,
+        }),
+        data_len: data.len(),
+        data,
+    };
+    Ok((proxy, link))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `frames` 8x4 BGRA frames whose pixels hold their own x, y and frame.
+    fn chunk(format: &str, frames: usize) -> CapturedChunk {
+        let (width, height) = (8, 4);
+        let mut data = Vec::new();
+        for frame in 0..frames {
+            for y in 0..height {
+                for x in 0..width {
+                    data.extend_from_slice(&[x as u8, y as u8, frame as u8, 255]);
+                }
+            }
+        }
+        CapturedChunk {
+            id: 7,
+            kind: "video".to_string(),
+            start_ts_unix_nanos: 1_000,
+            duration_ms: 1000,
+            metadata: json!({ "width": width, "height": height, "format": format, "frame_size": width * height * 4 }),
+            data_len: data.len(),
+            data,
+        }
+    }
+
+    #[test]
+    fn downscales_and_drops_frames() {
+        let config = ProxyConfig { width: 4, framerate: 2 };
+        let (proxy, link) = make_proxy(&chunk("BGRx", 10), &config).unwrap();
+        assert_eq!((link.width, link.height, link.frames), (4, 2, 2));
+        assert_eq!(link.chunk_id, 7);
+        assert_eq!(proxy.kind, PROXY_STREAM);
+        assert_eq!(proxy.data.len(), 4 * 2 * 4 * 2);
+        assert_eq!(proxy.metadata["source_chunk_id"], 7);
+        // second output pixel of the second row of the second kept frame
+        let pixel = &proxy.data[(4 * 2 + 4 + 1) * 4..][..4];
+        assert_eq!(pixel, &[2, 2, 5, 255]);
+    }
+
+    #[test]
+    fn never_upscales() {
+        let config = ProxyConfig { width: 1920, framerate: 30 };
+        let (_, link) = make_proxy(&chunk("RGBA", 10), &config).unwrap();
+        assert_eq!((link.width, link.height, link.frames), (8, 4, 10));
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(make_proxy(&chunk("NV12", 1), &ProxyConfig { width: 4, framerate: 2 }).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use std::f32::consts::TAU;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_audio as gst_audio;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::capture_manager::ensure_gstreamer_initialized;
+use crate::chunk_store::parse_chunk_name;
+use crate::compression;
+use crate::proxy;
+use crate::session::{SessionManifest, TrackMedia};
+use crate::uploads::UploadSpool;
+
+const BEEP_HZ: f32 = 1_000.0;
+const BEEP_AMPLITUDE: f32 = 0.2;
+
+/// What replaces the redacted audio.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactFill {
+    #[default]
+    Silence,
+    /// A 1kHz tone, so the cut is audible on playback.
+    Beep,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactedChunk {
+    /// The rewritten chunk data file.
+    pub path: String,
+    pub stream: String,
+    pub chunk_id: u64,
+    pub frames: usize,
+    /// SHA-256 of the rewritten data as stored, also in its `sha256` metadata.
+    pub sha256: String,
+}
+
+/// One `redact_range` call, kept in the session manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redaction {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub fill: RedactFill,
+    /// When the redaction ran.
+    pub unix_ms: u64,
+    /// Files that still hold the original audio in the range.
+    #[serde(default)]
+    pub unredacted: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionReport {
+    pub session_id: String,
+    pub start_unix_ms: u64,
+    pub end_unix_ms: u64,
+    pub chunks: Vec<RedactedChunk>,
+    /// MKV recordings whose audio tracks were re-encoded with the range filled.
+    pub recordings: Vec<String>,
+    /// Files that may hold audio in the range but were not rewritten:
+    /// recordings split into parts, and files that failed to redact. They
+    /// are also flagged in the manifest's `redactions`.
+    pub untouched: Vec<String>,
+}
+
+/// Overwrites the session's audio between `start_ms` and `end_ms` (offsets
+/// into the session) in the stored raw chunks: debug chunks in `chunk_dir`
+/// and chunks still waiting in the upload spool. Rewritten chunks get the
+/// range appended to their `redactions` metadata and a fresh `sha256`, and
+/// compressed ones are recompressed with fresh sizes. A recording in one
+/// file has its audio re-encoded; the video is copied as is.
+pub fn redact_range(
+    session_dir: &Path,
+    chunk_dir: &Path,
+    spool: &UploadSpool,
+    start_ms: u64,
+    end_ms: u64,
+    fill: RedactFill,
+) -> Result<RedactionReport> {
+    if end_ms <= start_ms {
+        return Err(anyhow!("empty redaction range {start_ms}..{end_ms}ms"));
+    }
+    let mut manifest = SessionManifest::read(session_dir)?;
+    let origin = manifest.started_unix_ms as u64;
+    let mut report = RedactionReport {
+        session_id: manifest.session_id.clone(),
+        start_unix_ms: origin + start_ms,
+        end_unix_ms: origin + end_ms,
+        chunks: Vec::new(),
+        recordings: Vec::new(),
+        untouched: Vec::new(),
+    };
+
+    let mut recordings: Vec<&String> = manifest.recording_parts.iter().chain(manifest.recording.iter()).collect();
+    recordings.sort();
+    recordings.dedup();
+    let audio_tracks = manifest
+        .tracks
+        .iter()
+        .filter(|track| track.media == TrackMedia::Audio)
+        .count();
+    match recordings.as_slice() {
+        [] => {}
+        // recording timestamps start with the session, so offsets carry over
+        [single] => {
+            let path = session_dir.join(single);
+            match redact_recording(&path, audio_tracks, start_ms..end_ms, fill) {
+                Ok(()) => report.recordings.push(path.to_string_lossy().to_string()),
+                Err(err) => {
+                    eprintln!("[redaction] failed to redact {}: {err}", path.display());
+                    report.untouched.push(path.to_string_lossy().to_string());
+                }
+            }
+        }
+        // later parts start at their first chunk, which the manifest doesn't keep
+        parts => report
+            .untouched
+            .extend(parts.iter().map(|name| session_dir.join(name).to_string_lossy().to_string())),
+    }
+    let range = (
+        report.start_unix_ms as u128 * 1_000_000,
+        report.end_unix_ms as u128 * 1_000_000,
+    );
+
+    // debug chunks carry no session id, so the session's time span decides
+    if chunk_dir.exists() {
+        let session_end = (origin + manifest.duration_ms) as u128 * 1_000_000;
+        for entry in std::fs::read_dir(chunk_dir)? {
+            let raw = entry?.path();
+            let Some((start_ns, id, stream)) = parse_chunk_name(&raw) else {
+                continue;
+            };
+            if proxy::is_video(&stream) || start_ns >= range.1 || start_ns > session_end {
+                continue;
+            }
+            let meta_path = raw.with_extension("json");
+            let redacted = (|| -> Result<Option<(usize, String)>> {
+                let mut metadata: Value = serde_json::from_slice(&std::fs::read(&meta_path)?)?;
+                let data = compression::decompress(&metadata, std::fs::read(&raw)?)?;
+                let Some((data, frames)) = redact_chunk(&mut metadata, data, start_ns, range, fill)? else {
+                    return Ok(None);
+                };
+                write_replacing(&raw, &data)?;
+                write_replacing(&meta_path, &serde_json::to_vec_pretty(&metadata)?)?;
+                Ok(Some((frames, sha256_hex(&data))))
+            })();
+            match redacted {
+                    Ok(Some((frames, sha256))) => report.chunks.push(RedactedChunk {
+                    path: raw.to_string_lossy().to_string(),
+                    stream,
+                    chunk_id: id,
+                    frames,
+                    sha256,
+                }),
+                Ok(None) => {}
+                Err(err) => {
+                    eprintln!("[redaction] failed to redact {}: {err}", raw.display());
+                    report.untouched.push(raw.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    for mut entry in spool.pending()? {
+        if entry.session_id != manifest.session_id || proxy::is_video(&entry.stream) || entry.start_ts_unix_nanos >= range.1 {
+            continue;
+        }
+        let raw = spool.data_path(&entry)?;
+        let redacted = (|| -> Result<Option<(usize, String)>> {
+            // the entry may be uploaded and removed while we look at it
+            let Ok(data) = std::fs::read(&raw) else {
+                return Ok(None);
+            };
+            let data = compression::decompress(&entry.metadata, data)?;
+            let start_ns = entry.start_ts_unix_nanos;
+            let Some((data, frames)) = redact_chunk(&mut entry.metadata, data, start_ns, range, fill)? else {
+                return Ok(None);
+            };
+            let sha256 = sha256_hex(&data);
+            Ok(spool.rewrite(&mut entry, &data)?.then_some((frames, sha256)))
+        })();
+        match redacted {
+            Ok(Some((frames, sha256))) => report.chunks.push(RedactedChunk {
+                path: raw.to_string_lossy().to_string(),
+                stream: entry.stream,
+                chunk_id: entry.chunk_id,
+                frames,
+                sha256,
+            }),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("[redaction] failed to redact {}: {err}", raw.display());
+                report.untouched.push(raw.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    manifest.redactions.push(Redaction {
+        start_ms,
+        end_ms,
+        fill,
+        unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        unredacted: report.untouched.clone(),
+    });
+    manifest.write(session_dir)?;
+
+    println!(
+        "[redaction] {} {}..{}ms: {} chunks and {} recordings rewritten, {} untouched",
+        report.session_id,
+        start_ms,
+        end_ms,
+        report.chunks.len(),
+        report.recordings.len(),
+        report.untouched.len()
+    );
+    Ok(report)
+}
+
+/// Re-encodes the audio tracks of the MKV at `path` with `range_ms` (file
+/// time) filled, copying the video, and swaps the result in.
+fn redact_recording(path: &Path, audio_tracks: usize, range_ms: Range<u64>, fill: RedactFill) -> Result<()> {
+    ensure_gstreamer_initialized()?;
+    let mut description = "filesrc name=input ! matroskademux name=demux \
+         matroskamux name=mux ! filesink name=output \
+         demux.video_0 ! queue ! mux. "
+        .to_string();
+    for index in 0..audio_tracks {
+        description.push_str(&format!(
+            "demux.audio_{index} ! queue ! decodebin ! audioconvert \
+             ! audio/x-raw,format=F32LE,layout=interleaved ! identity name=redact_{index} \
+             ! audioconvert ! audioresample ! opusenc ! queue ! mux. "
+        ));
+    }
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("redaction description did not produce a pipeline"))?;
+    let element = |name: &str| {
+        pipeline
+            .by_name(name)
+            .ok_or_else(|| anyhow!("redaction pipeline has no '{name}' element"))
+    };
+    let mut tmp = PathBuf::from(path);
+    tmp.as_mut_os_string().push(".tmp");
+    element("input")?.set_property("location", path.to_string_lossy().to_string());
+    element("output")?.set_property("location", tmp.to_string_lossy().to_string());
+
+    let range_ns = range_ms.start * 1_000_000..range_ms.end * 1_000_000;
+    for index in 0..audio_tracks {
+        let pad = element(&format!("redact_{index}"))?
+            .static_pad("src")
+            .ok_or_else(|| anyhow!("redaction filter has no src pad"))?;
+        let range_ns = range_ns.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(audio) = pad
+                .current_caps()
+                .and_then(|caps| gst_audio::AudioInfo::from_caps(&caps).ok())
+            else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(gst::PadProbeData::Buffer(buffer)) = &mut info.data else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(pts) = buffer.pts().map(gst::ClockTime::nseconds) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let rate = audio.rate() as u64;
+            let channels = audio.channels() as usize;
+            let to_frame = |ns: u64| ns.saturating_sub(pts) * rate / 1_000_000_000;
+            let (first, last) = (to_frame(range_ns.start), to_frame(range_ns.end));
+            let buffer = buffer.make_mut();
+            let Ok(mut map) = buffer.map_writable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let frames = map.len() / (channels * 4).max(1);
+            let filled = first.min(frames as u64) as usize..last.min(frames as u64) as usize;
+            let phase = (pts * rate / 1_000_000_000) as usize;
+            fill_samples(map.as_mut_slice(), 4, channels, rate as u32, filled, phase, fill);
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|err| anyhow!("failed to start redaction: {err:?}"))?;
+    let bus = pipeline.bus().ok_or_else(|| anyhow!("redaction pipeline has no bus"))?;
+    // a whole recording is re-encoded, so there is no sensible timeout
+    let message = bus.timed_pop_filtered(gst::ClockTime::NONE, &[gst::MessageType::Eos, gst::MessageType::Error]);
+    let _ = pipeline.set_state(gst::State::Null);
+    if let Some(gst::MessageView::Error(err)) = message.as_ref().map(|message| message.view()) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(anyhow!("redaction failed: {}", err.error()));
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Writes `fill` over `frames` of interleaved `data`. `phase` is the frame
+/// index `data` starts at in its stream, so a beep runs on across buffers.
+fn fill_samples(
+    data: &mut [u8],
+    sample_bytes: usize,
+    channels: usize,
+    rate: u32,
+    frames: Range<usize>,
+    phase: usize,
+    fill: RedactFill,
+) {
+    let frame_bytes = channels * sample_bytes;
+    for frame in frames {
+        let value = match fill {
+            RedactFill::Silence => 0.0,
+            RedactFill::Beep => BEEP_AMPLITUDE * (TAU * BEEP_HZ * (phase + frame) as f32 / rate as f32).sin(),
+        };
+        for channel in 0..channels {
+            let offset = frame * frame_bytes + channel * sample_bytes;
+            match sample_bytes {
+                4 => data[offset..offset + 4].copy_from_slice(&value.to_le_bytes()),
+                _ => data[offset..offset + 2].copy_from_slice(&((value * i16::MAX as f32) as i16).to_le_bytes()),
+            }
+        }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// The chunk's data with the overlap of `range` (unix nanos) filled, or
+/// `None` when they don't overlap.
+fn redact_chunk(
+    metadata: &mut Value,
+    mut data: Vec<u8>,
+    chunk_start_ns: u128,
+    range: (u128, u128),
+    fill: RedactFill,
+) -> Result<Option<(Vec<u8>, usize)>> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let rate = metadata["rate"].as_u64().unwrap_or_default() as u128;
+    let channels = metadata["channels"].as_u64().unwrap_or_default() as usize;
+    let sample_bytes = match metadata["format"].as_str() {
+        Some("F32LE") => 4,
+        Some("S16LE") => 2,
+        other => return Err(anyhow!("can't redact audio in format {other:?}")),
+    };
+    if rate == 0 || channels == 0 {
+        return Err(anyhow!("chunk metadata has no audio rate or channel count"));
+    }
+    let frame_bytes = channels * sample_bytes;
+    let frames = data.len() / frame_bytes;
+    let to_frame = |ns: u128| ((ns.saturating_sub(chunk_start_ns) * rate / 1_000_000_000) as usize).min(frames);
+    let (first, last) = (to_frame(range.0), to_frame(range.1));
+    if first >= last {
+        return Ok(None);
+    }
+
+    fill_samples(&mut data, sample_bytes, channels, rate as u32, first..last, 0, fill);
+
+    if !metadata.is_object() {
+        *metadata = json!({});
+    }
+    let redaction = json!({
+        "start_unix_ms": (range.0 / 1_000_000) as u64,
+        "end_unix_ms": (range.1 / 1_000_000) as u64,
+        "frames": last - first,
+    });
+    match metadata["redactions"].as_array_mut() {
+        Some(redactions) => redactions.push(redaction),
+        None => metadata["redactions"] = json!([redaction]),
+    }
+    let data = compression::recompress(metadata, data)?;
+    metadata["sha256"] = json!(sha256_hex(&data));
+    Ok(Some((data, last - first)))
+}
+
+fn write_replacing(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = PathBuf::from(path);
+    tmp.as_mut_os_string().push(".tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_NS: u128 = 1_000_000_000;
+
+    /// 100ms of mono S16LE at 1kHz, one frame per millisecond.
+    fn chunk() -> (Value, Vec<u8>) {
+        (json!({ "rate": 1000, "channels": 1, "format": "S16LE" }), vec![0x11; 200])
+    }
+
+    fn ms(ms: u128) -> u128 {
+        START_NS + ms * 1_000_000
+    }
+
+    #[test]
+    fn silences_the_overlap_and_records_it() {
+        let (mut metadata, data) = chunk();
+        let (data, frames) = redact_chunk(&mut metadata, data, START_NS, (ms(10), ms(20)), RedactFill::Silence)
+            .unwrap()
+            .unwrap();
+        assert_eq!(frames, 10);
+        assert!(data[..20].iter().all(|byte| *byte == 0x11));
+        assert!(data[20..40].iter().all(|byte| *byte == 0));
+        assert!(data[40..].iter().all(|byte| *byte == 0x11));
+        assert_eq!(
+            metadata["redactions"],
+            json!([{ "start_unix_ms": 1010, "end_unix_ms": 1020, "frames": 10 }])
+        );
+        assert_eq!(metadata["sha256"], json!(sha256_hex(&data)));
+
+        let (data, _) = redact_chunk(&mut metadata, data, START_NS, (ms(90), ms(500)), RedactFill::Silence)
+            .unwrap()
+            .unwrap();
+        assert!(data[180..].iter().all(|byte| *byte == 0));
+        assert_eq!(metadata["redactions"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn beeps_on_every_channel() {
+        let mut metadata = json!({ "rate": 8000, "channels": 2, "format": "F32LE" });
+        let (data, frames) = redact_chunk(&mut metadata, vec![0; 8 * 80], START_NS, (0, ms(5)), RedactFill::Beep)
+            .unwrap()
+            .unwrap();
+        assert_eq!(frames, 40);
+        let samples: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        assert!(samples.chunks_exact(2).all(|frame| frame[0] == frame[1]));
+        assert!(samples[..80].iter().any(|sample| *sample != 0.0));
+        assert!(samples.iter().all(|sample| sample.abs() <= BEEP_AMPLITUDE));
+        assert!(samples[80..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn leaves_chunks_outside_the_range_alone() {
+        let (mut metadata, data) = chunk();
+        let redacted = redact_chunk(&mut metadata, data, START_NS, (ms(200), ms(300)), RedactFill::Silence);
+        assert!(redacted.unwrap().is_none());
+        assert!(metadata.get("redactions").is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_sample_formats() {
+        let mut metadata = json!({ "rate": 1000, "channels": 1, "format": "S24LE" });
+        assert!(redact_chunk(&mut metadata, vec![0; 30], START_NS, (ms(0), ms(5)), RedactFill::Silence).is_err());
+    }
+}
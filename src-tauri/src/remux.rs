@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::Serialize;
+
+use crate::capture_manager::{ensure_gstreamer_initialized, CapturedChunk};
+use crate::compression;
+use crate::muxer::{make, size_lock, wait_for_eos, TrackSource};
+use crate::overlap;
+use crate::proxy;
+use crate::session::TrackMedia;
+use crate::stitch::Stitcher;
+
+const REMUX_DIR: &str = "remuxed";
+const STITCH_DIR: &str = "stitched";
+pub const DEFAULT_GAP_TOLERANCE_MS: u64 = 100;
+/// Used for the last video chunk of a stream when no earlier chunk tells us its length.
+const FALLBACK_CHUNK_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemuxOutput {
+    pub stream: String,
+    pub path: String,
+    pub chunks: usize,
+    pub gaps_filled: usize,
+    pub filled_ms: u64,
+}
+
+/// Debug chunk as written by `debug_save`: `chunk-{start_ns}-{id}-{kind}.raw`
+/// next to a `.json` holding the chunk metadata.
+struct RawChunkFile {
+    start_ts_unix_nanos: u128,
+    id: u64,
+    kind: String,
+    raw: PathBuf,
+}
+
+fn parse_chunk_file(path: &Path) -> Option<RawChunkFile> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_prefix("chunk-")?.strip_suffix(".raw")?;
+    let mut parts = stem.splitn(3, '-');
+    let start_ts_unix_nanos = parts.next()?.parse().ok()?;
+    let id = parts.next()?.parse().ok()?;
+    let kind = parts.next()?.to_string();
+    Some(RawChunkFile {
+        start_ts_unix_nanos,
+        id,
+        kind,
+        raw: path.to_path_buf(),
+    })
+}
+
+/// Converts saved `.raw` + `.json` debug chunk pairs into one playable file
+/// per stream: H.264 MP4 for video, WAV for each audio source.
+pub fn remux_raw_chunks(dir: &Path) -> Result<Vec<RemuxOutput>> {
+    convert(dir, REMUX_DIR, None)
+}
+
+/// Like [`remux_raw_chunks`], but every stream starts at the earliest chunk
+/// of the session and gaps longer than `gap_tolerance_ms` are filled with
+/// black video or silence, so the files line up on one timeline.
+pub fn stitch_raw_chunks(dir: &Path, gap_tolerance_ms: u64) -> Result<Vec<RemuxOutput>> {
+    convert(dir, STITCH_DIR, Some(gap_tolerance_ms))
+}
+
+fn convert(dir: &Path, out_name: &str, gap_tolerance_ms: Option<u64>) -> Result<Vec<RemuxOutput>> {
+    ensure_gstreamer_initialized()?;
+    let mut streams: BTreeMap<String, Vec<RawChunkFile>> = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        if let Some(file) = parse_chunk_file(&entry?.path()) {
+            streams.entry(file.kind.clone()).or_default().push(file);
+        }
+    }
+    if streams.is_empty() {
+        return Err(anyhow!("no raw debug chunks found in {}", dir.display()));
+    }
+
+    for files in streams.values_mut() {
+        files.sort_by_key(|file| (file.start_ts_unix_nanos, file.id));
+    }
+    let session_origin = streams
+        .values()
+        .map(|files| files[0].start_ts_unix_nanos)
+        .min()
+        .unwrap_or_default();
+
+    let out_dir = dir.join(out_name);
+    std::fs::create_dir_all(&out_dir)?;
+    let mut outputs = Vec::new();
+    for (stream, files) in streams {
+        let media = if proxy::is_video(&stream) {
+            TrackMedia::Video
+        } else {
+            TrackMedia::Audio
+        };
+        let extension = match media {
+            TrackMedia::Video => "mp4",
+            TrackMedia::Audio => "wav",
+        };
+        let path = out_dir.join(format!("{stream}.{extension}"));
+        let stitcher = gap_tolerance_ms.map(|tolerance| Stitcher::new(media, session_origin, tolerance));
+        match remux_stream(&stream, media, &files, &path, stitcher) {
+            Ok(output) => {
+                println!("[remux] {stream}: {} chunks -> {}", output.chunks, path.display());
+                outputs.push(output);
+            }
+            Err(err) => eprintln!("[remux] failed to remux {stream}: {err}"),
+        }
+    }
+    if outputs.is_empty() {
+        return Err(anyhow!("none of the streams in {} could be remuxed", dir.display()));
+    }
+    Ok(outputs)
+}
+
+fn remux_stream(
+    stream: &str,
+    media: TrackMedia,
+    files: &[RawChunkFile],
+    path: &Path,
+    mut stitcher: Option<Stitcher>,
+) -> Result<RemuxOutput> {
+    let pipeline = gst::Pipeline::new();
+    let appsrc = gst_app::AppSrc::builder()
+        .name(format!("{stream}_src"))
+        .format(gst::Format::Time)
+        .is_live(false)
+        // a single source per pipeline, so throttling the reader is safe here
+        .block(true)
+        .build();
+    let mut size_filter = None;
+    let chain = match media {
+        TrackMedia::Video => {
+            let (scale, size) = size_lock(stream)?;
+            size_filter = Some(size.clone());
+            vec![
+                make("videoconvert", format!("{stream}_convert"))?,
+                scale,
+                size,
+                make("x264enc", format!("{stream}_enc"))?,
+                make("h264parse", format!("{stream}_parse"))?,
+                make("mp4mux", format!("{stream}_mux"))?,
+            ]
+        }
+        TrackMedia::Audio => vec![
+            make("audioconvert", format!("{stream}_convert"))?,
+            make("wavenc", format!("{stream}_enc"))?,
+        ],
+    };
+    let sink = make("filesink", format!("{stream}_sink"))?;
+    sink.set_property("location", path.to_string_lossy().to_string());
+
+    pipeline.add(appsrc.upcast_ref::<gst::Element>())?;
+    let mut previous: gst::Element = appsrc.clone().upcast();
+    for element in chain.iter().chain(std::iter::once(&sink)) {
+        pipeline.add(element)?;
+        previous.link(element)?;
+        previous = element.clone();
+    }
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|err| anyhow!("failed to start remux pipeline: {err:?}"))?;
+
+    let mut source = TrackSource::new(appsrc, media);
+    if let Some(filter) = size_filter {
+        source = source.with_size_filter(filter);
+    }
+    // stitched streams share the session origin and keep their nominal chunk
+    // length, since gaps are filled explicitly
+    let (origin, nominal_ms) = match &stitcher {
+        Some(stitcher) => (stitcher.origin_unix_nanos(), Some(nominal_chunk_ms(files))),
+        None => (files[0].start_ts_unix_nanos, None),
+    };
+    let mut last_duration_ms = FALLBACK_CHUNK_MS;
+    let mut pushed = 0;
+    for (index, file) in files.iter().enumerate() {
+        // the debug metadata does not record the chunk length; take it from
+        // the gap to the next chunk of the same stream
+        let duration_ms = nominal_ms.unwrap_or_else(|| {
+            files
+                .get(index + 1)
+                .map(|next| (next.start_ts_unix_nanos.saturating_sub(file.start_ts_unix_nanos) / 1_000_000) as u64)
+                .filter(|ms| *ms > 0)
+                .unwrap_or(last_duration_ms)
+        });
+        last_duration_ms = duration_ms;
+
+        let chunk = match load_chunk(file, duration_ms) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                eprintln!("[remux] skipping chunk {} ({stream}): {err}", file.id);
+                continue;
+            }
+        };
+        if let Some(filler) = stitcher.as_mut().and_then(|stitcher| stitcher.place(&chunk)) {
+            let start_ns = filler.start_ts_unix_nanos.saturating_sub(origin) as u64;
+            if let Err(err) = source.push(&filler, start_ns) {
+                eprintln!("[remux] failed to fill gap before chunk {} ({stream}): {err}", file.id);
+            }
+        }
+        let start_ns = file.start_ts_unix_nanos.saturating_sub(origin) as u64;
+        if let Err(err) = source.push(&chunk, start_ns) {
+            eprintln!("[remux] skipping chunk {} ({stream}): {err}", file.id);
+            continue;
+        }
+        pushed += 1;
+    }
+
+    if pushed == 0 {
+        let _ = pipeline.set_state(gst::State::Null);
+        let _ = std::fs::remove_file(path);
+        return Err(anyhow!("no usable chunks"));
+    }
+    let _ = source.appsrc().end_of_stream();
+    wait_for_eos(&pipeline, "remux")?;
+    Ok(RemuxOutput {
+        stream: stream.to_string(),
+        path: path.to_string_lossy().to_string(),
+        chunks: pushed,
+        gaps_filled: stitcher.as_ref().map_or(0, |stitcher| stitcher.gaps_filled),
+        filled_ms: stitcher.as_ref().map_or(0, |stitcher| stitcher.filled_ms),
+    })
+}
+
+/// The usual spacing between chunk starts, which is the configured chunk
+/// duration whenever nothing was dropped.
+fn nominal_chunk_ms(files: &[RawChunkFile]) -> u64 {
+    let mut spacings: Vec<u64> = files
+        .windows(2)
+        .map(|pair| (pair[1].start_ts_unix_nanos.saturating_sub(pair[0].start_ts_unix_nanos) / 1_000_000) as u64)
+        .filter(|ms| *ms > 0)
+        .collect();
+    spacings.sort_unstable();
+    spacings.get(spacings.len() / 2).copied().unwrap_or(FALLBACK_CHUNK_MS)
+}
+
+fn load_chunk(file: &RawChunkFile, duration_ms: u64) -> Result<CapturedChunk> {
+    let metadata: serde_json::Value = serde_json::from_slice(&std::fs::read(file.raw.with_extension("json"))?)?;
+    if metadata.is_null() {
+        return Err(anyhow!("chunk has no recorded metadata"));
+    }
+    let data = compression::decompress(&metadata, std::fs::read(&file.raw)?)?;
+    let mut chunk = CapturedChunk {
+        id: file.id,
+        kind: file.kind.clone(),
+        start_ts_unix_nanos: file.start_ts_unix_nanos,
+        duration_ms,
+        metadata,
+        data_len: data.len(),
+        data,
+    };
+    // chunk starts are spaced by the nominal duration; an overlap comes on top
+    chunk.duration_ms += overlap::overlap_ms(&chunk);
+    Ok(overlap::trim(&chunk).unwrap_or(chunk))
+}
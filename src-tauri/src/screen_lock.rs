@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+#[cfg(target_os = "linux")]
+use crate::bus::{self, Bus};
+use crate::capture_manager::{CaptureManager, CaptureState, PauseReason};
+use crate::events::CaptureEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What to do with capture while the session is locked.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockAction {
+    #[default]
+    Pause,
+    /// Keep recording, with `screen_locked` set on the affected chunks.
+    Tag,
+}
+
+/// Written by the lock monitor thread, read by chunk buffers for tagging.
+#[derive(Default)]
+pub struct LockTracker {
+    locked: AtomicBool,
+}
+
+impl LockTracker {
+    pub fn reset(&self) {
+        self.locked.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Returns true when the flag actually flipped.
+    fn set_locked(&self, locked: bool) -> bool {
+        self.locked.swap(locked, Ordering::Relaxed) != locked
+    }
+}
+
+/// logind's lock hint for our session, falling back to the screen saver's
+/// active state on systems without logind.
+#[cfg(target_os = "linux")]
+fn session_locked() -> Option<bool> {
+    let logind = bus::call(
+        Bus::System,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.DBus.Properties",
+        "Get",
+        &("org.freedesktop.login1.Session", "LockedHint"),
+    )
+    .and_then(|reply| {
+        let hint: zbus::zvariant::OwnedValue = reply.body().deserialize().ok()?;
+        bool::try_from(hint).ok()
+    });
+    logind.or_else(|| {
+        let reply = bus::call(
+            Bus::Session,
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+            "GetActive",
+            &(),
+        )?;
+        reply.body().deserialize().ok()
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn session_locked() -> Option<bool> {
+    None
+}
+
+pub fn spawn(app: AppHandle) -> Result<()> {
+    std::thread::Builder::new()
+        .name("lock_monitor".into())
+        .spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            poll(&app);
+        })?;
+    Ok(())
+}
+
+fn poll(app: &AppHandle) {
+    let manager = app.state::<CaptureManager>();
+    let Some(action) = manager.options().screen_lock_action else {
+        return;
+    };
+    let state = manager.status();
+    if !matches!(state, CaptureState::Running | CaptureState::Paused) {
+        return;
+    }
+    let Some(locked) = session_locked() else {
+        return;
+    };
+    let tracker = manager.screen_lock();
+
+    if locked && tracker.set_locked(true) {
+        println!("[screen_lock] session locked, action={action:?}");
+        if action == LockAction::Pause {
+            if let Err(err) = manager.pause_for(PauseReason::ScreenLock) {
+                eprintln!("[screen_lock] failed to pause: {err}");
+            }
+        }
+        manager.events().emit(CaptureEvent::ScreenLockChanged { locked: true });
+    } else if !locked && tracker.set_locked(false) {
+        println!("[screen_lock] session unlocked");
+        if let Err(err) = manager.resume_for(PauseReason::ScreenLock) {
+            eprintln!("[screen_lock] failed to resume: {err}");
+        }
+        manager.events().emit(CaptureEvent::ScreenLockChanged { locked: false });
+    }
+}
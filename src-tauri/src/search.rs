@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::ensure_gstreamer_initialized;
+use crate::events::{CaptureEvent, EventBus};
+use crate::session::{self, SessionManifest};
+
+const THUMBNAIL_DIR: &str = "thumbnails";
+const THUMBNAIL_WIDTH: i32 = 320;
+const MAX_HITS: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct TimeRange {
+    #[serde(default)]
+    pub start_unix_ms: Option<u64>,
+    #[serde(default)]
+    pub end_unix_ms: Option<u64>,
+}
+
+impl TimeRange {
+    pub fn contains(&self, unix_ms: u64) -> bool {
+        self.start_unix_ms.is_none_or(|start| unix_ms >= start) && self.end_unix_ms.is_none_or(|end| unix_ms <= end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitSource {
+    Marker,
+    Window,
+    BrowserTab,
+    Clipboard,
+    Annotation,
+    /// A session's title or a note added from the library.
+    Note,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub source: HitSource,
+    /// The text that matched.
+    pub text: String,
+    pub unix_ms: u64,
+    /// Position in the session's recording.
+    pub offset_ms: u64,
+    /// Set for hits tied to a single chunk, e.g. annotations.
+    pub chunk_id: Option<u64>,
+    pub stream: Option<String>,
+    /// PNG frame at `offset_ms`, when the session was recorded to MKV and
+    /// the frame is already rendered; see [`Thumbnails`].
+    pub thumbnail: Option<String>,
+}
+
+/// Case-insensitive search over the session manifests under `root`: markers,
+/// window titles, browser tabs, clipboard text and chunk annotations (e.g.
+/// OCR text or transcripts written by an annotator). Newest sessions first,
+/// at most 200 hits.
+pub fn search_recordings(root: &Path, query: &str, range: TimeRange, thumbnails: &Thumbnails) -> Result<Vec<SearchHit>> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Err(anyhow!("search query is empty"));
+    }
+    let mut hits = Vec::new();
+    for (dir, manifest) in session::list_manifests(root)? {
+        let mut session_hits: Vec<SearchHit> = candidates(&manifest)
+            .into_iter()
+            .filter(|(_, text, unix_ms, _)| range.contains(*unix_ms) && text.to_lowercase().contains(&query))
+            .map(|(source, text, unix_ms, chunk)| SearchHit {
+                session_id: manifest.session_id.clone(),
+                source,
+                text,
+                unix_ms,
+                offset_ms: unix_ms.saturating_sub(manifest.started_unix_ms as u64),
+                chunk_id: chunk.as_ref().map(|(id, _)| *id),
+                stream: chunk.map(|(_, stream)| stream),
+                thumbnail: None,
+            })
+            .collect();
+        session_hits.sort_by_key(|hit| hit.unix_ms);
+        session_hits.truncate(MAX_HITS - hits.len());
+        if let Some(recording) = &manifest.recording {
+            for hit in &mut session_hits {
+                hit.thumbnail = thumbnails.get(&dir, &manifest.session_id, recording, hit.offset_ms);
+            }
+        }
+        hits.extend(session_hits);
+        if hits.len() >= MAX_HITS {
+            break;
+        }
+    }
+    Ok(hits)
+}
+
+type Candidate = (HitSource, String, u64, Option<(u64, String)>);
+
+fn candidates(manifest: &SessionManifest) -> Vec<Candidate> {
+    let mut found: Vec<Candidate> = Vec::new();
+    for marker in &manifest.markers {
+        if let Some(label) = &marker.label {
+            found.push((HitSource::Marker, label.clone(), marker.unix_ms as u64, None));
+        }
+    }
+    for span in &manifest.windows {
+        let text = [&span.app_id, &span.title]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" — ");
+        found.push((HitSource::Window, text, span.start_unix_ms, None));
+    }
+    for tab in &manifest.tabs {
+        let text = match &tab.title {
+            Some(title) => format!("{title} — {}", tab.url),
+            None => tab.url.clone(),
+        };
+        found.push((HitSource::BrowserTab, text, tab.start_unix_ms, None));
+    }
+    for change in &manifest.clipboard {
+        if let Some(text) = &change.text {
+            found.push((HitSource::Clipboard, text.clone(), change.unix_ms, None));
+        }
+    }
+    if let Some(title) = &manifest.title {
+        found.push((HitSource::Note, title.clone(), manifest.started_unix_ms as u64, None));
+    }
+    for note in &manifest.notes {
+        let unix_ms = manifest.started_unix_ms as u64 + note.offset_ms.unwrap_or(0);
+        found.push((HitSource::Note, note.text.clone(), unix_ms, None));
+    }
+    for record in &manifest.chunk_annotations {
+        let annotation = &record.annotation;
+        let mut text = annotation.summary.clone().unwrap_or_default();
+        for label in &annotation.labels {
+            text.push(' ');
+            text.push_str(label);
+        }
+        if let Some(extra) = annotation.extra.as_str() {
+            text.push(' ');
+            text.push_str(extra);
+        } else if !annotation.extra.is_null() {
+            text.push(' ');
+            text.push_str(&annotation.extra.to_string());
+        }
+        found.push((
+            HitSource::Annotation,
+            text.trim().to_string(),
+            (record.start_ts_unix_nanos / 1_000_000) as u64,
+            Some((record.chunk_id, record.stream.clone())),
+        ));
+    }
+    found
+}
+
+struct ThumbnailRequest {
+    session_id: String,
+    recording: PathBuf,
+    offset_ms: u64,
+    path: PathBuf,
+}
+
+/// Renders thumbnails one at a time on a background thread, so searching and
+/// listing sessions never wait for a decode. Callers get the thumbnails
+/// already on disk; missing ones are queued and announced with
+/// `CaptureEvent::ThumbnailReady` once written.
+pub struct Thumbnails {
+    sender: mpsc::Sender<ThumbnailRequest>,
+    /// Queued or failed, so repeated listings don't queue them again.
+    requested: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl Thumbnails {
+    pub fn new(events: EventBus) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel::<ThumbnailRequest>();
+        let requested: Arc<Mutex<HashSet<PathBuf>>> = Arc::default();
+        let worker_requested = Arc::clone(&requested);
+        std::thread::Builder::new()
+            .name("thumbnails".into())
+            .spawn(move || {
+                for request in receiver {
+                    if let Err(err) = render_thumbnail(&request.recording, request.offset_ms, &request.path) {
+                        eprintln!(
+                            "[search] no thumbnail at {}ms of {}: {err}",
+                            request.offset_ms,
+                            request.recording.display()
+                        );
+                        continue;
+                    }
+                    worker_requested
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&request.path);
+                    events.emit(CaptureEvent::ThumbnailReady {
+                        session_id: request.session_id,
+                        offset_ms: request.offset_ms,
+                        path: request.path.to_string_lossy().to_string(),
+                    });
+                }
+            })?;
+        Ok(Self { sender, requested })
+    }
+
+    /// The cached PNG of the recording's frame at `offset_ms`, or `None`
+    /// after queueing it.
+    pub fn get(&self, session_dir: &Path, session_id: &str, recording: &str, offset_ms: u64) -> Option<String> {
+        let path = thumbnail_path(session_dir, offset_ms);
+        if path.exists() {
+            return Some(path.to_string_lossy().to_string());
+        }
+        if self
+            .requested
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.clone())
+        {
+            let _ = self.sender.send(ThumbnailRequest {
+                session_id: session_id.to_string(),
+                recording: session_dir.join(recording),
+                offset_ms,
+                path,
+            });
+        }
+        None
+    }
+}
+
+fn thumbnail_path(session_dir: &Path, offset_ms: u64) -> PathBuf {
+    session_dir.join(THUMBNAIL_DIR).join(format!("{offset_ms}.png"))
+}
+
+/// Cached PNG of the recording's frame at `offset_ms`, rendered right away
+/// on first use; for jobs, which already run in the background.
+pub fn thumbnail(session_dir: &Path, recording: &str, offset_ms: u64) -> Option<String> {
+    let path = thumbnail_path(session_dir, offset_ms);
+    if !path.exists() {
+        if let Err(err) = render_thumbnail(&session_dir.join(recording), offset_ms, &path) {
+            eprintln!("[search] no thumbnail at {offset_ms}ms of {recording}: {err}");
+            return None;
+        }
+    }
+    Some(path.to_string_lossy().to_string())
+}
+
+fn render_thumbnail(recording: &Path, offset_ms: u64, out: &Path) -> Result<()> {
+    let (png, _, _) = render_png(recording, offset_ms, Some(THUMBNAIL_WIDTH))?;
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(out, png)?;
+    Ok(())
+}
+
+/// PNG of the frame at `offset_ms` into `recording`, with its size; scaled
+/// to `width` when given.
+fn render_png(recording: &Path, offset_ms: u64, width: Option<i32>) -> Result<(Vec<u8>, i32, i32)> {
+    ensure_gstreamer_initialized()?;
+    // our own recordings are MKV; imported ones may be in any container
+    let demux = match recording.extension().and_then(|extension| extension.to_str()) {
+        Some("mkv") => "matroskademux name=demux demux.video_0 ! decodebin",
+        _ => "decodebin caps=video/x-raw expose-all-streams=false",
+    };
+    let scale = width
+        .map(|width| format!(" ! videoscale ! video/x-raw,width={width}"))
+        .unwrap_or_default();
+    let description =
+        format!("filesrc name=src ! {demux} ! videoconvert{scale} ! pngenc snapshot=true ! appsink name=sink sync=false");
+    let pipeline = gst::parse::launch(&description)?
+        .dynamic_cast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("thumbnail pipeline is not a pipeline"))?;
+    let src = pipeline.by_name("src").ok_or_else(|| anyhow!("thumbnail pipeline has no source"))?;
+    src.set_property("location", recording.to_string_lossy().to_string());
+    let sink = pipeline
+        .by_name("sink")
+        .and_then(|sink| sink.dynamic_cast::<gst_app::AppSink>().ok())
+        .ok_or_else(|| anyhow!("thumbnail pipeline has no appsink"))?;
+
+    let rendered = (|| -> Result<(Vec<u8>, i32, i32)> {
+        pipeline.set_state(gst::State::Paused)?;
+        let (changed, _, _) = pipeline.state(gst::ClockTime::from_seconds(5));
+        changed?;
+        pipeline.seek_simple(
+            gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+            gst::ClockTime::from_mseconds(offset_ms),
+        )?;
+        let sample = sink
+            .try_pull_preroll(gst::ClockTime::from_seconds(5))
+            .ok_or_else(|| anyhow!("no frame at {offset_ms}ms"))?;
+        let (width, height) = sample
+            .caps()
+            .and_then(|caps| caps.structure(0))
+            .and_then(|structure| Some((structure.get::<i32>("width").ok()?, structure.get::<i32>("height").ok()?)))
+            .unwrap_or_default();
+        let buffer = sample.buffer().ok_or_else(|| anyhow!("empty thumbnail sample"))?;
+        let map = buffer.map_readable()?;
+        Ok((map.as_slice().to_vec(), width, height))
+    })();
+    let _ = pipeline.set_state(gst::State::Null);
+    rendered
+}
+
+/// The frame on screen at one moment of a finished session, as a PNG.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedFrame {
+    pub session_id: String,
+    pub unix_ms: u64,
+    /// Position in the session's recording.
+    pub offset_ms: u64,
+    /// The video chunk the frame was captured in, when known.
+    pub chunk_id: Option<u64>,
+    /// The recording file it was decoded from.
+    pub file: String,
+    pub width: i32,
+    pub height: i32,
+    pub data_base64: String,
+}
+
+/// Decodes the frame shown at wall-clock `unix_ms` of a session, e.g. the
+/// time of a search hit. The video chunk covering that moment is found from
+/// the manifest, and the keyframe index points at the recording part and
+/// position to seek to.
+pub fn extract_frame(root: &Path, session_id: &str, unix_ms: u64) -> Result<ExtractedFrame> {
+    session::validate_id(session_id)?;
+    let dir = root.join(session_id);
+    let manifest = SessionManifest::read(&dir)
+        .map_err(|err| anyhow!("session '{session_id}' has no readable manifest: {err}"))?;
+    let started_ms = manifest.started_unix_ms as u64;
+    if unix_ms < started_ms || unix_ms > started_ms + manifest.duration_ms {
+        return Err(anyhow!("session '{session_id}' wasn't recording at {unix_ms}"));
+    }
+    let offset_ms = unix_ms - started_ms;
+    let chunks = video_chunks(&manifest);
+    let chunk = chunks.iter().rev().find(|(_, start_ms, _)| *start_ms <= unix_ms).copied();
+
+    // the last indexed keyframe at or before the moment, and how far past it
+    // the frame lies
+    let keyframe = manifest
+        .keyframes
+        .iter()
+        .filter_map(|keyframe| {
+            let keyframe_chunk = keyframe.chunk_id?;
+            let (_, chunk_start_ms, _) = chunks.iter().find(|(id, _, _)| *id == keyframe_chunk)?;
+            Some((keyframe, chunk_start_ms + keyframe.offset_ms))
+        })
+        .filter(|(_, at_ms)| *at_ms <= unix_ms)
+        .max_by_key(|(_, at_ms)| *at_ms);
+    let (file, position_ms) = match keyframe {
+        Some((keyframe, at_ms)) => (keyframe.file.clone(), keyframe.pts_ms + (unix_ms - at_ms)),
+        None => (
+            manifest
+                .recording
+                .clone()
+                .ok_or_else(|| anyhow!("session '{session_id}' has no MKV recording"))?,
+            offset_ms,
+        ),
+    };
+    let (png, width, height) = render_png(&dir.join(&file), position_ms, None)?;
+    Ok(ExtractedFrame {
+        session_id: session_id.to_string(),
+        unix_ms,
+        offset_ms,
+        chunk_id: chunk.map(|(chunk_id, _, _)| chunk_id),
+        file,
+        width,
+        height,
+        data_base64: base64::engine::general_purpose::STANDARD.encode(png),
+    })
+}
+
+/// `(chunk_id, start_unix_ms, duration_ms)` of the session's video chunks:
+/// as recorded in its activity scores, or spread evenly over the session
+/// from the stream totals for sessions recorded without them.
+fn video_chunks(manifest: &SessionManifest) -> Vec<(u64, u64, u64)> {
+    let recorded: Vec<(u64, u64, u64)> = manifest
+        .activity
+        .iter()
+        .filter(|chunk| chunk.stream == "video")
+        .map(|chunk| (chunk.chunk_id, chunk.start_unix_ms, chunk.duration_ms))
+        .collect();
+    if !recorded.is_empty() {
+        return recorded;
+    }
+    let totals = manifest
+        .summary
+        .as_ref()
+        .and_then(|summary| summary.streams.get("video"))
+        .cloned()
+        .unwrap_or_default();
+    let average_ms = totals.duration_ms.checked_div(totals.chunks).unwrap_or(0);
+    (0..totals.chunks)
+        .map(|chunk_id| (chunk_id, manifest.started_unix_ms as u64 + chunk_id * average_ms, average_ms))
+        .collect()
+}
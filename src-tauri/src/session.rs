@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::anchors::SessionClock;
+use crate::annotations::ChunkRecord;
+use crate::browser::TabSpan;
+use crate::calendar::CalendarEvent;
+use crate::capture_manager::{CaptureOptions, CapturedChunk, Marker};
+use crate::chunk_map::ChunkLink;
+use crate::clipboard::ClipboardChange;
+use crate::consent::ConsentConfig;
+use crate::focus::WindowSpan;
+use crate::frame_hash::FrameHash;
+use crate::highlights::ChunkActivity;
+use crate::hdr::ColorInfo;
+use crate::moments::Moment;
+use crate::muxer::Keyframe;
+use crate::proxy::ProxyLink;
+use crate::redaction::Redaction;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const DEFAULT_OUTPUT_ROOT: &str = "recordings";
+
+pub fn output_root(options: &CaptureOptions) -> PathBuf {
+    options.output_dir.as_ref().map_or_else(sessions_root, PathBuf::from)
+}
+
+/// Where every session lives: `SC_SESSIONS_ROOT`, or `recordings` in the
+/// working directory. Output directories must lie inside it.
+pub fn sessions_root() -> PathBuf {
+    std::env::var_os("SC_SESSIONS_ROOT")
+        .filter(|root| !root.is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_OUTPUT_ROOT), PathBuf::from)
+}
+
+/// `path` canonicalized, or an error when it resolves outside the sessions
+/// root, for paths that come from the frontend or other clients.
+pub fn confine(path: &Path) -> Result<PathBuf> {
+    confine_to(path, &sessions_root())
+}
+
+/// [`confine`] against another root.
+pub fn confine_to(path: &Path, root: &Path) -> Result<PathBuf> {
+    let root = resolve(root)?;
+    let resolved = resolve(path)?;
+    if !resolved.starts_with(&root) {
+        return Err(anyhow!("{} is outside the sessions root {}", path.display(), root.display()));
+    }
+    Ok(resolved)
+}
+
+/// `path` with symlinks and `..` resolved. The part that doesn't exist yet
+/// is appended to its canonical parent and may only hold plain names.
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        let probe = if existing.as_os_str().is_empty() { Path::new(".") } else { existing };
+        match probe.canonicalize() {
+            Ok(resolved) => return Ok(missing.iter().rev().fold(resolved, |dir, name| dir.join(name))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(Component::Normal(name))) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return Err(anyhow!("can't resolve {}", path.display())),
+        }
+    }
+}
+
+pub fn session_dir(options: &CaptureOptions, session_id: &str) -> PathBuf {
+    output_root(options).join(session_id)
+}
+
+/// Rejects ids that would leave the output root once joined onto it, for
+/// commands that take a session id from the frontend.
+pub fn validate_id(session_id: &str) -> Result<()> {
+    if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.starts_with('.') {
+        return Err(anyhow!("invalid session id '{session_id}'"));
+    }
+    Ok(())
+}
+
+/// `recording.mkv`, then `recording-001.mkv`, ... once size-based splitting kicks in.
+pub fn recording_file_name(part: u32) -> String {
+    if part == 0 {
+        "recording.mkv".to_string()
+    } else {
+        format!("recording-{part:03}.mkv")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackMedia {
+    Video,
+    Audio,
+}
+
+/// One track of the muxed recording; `stream` matches `CapturedChunk::kind`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackInfo {
+    pub index: u32,
+    pub stream: String,
+    pub media: TrackMedia,
+    pub codec: String,
+    /// Negotiated colour description; video only, known once capture ran.
+    #[serde(default)]
+    pub color: Option<ColorInfo>,
+}
+
+/// Track layout of the muxed file: video first, then one discrete track per
+/// audio source so narration can be rebalanced in an editor.
+pub fn tracks_for(options: &CaptureOptions) -> Vec<TrackInfo> {
+    let mut tracks = vec![
+        TrackInfo {
+            index: 0,
+            stream: "video".into(),
+            media: TrackMedia::Video,
+            codec: "h264".into(),
+            color: None,
+        },
+        TrackInfo {
+            index: 1,
+            stream: "system_audio".into(),
+            media: TrackMedia::Audio,
+            codec: "opus".into(),
+            color: None,
+        },
+    ];
+    if options.capture_mic {
+        tracks.push(TrackInfo {
+            index: 2,
+            stream: "mic".into(),
+            media: TrackMedia::Audio,
+            codec: "opus".into(),
+            color: None,
+        });
+    }
+    tracks
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub session_id: String,
+    pub started_unix_ms: u128,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub recording: Option<String>,
+    #[serde(default)]
+    pub recording_parts: Vec<String>,
+    #[serde(default)]
+    pub tracks: Vec<TrackInfo>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// Focused-window timeline, when `track_active_window` was set.
+    #[serde(default)]
+    pub windows: Vec<WindowSpan>,
+    /// Clipboard changes, when `clipboard` monitoring was on.
+    #[serde(default)]
+    pub clipboard: Vec<ClipboardChange>,
+    /// Browser tabs reported by the companion extension.
+    #[serde(default)]
+    pub tabs: Vec<TabSpan>,
+    /// Annotations from the session's chunk annotators.
+    #[serde(default)]
+    pub chunk_annotations: Vec<ChunkRecord>,
+    /// Consent flags the session was started with.
+    #[serde(default)]
+    pub consent: Option<ConsentConfig>,
+    /// One entry per `video_proxy` chunk; it shares its id with the
+    /// full-quality video chunk it was made from.
+    #[serde(default)]
+    pub proxy_chunks: Vec<ProxyLink>,
+    /// Perceptual hashes of sampled frames, when `frame_hashes` was set.
+    #[serde(default)]
+    pub frame_hashes: Vec<FrameHash>,
+    /// Activity score of every chunk, for highlight reels.
+    #[serde(default)]
+    pub activity: Vec<ChunkActivity>,
+    /// Paired wall-clock and monotonic readings, for aligning the session
+    /// with external logs.
+    #[serde(default)]
+    pub clock: Option<SessionClock>,
+    /// The video chunks each audio chunk overlaps, when audio was cut on
+    /// its own `audio_chunk_duration_ms` cadence.
+    #[serde(default)]
+    pub chunk_map: Vec<ChunkLink>,
+    /// Keyframes of the recording files, for trimming without a scan.
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
+    /// What was on screen and said at each marker, under `moments/`.
+    #[serde(default)]
+    pub moments: Vec<Moment>,
+    /// Name given in the library; the session id stands in until then.
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Vec<SessionNote>,
+    /// The calendar event the session was recorded for.
+    #[serde(default)]
+    pub calendar_event: Option<CalendarEvent>,
+    /// Audio ranges overwritten by `redact_range`, with any files that
+    /// still hold the original audio.
+    #[serde(default)]
+    pub redactions: Vec<Redaction>,
+    #[serde(default)]
+    pub summary: Option<SessionSummary>,
+}
+
+/// A note added to a finished session from the library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionNote {
+    pub id: u64,
+    pub text: String,
+    /// When the note was written.
+    pub unix_ms: u128,
+    /// Position in the recording the note refers to, if any.
+    #[serde(default)]
+    pub offset_ms: Option<u64>,
+}
+
+impl SessionManifest {
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn read(dir: &Path) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE))?)?)
+    }
+}
+
+/// Every readable manifest under `root`, newest first.
+pub fn list_manifests(root: &Path) -> Result<Vec<(PathBuf, SessionManifest)>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let dir = entry?.path();
+        if !dir.join(MANIFEST_FILE).exists() {
+            continue;
+        }
+        match SessionManifest::read(&dir) {
+            Ok(manifest) => found.push((dir, manifest)),
+            Err(err) => eprintln!("[session] skipping unreadable manifest in {}: {err}", dir.display()),
+        }
+    }
+    found.sort_by(|(_, a), (_, b)| b.started_unix_ms.cmp(&a.started_unix_ms));
+    Ok(found)
+}
+
+/// Manifests under `root` whose window timeline has a span matching `query`
+/// by app id or title, newest first.
+pub fn find_by_window(root: &Path, query: &str) -> Result<Vec<SessionManifest>> {
+    Ok(list_manifests(root)?
+        .into_iter()
+        .map(|(_, manifest)| manifest)
+        .filter(|manifest| manifest.windows.iter().any(|span| span.matches(query)))
+        .collect())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamSummary {
+    pub chunks: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// Recap of a finished session, returned by `stop_capture` and stored in
+/// the manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub duration_ms: u64,
+    pub chunks: u64,
+    pub bytes: u64,
+    /// Frames the video rate converter dropped to hold the framerate.
+    pub dropped_frames: u64,
+    /// Keyed by stream, e.g. `video` or `mic`.
+    pub streams: BTreeMap<String, StreamSummary>,
+    pub output_dir: Option<String>,
+    pub files: Vec<String>,
+}
+
+/// Tallies every chunk as it is handed to the consumer, so the totals are
+/// complete the moment capture stops, even while the consumer still drains.
+#[derive(Default)]
+pub struct ChunkStats {
+    streams: Mutex<BTreeMap<String, StreamSummary>>,
+}
+
+impl ChunkStats {
+    pub fn record(&self, chunk: &CapturedChunk) {
+        let mut streams = self.streams.lock().unwrap_or_else(PoisonError::into_inner);
+        let stream = streams.entry(chunk.kind.clone()).or_default();
+        stream.chunks += 1;
+        stream.bytes += chunk.data_len as u64;
+        stream.duration_ms += chunk.duration_ms;
+    }
+
+    pub fn reset(&self) {
+        self.streams.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, StreamSummary> {
+        self.streams.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confine_keeps_paths_inside_the_root() {
+        let base = std::env::temp_dir().join(format!("sc-confine-{}", std::process::id()));
+        let root = base.join("recordings");
+        std::fs::create_dir_all(root.join("work")).unwrap();
+        std::fs::create_dir_all(base.join("elsewhere")).unwrap();
+        let root_real = root.canonicalize().unwrap();
+
+        assert_eq!(confine_to(&root.join("work"), &root).unwrap(), root_real.join("work"));
+        // not created yet, but would land inside
+        assert_eq!(confine_to(&root.join("new/dir"), &root).unwrap(), root_real.join("new/dir"));
+        assert!(confine_to(&root.join("../elsewhere"), &root).is_err());
+        assert!(confine_to(&root.join("new/../../elsewhere"), &root).is_err());
+        assert!(confine_to(&base.join("elsewhere"), &root).is_err());
+        #[cfg(unix)]
+        {
+            let link = root.join("escape");
+            let _ = std::fs::remove_file(&link);
+            std::os::unix::fs::symlink(base.join("elsewhere"), &link).unwrap();
+            assert!(confine_to(&link, &root).is_err());
+        }
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
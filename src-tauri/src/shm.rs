@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+fn make(factory: &str, name: &str) -> Result<gst::Element> {
+    gst::ElementFactory::make(factory)
+        .name(name)
+        .build()
+        .map_err(|_| anyhow!("missing GStreamer element '{factory}' — ensure required plugins are installed"))
+}
+
+/// Branches the video off `tee` into a `shmsink` listening on `socket_path`,
+/// so another local process can map the raw frames with `shmsrc` instead of
+/// receiving serialized chunks. shmsink doesn't carry caps, so the negotiated
+/// ones are written next to the socket as `<socket_path>.caps`.
+pub fn add_output(pipeline: &gst::Pipeline, tee: &gst::Element, socket_path: &str) -> Result<()> {
+    let queue = make("queue", "shm_queue")?;
+    // a reader that stops consuming must not hold back the recording
+    queue.set_property_from_str("leaky", "downstream");
+    let sink = make("shmsink", "shm_sink")?;
+    sink.set_property("socket-path", socket_path);
+    sink.set_property("wait-for-connection", false);
+    sink.set_property("sync", false);
+
+    let caps_path = format!("{socket_path}.caps");
+    queue
+        .static_pad("sink")
+        .ok_or_else(|| anyhow!("shm queue has no sink pad"))?
+        .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+            if let Some(gst::PadProbeData::Event(event)) = &info.data {
+                if let gst::EventView::Caps(caps) = event.view() {
+                    if let Err(err) = std::fs::write(&caps_path, caps.caps().to_string()) {
+                        eprintln!("[shm] failed to write {caps_path}: {err}");
+                    }
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+    pipeline.add_many([&queue, &sink])?;
+    gst::Element::link_many([tee, &queue, &sink])?;
+    println!("[shm] exposing raw frames on {socket_path}");
+    Ok(())
+}
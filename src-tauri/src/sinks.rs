@@ -0,0 +1,398 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::capture_manager::CapturedChunk;
+use crate::events::{CaptureEvent, EventBus};
+use crate::muxer::{EncoderSettings, MuxTarget, MuxWriter, RollingMkvWriter, SharedRecording};
+use crate::overrides::ElementOverrides;
+use crate::session::TrackInfo;
+use crate::uploads::UploadSpool;
+
+/// Chunks a sink may fall behind by before new ones are dropped for it.
+const SINK_QUEUE_CHUNKS: usize = 32;
+/// What the encoders send when no bitrate is set: x264enc's default, and a
+/// typical AAC rate.
+const DEFAULT_VIDEO_KBPS: u32 = 2048;
+const DEFAULT_AUDIO_KBPS: u32 = 128;
+/// First wait before a dropped stream is reopened; doubles per failed attempt.
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkTarget {
+    /// Matroska files under `<session dir>/<sink name>/`.
+    Mkv {
+        #[serde(default)]
+        max_file_size_mb: Option<u64>,
+    },
+    /// Live stream to an RTMP ingest such as `rtmp://host/app/key`.
+    Rtmp { url: String },
+    /// Each raw chunk POSTed to `url`, spooled to disk until it is accepted.
+    Upload { url: String },
+    /// MPEG-TS to a FIFO (created if missing) or file, or `-` for stdout.
+    MpegTs { path: String },
+}
+
+/// One extra output of a capture, next to the `record_mkv` recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub target: SinkTarget,
+    #[serde(default)]
+    pub encoder: EncoderSettings,
+    /// Streams the sink receives, e.g. `["video_proxy", "mic"]`; empty means all.
+    #[serde(default)]
+    pub streams: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkStatus {
+    Running,
+    /// A streaming sink lost its output and is being reopened with backoff;
+    /// chunks are dropped for it until it is back.
+    Reconnecting,
+    /// The sink hit an error and receives no more chunks; the capture and
+    /// the other sinks carry on.
+    Failed,
+    Stopped,
+}
+
+/// What a running sink needs to know about the session.
+pub struct SinkContext<'a> {
+    pub session_id: &'a str,
+    pub session_dir: PathBuf,
+    pub tracks: &'a [TrackInfo],
+    pub origin_unix_nanos: u128,
+    pub element_overrides: &'a ElementOverrides,
+    pub events: &'a EventBus,
+    pub uploads: &'a UploadSpool,
+    /// The session's `upload_limit_kbps`, applied to every network sink.
+    pub upload_limit_kbps: Option<u64>,
+}
+
+trait ChunkSink: Send {
+    fn push(&mut self, chunk: &CapturedChunk) -> Result<()>;
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Rebuilds a streaming sink after its output dropped, with timestamps
+/// starting at the given wall-clock instant.
+type Reopen = Box<dyn FnMut(u128) -> Result<Box<dyn ChunkSink>> + Send>;
+
+impl ChunkSink for RollingMkvWriter {
+    fn push(&mut self, chunk: &CapturedChunk) -> Result<()> {
+        RollingMkvWriter::push(self, chunk)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        RollingMkvWriter::finish(*self)
+    }
+}
+
+impl ChunkSink for MuxWriter {
+    fn push(&mut self, chunk: &CapturedChunk) -> Result<()> {
+        MuxWriter::push(self, chunk)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        MuxWriter::finish(*self)
+    }
+}
+
+struct UploadSink {
+    url: String,
+    session_id: String,
+    limit_kbps: Option<u64>,
+    spool: UploadSpool,
+}
+
+impl ChunkSink for UploadSink {
+    /// Only spooling can fail here; network errors are retried by the spool.
+    fn push(&mut self, chunk: &CapturedChunk) -> Result<()> {
+        self.spool.enqueue(&self.url, &self.session_id, self.limit_kbps, chunk)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A sink running on its own thread behind a bounded queue, so a slow
+/// network sink can't hold back the recording or the other sinks.
+pub struct SinkHandle {
+    name: String,
+    sender: mpsc::SyncSender<Arc<CapturedChunk>>,
+    thread: JoinHandle<()>,
+    failed: Arc<AtomicBool>,
+    recording: Option<SharedRecording>,
+    compressed: bool,
+    streams: Vec<String>,
+}
+
+impl SinkHandle {
+    /// Starts the sink described by `config`. A sink that can't be started
+    /// is reported as failed rather than failing the capture, so `None`
+    /// means the session continues without it.
+    pub fn start(config: &SinkConfig, context: &SinkContext) -> Option<Self> {
+        match Self::try_start(config, context) {
+            Ok(handle) => {
+                emit_status(context.events, &config.name, SinkStatus::Running, None);
+                Some(handle)
+            }
+            Err(err) => {
+                eprintln!("[sinks] {}: failed to start: {err}", config.name);
+                emit_status(context.events, &config.name, SinkStatus::Failed, Some(err.to_string()));
+                None
+            }
+        }
+    }
+
+    fn try_start(config: &SinkConfig, context: &SinkContext) -> Result<Self> {
+        let mut recording = None;
+        let encoder = match &config.target {
+            SinkTarget::Rtmp { .. } => capped_encoder(&config.encoder, context.upload_limit_kbps),
+            _ => config.encoder.clone(),
+        };
+        let sink: Box<dyn ChunkSink> = match &config.target {
+            SinkTarget::Mkv { max_file_size_mb } => {
+                let shared = SharedRecording::default();
+                recording = Some(Arc::clone(&shared));
+                Box::new(RollingMkvWriter::new(
+                    context.session_dir.join(&config.name),
+                    context.tracks.to_vec(),
+                    context.origin_unix_nanos,
+                    max_file_size_mb.map(|mb| mb * 1024 * 1024),
+                    context.element_overrides.clone(),
+                    encoder.clone(),
+                    None,
+                    shared,
+                )?)
+            }
+            SinkTarget::Rtmp { .. } | SinkTarget::MpegTs { .. } => open_stream(
+                &config.target,
+                context.tracks,
+                context.origin_unix_nanos,
+                context.element_overrides,
+                &encoder,
+            )?,
+            SinkTarget::Upload { url } => Box::new(UploadSink {
+                url: url.clone(),
+                session_id: context.session_id.to_string(),
+                limit_kbps: context.upload_limit_kbps,
+                spool: context.uploads.clone(),
+            }),
+        };
+
+        // streams can be picked up again where they left off; files would
+        // be overwritten and uploads already retry through the spool
+        let mut reopen: Option<Reopen> = match &config.target {
+            SinkTarget::Rtmp { .. } | SinkTarget::MpegTs { .. } => {
+                let target = config.target.clone();
+                let tracks = context.tracks.to_vec();
+                let overrides = context.element_overrides.clone();
+                let encoder = encoder.clone();
+                Some(Box::new(move |origin_unix_nanos| {
+                    open_stream(&target, &tracks, origin_unix_nanos, &overrides, &encoder)
+                }))
+            }
+            SinkTarget::Mkv { .. } | SinkTarget::Upload { .. } => None,
+        };
+
+        let (sender, receiver) = mpsc::sync_channel::<Arc<CapturedChunk>>(SINK_QUEUE_CHUNKS);
+        let name = config.name.clone();
+        let failed = Arc::new(AtomicBool::new(false));
+        let thread_failed = Arc::clone(&failed);
+        let events = context.events.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("sink_{name}"))
+            .spawn(move || {
+                let mut sink = Some(sink);
+                let mut backoff = RECONNECT_MIN;
+                let mut retry_at = Instant::now();
+                for chunk in receiver {
+                    if sink.is_none() {
+                        let Some(reopen) = reopen.as_mut() else {
+                            break;
+                        };
+                        // chunks arriving while the stream is down are dropped
+                        if Instant::now() < retry_at {
+                            continue;
+                        }
+                        match reopen(chunk.start_ts_unix_nanos) {
+                            Ok(reopened) => {
+                                println!("[sinks] {name}: reconnected");
+                                emit_status(&events, &name, SinkStatus::Running, None);
+                                backoff = RECONNECT_MIN;
+                                sink = Some(reopened);
+                            }
+                            Err(err) => {
+                                backoff = (backoff * 2).min(RECONNECT_MAX);
+                                eprintln!("[sinks] {name}: reconnect failed: {err}; retrying in {}s", backoff.as_secs());
+                                retry_at = Instant::now() + backoff;
+                                continue;
+                            }
+                        }
+                    }
+                    let Some(current) = sink.as_mut() else {
+                        break;
+                    };
+                    let Err(err) = current.push(&chunk) else {
+                        continue;
+                    };
+                    if reopen.is_none() {
+                        eprintln!("[sinks] {name}: {err}; disabling sink");
+                        thread_failed.store(true, Ordering::Relaxed);
+                        emit_status(&events, &name, SinkStatus::Failed, Some(err.to_string()));
+                        break;
+                    }
+                    eprintln!("[sinks] {name}: {err}; reconnecting in {}s", backoff.as_secs());
+                    emit_status(&events, &name, SinkStatus::Reconnecting, Some(err.to_string()));
+                    // still finalize a broken sink so what it wrote stays playable
+                    if let Some(Err(err)) = sink.take().map(|broken| broken.finish()) {
+                        eprintln!("[sinks] {name}: {err}");
+                    }
+                    retry_at = Instant::now() + backoff;
+                }
+                // finalized even after a failure, as above
+                let result = sink.map_or(Ok(()), |sink| sink.finish());
+                if thread_failed.load(Ordering::Relaxed) {
+                    return;
+                }
+                match result {
+                    Ok(()) => emit_status(&events, &name, SinkStatus::Stopped, None),
+                    Err(err) => {
+                        eprintln!("[sinks] {name}: {err}");
+                        emit_status(&events, &name, SinkStatus::Failed, Some(err.to_string()));
+                    }
+                }
+            })?;
+        println!("[sinks] started sink '{}'", config.name);
+        Ok(Self {
+            name: config.name.clone(),
+            sender,
+            thread,
+            failed,
+            recording,
+            compressed: matches!(config.target, SinkTarget::Upload { .. }),
+            streams: config.streams.clone(),
+        })
+    }
+
+    /// Whether the sink forwards raw chunks as-is and so takes them
+    /// compressed when `chunk_zstd_level` is set; encoding sinks never do.
+    pub fn takes_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Chapter and part bookkeeping for MKV sinks.
+    pub fn recording(&self) -> Option<&SharedRecording> {
+        self.recording.as_ref()
+    }
+
+    /// Queues `chunk` without blocking; it is dropped for this sink only if
+    /// the sink has fallen too far behind.
+    pub fn offer(&self, chunk: &Arc<CapturedChunk>) {
+        if self.failed.load(Ordering::Relaxed) || !(self.streams.is_empty() || self.streams.contains(&chunk.kind)) {
+            return;
+        }
+        match self.sender.try_send(Arc::clone(chunk)) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(_)) => {
+                eprintln!("[sinks] {}: queue full, dropping {} chunk {}", self.name, chunk.kind, chunk.id);
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// Drains the queue and finalizes the output.
+    pub fn close(self) {
+        drop(self.sender);
+        if self.thread.join().is_err() {
+            eprintln!("[sinks] {}: sink thread panicked", self.name);
+        }
+    }
+}
+
+/// `encoder` with its bitrates lowered so video and audio together fit in
+/// `limit_kbps`; audio gets at most a quarter of it.
+fn capped_encoder(encoder: &EncoderSettings, limit_kbps: Option<u64>) -> EncoderSettings {
+    let Some(limit) = limit_kbps else {
+        return encoder.clone();
+    };
+    let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+    let audio = encoder.audio_bitrate_kbps.unwrap_or(DEFAULT_AUDIO_KBPS).min(limit / 4).max(1);
+    let video = encoder
+        .video_bitrate_kbps
+        .unwrap_or(DEFAULT_VIDEO_KBPS)
+        .min(limit.saturating_sub(audio))
+        .max(1);
+    EncoderSettings {
+        video_bitrate_kbps: Some(video),
+        audio_bitrate_kbps: Some(audio),
+        ..encoder.clone()
+    }
+}
+
+/// An RTMP or MPEG-TS writer for `target`.
+fn open_stream(
+    target: &SinkTarget,
+    tracks: &[TrackInfo],
+    origin_unix_nanos: u128,
+    element_overrides: &ElementOverrides,
+    encoder: &EncoderSettings,
+) -> Result<Box<dyn ChunkSink>> {
+    let target = match target {
+        SinkTarget::Rtmp { url } => MuxTarget::Rtmp(url),
+        SinkTarget::MpegTs { path } => MuxTarget::MpegTs(path),
+        SinkTarget::Mkv { .. } | SinkTarget::Upload { .. } => return Err(anyhow!("not a streaming sink")),
+    };
+    Ok(Box::new(MuxWriter::new(target, tracks, origin_unix_nanos, element_overrides, encoder)?))
+}
+
+fn emit_status(events: &EventBus, sink: &str, status: SinkStatus, error: Option<String>) {
+    events.emit(CaptureEvent::SinkStatusChanged {
+        sink: sink.to_string(),
+        status,
+        error,
+    });
+}
+
+type ChunkListener = Arc<dyn Fn(&Arc<CapturedChunk>) + Send + Sync>;
+
+/// In-process consumers of every captured chunk, such as export servers.
+/// Listeners run on the chunk consumer thread and must hand off, not block.
+#[derive(Clone, Default)]
+pub struct ChunkTaps {
+    listeners: Arc<Mutex<Vec<ChunkListener>>>,
+}
+
+impl ChunkTaps {
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub fn subscribe(&self, listener: impl Fn(&Arc<CapturedChunk>) + Send + Sync + 'static) {
+        self.listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::new(listener));
+    }
+
+    pub fn offer(&self, chunk: &Arc<CapturedChunk>) {
+        let listeners = self
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for listener in listeners {
+            listener(chunk);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureState {
+    Idle,
+    Starting,
+    Running,
+    Paused,
+    Stopping,
+    Failed,
+}
+
+impl CaptureState {
+    pub fn is_active(self) -> bool {
+        matches!(
+            self,
+            CaptureState::Starting | CaptureState::Running | CaptureState::Paused | CaptureState::Stopping
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureState::Idle => "idle",
+            CaptureState::Starting => "starting",
+            CaptureState::Running => "running",
+            CaptureState::Paused => "paused",
+            CaptureState::Stopping => "stopping",
+            CaptureState::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: CaptureState,
+    pub to: CaptureState,
+}
+
+/// Owns the capture state and only lets it move along the documented edges:
+///
+/// ```text
+/// Idle/Failed -> Starting -> Running <-> Paused
+///                   |          |          |
+///                   +------> Stopping <---+ -> Idle
+/// any active state -> Failed -> Idle
+/// ```
+#[derive(Debug)]
+pub struct StateMachine {
+    state: CaptureState,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self {
+            state: CaptureState::Idle,
+        }
+    }
+}
+
+impl StateMachine {
+    pub fn state(&self) -> CaptureState {
+        self.state
+    }
+
+    pub fn can_transition(from: CaptureState, to: CaptureState) -> bool {
+        use CaptureState::*;
+        matches!(
+            (from, to),
+            (Idle | Failed, Starting)
+                | (Starting, Running | Stopping)
+                | (Running, Paused | Stopping)
+                | (Paused, Running | Stopping)
+                | (Stopping, Idle)
+                | (Starting | Running | Paused | Stopping, Failed)
+                | (Failed, Idle)
+        )
+    }
+
+    pub fn transition(&mut self, to: CaptureState) -> Result<Transition> {
+        let from = self.state;
+        if !Self::can_transition(from, to) {
+            return Err(anyhow!(
+                "invalid capture state transition {} -> {}",
+                from.as_str(),
+                to.as_str()
+            ));
+        }
+        self.state = to;
+        Ok(Transition { from, to })
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::capture_manager::CapturedChunk;
+use crate::hdr::HDR_FORMAT;
+use crate::overlap;
+use crate::proxy;
+use crate::session::TrackMedia;
+
+/// Lays chunks of one stream onto a continuous timeline, producing black
+/// video or silence for any stretch no chunk covers (dropped chunks, pauses),
+/// so exported media keeps wall-clock timing.
+pub struct Stitcher {
+    media: TrackMedia,
+    origin_unix_nanos: u128,
+    cursor_unix_nanos: u128,
+    tolerance_ms: u64,
+    pub gaps_filled: usize,
+    pub filled_ms: u64,
+}
+
+impl Stitcher {
+    /// `origin_unix_nanos` is where the timeline starts; a stream that begins
+    /// later than that gets a leading filler.
+    pub fn new(media: TrackMedia, origin_unix_nanos: u128, tolerance_ms: u64) -> Self {
+        Self {
+            media,
+            origin_unix_nanos,
+            cursor_unix_nanos: origin_unix_nanos,
+            tolerance_ms,
+            gaps_filled: 0,
+            filled_ms: 0,
+        }
+    }
+
+    pub fn origin_unix_nanos(&self) -> u128 {
+        self.origin_unix_nanos
+    }
+
+    /// Returns the filler to push before `next`, if the gap exceeds the
+    /// tolerance, and advances the timeline past `next`.
+    pub fn place(&mut self, next: &CapturedChunk) -> Option<CapturedChunk> {
+        let gap_ms = (next.start_ts_unix_nanos.saturating_sub(self.cursor_unix_nanos) / 1_000_000) as u64;
+        let filler = if gap_ms > self.tolerance_ms {
+            self.filler_for(next, gap_ms)
+        } else {
+            None
+        };
+        let end = next.start_ts_unix_nanos + played_ms(next) as u128 * 1_000_000;
+        self.cursor_unix_nanos = self.cursor_unix_nanos.max(end);
+        filler
+    }
+
+    fn filler_for(&mut self, template: &CapturedChunk, gap_ms: u64) -> Option<CapturedChunk> {
+        let data = match self.media {
+            TrackMedia::Video => black_frames(template, gap_ms)?,
+            TrackMedia::Audio => silence(template, gap_ms)?,
+        };
+        self.gaps_filled += 1;
+        self.filled_ms += gap_ms;
+        println!(
+            "[stitch] filling {gap_ms}ms gap before {} chunk {}",
+            template.kind, template.id
+        );
+        Some(CapturedChunk {
+            id: template.id,
+            kind: template.kind.clone(),
+            start_ts_unix_nanos: self.cursor_unix_nanos,
+            duration_ms: gap_ms,
+            metadata: template.metadata.clone(),
+            data_len: data.len(),
+            data,
+        })
+    }
+}
+
+fn audio_frame_bytes(chunk: &CapturedChunk) -> Option<(u64, u64)> {
+    let meta = &chunk.metadata;
+    let rate = meta["rate"].as_u64()?;
+    let channels = meta["channels"].as_u64()?;
+    let bytes_per_sample = if meta["format"].as_str().unwrap_or("F32LE").starts_with("S16") { 2 } else { 4 };
+    Some((rate, channels * bytes_per_sample))
+}
+
+/// How much of the timeline a chunk actually covers: audio is measured from
+/// its samples, video from the chunk duration.
+pub fn played_ms(chunk: &CapturedChunk) -> u64 {
+    if proxy::is_video(&chunk.kind) {
+        return chunk.duration_ms;
+    }
+    match audio_frame_bytes(chunk) {
+        Some((rate, bpf)) if rate > 0 && bpf > 0 => chunk.data.len() as u64 / bpf * 1000 / rate,
+        _ => chunk.duration_ms,
+    }
+}
+
+fn black_frames(template: &CapturedChunk, gap_ms: u64) -> Option<Vec<u8>> {
+    let meta = &template.metadata;
+    let (width, height) = (meta["width"].as_u64()?, meta["height"].as_u64()?);
+    let frame_size = meta["frame_size"].as_u64().unwrap_or(width * height * 4) as usize;
+    if frame_size == 0 {
+        return None;
+    }
+    let template_frames = (template.data.len() / frame_size) as u64;
+    let frames = (template_frames * gap_ms / template.duration_ms.max(1)).max(1) as usize;
+    let frame = match meta["format"].as_str().unwrap_or("RGBA") {
+        // 10-bit values in the high bits: Y at 64 (video black), UV at 512 (neutral)
+        HDR_FORMAT => {
+            let luma = (width * height) as usize;
+            let mut frame = [0x00, 0x10].repeat(luma);
+            frame.extend([0x00, 0x80].repeat(frame_size / 2 - luma));
+            frame
+        }
+        "ARGB" | "ABGR" => [255, 0, 0, 0].repeat(frame_size / 4),
+        _ => [0, 0, 0, 255].repeat(frame_size / 4),
+    };
+    Some(frame.repeat(frames))
+}
+
+fn silence(template: &CapturedChunk, gap_ms: u64) -> Option<Vec<u8>> {
+    let (rate, bpf) = audio_frame_bytes(template)?;
+    Some(vec![0; (rate * gap_ms / 1000 * bpf) as usize])
+}
+
+/// Compares each chunk's start with where the previous chunk of the same
+/// stream ended, so dropped or overlapping data shows up while recording.
+pub struct GapTracker {
+    tolerance_ms: u64,
+    expected_start_unix_nanos: HashMap<String, u128>,
+}
+
+impl GapTracker {
+    pub fn new(tolerance_ms: u64) -> Self {
+        Self {
+            tolerance_ms,
+            expected_start_unix_nanos: HashMap::new(),
+        }
+    }
+
+    /// Returns the deviation in ms (negative for an overlap) when it exceeds
+    /// the tolerance, and records it as `gap_ms` in the chunk metadata.
+    pub fn check(&mut self, chunk: &mut CapturedChunk) -> Option<i64> {
+        // an overlap repeats the end of the previous chunk; the new part starts after it
+        let start = chunk.start_ts_unix_nanos + overlap::overlap_ms(chunk) as u128 * 1_000_000;
+        let end = chunk.start_ts_unix_nanos + played_ms(chunk) as u128 * 1_000_000;
+        let expected = self.expected_start_unix_nanos.insert(chunk.kind.clone(), end)?;
+        let gap_ms = ((start as i128 - expected as i128) / 1_000_000) as i64;
+        if gap_ms.unsigned_abs() <= self.tolerance_ms {
+            return None;
+        }
+        chunk.metadata["gap_ms"] = json!(gap_ms);
+        Some(gap_ms)
+    }
+}
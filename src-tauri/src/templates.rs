@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs;
+use crate::capture_manager::{CaptureOptions, CaptureTarget};
+use crate::displays::DisplayTracker;
+use crate::focus::{self, FocusedWindow};
+
+const DEFAULT_STORE_FILE: &str = "recording_templates.json";
+
+/// What a template records, resolved to a [`CaptureTarget`] at start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateTarget {
+    /// The focused window, if its app id or title contains `pattern`
+    /// (case-insensitive).
+    Window { pattern: String },
+    /// A display by name, as in `list_capture_targets`; `None` is whichever
+    /// display the portal hands out.
+    Display {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    Device { path: String },
+    FollowFocus {
+        #[serde(default = "CaptureTarget::default_min_dwell_ms")]
+        min_dwell_ms: u64,
+    },
+}
+
+/// A saved recording setup: a target, audio devices and start options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingTemplate {
+    pub name: String,
+    pub target: TemplateTarget,
+    /// PulseAudio source for system audio, e.g. a sink's `.monitor`.
+    #[serde(default)]
+    pub system_audio_device: Option<String>,
+    /// PulseAudio source for the mic; setting it turns `capture_mic` on.
+    #[serde(default)]
+    pub mic_device: Option<String>,
+    /// Start options; `target` is replaced by the resolved one.
+    #[serde(default)]
+    pub options: CaptureOptions,
+}
+
+/// Kept in the app config directory; `SC_TEMPLATES_FILE` overrides it.
+fn store_path() -> Result<PathBuf> {
+    match std::env::var_os("SC_TEMPLATES_FILE") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_dirs::config_file(DEFAULT_STORE_FILE),
+    }
+}
+
+pub fn list() -> Result<Vec<RecordingTemplate>> {
+    let path = store_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+fn write(templates: &[RecordingTemplate]) -> Result<()> {
+    std::fs::write(store_path()?, serde_json::to_vec_pretty(templates)?)?;
+    Ok(())
+}
+
+/// Adds `template`, replacing one with the same name.
+pub fn save(template: RecordingTemplate) -> Result<()> {
+    if template.name.trim().is_empty() {
+        return Err(anyhow!("template name is empty"));
+    }
+    let mut templates = list()?;
+    templates.retain(|saved| saved.name != template.name);
+    templates.push(template);
+    write(&templates)
+}
+
+/// Returns whether a template by that name existed.
+pub fn delete(name: &str) -> Result<bool> {
+    let mut templates = list()?;
+    let before = templates.len();
+    templates.retain(|saved| saved.name != name);
+    if templates.len() == before {
+        return Ok(false);
+    }
+    write(&templates)?;
+    Ok(true)
+}
+
+/// The start options for template `name`, with its target resolved against
+/// what is on screen right now.
+pub fn resolve(name: &str, displays: &DisplayTracker) -> Result<CaptureOptions> {
+    let template = list()?
+        .into_iter()
+        .find(|saved| saved.name == name)
+        .ok_or_else(|| anyhow!("no recording template named '{name}'"))?;
+    let mut options = template.options;
+    options.target = match template.target {
+        TemplateTarget::Window { pattern } => {
+            let pattern = pattern.to_lowercase();
+            let matching: Vec<FocusedWindow> = focus::list_windows()?
+                .into_iter()
+                .filter(|window| {
+                    [&window.app_id, &window.title]
+                        .into_iter()
+                        .flatten()
+                        .any(|value| value.to_lowercase().contains(&pattern))
+                })
+                .collect();
+            // the focused one first, when several match
+            let focused = focus::focused_window().ok().flatten().map(|window| window.id);
+            let (window, node_id) = matching
+                .iter()
+                .filter_map(|window| Some((window, window.node_id?)))
+                .max_by_key(|(window, _)| focused.as_ref() == Some(&window.id))
+                .ok_or_else(|| match matching.first() {
+                    Some(window) => anyhow!(
+                        "{} matches '{pattern}' but no PipeWire node shares it; share it through the screencast portal first",
+                        window.label()
+                    ),
+                    None => anyhow!("no window matches '{pattern}'"),
+                })?;
+            println!("[templates] {name}: recording window {}", window.label());
+            CaptureTarget::Window { id: node_id.to_string() }
+        }
+        TemplateTarget::Display { name: display } => {
+            if let Some(display) = &display {
+                if !displays.list().iter().any(|connected| &connected.name == display) {
+                    return Err(anyhow!("display {display} is not connected"));
+                }
+            }
+            options.display = display;
+            CaptureTarget::FullDisplay
+        }
+        TemplateTarget::Device { path } => CaptureTarget::Device { path },
+        TemplateTarget::FollowFocus { min_dwell_ms } => CaptureTarget::FollowFocus { min_dwell_ms },
+    };
+    // a custom source in the template's own options wins over the device
+    if let Some(device) = template.system_audio_device {
+        options
+            .custom_sources
+            .system_audio
+            .get_or_insert_with(|| format!("pulsesrc device={}", quote_launch_value(&device)));
+    }
+    if let Some(device) = template.mic_device {
+        options.capture_mic = true;
+        options
+            .custom_sources
+            .mic
+            .get_or_insert_with(|| format!("pulsesrc device={}", quote_launch_value(&device)));
+    }
+    Ok(options)
+}
+
+/// `value` as a double-quoted launch-line string, so spaces, `!` and quotes
+/// in a device name can't break or extend the pipeline description.
+fn quote_launch_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use thread_priority::{set_current_thread_priority, ThreadPriority, ThreadPriorityValue};
+
+/// Scheduling hints applied to a capture-related thread from inside that thread.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThreadTuning {
+    /// Cross-platform priority, 0 (lowest) to 99 (highest).
+    #[serde(default)]
+    pub priority: Option<u8>,
+    /// Pin the thread to this CPU core.
+    #[serde(default)]
+    pub cpu: Option<usize>,
+}
+
+impl ThreadTuning {
+    pub fn is_default(&self) -> bool {
+        self.priority.is_none() && self.cpu.is_none()
+    }
+
+    pub fn apply_to_current(&self, label: &str) {
+        if let Some(priority) = self.priority {
+            let applied = ThreadPriorityValue::try_from(priority.min(99))
+                .map_err(|err| format!("{err:?}"))
+                .and_then(|value| {
+                    set_current_thread_priority(ThreadPriority::Crossplatform(value))
+                        .map_err(|err| format!("{err:?}"))
+                });
+            if let Err(err) = applied {
+                eprintln!("[threading] {label}: failed to set priority {priority}: {err}");
+            }
+        }
+        if let Some(cpu) = self.cpu {
+            let core = core_affinity::get_core_ids()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|core| core.id == cpu);
+            match core {
+                Some(core) if core_affinity::set_for_current(core) => {}
+                _ => eprintln!("[threading] {label}: failed to pin to cpu {cpu}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::annotations::{Annotation, ChunkAnnotator};
+use crate::capture_manager::CapturedChunk;
+use crate::proxy;
+
+/// Marks an annotation as a translation rather than a transcript.
+pub const TRANSLATION_LABEL: &str = "translation";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Turns text into another language. Implement it to plug in a local model;
+/// [`HttpTranslator`] talks to LibreTranslate-compatible servers.
+pub trait Translator: Send + Sync {
+    /// `source` is the ISO 639-1 code of `text`, when known.
+    fn translate(&self, text: &str, source: Option<&str>, target: &str) -> Result<String>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationConfig {
+    /// ISO 639-1 code to translate into, e.g. `en`.
+    pub target_language: String,
+    /// A LibreTranslate-compatible `/translate` endpoint.
+    pub url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+pub struct HttpTranslator {
+    url: String,
+    api_key: Option<String>,
+}
+
+impl HttpTranslator {
+    pub fn new(url: String, api_key: Option<String>) -> Self {
+        Self { url, api_key }
+    }
+}
+
+impl Translator for HttpTranslator {
+    fn translate(&self, text: &str, source: Option<&str>, target: &str) -> Result<String> {
+        let mut body = json!({
+            "q": text,
+            "source": source.unwrap_or("auto"),
+            "target": target,
+            "format": "text",
+        });
+        if let Some(api_key) = &self.api_key {
+            body["api_key"] = json!(api_key);
+        }
+        let response: Value = ureq::post(&self.url)
+            .timeout(REQUEST_TIMEOUT)
+            .send_json(body)
+            .map_err(|err| anyhow!("translation request failed: {err}"))?
+            .into_json()?;
+        response["translatedText"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("translation response has no translatedText"))
+    }
+}
+
+/// Translates the transcript of each audio chunk, segment by segment when
+/// the transcriber gave segments. Runs after the annotators it translates.
+pub struct TranslationAnnotator {
+    name: String,
+    translator: Box<dyn Translator>,
+    target_language: String,
+}
+
+impl TranslationAnnotator {
+    pub fn new(translator: Box<dyn Translator>, target_language: String) -> Self {
+        Self {
+            name: format!("translation_{target_language}"),
+            translator,
+            target_language,
+        }
+    }
+
+    pub fn from_config(config: &TranslationConfig) -> Self {
+        Self::new(
+            Box::new(HttpTranslator::new(config.url.clone(), config.api_key.clone())),
+            config.target_language.clone(),
+        )
+    }
+}
+
+/// The first annotation in `earlier` that transcribes the chunk.
+pub fn transcript(earlier: &[Annotation]) -> Option<&Annotation> {
+    earlier.iter().find(|annotation| {
+        annotation.summary.is_some() && !annotation.labels.iter().any(|label| label == TRANSLATION_LABEL)
+    })
+}
+
+impl ChunkAnnotator for TranslationAnnotator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants(&self, chunk: &CapturedChunk) -> bool {
+        !proxy::is_video(&chunk.kind)
+    }
+
+    /// Without a transcript before it there is nothing to translate.
+    fn annotate(&self, _chunk: &CapturedChunk) -> Result<Option<Annotation>> {
+        Ok(None)
+    }
+
+    fn annotate_after(&self, _chunk: &CapturedChunk, earlier: &[Annotation]) -> Result<Option<Annotation>> {
+        let Some(transcript) = transcript(earlier) else {
+            return Ok(None);
+        };
+        let source = transcript.language.as_deref();
+        if source == Some(self.target_language.as_str()) {
+            return Ok(None);
+        }
+        let target = self.target_language.as_str();
+        let segments = match transcript.extra["segments"].as_array() {
+            Some(segments) => segments
+                .iter()
+                .filter(|segment| segment["text"].as_str().is_some_and(|text| !text.trim().is_empty()))
+                .map(|segment| {
+                    let text = segment["text"].as_str().unwrap_or_default();
+                    let mut translated = segment.clone();
+                    translated["source_text"] = json!(text);
+                    translated["text"] = json!(self.translator.translate(text, source, target)?);
+                    Ok(translated)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let summary = if segments.is_empty() {
+            let text = transcript.summary.as_deref().unwrap_or_default();
+            self.translator.translate(text, source, target)?
+        } else {
+            segments
+                .iter()
+                .filter_map(|segment| segment["text"].as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        Ok(Some(Annotation {
+            summary: Some(summary),
+            labels: vec![TRANSLATION_LABEL.to_string()],
+            language: Some(self.target_language.clone()),
+            extra: json!({ "source_language": source, "segments": segments }),
+            ..Annotation::default()
+        }))
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app_dirs;
+use crate::bandwidth::BandwidthLimiter;
+use crate::capture_manager::CapturedChunk;
+use crate::events::{CaptureEvent, EventBus};
+
+const DEFAULT_SPOOL_DIR: &str = "upload_spool";
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// Longest wait between attempts at one entry.
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+/// Per socket operation rather than per request, since a capped upload of a
+/// large chunk legitimately takes a long time.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A chunk waiting in the spool; the raw data sits next to this record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    /// File stem of the spool entry, derived on load.
+    #[serde(skip_deserializing, default)]
+    pub id: String,
+    pub url: String,
+    pub session_id: String,
+    pub chunk_id: u64,
+    pub stream: String,
+    pub start_ts_unix_nanos: u128,
+    pub duration_ms: u64,
+    pub metadata: Value,
+    pub bytes: usize,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// The server refused the chunk outright (a 4xx other than 408/429);
+    /// it is kept for inspection but never retried.
+    #[serde(default)]
+    pub rejected: bool,
+    /// Earliest time the entry is tried again, doubling per failed attempt.
+    #[serde(default)]
+    pub retry_after_unix_ms: u64,
+    /// The session's `upload_limit_kbps`, kept so retries after it ended, or
+    /// after a restart, are paced the same.
+    #[serde(default)]
+    pub limit_kbps: Option<u64>,
+}
+
+struct SpoolInner {
+    /// Resolved on first use, once the app directories are known.
+    dir: OnceCell<PathBuf>,
+    wake: Mutex<Option<mpsc::Sender<()>>>,
+    limiter: BandwidthLimiter,
+    /// Set by [`UploadSpool::start`]; completed uploads are announced here.
+    events: Mutex<Option<EventBus>>,
+}
+
+/// Chunks for upload sinks are written here first and deleted once the
+/// server accepted them, so nothing is lost to a flaky network or a restart.
+#[derive(Clone)]
+pub struct UploadSpool {
+    inner: Arc<SpoolInner>,
+}
+
+impl Default for UploadSpool {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(SpoolInner {
+                dir: OnceCell::new(),
+                wake: Mutex::new(None),
+                limiter: BandwidthLimiter::default(),
+                events: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl UploadSpool {
+    /// Kept in the app data directory; `SC_UPLOAD_SPOOL_DIR` overrides it.
+    fn dir(&self) -> Result<&Path> {
+        self.inner
+            .dir
+            .get_or_try_init(|| match std::env::var_os("SC_UPLOAD_SPOOL_DIR") {
+                Some(dir) => Ok(PathBuf::from(dir)),
+                None => app_dirs::data_file(DEFAULT_SPOOL_DIR),
+            })
+            .map(PathBuf::as_path)
+    }
+
+    /// Starts the uploader thread; entries left over from a previous run are
+    /// retried right away.
+    pub fn start(&self, events: EventBus) -> Result<()> {
+        let mut wake = self.inner.wake.lock().unwrap_or_else(PoisonError::into_inner);
+        if wake.is_some() {
+            return Ok(());
+        }
+        *self.inner.events.lock().unwrap_or_else(PoisonError::into_inner) = Some(events);
+        let (tx, rx) = mpsc::channel::<()>();
+        let spool = self.clone();
+        std::thread::Builder::new()
+            .name("uploads".into())
+            .spawn(move || loop {
+                spool.flush();
+                match rx.recv_timeout(RETRY_INTERVAL) {
+                    Ok(()) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            })?;
+        *wake = Some(tx);
+        Ok(())
+    }
+
+    /// Persists `chunk` for upload to `url`, paced at `limit_kbps`, and
+    /// nudges the uploader.
+    pub fn enqueue(&self, url: &str, session_id: &str, limit_kbps: Option<u64>, chunk: &CapturedChunk) -> Result<()> {
+        let dir = self.dir()?;
+        std::fs::create_dir_all(dir)?;
+        let id = format!("{}-{}-{}-{}", chunk.start_ts_unix_nanos, session_id, chunk.id, chunk.kind);
+        let entry = PendingUpload {
+            id: id.clone(),
+            url: url.to_string(),
+            session_id: session_id.to_string(),
+            chunk_id: chunk.id,
+            stream: chunk.kind.clone(),
+            start_ts_unix_nanos: chunk.start_ts_unix_nanos,
+            duration_ms: chunk.duration_ms,
+            metadata: chunk.metadata.clone(),
+            bytes: chunk.data.len(),
+            attempts: 0,
+            last_error: None,
+            rejected: false,
+            retry_after_unix_ms: 0,
+            limit_kbps,
+        };
+        std::fs::write(dir.join(format!("{id}.raw")), &chunk.data)?;
+        // the record is written last, so a half-written entry is never picked up
+        write_record(dir, &entry)?;
+        if let Some(wake) = self.inner.wake.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+            let _ = wake.send(());
+        }
+        Ok(())
+    }
+
+    /// Entries not yet accepted by their server, oldest first.
+    pub fn pending(&self) -> Result<Vec<PendingUpload>> {
+        let dir = self.dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match read_record(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => eprintln!("[uploads] skipping {}: {err}", path.display()),
+            }
+        }
+        entries.sort_by(|a, b| a.start_ts_unix_nanos.cmp(&b.start_ts_unix_nanos).then_with(|| a.id.cmp(&b.id)));
+        Ok(entries)
+    }
+
+    /// Where the data of `entry` is spooled.
+    pub fn data_path(&self, entry: &PendingUpload) -> Result<PathBuf> {
+        Ok(self.dir()?.join(format!("{}.raw", entry.id)))
+    }
+
+    /// Replaces the data of a pending entry, along with its metadata and
+    /// size. `false` when the entry was uploaded in the meantime.
+    pub fn rewrite(&self, entry: &mut PendingUpload, data: &[u8]) -> Result<bool> {
+        let dir = self.dir()?;
+        let raw_path = self.data_path(entry)?;
+        if !raw_path.exists() {
+            return Ok(false);
+        }
+        let tmp = dir.join(format!("{}.raw.tmp", entry.id));
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &raw_path)?;
+        entry.bytes = data.len();
+        write_record(dir, entry)?;
+        Ok(true)
+    }
+
+    /// Uploads pending entries in order. After a failure the rest of that
+    /// server's entries wait for the next pass, so an unreachable server isn't
+    /// hammered with every chunk; entries backing off are skipped, so one bad
+    /// chunk doesn't hold up the ones behind it.
+    fn flush(&self) {
+        let entries = match self.pending() {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("[uploads] failed to read spool: {err}");
+                return;
+            }
+        };
+        let Ok(dir) = self.dir() else {
+            return;
+        };
+        let now_ms = unix_now_ms();
+        let mut failing_urls: Vec<String> = Vec::new();
+        for mut entry in entries {
+            if entry.rejected || entry.retry_after_unix_ms > now_ms || failing_urls.contains(&entry.url) {
+                continue;
+            }
+            let raw_path = dir.join(format!("{}.raw", entry.id));
+            let record_path = dir.join(format!("{}.json", entry.id));
+            let data = match std::fs::read(&raw_path) {
+                Ok(data) => data,
+                Err(err) => {
+                    eprintln!("[uploads] dropping {}: chunk data unreadable: {err}", entry.id);
+                    let _ = std::fs::remove_file(&record_path);
+                    continue;
+                }
+            };
+            self.inner.limiter.set_limit_kbps(entry.limit_kbps);
+            match upload(&entry, &data, &self.inner.limiter) {
+                Ok(()) => {
+                    let _ = std::fs::remove_file(&record_path);
+                    let _ = std::fs::remove_file(&raw_path);
+                    println!("[uploads] uploaded {} chunk {} to {}", entry.stream, entry.chunk_id, entry.url);
+                    if let Some(events) = self.inner.events.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+                        events.emit(CaptureEvent::UploadCompleted {
+                            session_id: entry.session_id.clone(),
+                            stream: entry.stream.clone(),
+                            chunk_id: entry.chunk_id,
+                            url: entry.url.clone(),
+                            bytes: data.len(),
+                            attempts: entry.attempts + 1,
+                        });
+                    }
+                }
+                Err(UploadError::Rejected(err)) => {
+                    entry.attempts += 1;
+                    eprintln!("[uploads] {} rejected, setting it aside: {err}", entry.id);
+                    entry.last_error = Some(err);
+                    entry.rejected = true;
+                    if let Err(err) = write_record(dir, &entry) {
+                        eprintln!("[uploads] failed to update {}: {err}", entry.id);
+                    }
+                }
+                Err(UploadError::Retry(err)) => {
+                    entry.attempts += 1;
+                    let backoff = RETRY_INTERVAL
+                        .saturating_mul(1 << (entry.attempts - 1).min(16))
+                        .min(MAX_BACKOFF);
+                    eprintln!(
+                        "[uploads] {} attempt {} failed, retrying in {}s: {err}",
+                        entry.id,
+                        entry.attempts,
+                        backoff.as_secs()
+                    );
+                    entry.last_error = Some(err);
+                    entry.retry_after_unix_ms = now_ms + backoff.as_millis() as u64;
+                    if let Err(err) = write_record(dir, &entry) {
+                        eprintln!("[uploads] failed to update {}: {err}", entry.id);
+                    }
+                    failing_urls.push(entry.url);
+                }
+            }
+        }
+    }
+}
+
+enum UploadError {
+    /// Sending it again won't help.
+    Rejected(String),
+    Retry(String),
+}
+
+fn upload(entry: &PendingUpload, data: &[u8], limiter: &BandwidthLimiter) -> Result<(), UploadError> {
+    let result = ureq::AgentBuilder::new()
+        .timeout_connect(IO_TIMEOUT)
+        .timeout_read(IO_TIMEOUT)
+        .timeout_write(IO_TIMEOUT)
+        .build()
+        .post(&entry.url)
+        .set("Content-Type", "application/octet-stream")
+        .set("Content-Length", &data.len().to_string())
+        .set("X-Session-Id", &entry.session_id)
+        .set("X-Chunk-Id", &entry.chunk_id.to_string())
+        .set("X-Chunk-Stream", &entry.stream)
+        .set("X-Chunk-Start-Unix-Nanos", &entry.start_ts_unix_nanos.to_string())
+        .set("X-Chunk-Duration-Ms", &entry.duration_ms.to_string())
+        .set("X-Chunk-Metadata", &entry.metadata.to_string())
+        .send(limiter.throttle(data));
+    match result {
+        Ok(_) => Ok(()),
+        // timeouts and rate limits are worth another try
+        Err(ureq::Error::Status(status, _)) if (400..500).contains(&status) && status != 408 && status != 429 => {
+            Err(UploadError::Rejected(format!("{} answered {status}", entry.url)))
+        }
+        Err(err) => Err(UploadError::Retry(format!("upload to {} failed: {err}", entry.url))),
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn write_record(dir: &Path, entry: &PendingUpload) -> Result<()> {
+    let tmp = dir.join(format!("{}.json.tmp", entry.id));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(entry)?)?;
+    std::fs::rename(&tmp, dir.join(format!("{}.json", entry.id)))?;
+    Ok(())
+}
+
+fn read_record(path: &Path) -> Result<PendingUpload> {
+    let mut entry: PendingUpload = serde_json::from_slice(&std::fs::read(path)?)?;
+    entry.id = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("invalid spool file name"))?
+        .to_string();
+    Ok(entry)
+}
@@ -0,0 +1,428 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::app_dirs;
+use crate::audio_ring::{AudioRing, PcmFormat};
+use crate::capture_manager::{self, CaptureManager, CaptureState};
+use crate::events::CaptureEvent;
+use crate::templates;
+
+const DEFAULT_STORE_FILE: &str = "wake_word.json";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Audio searched for the phrase on each poll; also the longest phrase.
+const LISTEN_MS: u64 = 3_000;
+/// At least a full listen window, so one utterance fires once.
+const COOLDOWN: Duration = Duration::from_millis(LISTEN_MS);
+const RATE: u32 = 16_000;
+/// 25ms analysis frames every 10ms.
+const FRAME: usize = 400;
+const HOP: usize = 160;
+/// Centre frequencies of the filter bank, roughly mel-spaced over speech.
+const BANDS: [f32; 16] = [
+    150.0, 250.0, 350.0, 460.0, 580.0, 720.0, 880.0, 1060.0, 1270.0, 1510.0, 1790.0, 2120.0, 2500.0, 2950.0, 3480.0,
+    4100.0,
+];
+/// Frames quieter than this are silence, whatever the room.
+const MIN_SPEECH_RMS: f32 = 0.01;
+/// Average per-frame feature distance below which speech matches a
+/// recording of the phrase; `SC_WAKE_WORD_THRESHOLD` tunes it.
+const DEFAULT_THRESHOLD: f32 = 4.0;
+
+type Features = Vec<[f32; BANDS.len()]>;
+
+/// The phrase and the recordings of it the detector compares speech with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Enrollment {
+    phrase: String,
+    templates: Vec<Features>,
+}
+
+/// Kept in the app data directory; `SC_WAKE_WORD_FILE` overrides it.
+fn store_path() -> Result<PathBuf> {
+    match std::env::var_os("SC_WAKE_WORD_FILE") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_dirs::data_file(DEFAULT_STORE_FILE),
+    }
+}
+
+fn load() -> Result<Option<Enrollment>> {
+    let path = store_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+}
+
+/// Detection is on by default when built in; `SC_WAKE_WORD=0` turns it off.
+fn enabled_from_env() -> bool {
+    !matches!(std::env::var("SC_WAKE_WORD").as_deref(), Ok("0") | Ok("false") | Ok("off"))
+}
+
+/// `SC_WAKE_WORD_TEMPLATE` names a recording template to start when the
+/// phrase is heard while nothing is being recorded.
+fn auto_template() -> Option<String> {
+    std::env::var("SC_WAKE_WORD_TEMPLATE").ok().filter(|name| !name.is_empty())
+}
+
+fn threshold() -> f32 {
+    std::env::var("SC_WAKE_WORD_THRESHOLD")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+/// The mic ring the detector listens on between sessions.
+pub struct StandbyRing(Arc<AudioRing>);
+
+/// The ring holding the newest mic audio: the session's while one records
+/// the mic into a ring long enough, the standby one between sessions.
+fn listening_ring<'a>(manager: &'a CaptureManager, standby: &'a AudioRing) -> Option<&'a AudioRing> {
+    if manager.status() == CaptureState::Idle {
+        return (standby.capacity_ms() > 0).then_some(standby);
+    }
+    (manager.audio_ring().capacity_ms() >= LISTEN_MS).then(|| manager.audio_ring())
+}
+
+/// Records the last `ms` of mic audio as an example of `phrase`. Enrolling a
+/// different phrase replaces the old one; a few examples of the same phrase
+/// make detection more forgiving.
+pub fn enroll(app: &AppHandle, phrase: String, ms: u64) -> Result<()> {
+    let phrase = phrase.trim().to_string();
+    if phrase.is_empty() {
+        return Err(anyhow!("wake phrase is empty"));
+    }
+    if ms == 0 || ms > LISTEN_MS {
+        return Err(anyhow!("ms must be between 1 and {LISTEN_MS}"));
+    }
+    let standby = app
+        .try_state::<StandbyRing>()
+        .ok_or_else(|| anyhow!("wake word detection is off"))?;
+    let manager = app.state::<CaptureManager>();
+    let ring = listening_ring(&manager, &standby.0)
+        .ok_or_else(|| anyhow!("no mic ring to enroll from; record with audio_ring_ms of at least {LISTEN_MS}"))?;
+    let (format, data, _) = ring.recent(ms)?;
+    let samples = mono_16k(&format, &data)?;
+    let speech = trim_silence(&samples).ok_or_else(|| anyhow!("no speech in the last {ms}ms"))?;
+
+    let mut enrollment = load()?.filter(|enrollment| enrollment.phrase == phrase).unwrap_or_default();
+    enrollment.phrase = phrase;
+    enrollment.templates.push(features(speech));
+    std::fs::write(store_path()?, serde_json::to_vec_pretty(&enrollment)?)?;
+    println!(
+        "[wake_word] enrolled '{}' ({} examples)",
+        enrollment.phrase,
+        enrollment.templates.len()
+    );
+    Ok(())
+}
+
+/// Listens for the enrolled phrase on the mic ring: drops a marker while
+/// recording, or starts `SC_WAKE_WORD_TEMPLATE` when idle.
+pub fn spawn(app: AppHandle) -> Result<()> {
+    if !enabled_from_env() {
+        println!("[wake_word] detection disabled");
+        return Ok(());
+    }
+    let standby = Arc::new(AudioRing::default());
+    app.manage(StandbyRing(Arc::clone(&standby)));
+    std::thread::Builder::new()
+        .name("wake_word".into())
+        .spawn(move || {
+            let mut detector = Detector::default();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                detector.poll(&app, &standby);
+            }
+        })?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Detector {
+    /// Feeds the standby ring while no session runs.
+    listener: Option<gst::Pipeline>,
+    last_fired: Option<Instant>,
+    /// The enrollment file's modification time when last loaded.
+    loaded: Option<(SystemTime, Enrollment)>,
+    listener_failed: bool,
+}
+
+impl Detector {
+    fn poll(&mut self, app: &AppHandle, standby: &Arc<AudioRing>) {
+        let manager = app.state::<CaptureManager>();
+        // the mic is only opened between sessions when hearing the phrase
+        // can start one
+        let idle = manager.status() == CaptureState::Idle && auto_template().is_some();
+        if idle && self.listener.is_none() {
+            match start_listener(standby) {
+                Ok(pipeline) => {
+                    self.listener_failed = false;
+                    self.listener = Some(pipeline);
+                }
+                Err(err) => {
+                    if !std::mem::replace(&mut self.listener_failed, true) {
+                        eprintln!("[wake_word] failed to open the mic: {err}");
+                    }
+                }
+            }
+        } else if !idle {
+            // the session owns the mic now
+            if let Some(pipeline) = self.listener.take() {
+                let _ = pipeline.set_state(gst::State::Null);
+                standby.clear();
+            }
+        }
+
+        if self.last_fired.is_some_and(|at| at.elapsed() < COOLDOWN) {
+            return;
+        }
+        let Some(enrollment) = self.enrollment() else {
+            return;
+        };
+        let Some(ring) = listening_ring(&manager, standby) else {
+            return;
+        };
+        let Ok((format, data, _)) = ring.recent(LISTEN_MS) else {
+            return;
+        };
+        let Ok(samples) = mono_16k(&format, &data) else {
+            return;
+        };
+        if samples.chunks(FRAME).all(|frame| rms(frame) < MIN_SPEECH_RMS) {
+            return;
+        }
+        let heard = features(&samples);
+        let Some(distance) = enrollment
+            .templates
+            .iter()
+            .map(|template| match_distance(template, &heard))
+            .min_by(f32::total_cmp)
+            .filter(|distance| *distance < threshold())
+        else {
+            return;
+        };
+        let phrase = enrollment.phrase.clone();
+        self.last_fired = Some(Instant::now());
+        println!("[wake_word] heard '{phrase}' (distance {distance:.2})");
+        fire(&manager, phrase, distance);
+    }
+
+    /// The enrolled phrase, reloaded whenever the file changes.
+    fn enrollment(&mut self) -> Option<&Enrollment> {
+        let modified = std::fs::metadata(store_path().ok()?).and_then(|meta| meta.modified()).ok()?;
+        if self.loaded.as_ref().map(|(at, _)| *at) != Some(modified) {
+            match load() {
+                Ok(Some(enrollment)) => self.loaded = Some((modified, enrollment)),
+                Ok(None) => self.loaded = None,
+                Err(err) => {
+                    eprintln!("[wake_word] failed to load the enrollment: {err}");
+                    self.loaded = None;
+                }
+            }
+        }
+        self.loaded
+            .as_ref()
+            .map(|(_, enrollment)| enrollment)
+            .filter(|enrollment| !enrollment.templates.is_empty())
+    }
+}
+
+fn fire(manager: &CaptureManager, phrase: String, distance: f32) {
+    let mut marker_id = None;
+    let mut template = None;
+    match manager.status() {
+        CaptureState::Running => match manager.add_marker(Some(format!("wake: {phrase}"))) {
+            Ok(marker) => marker_id = Some(marker.id),
+            Err(err) => eprintln!("[wake_word] failed to add a marker: {err}"),
+        },
+        CaptureState::Idle => {
+            if let Some(name) = auto_template() {
+                let result = templates::resolve(&name, manager.displays()).and_then(|options| manager.start_capture(options));
+                match result {
+                    Ok(()) => {
+                        println!("[wake_word] started template {name}");
+                        template = Some(name);
+                    }
+                    Err(err) => eprintln!("[wake_word] failed to start template {name}: {err}"),
+                }
+            }
+        }
+        _ => {}
+    }
+    manager.events().emit(CaptureEvent::WakeWordHeard {
+        phrase,
+        distance,
+        marker_id,
+        template,
+    });
+}
+
+fn start_listener(ring: &Arc<AudioRing>) -> Result<gst::Pipeline> {
+    capture_manager::ensure_gstreamer_initialized()?;
+    let device = std::env::var("SC_MIC_AUDIO_DEVICE").unwrap_or_else(|_| "@DEFAULT_SOURCE@".to_string());
+    let description = format!(
+        "pulsesrc name=wake_word_source device=\"{device}\" ! audioconvert ! audioresample \
+         ! audio/x-raw,format=S16LE,channels=1,rate={RATE} ! appsink name=wake_word_sink sync=false"
+    );
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("wake word listener is not a pipeline"))?;
+    let appsink = pipeline
+        .by_name("wake_word_sink")
+        .and_then(|sink| sink.dynamic_cast::<gst_app::AppSink>().ok())
+        .ok_or_else(|| anyhow!("wake word listener has no appsink"))?;
+    ring.reset(LISTEN_MS);
+    let ring = Arc::clone(ring);
+    let format = PcmFormat {
+        rate: RATE,
+        channels: 1,
+        format: Some("S16LE".to_string()),
+        bytes_per_sample: 2,
+    };
+    appsink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |appsink| {
+                let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Error)?;
+                if let Some(buffer) = sample.buffer() {
+                    if let Ok(map) = buffer.map_readable() {
+                        let unix_ms = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|d| d.as_millis() as u64)
+                            .unwrap_or_default();
+                        ring.push(format.clone(), map.as_slice(), unix_ms);
+                    }
+                }
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| anyhow!("failed to start the wake word listener"))?;
+    println!("[wake_word] listening on {device}");
+    Ok(pipeline)
+}
+
+/// Interleaved PCM mixed down to one channel at [`RATE`].
+fn mono_16k(format: &PcmFormat, data: &[u8]) -> Result<Vec<f32>> {
+    let channels = format.channels.max(1) as usize;
+    let samples: Vec<f32> = match format.format.as_deref() {
+        Some("F32LE") => data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect(),
+        Some("S16LE") => data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+            .collect(),
+        other => return Err(anyhow!("unsupported sample format {other:?}")),
+    };
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    if format.rate == RATE {
+        return Ok(mono);
+    }
+    // linear interpolation is plenty for band energies up to 4kHz
+    let step = format.rate as f32 / RATE as f32;
+    let len = (mono.len() as f32 / step) as usize;
+    Ok((0..len)
+        .map(|index| {
+            let position = index as f32 * step;
+            let base = position as usize;
+            let next = mono.get(base + 1).copied().unwrap_or(mono[base]);
+            mono[base] + (next - mono[base]) * position.fract()
+        })
+        .collect())
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    (frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len().max(1) as f32).sqrt()
+}
+
+/// `samples` without the silence around the speech in it.
+fn trim_silence(samples: &[f32]) -> Option<&[f32]> {
+    let loud: Vec<usize> = samples
+        .chunks(HOP)
+        .enumerate()
+        .filter(|(_, frame)| rms(frame) >= MIN_SPEECH_RMS)
+        .map(|(index, _)| index)
+        .collect();
+    let (first, last) = (*loud.first()?, *loud.last()?);
+    Some(&samples[first * HOP..((last + 1) * HOP).min(samples.len())])
+}
+
+/// Log filter-bank energies per frame, each band's mean removed so the
+/// microphone's and room's colouring cancel out.
+fn features(samples: &[f32]) -> Features {
+    let mut frames: Features = samples
+        .windows(FRAME)
+        .step_by(HOP)
+        .map(|frame| {
+            let mut bands = [0.0; BANDS.len()];
+            for (band, frequency) in bands.iter_mut().zip(BANDS) {
+                *band = (goertzel(frame, frequency) + 1e-9).ln();
+            }
+            bands
+        })
+        .collect();
+    if frames.is_empty() {
+        return frames;
+    }
+    for band in 0..BANDS.len() {
+        let mean = frames.iter().map(|frame| frame[band]).sum::<f32>() / frames.len() as f32;
+        frames.iter_mut().for_each(|frame| frame[band] -= mean);
+    }
+    frames
+}
+
+/// Power of `frequency` in a Hann-windowed frame.
+fn goertzel(frame: &[f32], frequency: f32) -> f32 {
+    let omega = 2.0 * std::f32::consts::PI * frequency / RATE as f32;
+    let coefficient = 2.0 * omega.cos();
+    let (mut previous, mut before) = (0.0f32, 0.0f32);
+    let last = (frame.len() - 1).max(1) as f32;
+    for (index, sample) in frame.iter().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * index as f32 / last).cos();
+        let current = sample * window + coefficient * previous - before;
+        before = previous;
+        previous = current;
+    }
+    (previous * previous + before * before - coefficient * previous * before) / frame.len() as f32
+}
+
+fn distance(a: &[f32; BANDS.len()], b: &[f32; BANDS.len()]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt()
+}
+
+/// The best match of `template` anywhere in `heard`: dynamic time warping
+/// free to start and end at any frame of `heard`, averaged over the
+/// template's length.
+fn match_distance(template: &Features, heard: &Features) -> f32 {
+    if template.is_empty() || heard.len() < template.len() / 2 {
+        return f32::INFINITY;
+    }
+    let mut previous: Vec<f32> = heard.iter().map(|frame| distance(&template[0], frame)).collect();
+    for row in &template[1..] {
+        let mut current = vec![f32::INFINITY; heard.len()];
+        for (column, frame) in heard.iter().enumerate() {
+            let best = if column == 0 {
+                previous[0]
+            } else {
+                previous[column].min(previous[column - 1]).min(current[column - 1])
+            };
+            current[column] = best + distance(row, frame);
+        }
+        previous = current;
+    }
+    previous.into_iter().fold(f32::INFINITY, f32::min) / template.len() as f32
+}
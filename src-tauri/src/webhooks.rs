@@ -0,0 +1,179 @@
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::events::{CaptureEvent, EventBus};
+use crate::state_machine::CaptureState;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    pub max_attempts: u32,
+}
+
+impl WebhookConfig {
+    /// `SC_WEBHOOK_URLS` is a comma-separated list; webhooks are off when it is unset.
+    pub fn from_env() -> Option<Self> {
+        let urls: Vec<String> = std::env::var("SC_WEBHOOK_URLS")
+            .ok()?
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect();
+        if urls.is_empty() {
+            return None;
+        }
+        let max_attempts = std::env::var("SC_WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+            .max(1);
+        Some(Self { urls, max_attempts })
+    }
+}
+
+pub fn spawn(events: &EventBus, config: WebhookConfig) -> Result<()> {
+    let (tx, rx) = mpsc::channel::<Value>();
+    std::thread::Builder::new()
+        .name("webhooks".into())
+        .spawn(move || {
+            for payload in rx {
+                for url in &config.urls {
+                    deliver(url, &payload, config.max_attempts);
+                }
+            }
+        })?;
+
+    events.subscribe(move |event| {
+        let Some(data) = data(event) else {
+            return;
+        };
+        let payload = json!({
+            "event": event.name(),
+            "sent_at_unix_ms": SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default(),
+            "data": data,
+        });
+        let _ = tx.send(payload);
+    });
+    Ok(())
+}
+
+/// What a webhook is told about `event`, field by field: the endpoints are
+/// third parties, so capture options (sink URLs with stream keys, annotator
+/// endpoints), local paths and upload URLs stay out. `None` for events
+/// webhooks don't receive.
+fn data(event: &CaptureEvent) -> Option<Value> {
+    let data = match event {
+        CaptureEvent::SessionStarted {
+            session_id,
+            started_unix_ms,
+            ..
+        } => json!({
+            "session_id": session_id,
+            "started_unix_ms": started_unix_ms,
+            "state": CaptureState::Running,
+        }),
+        CaptureEvent::SessionStopped {
+            session_id,
+            duration_ms,
+            marker_count,
+            ..
+        } => json!({
+            "session_id": session_id,
+            "duration_ms": duration_ms,
+            "marker_count": marker_count,
+            "state": CaptureState::Idle,
+        }),
+        CaptureEvent::SessionFailed { session_id, error } => json!({
+            "session_id": session_id,
+            "error": error,
+            "state": CaptureState::Failed,
+        }),
+        CaptureEvent::UploadCompleted {
+            session_id,
+            stream,
+            chunk_id,
+            bytes,
+            attempts,
+            ..
+        } => json!({
+            "session_id": session_id,
+            "stream": stream,
+            "chunk_id": chunk_id,
+            "bytes": bytes,
+            "attempts": attempts,
+        }),
+        _ => return None,
+    };
+    Some(data)
+}
+
+fn deliver(url: &str, payload: &Value, max_attempts: u32) {
+    let mut backoff = Duration::from_secs(1);
+    for attempt in 1..=max_attempts {
+        match ureq::post(url).timeout(REQUEST_TIMEOUT).send_json(payload) {
+            Ok(_) => return,
+            Err(err) => {
+                eprintln!("[webhooks] {url} attempt {attempt}/{max_attempts} failed: {err}");
+                if attempt < max_attempts {
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_manager::CaptureOptions;
+    use crate::muxer::EncoderSettings;
+    use crate::sinks::{SinkConfig, SinkTarget};
+
+    #[test]
+    fn session_started_leaves_options_out() {
+        let mut options = CaptureOptions::default();
+        options.sinks = vec![SinkConfig {
+            name: "live".to_string(),
+            target: SinkTarget::Rtmp {
+                url: "rtmp://live.example.com/app/secret-key".to_string(),
+            },
+            encoder: EncoderSettings::default(),
+            streams: Vec::new(),
+        }];
+        let event = CaptureEvent::SessionStarted {
+            session_id: "session-1".to_string(),
+            started_unix_ms: 1_000,
+            options,
+        };
+        let data = data(&event).unwrap();
+        assert_eq!(
+            data,
+            json!({ "session_id": "session-1", "started_unix_ms": 1_000, "state": "running" })
+        );
+        assert!(!data.to_string().contains("secret-key"));
+    }
+
+    #[test]
+    fn upload_completed_leaves_the_url_out() {
+        let event = CaptureEvent::UploadCompleted {
+            session_id: "session-1".to_string(),
+            stream: "video".to_string(),
+            chunk_id: 4,
+            url: "https://uploads.example.com/?token=abc".to_string(),
+            bytes: 512,
+            attempts: 2,
+        };
+        assert!(data(&event).unwrap().get("url").is_none());
+        assert!(data(&CaptureEvent::IdleChanged { idle: true }).is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use std::os::fd::AsRawFd;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use anyhow::{anyhow, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde_json::Value;
+
+/// Records through wlroots' `wlr-screencopy` protocol, so it runs on Sway,
+/// Hyprland and other wlroots compositors without a ScreenCast portal.
+const RECORDER: &str = "wf-recorder";
+
+/// Keeps the recorder running; it is stopped when this is dropped.
+pub struct Screencopy {
+    child: Child,
+    output: String,
+    // fdsrc reads from this descriptor, so it must outlive the pipeline
+    _stdout: ChildStdout,
+}
+
+impl Screencopy {
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+}
+
+impl Drop for Screencopy {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Whether the session runs on a wlroots compositor we know how to query.
+pub fn supported() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        && (std::env::var_os("SWAYSOCK").is_some()
+            || std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some())
+}
+
+/// Starts the recorder on one output, `wanted` (else `SC_WLR_OUTPUT`) or the
+/// focused one, and returns a source bin reading its raw RGBA frames.
+pub fn start(framerate: u32, wanted: Option<&str>, name: &str) -> Result<(gst::Element, Screencopy)> {
+    let wanted = wanted
+        .map(str::to_string)
+        .or_else(|| std::env::var("SC_WLR_OUTPUT").ok());
+    let output = match wanted {
+        Some(wanted) => outputs()?
+            .into_iter()
+            .find(|output| output.name == wanted)
+            .ok_or_else(|| anyhow!("output {wanted} not found"))?,
+        None => {
+            let outputs = outputs()?;
+            let focused = outputs.iter().position(|output| output.focused).unwrap_or(0);
+            outputs
+                .into_iter()
+                .nth(focused)
+                .ok_or_else(|| anyhow!("compositor reported no outputs"))?
+        }
+    };
+
+    let mut child = Command::new(RECORDER)
+        .args(["-o", &output.name, "-c", "rawvideo", "-m", "rawvideo", "-x", "rgba"])
+        .args(["-r", &framerate.to_string(), "-y", "-f", "pipe:1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| anyhow!("failed to start {RECORDER}: {err}"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("{RECORDER} has no stdout"))?;
+
+    let description = format!(
+        "fdsrc fd={} do-timestamp=true ! rawvideoparse format=rgba width={} height={} framerate={framerate}/1",
+        stdout.as_raw_fd(),
+        output.width,
+        output.height,
+    );
+    let capture = Screencopy {
+        child,
+        output: output.name.clone(),
+        _stdout: stdout,
+    };
+    let bin = gst::parse::bin_from_description_full(&description, true, None, gst::ParseFlags::FATAL_ERRORS)
+        .map_err(|err| anyhow!("failed to build screencopy source: {err}"))?;
+    bin.set_property("name", name);
+    println!(
+        "[wlr_screencopy] capturing output {} ({}x{}) with {RECORDER}",
+        output.name, output.width, output.height
+    );
+    Ok((bin.upcast(), capture))
+}
+
+struct Output {
+    name: String,
+    width: u64,
+    height: u64,
+    focused: bool,
+}
+
+fn outputs() -> Result<Vec<Output>> {
+    if std::env::var_os("SWAYSOCK").is_some() {
+        let outputs: Value = serde_json::from_str(&run("swaymsg", &["-t", "get_outputs", "-r"])?)?;
+        return Ok(outputs
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|output| output["active"].as_bool() != Some(false))
+            .filter_map(|output| {
+                Some(Output {
+                    name: output["name"].as_str()?.to_string(),
+                    width: output["current_mode"]["width"].as_u64()?,
+                    height: output["current_mode"]["height"].as_u64()?,
+                    focused: output["focused"].as_bool() == Some(true),
+                })
+            })
+            .collect());
+    }
+    let monitors: Value = serde_json::from_str(&run("hyprctl", &["monitors", "-j"])?)?;
+    Ok(monitors
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|monitor| {
+            Some(Output {
+                name: monitor["name"].as_str()?.to_string(),
+                width: monitor["width"].as_u64()?,
+                height: monitor["height"].as_u64()?,
+                focused: monitor["focused"].as_bool() == Some(true),
+            })
+        })
+        .collect())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}